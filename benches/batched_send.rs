@@ -0,0 +1,31 @@
+//! Measures sending of the windows of data packets.
+//! Compare `cargo bench --bench batched_send` with `cargo bench --bench batched_send --features batched`,
+//! the batched send passes the whole window to the system by single call on Linux.
+use std::net::UdpSocket;
+use std::time::Instant;
+use udp_transfer::send_batch;
+
+const WINDOWS: usize = 20_000;
+const WINDOW_SIZE: usize = 15;
+const PACKET_SIZE: usize = 1400;
+
+fn main() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let target = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = target.local_addr().unwrap();
+    let window = vec![vec![7; PACKET_SIZE]; WINDOW_SIZE];
+
+    let start = Instant::now();
+    let mut calls = 0;
+    for _ in 0..WINDOWS {
+        calls += send_batch(&socket, &window, addr).unwrap();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{} packets in {:?}, {:.0} packets/s, {} system calls",
+        WINDOWS * WINDOW_SIZE,
+        elapsed,
+        (WINDOWS * WINDOW_SIZE) as f64 / elapsed.as_secs_f64(),
+        calls
+    );
+}
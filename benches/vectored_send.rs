@@ -0,0 +1,36 @@
+//! Measures sending of the data packets split into the header, content, and checksum.
+//! Compare `cargo bench --bench vectored_send` with `cargo bench --bench vectored_send --features vectored`,
+//! the vectored send doesn't copy the content into the send buffer.
+use std::net::UdpSocket;
+use std::time::Instant;
+use udp_transfer::send_parts;
+
+const PACKETS: usize = 200_000;
+const CONTENT_SIZE: usize = 1400;
+
+fn main() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let target = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = target.local_addr().unwrap();
+    let header = vec![0; 9];
+    let content = vec![7; CONTENT_SIZE];
+    let checksum = vec![0; 16];
+    let mut buffer = vec![0; 65535];
+
+    let start = Instant::now();
+    let mut copied = 0;
+    for _ in 0..PACKETS {
+        send_parts(&socket, &[&header, &content, &checksum], &mut buffer, addr).unwrap();
+        if !cfg!(feature = "vectored") {
+            copied += header.len() + content.len() + checksum.len();
+        }
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{} packets in {:?}, {:.0} packets/s, {} bytes copied into the send buffer",
+        PACKETS,
+        elapsed,
+        PACKETS as f64 / elapsed.as_secs_f64(),
+        copied
+    );
+}
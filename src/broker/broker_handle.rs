@@ -0,0 +1,118 @@
+use std::thread::{JoinHandle, Result};
+use super::config::Direction;
+use super::packet_queue::SharedQueue;
+use super::packet_wrapper::PacketWrapper;
+
+/// Handle of the running broker, that allows to put packets into its queues.
+pub struct BrokerHandle {
+    thread: JoinHandle<()>,
+    to_receiver: SharedQueue,
+    to_sender: SharedQueue,
+}
+
+impl BrokerHandle {
+    pub(super) fn new(thread: JoinHandle<()>, to_receiver: SharedQueue, to_sender: SharedQueue) -> Self {
+        return BrokerHandle {
+            thread,
+            to_receiver,
+            to_sender,
+        };
+    }
+
+    fn queue(&self, direction: Direction) -> &SharedQueue {
+        return match direction {
+            Direction::ToReceiver => &self.to_receiver,
+            Direction::ToSender => &self.to_sender,
+        };
+    }
+
+    /// Send the raw `content` in the `direction` after `delay` milliseconds.
+    /// The packet is not dropped, modified, nor delayed by the impairments of the broker.
+    pub fn inject(&self, direction: Direction, content: Vec<u8>, delay: u32) {
+        let (queue, condvar) = &**self.queue(direction);
+        queue.lock().expect("Can't lock queue to inject packet").push(PacketWrapper::new(content, delay));
+        condvar.notify_one();
+    }
+
+    /// Number of packets forwarded in the `direction` so far, including the injected ones.
+    pub fn forwarded(&self, direction: Direction) -> u64 {
+        let (queue, _) = &**self.queue(direction);
+        return queue.lock().expect("Can't lock queue to count packets").taken();
+    }
+
+    /// Wait for the broker to terminate.
+    pub fn join(self) -> Result<()> {
+        return self.thread.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::UdpSocket;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::mpsc::channel;
+    use std::thread::sleep;
+    use std::time::Duration;
+    use crate::broker::config::{Config, Direction};
+    use crate::broker::handled_logic;
+    use crate::packet::{EndPacket, InitPacket, Packet};
+    use crate::receiver;
+    use crate::receiver::ReceiverEvent;
+
+    #[test]
+    fn injected_end_closes_connection() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3168";
+        const BROKER_SEND_PART: &str = "127.0.0.1:3169";
+        const BROKER_RECV_PART: &str = "127.0.0.1:3170";
+        const SENDER_ADDR: &str = "127.0.0.1:3171";
+        const TARGET_DIR: &str = "received_injected";
+        let (events, observed) = channel();
+        let brk = Arc::new(AtomicBool::new(false));
+        let rc = receiver::config::Config {
+            bindaddr: String::from(RECEIVER_ADDR),
+            directory: String::from(TARGET_DIR),
+            events: Some(events),
+            ..receiver::config::Config::new()
+        };
+        let receiver = receiver::breakable_logic(rc, brk.clone());
+        let bc = Config {
+            sender_bindaddr: String::from(BROKER_SEND_PART),
+            sender_addr: String::from(SENDER_ADDR),
+            receiver_bindaddr: String::from(BROKER_RECV_PART),
+            receiver_addr: String::from(RECEIVER_ADDR),
+            read_timeout: 50,
+            ..Config::new()
+        };
+        let broker = handled_logic(bc, brk.clone());
+        sleep(Duration::from_millis(100));
+
+        // establish the connection through the broker
+        let socket = UdpSocket::bind(SENDER_ADDR).unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let mut buffer = vec![0; 2000];
+        let size = Packet::from(InitPacket::new(4, 100, 16)).to_bin_buff(&mut buffer, 16);
+        socket.send_to(&buffer[..size], BROKER_SEND_PART).unwrap();
+        let (size, _) = socket.recv_from(&mut buffer).unwrap();
+        let id = match Packet::from_bin(&buffer[..size], 16).unwrap() {
+            Packet::Init(p) => p.header.id,
+            p => panic!("{:?}", p),
+        };
+        // end packet claiming data the receiver never got
+        let crafted = Packet::from(EndPacket::new(id, 5)).to_bin(16);
+        broker.inject(Direction::ToReceiver, crafted, 50);
+        let (size, _) = socket.recv_from(&mut buffer).unwrap();
+        match Packet::from_bin(&buffer[..size], 16).unwrap() {
+            Packet::Error(p) => assert_eq!(p.header.id, id),
+            p => panic!("{:?}", p),
+        };
+
+        brk.store(true, Ordering::SeqCst);
+        broker.join().unwrap();
+        receiver.join().unwrap().unwrap();
+        let closed = observed.try_iter()
+            .any(|event| matches!(event, ReceiverEvent::ConnectionClosed(reason) if reason == "end packet with some data left"));
+        assert!(closed);
+        std::fs::remove_dir_all(TARGET_DIR).unwrap();
+    }
+}
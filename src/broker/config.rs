@@ -1,8 +1,287 @@
+use std::cmp::max;
 use std::net::{SocketAddrV4};
 use std::str::FromStr;
-use argparse::{ArgumentParser, StoreTrue, Store};
+use std::time::Duration;
+use std::sync::mpsc::Sender;
+use argparse::{ArgumentParser, StoreTrue, Store, StoreOption};
 use crate::loggable::Loggable;
 use crate::BUFFER_SIZE;
+use crate::packet::Flag;
+use super::events::BrokerEvent;
+
+/// How the broker decides about the delay of the packets.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DelayModel {
+    /// Every packet gets its own random delay, packets may be reordered.
+    IndependentRandom,
+    /// Packets wait in the virtual queue and are send in the order they arrived.
+    QueueFifo,
+}
+
+impl FromStr for DelayModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "independent" => Ok(DelayModel::IndependentRandom),
+            "queue" => Ok(DelayModel::QueueFifo),
+            _ => Err(format!("Unknown delay model {}", s)),
+        }
+    }
+}
+
+/// Direction in which the broker forwards packets.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    /// Packets from the sender to the receiver.
+    ToReceiver,
+    /// Packets from the receiver back to the sender.
+    ToSender,
+}
+
+impl Direction {
+    /// Name of the thread, that forwards the packets in this direction.
+    pub fn thread_name(&self) -> &'static str {
+        return match self {
+            Direction::ToReceiver => "BrokerFromSender",
+            Direction::ToSender => "BrokerFromReceiver",
+        };
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "to_receiver" => Ok(Direction::ToReceiver),
+            "to_sender" => Ok(Direction::ToSender),
+            _ => Err(format!("Unknown direction {}", s)),
+        }
+    }
+}
+
+/// How the random decisions of the two directions relate to each other.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RngMode {
+    /// Both directions draw from a single random stream.
+    Shared,
+    /// Every direction has its own random stream.
+    Independent,
+}
+
+impl FromStr for RngMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "shared" => Ok(RngMode::Shared),
+            "independent" => Ok(RngMode::Independent),
+            _ => Err(format!("Unknown random generator mode {}", s)),
+        }
+    }
+}
+
+/// Named conditions of the link, that are selected randomly by the `weight`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImpairmentProfile {
+    pub name: String,
+    pub weight: f32,
+    pub drop_rate: f32,
+    pub delay_mean: f32,
+    pub delay_std: f32,
+    pub modify_prob: f32,
+}
+
+impl FromStr for ImpairmentProfile {
+    type Err = String;
+
+    /// Parse the profile in format `name:weight:drop_rate:delay_mean:delay_std:modify`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let values = parts.iter().skip(1).map(|value| f32::from_str(value)).collect::<Result<Vec<f32>, _>>();
+        return match (parts.first(), values.as_deref()) {
+            (Some(name), Ok([weight, drop_rate, delay_mean, delay_std, modify_prob])) if *weight >= 0.0 => Ok(ImpairmentProfile {
+                name: String::from(*name),
+                weight: *weight,
+                drop_rate: *drop_rate,
+                delay_mean: *delay_mean,
+                delay_std: *delay_std,
+                modify_prob: *modify_prob,
+            }),
+            _ => Err(format!("Invalid impairment profile {}", s)),
+        };
+    }
+}
+
+/// Which packets the broker forwards by their flag.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FlagFilter {
+    /// Forward only the packets with one of the flags.
+    Allow(Vec<Flag>),
+    /// Drop the packets with one of the flags.
+    Deny(Vec<Flag>),
+}
+
+impl FlagFilter {
+    /// Whether the packet with the `flag` passes the filter.
+    pub fn forwards(&self, flag: &Flag) -> bool {
+        return match self {
+            FlagFilter::Allow(flags) => flags.contains(flag),
+            FlagFilter::Deny(flags) => !flags.contains(flag),
+        };
+    }
+}
+
+impl FromStr for FlagFilter {
+    type Err = String;
+
+    /// Parse the filter in format `allow:flags` or `deny:flags`, where the flags are separated by comma.
+    /// The data flag covers the padded data packets as well, the padded flag only them.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ':');
+        let mode = parts.next().unwrap_or("");
+        let flags = parts.next().unwrap_or("").split(',').filter(|flag| !flag.is_empty()).map(|flag| match flag {
+            "none" => Ok(vec![Flag::None]),
+            "init" => Ok(vec![Flag::Init]),
+            "data" => Ok(vec![Flag::Data, Flag::Padded]),
+            "error" => Ok(vec![Flag::Error]),
+            "end" => Ok(vec![Flag::End]),
+            "gap" => Ok(vec![Flag::Gap]),
+            "padded" => Ok(vec![Flag::Padded]),
+            "ack" => Ok(vec![Flag::Ack]),
+            "metadata" => Ok(vec![Flag::Metadata]),
+            _ => Err(format!("Unknown flag {}", flag)),
+        }).collect::<Result<Vec<Vec<Flag>>, String>>()?.concat();
+        return match mode {
+            "allow" => Ok(FlagFilter::Allow(flags)),
+            "deny" => Ok(FlagFilter::Deny(flags)),
+            _ => Err(format!("Invalid flag filter {}", s)),
+        };
+    }
+}
+
+/// Active queue management, that delays and drops the packets by the number of packets waiting in the queue.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aqm {
+    /// Delay in milliseconds added for every packet waiting in the queue.
+    pub delay_per_packet: f32,
+    /// Number of waiting packets from which the packets are dropped.
+    pub min_threshold: usize,
+    /// Number of waiting packets from which all the packets are dropped.
+    pub max_threshold: usize,
+}
+
+impl Aqm {
+    /// Delay in milliseconds of the packet, that finds `depth` packets in the queue.
+    pub fn delay(&self, depth: usize) -> f32 {
+        return f32::max(0.0, self.delay_per_packet) * depth as f32;
+    }
+
+    /// Probability the packet, that finds `depth` packets in the queue, is dropped.
+    /// It grows linearly between the thresholds.
+    pub fn drop_probability(&self, depth: usize) -> f32 {
+        if depth < self.min_threshold {
+            return 0.0;
+        }
+        if depth >= self.max_threshold {
+            return 1.0;
+        }
+        return (depth - self.min_threshold) as f32 / (self.max_threshold - self.min_threshold) as f32;
+    }
+}
+
+impl FromStr for Aqm {
+    type Err = String;
+
+    /// Parse the queue management in format `delay_per_packet:min_threshold:max_threshold`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let thresholds = parts.iter().skip(1).map(|value| usize::from_str(value)).collect::<Result<Vec<usize>, _>>();
+        return match (parts.first().map(|delay| f32::from_str(delay)), thresholds.as_deref()) {
+            (Some(Ok(delay_per_packet)), Ok([min_threshold, max_threshold])) if min_threshold <= max_threshold => Ok(Aqm {
+                delay_per_packet,
+                min_threshold: *min_threshold,
+                max_threshold: *max_threshold,
+            }),
+            _ => Err(format!("Invalid queue management {}", s)),
+        };
+    }
+}
+
+/// How the packets are split between the virtual paths.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathAssignment {
+    /// The paths take the packets in turn.
+    RoundRobin,
+    /// The path is selected by the hash of the packet header, so the repeated packet takes the same path.
+    Hash,
+}
+
+/// Two virtual paths with their own delays, as when the packets are routed over more links.
+/// The packets on the same path keep their order, so they are reordered only between the paths.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Multipath {
+    pub assignment: PathAssignment,
+    /// Mean and standard deviation of the delay in milliseconds of every path.
+    pub delays: [(f32, f32); 2],
+}
+
+impl FromStr for Multipath {
+    type Err = String;
+
+    /// Parse the paths in format `assignment:mean:std:mean:std`, where the assignment is round_robin or hash.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let assignment = match parts.first() {
+            Some(&"round_robin") => PathAssignment::RoundRobin,
+            Some(&"hash") => PathAssignment::Hash,
+            _ => return Err(format!("Invalid multipath {}", s)),
+        };
+        let values = parts.iter().skip(1).map(|value| f32::from_str(value)).collect::<Result<Vec<f32>, _>>();
+        return match values.as_deref() {
+            Ok([first_mean, first_std, second_mean, second_std]) => Ok(Multipath {
+                assignment,
+                delays: [(*first_mean, *first_std), (*second_mean, *second_std)],
+            }),
+            _ => Err(format!("Invalid multipath {}", s)),
+        };
+    }
+}
+
+/// Parse impairment profiles separated by comma.
+fn parse_profiles(profiles: &str) -> Result<Vec<ImpairmentProfile>, String> {
+    return profiles.split(',').filter(|profile| !profile.is_empty()).map(ImpairmentProfile::from_str).collect();
+}
+
+/// Parse time windows in format `start:duration` separated by comma.
+fn parse_windows(windows: &str) -> Result<Vec<(u32, u32)>, String> {
+    return windows.split(',').filter(|window| !window.is_empty()).map(|window| {
+        let mut parts = window.splitn(2, ':').map(u32::from_str);
+        match (parts.next(), parts.next()) {
+            (Some(Ok(start)), Some(Ok(duration))) => Ok((start, duration)),
+            _ => Err(format!("Invalid time window {}", window)),
+        }
+    }).collect();
+}
+
+/// Parse port translations in format `from:to` separated by comma.
+fn parse_remap(remap: &str) -> Result<Vec<(u16, u16)>, String> {
+    return remap.split(',').filter(|pair| !pair.is_empty()).map(|pair| {
+        let mut ports = pair.splitn(2, ':').map(u16::from_str);
+        match (ports.next(), ports.next()) {
+            (Some(Ok(from)), Some(Ok(to))) => Ok((from, to)),
+            _ => Err(format!("Invalid port translation {}", pair)),
+        }
+    }).collect();
+}
+
+/// Parse sequence numbers separated by comma.
+fn parse_seqs(seqs: &str) -> Result<Vec<u16>, String> {
+    return seqs.split(',').filter(|seq| !seq.is_empty()).map(|seq| {
+        u16::from_str(seq).map_err(|_| format!("Invalid sequence number {}", seq))
+    }).collect();
+}
 
 #[derive(Clone)]
 pub struct Config {
@@ -16,6 +295,36 @@ pub struct Config {
     pub delay_std: f32,
     pub drop_rate: f32,
     pub modify_prob: f32,
+    pub delay_model: DelayModel,
+    pub service_time: f32,
+    pub max_queue_bytes: Option<usize>,
+    pub time_scale: f32,
+    pub blackhole_direction: Direction,
+    pub blackhole_windows: Vec<(u32, u32)>,
+    pub read_timeout: u32,
+    pub reorder_window: usize,
+    pub impair_after_packets: u64,
+    pub impair_after_ms: u32,
+    pub seed: Option<u64>,
+    pub rng_mode: RngMode,
+    pub control_bindaddr: Option<String>,
+    pub coalesce_prob: f32,
+    pub truncate_prob: f32,
+    pub remap_ports: Vec<(u16, u16)>,
+    pub mark_queue_bytes: Option<usize>,
+    pub ack_compression: bool,
+    pub drop_seqs_to_receiver: Vec<u16>,
+    pub drop_seqs_to_sender: Vec<u16>,
+    pub duplicate_acks: u32,
+    pub base_delay_ms: u32,
+    pub profiles: Vec<ImpairmentProfile>,
+    pub profile_interval: u32,
+    pub flag_filter: Option<FlagFilter>,
+    pub aqm: Option<Aqm>,
+    pub processing_delay_ms: f32,
+    pub detect_desync: bool,
+    pub multipath: Option<Multipath>,
+    pub events: Option<Sender<BrokerEvent>>,
 }
 
 impl Config {
@@ -31,6 +340,36 @@ impl Config {
             delay_std: 0.0,
             drop_rate: 0.0,
             modify_prob: 0.0,
+            delay_model: DelayModel::IndependentRandom,
+            service_time: 0.0,
+            max_queue_bytes: None,
+            time_scale: 1.0,
+            blackhole_direction: Direction::ToReceiver,
+            blackhole_windows: Vec::new(),
+            read_timeout: 1000,
+            reorder_window: 0,
+            impair_after_packets: 0,
+            impair_after_ms: 0,
+            seed: None,
+            rng_mode: RngMode::Independent,
+            control_bindaddr: None,
+            coalesce_prob: 0.0,
+            truncate_prob: 0.0,
+            remap_ports: Vec::new(),
+            mark_queue_bytes: None,
+            ack_compression: false,
+            drop_seqs_to_receiver: Vec::new(),
+            drop_seqs_to_sender: Vec::new(),
+            duplicate_acks: 0,
+            base_delay_ms: 0,
+            profiles: Vec::new(),
+            profile_interval: 0,
+            flag_filter: None,
+            aqm: None,
+            processing_delay_ms: 0.0,
+            detect_desync: false,
+            multipath: None,
+            events: None,
         };
     }
 
@@ -46,6 +385,60 @@ impl Config {
     pub fn receiver_addr(&self) -> SocketAddrV4 {
         return SocketAddrV4::from_str(self.receiver_addr.as_str()).expect("Invalid address of the receiver");
     }
+    /// Put the `next` broker between this one and the receiver.
+    /// This broker forwards the packets to the `next` one, which sends the answers back to it.
+    pub fn chain(&mut self, next: &mut Config) {
+        next.sender_addr = self.receiver_bindaddr.clone();
+        next.receiver_addr = self.receiver_addr.clone();
+        self.receiver_addr = next.sender_bindaddr.clone();
+    }
+    pub fn control_bind(&self) -> Option<SocketAddrV4> {
+        return self.control_bindaddr.as_ref().map(|addr| {
+            SocketAddrV4::from_str(addr.as_str()).expect("Invalid bind address for the control")
+        });
+    }
+
+    /// Address the packets for the `addr` are forwarded to, with the port translated by the remap.
+    pub fn remap(&self, addr: SocketAddrV4) -> SocketAddrV4 {
+        return match self.remap_ports.iter().find(|(from, _)| *from == addr.port()) {
+            Some((_, to)) => SocketAddrV4::new(*addr.ip(), *to),
+            None => addr,
+        };
+    }
+
+    /// How long the sockets wait for a packet, at least millisecond as the zero timeout is rejected by the socket.
+    pub fn read_timeout(&self) -> Duration {
+        return Duration::from_millis(max(self.read_timeout, 1) as u64);
+    }
+
+    /// Sequence numbers of the data packets in the `direction`, that are dropped the first time they come.
+    pub fn drop_seqs(&self, direction: Direction) -> &[u16] {
+        return match direction {
+            Direction::ToReceiver => &self.drop_seqs_to_receiver,
+            Direction::ToSender => &self.drop_seqs_to_sender,
+        };
+    }
+
+    /// Whether the packets in the `direction` should be dropped `elapsed` milliseconds after the broker started.
+    pub fn is_blackholed(&self, direction: Direction, elapsed: u128) -> bool {
+        return direction == self.blackhole_direction && self.blackhole_windows.iter().any(|(start, duration)| {
+            *start as u128 <= elapsed && elapsed < *start as u128 + *duration as u128
+        });
+    }
+
+    /// Whether the packet should be dropped, modified, or delayed,
+    /// when `packets` were received before it in its direction, `elapsed` milliseconds after the broker started.
+    pub fn is_impaired(&self, packets: u64, elapsed: u128) -> bool {
+        return packets >= self.impair_after_packets && elapsed >= self.impair_after_ms as u128;
+    }
+
+    /// Pass the `event` to the observer of the broker, if there is any.
+    pub fn emit(&self, event: BrokerEvent) {
+        if let Some(events) = &self.events {
+            // the observer may be gone already, the broker doesn't depend on it
+            let _ = events.send(event);
+        }
+    }
 
     pub fn vlog(&self, text: &str){
         Loggable::vlog(self, text)
@@ -56,6 +449,11 @@ impl Config {
 
     pub fn from_command_line() -> Self {
         let mut config = Config::new();
+        let mut blackhole_windows = String::new();
+        let mut remap_ports = String::new();
+        let mut drop_seqs_to_receiver = String::new();
+        let mut drop_seqs_to_sender = String::new();
+        let mut profiles = String::new();
         {
             let mut parser = ArgumentParser::new();
             parser.refer(&mut config.verbose)
@@ -78,8 +476,71 @@ impl Config {
                 .add_option(&["-d", "--drop_rate"], Store, "Percentage of dropout of packets between 0 and 1");
             parser.refer(&mut config.modify_prob)
                 .add_option(&["-m", "--modify"], Store, "Probability of byte modification");
+            parser.refer(&mut config.delay_model)
+                .add_option(&["--delay_model"], Store, "How to delay packets, independent or queue");
+            parser.refer(&mut config.service_time)
+                .add_option(&["--service_time"], Store, "Time to process single packet in the queue delay model");
+            parser.refer(&mut config.max_queue_bytes)
+                .add_option(&["--max_queue_bytes"], StoreOption, "Maximum number of bytes waiting in the queue, other packets are dropped");
+            parser.refer(&mut config.time_scale)
+                .add_option(&["--time_scale"], Store, "Multiplier of all the delays");
+            parser.refer(&mut config.blackhole_direction)
+                .add_option(&["--blackhole_direction"], Store, "Direction in which drop all packets during the blackhole windows, to_receiver or to_sender");
+            parser.refer(&mut blackhole_windows)
+                .add_option(&["--blackhole_windows"], Store, "Time windows in milliseconds from the start in format start:duration separated by comma");
+            parser.refer(&mut config.read_timeout)
+                .add_option(&["--read_timeout"], Store, "How long to wait for a packet in milliseconds before checking for the shutdown, at least 1");
+            parser.refer(&mut config.reorder_window)
+                .add_option(&["--reorder_window"], Store, "Maximum number of positions by which packet may be reordered, 0 for unlimited");
+            parser.refer(&mut config.impair_after_packets)
+                .add_option(&["--impair_after_packets"], Store, "Number of packets in each direction passed without drop, modification, or delay");
+            parser.refer(&mut config.impair_after_ms)
+                .add_option(&["--impair_after_ms"], Store, "Time in milliseconds from the start when the packets pass without drop, modification, or delay");
+            parser.refer(&mut config.seed)
+                .add_option(&["--seed"], StoreOption, "Seed of the random decisions, so they repeat in every run");
+            parser.refer(&mut config.rng_mode)
+                .add_option(&["--rng_mode"], Store, "Whether the directions share the random decisions, shared or independent");
+            parser.refer(&mut config.control_bindaddr)
+                .add_option(&["--control"], StoreOption, "Address in format IP:port on which accept commands like set drop_rate 0.5");
+            parser.refer(&mut config.coalesce_prob)
+                .add_option(&["--coalesce"], Store, "Probability the packet is merged with the following one into single datagram");
+            parser.refer(&mut config.truncate_prob)
+                .add_option(&["--truncate"], Store, "Probability only random prefix of the packet is delivered");
+            parser.refer(&mut remap_ports)
+                .add_option(&["--remap"], Store, "Translation of the destination ports in format from:to separated by comma");
+            parser.refer(&mut config.mark_queue_bytes)
+                .add_option(&["--mark_queue_bytes"], StoreOption, "Number of bytes waiting in the queue from which the packets to the receiver are marked as congested");
+            parser.refer(&mut config.ack_compression)
+                .add_option(&["--ack_compression"], StoreTrue, "Report acknowledges of the same connection waiting in the queue together");
+            parser.refer(&mut drop_seqs_to_receiver)
+                .add_option(&["--drop_seqs_to_receiver"], Store, "Sequence numbers of the data packets to the receiver separated by comma, that are dropped the first time");
+            parser.refer(&mut drop_seqs_to_sender)
+                .add_option(&["--drop_seqs_to_sender"], Store, "Sequence numbers of the data packets to the sender separated by comma, that are dropped the first time");
+            parser.refer(&mut config.duplicate_acks)
+                .add_option(&["--duplicate_acks"], Store, "Number of copies of every acknowledge send to the sender");
+            parser.refer(&mut config.base_delay_ms)
+                .add_option(&["--base_delay_ms"], Store, "Fixed delay of every packet in milliseconds added to the random one");
+            parser.refer(&mut profiles)
+                .add_option(&["--profiles"], Store, "Impairment profiles in format name:weight:drop_rate:delay_mean:delay_std:modify separated by comma, that replace the other impairments");
+            parser.refer(&mut config.profile_interval)
+                .add_option(&["--profile_interval"], Store, "How long in milliseconds the selected profile is used, 0 selects it for every packet");
+            parser.refer(&mut config.flag_filter)
+                .add_option(&["--flag_filter"], StoreOption, "Flags of the packets to forward in format allow:flags or to drop in format deny:flags, the flags like data or end are separated by comma, data include the padded data");
+            parser.refer(&mut config.aqm)
+                .add_option(&["--aqm"], StoreOption, "Delay and drop the packets by the number of packets waiting in the queue, in format delay_per_packet:min_threshold:max_threshold");
+            parser.refer(&mut config.processing_delay_ms)
+                .add_option(&["--processing_delay_ms"], Store, "Time in milliseconds to process every packet before it is forwarded, the packets are processed one after another");
+            parser.refer(&mut config.detect_desync)
+                .add_option(&["--detect_desync"], StoreTrue, "Log the packets, whose flag doesn't follow the previous ones of the connection, like data before init");
+            parser.refer(&mut config.multipath)
+                .add_option(&["--multipath"], StoreOption, "Split the packets between two paths with their own random delay instead of the delay mean and deviation, in format round_robin:mean:std:mean:std or hash:mean:std:mean:std");
             parser.parse_args_or_exit();
         }
+        config.blackhole_windows = parse_windows(&blackhole_windows).expect("Invalid blackhole windows");
+        config.remap_ports = parse_remap(&remap_ports).expect("Invalid port translation");
+        config.drop_seqs_to_receiver = parse_seqs(&drop_seqs_to_receiver).expect("Invalid sequence numbers to drop");
+        config.drop_seqs_to_sender = parse_seqs(&drop_seqs_to_sender).expect("Invalid sequence numbers to drop");
+        config.profiles = parse_profiles(&profiles).expect("Invalid impairment profiles");
         return config;
     }
 }
@@ -0,0 +1,93 @@
+use std::collections::{HashMap, HashSet};
+use std::num::Wrapping;
+
+/// Half of the sequence numbers, the numbers further behind the highest one are considered to be after the wrap.
+const HALF_RANGE: u16 = 0x8000;
+
+/// How the data packet was delivered in comparison to the previously delivered ones.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Delivery {
+    /// The packet follows all the packets delivered before it.
+    InOrder,
+    /// Packet with higher sequence number was delivered before it.
+    Reordered,
+    /// Packet with the same sequence number was delivered already.
+    Duplicate,
+}
+
+/// Sequence numbers delivered in a single connection.
+struct ConnectionOrder {
+    highest: u16,
+    delivered: HashSet<u16>,
+}
+
+/// Tracks sequence numbers of the data packets delivered in one direction.
+pub struct DeliveryOrder {
+    connections: HashMap<u32, ConnectionOrder>,
+}
+
+impl DeliveryOrder {
+    pub fn new() -> Self {
+        return DeliveryOrder {
+            connections: HashMap::new(),
+        };
+    }
+
+    /// Record data packet with the sequence number `seq` delivered in the connection `id`.
+    pub fn record(&mut self, id: u32, seq: u16) -> Delivery {
+        let connection = match self.connections.get_mut(&id) {
+            Some(connection) => connection,
+            None => {
+                let mut delivered = HashSet::new();
+                delivered.insert(seq);
+                self.connections.insert(id, ConnectionOrder { highest: seq, delivered });
+                return Delivery::InOrder;
+            }
+        };
+        if !connection.delivered.insert(seq) {
+            return Delivery::Duplicate;
+        }
+        let ahead = (Wrapping(seq) - Wrapping(connection.highest)).0;
+        if ahead >= HALF_RANGE {
+            return Delivery::Reordered;
+        }
+        // forget the numbers, that will be used again after the wrap
+        for forgotten in 0..ahead {
+            let forgotten = Wrapping(connection.highest) + Wrapping(forgotten) - Wrapping(HALF_RANGE);
+            connection.delivered.remove(&forgotten.0);
+        }
+        connection.highest = seq;
+        return Delivery::InOrder;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Delivery, DeliveryOrder};
+
+    #[test]
+    fn reordered_and_duplicated_packets_are_reported() {
+        let mut order = DeliveryOrder::new();
+        let delivered = [0, 1, 3, 2, 4, 3, 6, 5, 7]
+            .iter()
+            .map(|seq| order.record(1, *seq))
+            .collect::<Vec<Delivery>>();
+        assert_eq!(delivered, vec![
+            Delivery::InOrder, Delivery::InOrder, Delivery::InOrder,
+            Delivery::Reordered, Delivery::InOrder, Delivery::Duplicate,
+            Delivery::InOrder, Delivery::Reordered, Delivery::InOrder,
+        ]);
+        // other connection is tracked separately
+        assert_eq!(order.record(2, 0), Delivery::InOrder);
+    }
+
+    #[test]
+    fn sequence_numbers_wrap() {
+        let mut order = DeliveryOrder::new();
+        assert_eq!(order.record(1, u16::MAX - 1), Delivery::InOrder);
+        assert_eq!(order.record(1, 0), Delivery::InOrder);
+        assert_eq!(order.record(1, u16::MAX), Delivery::Reordered);
+        assert_eq!(order.record(1, 1), Delivery::InOrder);
+        assert_eq!(order.record(1, 0), Delivery::Duplicate);
+    }
+}
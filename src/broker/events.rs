@@ -0,0 +1,14 @@
+use super::config::Direction;
+
+/// What happened in the broker, for the application observing it.
+#[derive(Clone, Debug)]
+pub enum BrokerEvent {
+    /// Acknowledges of the connection with the identifier were waiting in the queue together, with their number.
+    AckCompression(u32, usize),
+    /// Data packet with the sequence number was dropped in the direction, because it was listed.
+    SeqDropped(Direction, u16),
+    /// Packet in the direction was dropped by the queue management, because of the number of packets in the queue.
+    AqmDropped(Direction, usize),
+    /// Packet of the connection with the identifier in the direction doesn't follow the previous ones, with the description.
+    Desync(Direction, u32, String),
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use super::config::Direction;
+use crate::packet::{Flag, PacketHeader};
+
+/// Tracker shared by both directions of the broker.
+pub type SharedTracker = Arc<Mutex<FlagTracker>>;
+
+/// Flags seen in single connection.
+#[derive(Default)]
+struct ConnectionFlags {
+    /// Whether the init packet with the connection identifier passed in any direction.
+    init: bool,
+    /// Whether the data packet passed to the receiver and to the sender.
+    data_to_receiver: bool,
+    data_to_sender: bool,
+}
+
+/// Flags of the packets per connection, so the broker notices the sequences the protocol doesn't allow.
+pub struct FlagTracker {
+    connections: HashMap<u32, ConnectionFlags>,
+}
+
+impl FlagTracker {
+    pub fn shared() -> SharedTracker {
+        return Arc::new(Mutex::new(FlagTracker {
+            connections: HashMap::new(),
+        }));
+    }
+
+    /// Register the packet with the `header` going in the `direction`.
+    /// Returns the description of the unexpected sequence, when the packet doesn't follow the previous ones.
+    pub fn observe(&mut self, direction: Direction, header: &PacketHeader) -> Option<String> {
+        // the sender asks for the connection without its identifier and the errors may come anytime
+        if header.id == 0 || header.flag == Flag::Error || header.flag == Flag::None {
+            return None;
+        }
+        let flags = self.connections.entry(header.id).or_default();
+        let data_seen = match direction {
+            Direction::ToReceiver => &mut flags.data_to_receiver,
+            Direction::ToSender => &mut flags.data_to_sender,
+        };
+        return match header.flag {
+            Flag::Init => {
+                flags.init = true;
+                None
+            }
+            _ if !flags.init => Some(format!("{:?} before init", header.flag)),
+            Flag::Data | Flag::Padded => {
+                *data_seen = true;
+                None
+            }
+            Flag::End if !*data_seen => Some(String::from("End before any data")),
+            _ => None,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FlagTracker;
+    use crate::broker::config::Direction;
+    use crate::packet::{Flag, PacketHeader};
+
+    fn header(id: u32, flag: Flag) -> PacketHeader {
+        return PacketHeader { id, seq: 0, ack: 0, flag };
+    }
+
+    #[test]
+    fn unexpected_sequences_are_reported() {
+        let tracker = FlagTracker::shared();
+        let mut tracker = tracker.lock().unwrap();
+        // regular connection
+        assert_eq!(tracker.observe(Direction::ToReceiver, &header(0, Flag::Init)), None);
+        assert_eq!(tracker.observe(Direction::ToSender, &header(5, Flag::Init)), None);
+        assert_eq!(tracker.observe(Direction::ToReceiver, &header(5, Flag::Data)), None);
+        assert_eq!(tracker.observe(Direction::ToSender, &header(5, Flag::Data)), None);
+        assert_eq!(tracker.observe(Direction::ToReceiver, &header(5, Flag::End)), None);
+        assert_eq!(tracker.observe(Direction::ToSender, &header(5, Flag::End)), None);
+        // data of unknown connection
+        assert_eq!(tracker.observe(Direction::ToReceiver, &header(6, Flag::Data)), Some(String::from("Data before init")));
+        assert_eq!(tracker.observe(Direction::ToSender, &header(6, Flag::Error)), None);
+        // end right after the handshake
+        assert_eq!(tracker.observe(Direction::ToSender, &header(7, Flag::Init)), None);
+        assert_eq!(tracker.observe(Direction::ToReceiver, &header(7, Flag::End)), Some(String::from("End before any data")));
+    }
+}
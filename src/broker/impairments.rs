@@ -0,0 +1,116 @@
+use std::str::FromStr;
+use rand::Rng;
+use super::config::{Config, ImpairmentProfile};
+
+/// Parameters of the impairments, that may change while the broker runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Impairments {
+    pub drop_rate: f32,
+    pub delay_mean: f32,
+    pub delay_std: f32,
+    pub modify_prob: f32,
+}
+
+impl Impairments {
+    /// Impairments the broker starts with.
+    pub fn from_config(config: &Config) -> Self {
+        return Impairments {
+            drop_rate: config.drop_rate,
+            delay_mean: config.delay_mean,
+            delay_std: config.delay_std,
+            modify_prob: config.modify_prob,
+        };
+    }
+
+    /// Impairments of the link in the state described by the `profile`.
+    pub fn from_profile(profile: &ImpairmentProfile) -> Self {
+        return Impairments {
+            drop_rate: profile.drop_rate,
+            delay_mean: profile.delay_mean,
+            delay_std: profile.delay_std,
+            modify_prob: profile.modify_prob,
+        };
+    }
+
+    /// Apply the control `command` in format `set <parameter> <value>`.
+    /// The parameters are `drop_rate`, `delay_mean`, `delay_std`, and `modify`.
+    pub fn apply(&mut self, command: &str) -> Result<(), String> {
+        let parts: Vec<&str> = command.split_whitespace().collect();
+        let (name, value) = match parts.as_slice() {
+            ["set", name, value] => (*name, *value),
+            _ => return Err(format!("Invalid command {}", command.trim())),
+        };
+        let value = f32::from_str(value).map_err(|_| format!("Invalid value {}", value))?;
+        let parameter = match name {
+            "drop_rate" => &mut self.drop_rate,
+            "delay_mean" => &mut self.delay_mean,
+            "delay_std" => &mut self.delay_std,
+            "modify" => &mut self.modify_prob,
+            _ => return Err(format!("Unknown parameter {}", name)),
+        };
+        *parameter = value;
+        return Ok(());
+    }
+}
+
+/// Select one of the `profiles` randomly using the `rng`, the profiles with bigger weight are selected more often.
+pub fn choose_profile<'a>(profiles: &'a [ImpairmentProfile], rng: &mut impl Rng) -> &'a ImpairmentProfile {
+    let total: f32 = profiles.iter().map(|profile| profile.weight).sum();
+    let mut point = rng.gen::<f32>() * total;
+    for profile in profiles {
+        if point < profile.weight {
+            return profile;
+        }
+        point -= profile.weight;
+    }
+    return profiles.last().expect("There is no impairment profile");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use rand::{SeedableRng, rngs::StdRng};
+    use crate::broker::config::{Config, ImpairmentProfile};
+    use super::{choose_profile, Impairments};
+
+    #[test]
+    fn profiles_are_chosen_by_weight() {
+        let clean = ImpairmentProfile::from_str("clean:3:0:0:0:0").unwrap();
+        let lossy = ImpairmentProfile::from_str("lossy:1:0.5:10:5:0").unwrap();
+        assert_eq!(Impairments::from_profile(&lossy), Impairments {
+            drop_rate: 0.5,
+            delay_mean: 10.0,
+            delay_std: 5.0,
+            modify_prob: 0.0,
+        });
+        assert!(ImpairmentProfile::from_str("lossy:1:0.5").is_err());
+        let profiles = vec![clean, lossy];
+        let mut rng = StdRng::seed_from_u64(7);
+        let lossy_count = (0..4000).filter(|_| choose_profile(&profiles, &mut rng).name == "lossy").count();
+        assert!(lossy_count > 900 && lossy_count < 1100, "{}", lossy_count);
+    }
+
+    #[test]
+    fn commands_change_parameters() {
+        let mut impairments = Impairments::from_config(&Config::new());
+        impairments.apply("set drop_rate 0.5").unwrap();
+        impairments.apply("set delay_mean 20\n").unwrap();
+        impairments.apply("set modify 0.01").unwrap();
+        assert_eq!(impairments, Impairments {
+            drop_rate: 0.5,
+            delay_mean: 20.0,
+            delay_std: 0.0,
+            modify_prob: 0.01,
+        });
+    }
+
+    #[test]
+    fn invalid_commands_are_rejected() {
+        let mut impairments = Impairments::from_config(&Config::new());
+        assert!(impairments.apply("set drop_rate").is_err());
+        assert!(impairments.apply("set drop_rate high").is_err());
+        assert!(impairments.apply("set latency 10").is_err());
+        assert!(impairments.apply("get drop_rate 1").is_err());
+        assert_eq!(impairments, Impairments::from_config(&Config::new()));
+    }
+}
@@ -1,12 +1,22 @@
 use std::{thread, thread::JoinHandle};
 use std::cmp::min;
-use std::collections::BinaryHeap;
+use std::collections::{HashMap, HashSet};
 use std::net::{SocketAddrV4, UdpSocket};
-use std::sync::{Arc, Condvar, Mutex};
-use std::time::Duration;
-use rand::{distributions::Uniform, Rng, thread_rng};
-use super::config::Config;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use rand::{distributions::Uniform, Rng, SeedableRng, rngs::StdRng};
+use super::config::{Config, DelayModel, Direction, RngMode};
 use super::packet_wrapper::PacketWrapper;
+use super::packet_queue::{PacketQueue, SharedQueue};
+use super::reorder_window::ReorderWindow;
+use super::multipath::Paths;
+use super::delivery_order::{DeliveryOrder, Delivery};
+use super::impairments::{choose_profile, Impairments};
+use super::broker_handle::BrokerHandle;
+use super::events::BrokerEvent;
+use super::flag_tracker::{FlagTracker, SharedTracker};
+use crate::loggable::Loggable;
+use crate::packet::{Flag, PacketHeader, ToBin};
 use std::sync::atomic::{AtomicBool, Ordering};
 use crate::{recv_with_timeout, BUFFER_SIZE};
 
@@ -17,31 +27,67 @@ pub fn breakable_logic(config: Config, brk: Arc<AtomicBool>) -> JoinHandle<()> {
     thread::Builder::new()
         .name(String::from("Broker"))
         .spawn(move || {
-            broker(config, brk);
+            broker(config, brk, PacketQueue::shared(), PacketQueue::shared());
         }).expect("Can't create thread for the broker")
 }
 
+/// Creates the broker, into which the packets may be injected by the returned handle.
+/// `brk` parameter should be set to `true` when the broker should terminate.
+pub fn handled_logic(config: Config, brk: Arc<AtomicBool>) -> BrokerHandle {
+    let to_receiver = PacketQueue::shared();
+    let to_sender = PacketQueue::shared();
+    let (broker_to_receiver, broker_to_sender) = (to_receiver.clone(), to_sender.clone());
+    let thread = thread::Builder::new()
+        .name(String::from("Broker"))
+        .spawn(move || {
+            broker(config, brk, broker_to_receiver, broker_to_sender);
+        }).expect("Can't create thread for the broker");
+    return BrokerHandle::new(thread, to_receiver, to_sender);
+}
+
 /// Creates the broker and keep running.
 /// There is no way how to terminate the execution.
 pub fn logic(config: Config) -> () {
     let brk = Arc::new(AtomicBool::new(false));
-    broker(config, brk);
+    broker(config, brk, PacketQueue::shared(), PacketQueue::shared());
 }
 
 /// Creates the broker and spawn all the threads.
-fn broker(config: Config, brk: Arc<AtomicBool>) -> () {
+/// The packets waiting to be send in each direction are stored `to_receiver` and `to_sender` queues.
+fn broker(config: Config, brk: Arc<AtomicBool>, to_receiver: SharedQueue, to_sender: SharedQueue) -> () {
     // create sockets
     let send_socket = Arc::new(UdpSocket::bind(config.sender_bind()).expect("Can't bind sender socket"));
     let recv_socket = Arc::new(UdpSocket::bind(config.receiver_bind()).expect("Can't bind sender socket"));
-    config.vlog(&format!("Sockets created --> {} <--> {} --> {}", config.sender_bind(), config.receiver_bind(), config.receiver_addr()));
+    config.vlog(&format!(
+        "Sockets created --> {} <--> {} --> {}",
+        send_socket.local_addr().expect("Can't get address of the sender socket"),
+        recv_socket.local_addr().expect("Can't get address of the receiver socket"),
+        config.receiver_addr()
+    ));
+
+    // random generators of the directions
+    let (to_receiver_rng, to_sender_rng) = direction_rngs(&config);
+    // impairments shared by both directions, they may be changed by the control
+    let impairments = Arc::new(Mutex::new(Impairments::from_config(&config)));
+    let control = config.control_bind().map(|addr| {
+        control_part(&config, addr, &impairments, brk.clone())
+    });
+    // flags of the connections seen in both directions
+    let tracker = match config.detect_desync {
+        true => Some(FlagTracker::shared()),
+        false => None,
+    };
 
     // create sender part
     let from_sender = handle(
         Arc::clone(&send_socket),
         Arc::clone(&recv_socket),
         config.clone(),
-        config.receiver_addr(),
-        "BrokerFromSender",
+        Direction::ToReceiver,
+        to_receiver,
+        to_receiver_rng,
+        impairments.clone(),
+        tracker.clone(),
         brk.clone(),
     );
     // create receiver part
@@ -49,35 +95,109 @@ fn broker(config: Config, brk: Arc<AtomicBool>) -> () {
         Arc::clone(&recv_socket),
         Arc::clone(&send_socket),
         config.clone(),
-        config.sender_addr(),
-        "BrokerFromReceiver",
+        Direction::ToSender,
+        to_sender,
+        to_sender_rng,
+        impairments.clone(),
+        tracker,
         brk.clone(),
     );
 
     // wait for them to end
     from_sender.join().expect("Can't join thread from sender");
     from_receiver.join().expect("Can't join threads from receiver");
+    if let Some(control) = control {
+        control.join().expect("Can't join control thread");
+    }
+}
+
+/// Accepts commands on the `addr` and change the `impairments` by them.
+/// Every command is answered by `OK` or by the `ERR` with the reason.
+fn control_part(
+    config: &Config,
+    addr: SocketAddrV4,
+    impairments: &Arc<Mutex<Impairments>>,
+    brk: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    let config = config.clone();
+    let impairments = impairments.clone();
+    let socket = UdpSocket::bind(addr).expect("Can't bind control socket");
+    config.vlog(&format!("Control socket created on {}", socket.local_addr().expect("Can't get address of the control socket")));
+
+    thread::Builder::new()
+        .name(String::from("BrokerControl"))
+        .spawn(move || {
+            let mut buff = vec![0; BUFFER_SIZE];
+            socket.set_read_timeout(Some(config.read_timeout()))
+                  .expect("Can't change read timeout of the control socket");
+            while !brk.load(Ordering::SeqCst) {
+                let (size, from) = match socket.recv_from(&mut buff) {
+                    Ok(recv) => recv,
+                    Err(_) => continue,
+                };
+                let command = String::from_utf8_lossy(&buff[..size]).into_owned();
+                let answer = {
+                    let mut impairments = impairments.lock().expect("Can't lock impairments");
+                    match impairments.apply(&command) {
+                        Ok(()) => {
+                            config.vlog(&format!("Impairments changed to {:?}", *impairments));
+                            String::from("OK")
+                        }
+                        Err(e) => format!("ERR {}", e),
+                    }
+                };
+                if let Err(e) = socket.send_to(answer.as_bytes(), from) {
+                    eprintln!("Error answering control command {}", e);
+                }
+            }
+        }).expect("Can't create control thread")
+}
+
+/// Random generators for the packets going to the receiver and to the sender.
+/// With the seed in the `config` the generators repeat the same decisions in every run.
+/// In the shared mode both directions use the same generator.
+fn direction_rngs(config: &Config) -> (Arc<Mutex<StdRng>>, Arc<Mutex<StdRng>>) {
+    let create = |offset: u64| Arc::new(Mutex::new(match config.seed {
+        Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(offset)),
+        None => StdRng::from_entropy(),
+    }));
+    let to_receiver = create(0);
+    let to_sender = match config.rng_mode {
+        RngMode::Shared => to_receiver.clone(),
+        RngMode::Independent => create(1),
+    };
+    return (to_receiver, to_sender);
 }
 
 /// Handles one part of the communication.
-/// It receive packets from socket `send_socket` and resend them to `send_addr` from the `send_socket`.
+/// It receive packets from socket `send_socket` and resend them from the `send_socket`
+/// to the sender or receiver, depending on the `direction`.
+/// The packets wait for sending in the `queue`.
+/// Random decisions about the packets are made by the `rng` using current `impairments`.
+/// The flags of the received packets are checked by the `tracker`, if there is any.
+#[allow(clippy::too_many_arguments)]
 fn handle(
     receive_socket: Arc<UdpSocket>,
     send_socket: Arc<UdpSocket>,
     config: Config,
-    send_addr: SocketAddrV4,
-    thread_name: &str,
+    direction: Direction,
+    queue: SharedQueue,
+    rng: Arc<Mutex<StdRng>>,
+    impairments: Arc<Mutex<Impairments>>,
+    tracker: Option<SharedTracker>,
     brk: Arc<AtomicBool>,
 ) -> JoinHandle<()> {
+    let thread_name = direction.thread_name();
     let thread_name_copied = String::from(thread_name);
+    let send_addr = match direction {
+        Direction::ToReceiver => config.receiver_addr(),
+        Direction::ToSender => config.sender_addr(),
+    };
     thread::Builder::new().name(String::from(thread_name)).spawn(move || {
-        let queue = Arc::new(Mutex::new(BinaryHeap::<PacketWrapper>::new()));
-        let condvar = Arc::new(Condvar::new());
-
-        let sending = sending_part(&config, &queue, &condvar, &send_socket, send_addr,
+        let sending = sending_part(&config, &queue, &send_socket, send_addr,
                                    &thread_name_copied, brk.clone());
-        let receiving = receiving_part(&config, &queue, &condvar, &receive_socket,
-                                       &thread_name_copied, brk.clone());
+        let receiving = receiving_part(&config, &queue, &receive_socket,
+                                       direction, rng, impairments, tracker, brk.clone());
 
         sending.join().expect(&format!("Can't join sending part for the {}", thread_name_copied));
         receiving.join().expect(&format!("Can't join receiving part for the {}", thread_name_copied));
@@ -86,19 +206,38 @@ fn handle(
 
 /// Handles receiving part of the communication.
 /// It receives packets from `socket` and add them to the `queue`.
-/// After adding content to the `queue` it notifies other thread (one) using `condvar` variable.
-/// It decides about the delay, modification, and whether the packet should be dropped.
+/// After adding content to the `queue` it notifies other thread (one) using its condition variable.
+/// It decides about the delay, modification, and whether the packet should be dropped,
+/// the `impairments` are read for every packet, so they may change while running.
+/// When there are impairment profiles, they replace the `impairments` and one of them is selected by the weights
+/// for every packet or for every profile interval.
+/// All the packets are dropped when the `direction` is blackholed at the time since the part started.
+/// Data packets with the listed sequence numbers are dropped the first time they come.
+/// Packets before the impairment warmup is over are not dropped, modified, nor delayed.
+/// Every packet waits for the base delay on top of the random one.
+/// The packets split between the virtual paths get the random delay of their path instead.
+/// The packet may be held and send in single datagram together with the following one.
+/// Only random prefix of the packet may be delivered.
+/// Packets to the receiver are marked as congested, when too many bytes wait in the `queue`.
+/// The queue management delays and drops the packets by the number of packets waiting in the `queue`.
+/// Acknowledges of the same connection waiting in the `queue` together are reported.
+/// Acknowledges to the sender may be send more times.
+/// The random decisions are made by the `rng`.
+/// Packets, whose flag doesn't follow the previous ones of the connection, are logged by the `tracker`.
+#[allow(clippy::too_many_arguments)]
 fn receiving_part(
     config: &Config,
-    queue: &Arc<Mutex<BinaryHeap<PacketWrapper>>>,
-    condvar: &Arc<Condvar>,
+    queue: &SharedQueue,
     socket: &Arc<UdpSocket>,
-    thread_name: &str,
+    direction: Direction,
+    rng: Arc<Mutex<StdRng>>,
+    impairments: Arc<Mutex<Impairments>>,
+    tracker: Option<SharedTracker>,
     brk: Arc<AtomicBool>,
 ) -> JoinHandle<()> {
+    let thread_name = direction.thread_name();
     let config = config.clone();
     let queue = queue.clone();
-    let condvar = condvar.clone();
     let socket = socket.clone();
 
     thread::Builder::new()
@@ -106,13 +245,24 @@ fn receiving_part(
         .spawn(move || {
             // create variables
             let mut buff = vec![0; BUFFER_SIZE];
-            let mut rand_gen = thread_rng();
             let probability_dist = Uniform::new(0.0, 1.0);
             let byte_dist = Uniform::new(0, 255);
+            let mut last_send_at = Instant::now();
+            let started_at = Instant::now();
+            let mut received_packets: u64 = 0;
+            let mut reorder_window = match config.reorder_window {
+                0 => None,
+                size => Some(ReorderWindow::new(size)),
+            };
+            let mut held: Option<Vec<u8>> = None;
+            let mut ack_compressions = HashMap::<u32, u64>::new();
+            let mut seqs_to_drop = config.drop_seqs(direction).iter().cloned().collect::<HashSet<u16>>();
+            let mut profile: Option<(Impairments, Instant)> = None;
+            let mut paths = config.multipath.map(Paths::new);
 
             while !brk.load(Ordering::SeqCst) {
                 // set socket timeout
-                socket.set_read_timeout(Some(Duration::from_millis(1000)))
+                socket.set_read_timeout(Some(config.read_timeout()))
                       .expect("Can't change read timeout of the packet");
                 // receive packet
                 let recv = recv_with_timeout(&socket, &mut buff, Box::new(&config));
@@ -122,30 +272,183 @@ fn receiving_part(
                 let (size, sender) = recv.unwrap();
                 config.vlog(&format!("Received {}b of data from {}.", size, sender));
 
+                // report the flag, that the state of the connection doesn't expect
+                if let (Some(tracker), Some(header)) = (&tracker, unmarked_header(&buff[..size])) {
+                    if let Some(desync) = tracker.lock().expect("Can't lock flag tracker").observe(direction, &header) {
+                        config.log(&format!("Desync of connection {} in {:?}: {}", header.id, direction, desync));
+                        config.emit(BrokerEvent::Desync(direction, header.id, desync));
+                    }
+                }
+
+                // drop everything in the blackholed direction
+                if config.is_blackholed(direction, started_at.elapsed().as_millis()) {
+                    config.vlog(&format!("Packet drop, {:?} is blackholed", direction));
+                    continue;
+                }
+
+                // forward only the packets passing the flag filter
+                if let (Some(filter), Some(header)) = (&config.flag_filter, unmarked_header(&buff[..size])) {
+                    if !filter.forwards(&header.flag) {
+                        config.vlog(&format!("Packet with flag {:?} drop, as it is filtered", header.flag));
+                        continue;
+                    }
+                }
+
+                // drop the listed data packets, each only once
+                if let Some(seq) = data_seq(&buff[..size]) {
+                    if seqs_to_drop.remove(&seq) {
+                        config.vlog(&format!("Packet with seq {} drop, as it is listed", seq));
+                        config.emit(BrokerEvent::SeqDropped(direction, seq));
+                        continue;
+                    }
+                }
+
+                // the packets pass clean until the warmup is over
+                let impaired = config.is_impaired(received_packets, started_at.elapsed().as_millis());
+                received_packets += 1;
+
+                // the generator may be shared with the other direction
+                let mut rand_gen = rng.lock().expect("Can't lock random generator");
+
+                // the conditions of the link switch among the profiles
+                let profile_expired = match profile {
+                    Some((_, selected_at)) => selected_at.elapsed() >= Duration::from_millis(config.profile_interval as u64),
+                    None => true,
+                };
+                if !config.profiles.is_empty() && profile_expired {
+                    let selected = choose_profile(&config.profiles, &mut *rand_gen);
+                    config.vlog(&format!("Impairment profile {} selected", selected.name));
+                    profile = Some((Impairments::from_profile(selected), Instant::now()));
+                }
+                let current = match profile {
+                    Some((profile, _)) => profile,
+                    None => *impairments.lock().expect("Can't lock impairments"),
+                };
+
                 // drop packet if dropout
-                if rand_gen.sample(probability_dist) < config.drop_rate {
+                if impaired && is_dropped(&mut *rand_gen, current.drop_rate) {
                     config.vlog("Packet drop");
                     continue;
                 }
 
                 // modify packet and shorten it if necessary
                 let content_length = min(size, config.packet_size as usize);
-                if config.modify_prob > 0.0 {
+                if impaired && current.modify_prob > 0.0 {
                     for i in 0..content_length {
-                        if rand_gen.sample(probability_dist) < config.modify_prob {
+                        if rand_gen.sample(probability_dist) < current.modify_prob {
                             buff[i] = rand_gen.sample(byte_dist);
                         }
                     }
                 }
+                // deliver only part of the packet
+                let content_length = match impaired && content_length > 1 && rand_gen.sample(probability_dist) < config.truncate_prob {
+                    true => {
+                        let truncated = rand_gen.gen_range(1, content_length);
+                        config.vlog(&format!("Packet truncated from {}b to {}b", content_length, truncated));
+                        truncated
+                    }
+                    false => content_length,
+                };
                 let content = Vec::from(&buff[..content_length]);
 
-                // get delay and create wrapper
-                let delay: f32 = f32::max(0.0, config.delay_std * rand_gen.gen::<f32>() + config.delay_mean);
-                let wrapper = PacketWrapper::new(content, delay as u32);
+                // merge the packet with the held one, as the network card coalescing datagrams would
+                let content = match held.take() {
+                    Some(mut previous) => {
+                        previous.extend(content);
+                        config.vlog(&format!("Packet merged with the previous one into {}b", previous.len()));
+                        previous
+                    }
+                    None if impaired && rand_gen.sample(probability_dist) < config.coalesce_prob => {
+                        config.vlog("Packet held to be merged with the next one");
+                        held = Some(content);
+                        continue;
+                    }
+                    None => content,
+                };
+
+                // get delay and create wrapper, all the delays are scaled by the time scale
+                let scale = f32::max(0.0, config.time_scale);
+                let mut wrapper = match (paths.as_mut(), config.delay_model) {
+                    _ if !impaired => PacketWrapper::new(content, 0),
+                    // the packet takes one of the paths and doesn't overtake the previous packets on it
+                    (Some(paths), _) => {
+                        let path = paths.assign(&content);
+                        let delay = paths.delay(path, &mut *rand_gen);
+                        config.vlog(&format!("Packet takes path {} with delay {}ms", path, delay));
+                        let send_at = paths.send_at(path, Duration::from_secs_f32(delay * scale / 1000.0));
+                        PacketWrapper::new_at(content, send_at)
+                    }
+                    (None, DelayModel::IndependentRandom) => {
+                        let delay: f32 = f32::max(0.0, current.delay_std * rand_gen.gen::<f32>() + current.delay_mean);
+                        PacketWrapper::new(content, (delay * scale) as u32)
+                    }
+                    (None, DelayModel::QueueFifo) => {
+                        // packet waits until the previous one is send and then for its own processing
+                        let arrival = Instant::now() + Duration::from_secs_f32(f32::max(0.0, current.delay_mean) * scale / 1000.0);
+                        let processing = f32::max(0.0, config.service_time + current.delay_std * rand_gen.gen::<f32>()) * scale;
+                        let send_at = Instant::max(last_send_at, arrival) + Duration::from_secs_f32(processing / 1000.0);
+                        last_send_at = send_at;
+                        PacketWrapper::new_at(content, send_at)
+                    }
+                };
+                wrapper.delay(Duration::from_secs_f32(config.base_delay_ms as f32 * scale / 1000.0));
+                let aqm = config.aqm.filter(|_| impaired).map(|aqm| (aqm, rand_gen.sample(probability_dist)));
+                drop(rand_gen);
+                // packet can't overtake the packets too far before it
+                if let Some(window) = reorder_window.as_mut() {
+                    if let Some(earliest) = window.earliest() {
+                        wrapper.postpone(earliest);
+                    }
+                    window.push(wrapper.send_at());
+                }
 
                 // add packet to the queue
                 {
+                    let (queue, condvar) = &*queue;
                     let mut queue = queue.lock().expect("Can't lock mutex from receiving part");
+                    if !queue.fits(wrapper.content().len(), config.max_queue_bytes) {
+                        config.vlog(&format!("Queue with {}b is full, packet drop", queue.bytes()));
+                        continue;
+                    }
+                    // the fuller queue delays the packets more and eventually drops them
+                    if let Some((aqm, sample)) = aqm {
+                        let depth = queue.len();
+                        if sample < aqm.drop_probability(depth) {
+                            config.vlog(&format!("Queue with {} packets is congested, packet drop", depth));
+                            config.emit(BrokerEvent::AqmDropped(direction, depth));
+                            continue;
+                        }
+                        wrapper.delay(Duration::from_secs_f32(aqm.delay(depth) * scale / 1000.0));
+                    }
+                    let congested = matches!(config.mark_queue_bytes, Some(threshold) if queue.bytes() >= threshold);
+                    if congested && direction == Direction::ToReceiver {
+                        config.vlog(&format!("Queue with {}b is congested, packet marked", queue.bytes()));
+                        wrapper.mark_congestion();
+                    }
+                    let acknowledged = match config.ack_compression && direction == Direction::ToSender {
+                        true => acknowledged_connection(wrapper.content()),
+                        false => None,
+                    };
+                    if let Some(id) = acknowledged {
+                        let queued = queue.iter().filter(|packet| acknowledged_connection(packet.content()) == Some(id)).count() + 1;
+                        if queued > 1 {
+                            let compressions = ack_compressions.entry(id).or_insert(0);
+                            *compressions += 1;
+                            config.vlog(&format!("Ack compression in connection {}, {} acks queued, {} times so far", id, queued, compressions));
+                            config.emit(BrokerEvent::AckCompression(id, queued));
+                        }
+                    }
+                    // the copies of the acknowledge follow it immediately
+                    let copies = match direction == Direction::ToSender && acknowledged_connection(wrapper.content()).is_some() {
+                        true => config.duplicate_acks,
+                        false => 0,
+                    };
+                    for _ in 0..copies {
+                        queue.push(PacketWrapper::new_at(wrapper.content().clone(), wrapper.send_at()));
+                    }
+                    if copies > 0 {
+                        config.vlog(&format!("Acknowledge duplicated {} times", copies));
+                    }
                     queue.push(wrapper);
                     condvar.notify_one();
                 }
@@ -154,14 +457,19 @@ fn receiving_part(
         }).expect(&format!("Can't create receiving part of the {}", thread_name))
 }
 
+/// Decide by the `rng`, whether the packet is dropped with the `drop_rate` probability.
+fn is_dropped(rng: &mut impl Rng, drop_rate: f32) -> bool {
+    return rng.sample(Uniform::new(0.0, 1.0)) < drop_rate;
+}
+
 /// Handles sending part of the communication.
 /// It pulls packets from the `queue` (after the required amount of time passed) and
-/// send them to `sendaddr` using `socket`.
-/// When new packet arrive into the `queue` it should be signaled using `condvar`.
+/// send them to `send_addr` using `socket`, with the port translated by the remap.
+/// When new packet arrive into the `queue` it should be signaled using its condition variable.
+/// Every packet is processed for the processing delay before it is send, so the delays of the queued packets add up.
 fn sending_part(
     config: &Config,
-    queue: &Arc<Mutex<BinaryHeap<PacketWrapper>>>,
-    condvar: &Arc<Condvar>,
+    queue: &SharedQueue,
     socket: &Arc<UdpSocket>,
     send_addr: SocketAddrV4,
     thread_name: &str,
@@ -169,12 +477,17 @@ fn sending_part(
 ) -> JoinHandle<()> {
     let config = config.clone();
     let queue = queue.clone();
-    let condvar = condvar.clone();
     let socket = socket.clone();
+    let direction_name = String::from(thread_name);
+    let send_addr = config.remap(send_addr);
 
     thread::Builder::new()
         .name(String::from(format!("{}_send", thread_name)))
         .spawn(move || {
+            let mut delivery_order = DeliveryOrder::new();
+            let mut forwarded: u64 = 0;
+            let processing = Duration::from_secs_f32(f32::max(0.0, config.processing_delay_ms * config.time_scale) / 1000.0);
+            let (queue, condvar) = &*queue;
             while !brk.load(Ordering::SeqCst) {
                 // get packet to send
                 let to_send = {
@@ -185,7 +498,7 @@ fn sending_part(
                         // get the wait time based on the first packet that should be send
                         let wait_time = queue_guard.peek().map_or(Duration::from_secs(u64::MAX), |wrapper| { wrapper.send_in() });
                         // if it should be already send break the loop waiting for the packet
-                        if wait_time.as_millis() == 0 {
+                        if wait_time.as_nanos() == 0 {
                             break;
                         }
                         // wait time cannot be longer than 1s because of the termination
@@ -210,13 +523,154 @@ fn sending_part(
                     packet
                 };
 
+                // process the packet, while the other ones wait in the queue
+                if processing.as_nanos() > 0 {
+                    thread::sleep(processing);
+                }
                 // send packet
                 match socket.send_to(to_send.content(), send_addr) {
                     Ok(send_size) => config.vlog(&format!("Send data of size {}b to {}", send_size, send_addr)),
                     Err(e) => eprintln!("Error sending data {}", e),
                 };
+                // describe the packet, so the delivery order can be reconstructed
+                if config.is_verbose() {
+                    forwarded += 1;
+                    config.vlog(&format!(
+                        "{} forwarded #{}: {}",
+                        direction_name,
+                        forwarded,
+                        describe_packet(to_send.content(), &mut delivery_order)
+                    ));
+                }
             };
         }).expect(&format!("Can't create sender part of the {}", thread_name))
 }
 
+/// Header of the packet in the `content`, without the congestion mark of the previous broker.
+fn unmarked_header(content: &[u8]) -> Option<PacketHeader> {
+    let mut header_bytes = Vec::from(&content[..min(content.len(), PacketHeader::bin_size())]);
+    PacketHeader::take_congestion(&mut header_bytes);
+    return PacketHeader::from_bin(&header_bytes).ok();
+}
+
+/// Sequence number of the packet in the `content`, if it is a data packet.
+fn data_seq(content: &[u8]) -> Option<u16> {
+    return match unmarked_header(content) {
+        Some(header) if header.flag == Flag::Data || header.flag == Flag::Padded => Some(header.seq),
+        _ => None,
+    };
+}
 
+/// Connection acknowledged by the packet in the `content`, if it is an acknowledge.
+fn acknowledged_connection(content: &[u8]) -> Option<u32> {
+    return match unmarked_header(content) {
+        Some(header) if header.flag == Flag::Data || header.flag == Flag::Ack => Some(header.id),
+        _ => None,
+    };
+}
+
+/// Describe the header of the forwarded packet in the `content`.
+/// Data packets are compared with the ones forwarded before them in the `delivery_order`.
+fn describe_packet(content: &[u8], delivery_order: &mut DeliveryOrder) -> String {
+    let mut header_bytes = Vec::from(&content[..min(content.len(), PacketHeader::bin_size())]);
+    let congested = PacketHeader::take_congestion(&mut header_bytes);
+    let header = match PacketHeader::from_bin(&header_bytes) {
+        Ok(header) => header,
+        Err(e) => return format!("undecodable header {:?}", e),
+    };
+    let mut description = format!("connection {} seq {} ack {} flag {:?}", header.id, header.seq, header.ack, header.flag);
+    if congested {
+        description.push_str(" congested");
+    }
+    return match header.flag {
+        Flag::Data | Flag::Padded => match delivery_order.record(header.id, header.seq) {
+            Delivery::InOrder => description,
+            Delivery::Reordered => format!("{} reordered", description),
+            Delivery::Duplicate => format!("{} duplicate", description),
+        },
+        _ => description,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::UdpSocket;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use rand::rngs::StdRng;
+    use crate::packet::{DataPacket, Packet, PacketHeader};
+    use crate::broker::config::{Config, RngMode};
+    use crate::broker::delivery_order::DeliveryOrder;
+    use super::{data_seq, describe_packet, direction_rngs, is_dropped};
+
+    fn drop_pattern(rng: &Arc<Mutex<StdRng>>) -> Vec<bool> {
+        let mut rng = rng.lock().unwrap();
+        return (0..100).map(|_| is_dropped(&mut *rng, 0.3)).collect();
+    }
+
+    #[test]
+    fn same_seed_drops_same_packets() {
+        let config = Config {
+            seed: Some(42),
+            rng_mode: RngMode::Independent,
+            ..Config::new()
+        };
+        let (first_to_receiver, first_to_sender) = direction_rngs(&config);
+        let (second_to_receiver, second_to_sender) = direction_rngs(&config);
+        let to_receiver = drop_pattern(&first_to_receiver);
+        let to_sender = drop_pattern(&first_to_sender);
+        assert_eq!(to_receiver, drop_pattern(&second_to_receiver));
+        assert_eq!(to_sender, drop_pattern(&second_to_sender));
+        // the directions are reproducible, but distinct
+        assert_ne!(to_receiver, to_sender);
+    }
+
+    #[test]
+    fn shared_mode_uses_single_stream() {
+        let config = Config {
+            seed: Some(42),
+            rng_mode: RngMode::Shared,
+            ..Config::new()
+        };
+        let (to_receiver, to_sender) = direction_rngs(&config);
+        assert!(Arc::ptr_eq(&to_receiver, &to_sender));
+        // the stream is the same as the one of the independent direction to the receiver
+        let (independent, _) = direction_rngs(&Config { rng_mode: RngMode::Independent, ..config });
+        assert_eq!(drop_pattern(&to_receiver), drop_pattern(&independent));
+    }
+
+    #[test]
+    fn forwarded_packets_are_described() {
+        let mut order = DeliveryOrder::new();
+        let descriptions = [0, 2, 1, 2]
+            .iter()
+            .map(|seq| Packet::from(DataPacket::new(vec![1, 2, 3], 7, *seq, 0)).to_bin(0))
+            .map(|content| describe_packet(&content, &mut order))
+            .collect::<Vec<String>>();
+        assert_eq!(descriptions, vec![
+            "connection 7 seq 0 ack 0 flag Data",
+            "connection 7 seq 2 ack 0 flag Data",
+            "connection 7 seq 1 ack 0 flag Data reordered",
+            "connection 7 seq 2 ack 0 flag Data duplicate",
+        ]);
+        assert!(describe_packet(&[1, 2], &mut order).starts_with("undecodable header"));
+    }
+
+    #[test]
+    fn packet_marked_by_previous_broker_is_recognized() {
+        let mut content = Packet::from(DataPacket::new(vec![1, 2, 3], 7, 5, 0)).to_bin(0);
+        PacketHeader::mark_congestion(&mut content);
+        assert_eq!(data_seq(&content), Some(5));
+    }
+
+    #[test]
+    fn zero_read_timeout_is_accepted_by_socket() {
+        let config = Config {
+            read_timeout: 0,
+            ..Config::new()
+        };
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(config.read_timeout())).unwrap();
+        assert_eq!(config.read_timeout(), Duration::from_millis(1));
+    }
+}
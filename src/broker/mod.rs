@@ -1,6 +1,17 @@
 mod logic;
 pub mod config;
 mod packet_wrapper;
+mod packet_queue;
+mod reorder_window;
+mod multipath;
+mod delivery_order;
+mod impairments;
+mod broker_handle;
+mod events;
+mod flag_tracker;
 
 pub use logic::breakable_logic;
 pub use logic::logic;
+pub use logic::handled_logic;
+pub use broker_handle::BrokerHandle;
+pub use events::BrokerEvent;
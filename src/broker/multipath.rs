@@ -0,0 +1,91 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::time::{Duration, Instant};
+use rand::Rng;
+use crate::packet::PacketHeader;
+use super::config::{Multipath, PathAssignment};
+
+/// Virtual paths, that split the packets and delay them independently.
+/// Packet is never send before the previous packet of the same path.
+pub struct Paths {
+    multipath: Multipath,
+    /// Path of the following packet in the round robin.
+    next: usize,
+    /// Send time of the last packet of every path.
+    last: [Option<Instant>; 2],
+}
+
+impl Paths {
+    pub fn new(multipath: Multipath) -> Self {
+        return Paths {
+            multipath,
+            next: 0,
+            last: [None, None],
+        };
+    }
+
+    /// Select the path of the packet with the `content`.
+    pub fn assign(&mut self, content: &[u8]) -> usize {
+        return match self.multipath.assignment {
+            PathAssignment::RoundRobin => {
+                let path = self.next;
+                self.next = (self.next + 1) % self.last.len();
+                path
+            }
+            PathAssignment::Hash => {
+                let mut hasher = DefaultHasher::new();
+                hasher.write(&content[..content.len().min(PacketHeader::bin_size())]);
+                (hasher.finish() % self.last.len() as u64) as usize
+            }
+        };
+    }
+
+    /// Random delay in milliseconds of the packet on the `path`.
+    pub fn delay(&self, path: usize, rng: &mut impl Rng) -> f32 {
+        let (mean, std) = self.multipath.delays[path];
+        return f32::max(0.0, std * rng.gen::<f32>() + mean);
+    }
+
+    /// Time at which the packet on the `path` is send, after the `delay` but not before the previous packet of the path.
+    pub fn send_at(&mut self, path: usize, delay: Duration) -> Instant {
+        let mut send_at = Instant::now() + delay;
+        if let Some(last) = self.last[path] {
+            // strictly later, so the packets with the same time are not swapped
+            send_at = Instant::max(send_at, last + Duration::from_micros(1));
+        }
+        self.last[path] = Some(send_at);
+        return send_at;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::Duration;
+    use crate::broker::config::Multipath;
+    use super::Paths;
+
+    #[test]
+    fn packets_keep_order_on_their_path() {
+        let mut paths = Paths::new(Multipath::from_str("round_robin:0:0:50:0").unwrap());
+        assert_eq!((0..4).map(|_| paths.assign(&[])).collect::<Vec<usize>>(), vec![0, 1, 0, 1]);
+        let slow = paths.send_at(1, Duration::from_millis(50));
+        let fast = paths.send_at(0, Duration::from_millis(0));
+        assert!(fast < slow);
+        // the later packet on the slow path doesn't overtake the previous one
+        assert!(paths.send_at(1, Duration::from_millis(0)) > slow);
+        assert!(Multipath::from_str("round_robin:0:0:50").is_err());
+        assert!(Multipath::from_str("random:0:0:50:0").is_err());
+    }
+
+    #[test]
+    fn repeated_packet_takes_same_path() {
+        let mut paths = Paths::new(Multipath::from_str("hash:0:0:50:0").unwrap());
+        let packets = (0..64u8).map(|seq| vec![0, 0, 0, 1, 0, seq, 0, 0, 4, 7, 7]).collect::<Vec<Vec<u8>>>();
+        let assigned = packets.iter().map(|packet| paths.assign(packet)).collect::<Vec<usize>>();
+        assert_eq!(packets.iter().map(|packet| paths.assign(packet)).collect::<Vec<usize>>(), assigned);
+        assert!(assigned.contains(&0) && assigned.contains(&1));
+        // only the header decides, not the data
+        assert_eq!(paths.assign(&[0, 0, 0, 1, 0, 3, 0, 0, 4, 1, 1]), assigned[3]);
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Condvar, Mutex};
+use super::packet_wrapper::PacketWrapper;
+
+/// Queue of the packets waiting to be send, with the variable signaling new packets.
+pub type SharedQueue = Arc<(Mutex<PacketQueue>, Condvar)>;
+
+/// Queue of the packets waiting to be send, ordered by the time they should be send.
+/// It keeps track of the total size of the queued content and of the number of packets taken from it.
+pub struct PacketQueue {
+    heap: BinaryHeap<PacketWrapper>,
+    bytes: usize,
+    taken: u64,
+}
+
+impl PacketQueue {
+    pub fn new() -> Self {
+        return PacketQueue {
+            heap: BinaryHeap::new(),
+            bytes: 0,
+            taken: 0,
+        };
+    }
+
+    /// Empty queue, that can be shared between the threads.
+    pub fn shared() -> SharedQueue {
+        return Arc::new((Mutex::new(PacketQueue::new()), Condvar::new()));
+    }
+
+    /// Number of bytes of all the packets within the queue.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Number of packets within the queue.
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// Number of packets taken from the queue so far.
+    pub fn taken(&self) -> u64 {
+        self.taken
+    }
+
+    /// Whether packet of `size` bytes fits into the queue limited to `max_bytes`.
+    pub fn fits(&self, size: usize, max_bytes: Option<usize>) -> bool {
+        return max_bytes.map_or(true, |max_bytes| self.bytes + size <= max_bytes);
+    }
+
+    pub fn push(&mut self, packet: PacketWrapper) {
+        self.bytes += packet.content().len();
+        self.heap.push(packet);
+    }
+
+    /// Packets within the queue in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = &PacketWrapper> {
+        self.heap.iter()
+    }
+
+    pub fn peek(&self) -> Option<&PacketWrapper> {
+        self.heap.peek()
+    }
+
+    pub fn pop(&mut self) -> Option<PacketWrapper> {
+        let packet = self.heap.pop();
+        if let Some(p) = &packet {
+            self.bytes -= p.content().len();
+            self.taken += 1;
+        }
+        return packet;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PacketQueue;
+    use crate::broker::packet_wrapper::PacketWrapper;
+
+    #[test]
+    fn drops_packets_over_byte_limit() {
+        const MAX_BYTES: Option<usize> = Some(2500);
+        let mut queue = PacketQueue::new();
+        let mut dropped = 0;
+        for _ in 0..5 {
+            if queue.fits(1000, MAX_BYTES) {
+                queue.push(PacketWrapper::new(vec![0; 1000], 0));
+            } else {
+                dropped += 1;
+            }
+            assert!(queue.bytes() <= MAX_BYTES.unwrap());
+        }
+        assert_eq!(dropped, 3);
+        assert_eq!(queue.bytes(), 2000);
+        // popping frees the space
+        queue.pop().unwrap();
+        assert_eq!(queue.bytes(), 1000);
+        assert!(queue.fits(1000, MAX_BYTES));
+        assert!(queue.fits(1000000, None));
+    }
+}
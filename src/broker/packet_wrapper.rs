@@ -1,6 +1,7 @@
 use std::time::{Instant, Duration};
 use std::ops::Add;
 use std::cmp::{Ord, Ordering};
+use crate::packet::PacketHeader;
 
 /// Structure that stores data temporally before they are send.
 pub struct PacketWrapper {
@@ -11,12 +12,30 @@ pub struct PacketWrapper {
 impl PacketWrapper {
     pub fn new(content: Vec<u8>, send_in_millis: u32) -> PacketWrapper {
         let send_at = Instant::now().add(Duration::from_millis(send_in_millis as u64));
+        return PacketWrapper::new_at(content, send_at);
+    }
+
+    pub fn new_at(content: Vec<u8>, send_at: Instant) -> PacketWrapper {
         return PacketWrapper {
             content,
             send_at,
         };
     }
 
+    /// Send the packet not sooner than at `earliest`.
+    pub fn postpone(&mut self, earliest: Instant) {
+        self.send_at = Instant::max(self.send_at, earliest);
+    }
+
+    /// Send the packet later `by` the duration.
+    pub fn delay(&mut self, by: Duration) {
+        self.send_at += by;
+    }
+
+    pub fn send_at(&self) -> Instant {
+        self.send_at
+    }
+
     pub fn send_in(&self) -> Duration {
         self.send_at
             .checked_duration_since(Instant::now())
@@ -24,23 +43,29 @@ impl PacketWrapper {
     }
 
     pub fn should_be_send(&self) -> bool {
-        self.send_at < Instant::now()
+        self.send_at <= Instant::now()
     }
 
     pub fn content(&self) -> &Vec<u8> {
         &self.content
     }
+
+    /// Mark the packet as passed through the congested network.
+    pub fn mark_congestion(&mut self) {
+        PacketHeader::mark_congestion(&mut self.content);
+    }
 }
 
+/// Packets are ordered in reverse, so the max-heap returns the packet that should be send first.
 impl Ord for PacketWrapper {
     fn cmp(&self, other: &Self) -> Ordering {
-        return self.send_at.cmp(&other.send_at);
+        return other.send_at.cmp(&self.send_at);
     }
 }
 
 impl PartialOrd for PacketWrapper {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        return self.send_at.partial_cmp(&other.send_at);
+        return Some(self.cmp(other));
     }
 }
 
@@ -50,4 +75,20 @@ impl PartialEq for PacketWrapper {
     }
 }
 
-impl Eq for PacketWrapper {}
\ No newline at end of file
+impl Eq for PacketWrapper {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BinaryHeap;
+    use super::PacketWrapper;
+
+    #[test]
+    fn heap_returns_earliest_packet() {
+        let mut heap = BinaryHeap::new();
+        heap.push(PacketWrapper::new(vec![2], 20));
+        heap.push(PacketWrapper::new(vec![1], 10));
+        heap.push(PacketWrapper::new(vec![3], 30));
+        let order = std::iter::from_fn(|| heap.pop()).map(|p| p.content()[0]).collect::<Vec<u8>>();
+        assert_eq!(order, vec![1, 2, 3]);
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Limits how far can packet move in the delivery order.
+/// Packet is never send before the packets, that arrived `size` or more positions before it.
+pub struct ReorderWindow {
+    size: usize,
+    /// Send times of the last `size` packets.
+    recent: VecDeque<Instant>,
+    /// Latest send time of the packets, that left the window.
+    released: Option<Instant>,
+}
+
+impl ReorderWindow {
+    pub fn new(size: usize) -> Self {
+        return ReorderWindow {
+            size,
+            recent: VecDeque::with_capacity(size),
+            released: None,
+        };
+    }
+
+    /// Earliest time at which the following packet can be send.
+    pub fn earliest(&self) -> Option<Instant> {
+        // strictly later, so the packets with the same time are not swapped
+        return self.released.map(|released| released + Duration::from_micros(1));
+    }
+
+    /// Record packet, that is send at `send_at`.
+    pub fn push(&mut self, send_at: Instant) {
+        self.recent.push_back(send_at);
+        if self.recent.len() >= self.size {
+            let oldest = self.recent.pop_front().expect("Reorder window is empty");
+            self.released = Some(match self.released {
+                Some(released) => Instant::max(released, oldest),
+                None => oldest,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use super::ReorderWindow;
+
+    #[test]
+    fn packet_is_not_send_before_packets_out_of_window() {
+        let now = Instant::now();
+        let mut window = ReorderWindow::new(2);
+        assert_eq!(window.earliest(), None);
+        window.push(now + Duration::from_millis(100));
+        assert_eq!(window.earliest(), None);
+        window.push(now + Duration::from_millis(10));
+        assert!(window.earliest().unwrap() > now + Duration::from_millis(100));
+        window.push(now + Duration::from_millis(200));
+        assert!(window.earliest().unwrap() > now + Duration::from_millis(100));
+        window.push(now + Duration::from_millis(150));
+        assert!(window.earliest().unwrap() > now + Duration::from_millis(200));
+    }
+}
@@ -1,9 +1,62 @@
 use std::net::SocketAddr;
 use crate::loggable::Loggable;
+use crate::packet::{ErrorReason, InitPacket, PacketHeader};
 use std::num::Wrapping;
+use std::cmp::{max, min};
+use std::fmt::{self, Display, Formatter};
+use std::io::ErrorKind;
+
+/// Errors that prevent the transfer.
+#[derive(Debug, PartialEq)]
+pub enum TransferError {
+    /// Packet of this size can't hold the init packet with the checksum.
+    PacketTooSmall(u16), // packet size
+    /// The packet size would have to be lowered below the configured minimum.
+    PacketBelowFloor(u16, u16), // packet size, minimal packet size
+    /// The file can't be accessed.
+    Io(String, ErrorKind), // path, kind of the system error
+    /// The transfer failed during the communication.
+    Failed(String), // reason
+    /// The receiver doesn't know the connection anymore, probably because it restarted.
+    ReceiverGone(u32), // connection id
+    /// The transfer was cancelled by the user.
+    Cancelled(bool), // whether the receiver confirmed it deleted the partial file
+    /// The transfer was cancelled through its control for the reason.
+    CancelledFor(String, bool), // reason, whether the receiver confirmed it deleted the partial file
+    /// The receiver closed the connection by the error packet.
+    ReceiverClosed(ErrorReason), // reason send by the receiver
+}
+
+impl Display for TransferError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return match self {
+            TransferError::PacketTooSmall(size) => write!(f, "Negotiated packet size {}b is too small to carry payload", size),
+            TransferError::PacketBelowFloor(size, floor) => write!(f, "Packet of {}b is smaller than minimum {}b", size, floor),
+            TransferError::Io(path, kind) => write!(f, "Can't access file {}: {}", path, kind),
+            TransferError::Failed(reason) => write!(f, "{}", reason),
+            TransferError::ReceiverGone(id) => write!(f, "Receiver doesn't know connection {}", id),
+            TransferError::Cancelled(true) => write!(f, "Transfer cancelled, the receiver deleted the partial file"),
+            TransferError::Cancelled(false) => write!(f, "Transfer cancelled, the receiver didn't confirm the cleanup"),
+            TransferError::CancelledFor(reason, true) => write!(f, "Transfer cancelled ({}), the receiver deleted the partial file", reason),
+            TransferError::CancelledFor(reason, false) => write!(f, "Transfer cancelled ({}), the receiver didn't confirm the cleanup", reason),
+            TransferError::ReceiverClosed(reason) => write!(f, "Receiver closed the connection because of {}", reason),
+        };
+    }
+}
+
+impl From<String> for TransferError {
+    fn from(reason: String) -> Self {
+        return TransferError::Failed(reason);
+    }
+}
+
+/// Largest window, that covers less than half of the sequence numbers.
+/// Bigger windows would make the acknowledge of the previous window look like one within the current window.
+pub const MAX_WINDOW_SIZE: u16 = 32767;
 
 /// Properties that does not change during transmission.
 /// The received and sender agree on them beforehand.
+#[derive(Clone, Debug)]
 pub struct ConnectionProperties {
     /// Connection identifier.
     pub id: u32,
@@ -28,6 +81,30 @@ impl ConnectionProperties {
         }
     }
 
+    /// Smallest packet, that holds the init packet with the checksum of `checksum_size` and some data.
+    pub fn min_packet_size(checksum_size: u16) -> usize {
+        return PacketHeader::bin_size() + 7 + checksum_size as usize;
+    }
+
+    /// Agree on the connection properties between the `local` and `remote` side.
+    /// It uses the smaller packet and window size and the bigger checksum size, the window has at least one packet.
+    /// The window is clamped to the `MAX_WINDOW_SIZE`, so the sequence numbers are unambiguous.
+    pub fn negotiate(
+        id: u32,
+        local: &InitPacket,
+        remote: &InitPacket,
+        socket_addr: SocketAddr,
+    ) -> Result<Self, TransferError> {
+        let window_size = min(local.window_size, remote.window_size).clamp(1, MAX_WINDOW_SIZE);
+        let packet_size = min(local.packet_size, remote.packet_size);
+        let checksum_size = max(local.checksum_size, remote.checksum_size);
+        // the init packet with the checksum must fit into the packet
+        if (packet_size as usize) < Self::min_packet_size(checksum_size) {
+            return Err(TransferError::PacketTooSmall(packet_size));
+        }
+        return Ok(ConnectionProperties::new(id, checksum_size, window_size, packet_size, socket_addr));
+    }
+
     /// Check whether the `ack` number is within windows starting at `window_position` and specified by this connection.
     pub fn is_within_window(&self, ack: u16, window_position: u16, log: Box<&dyn Loggable>) -> bool {
         // get window borders
@@ -48,4 +125,77 @@ impl ConnectionProperties {
         ));
         return is_within;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+    use crate::packet::InitPacket;
+    use crate::sender::config::Config;
+    use super::{ConnectionProperties, TransferError, MAX_WINDOW_SIZE};
+
+    fn negotiate(local: (u16, u16, u16), remote: (u16, u16, u16)) -> Result<ConnectionProperties, TransferError> {
+        let addr = SocketAddr::from_str("127.0.0.1:3000").unwrap();
+        ConnectionProperties::negotiate(1, &InitPacket::from(local), &InitPacket::from(remote), addr)
+    }
+
+    #[test]
+    fn takes_smaller_sizes_and_bigger_checksum() {
+        let props = negotiate((15, 1500, 16), (20, 1000, 32)).unwrap();
+        assert_eq!(props.id, 1);
+        assert_eq!(props.window_size, 15);
+        assert_eq!(props.packet_size, 1000);
+        assert_eq!(props.checksum_size, 32);
+    }
+
+    #[test]
+    fn is_symmetric() {
+        let first = negotiate((15, 1500, 16), (20, 1000, 32)).unwrap();
+        let second = negotiate((20, 1000, 32), (15, 1500, 16)).unwrap();
+        assert_eq!(first.window_size, second.window_size);
+        assert_eq!(first.packet_size, second.packet_size);
+        assert_eq!(first.checksum_size, second.checksum_size);
+    }
+
+    #[test]
+    fn window_has_at_least_one_packet() {
+        let props = negotiate((0, 1500, 16), (15, 1500, 16)).unwrap();
+        assert_eq!(props.window_size, 1);
+    }
+
+    #[test]
+    fn window_is_clamped_to_half_of_sequence_space() {
+        let props = negotiate((65535, 1500, 16), (65000, 1500, 16)).unwrap();
+        assert_eq!(props.window_size, MAX_WINDOW_SIZE);
+        let props = negotiate((65535, 1500, 16), (MAX_WINDOW_SIZE - 1, 1500, 16)).unwrap();
+        assert_eq!(props.window_size, MAX_WINDOW_SIZE - 1);
+    }
+
+    #[test]
+    fn window_is_unambiguous_for_large_windows() {
+        let log = Config::new();
+        for requested in [1, 2, 1000, 32766, 32767, 32768, 40000, 65535].iter() {
+            let props = negotiate((*requested, 1500, 16), (65535, 1500, 16)).unwrap();
+            let window = props.window_size;
+            for position in [0, 1, 16384, 32767, 32768, 65000, 65535].iter() {
+                // exactly the window sized range from the position is within the window
+                for ack in 0..=65535u16 {
+                    let distance = ack.wrapping_sub(*position);
+                    let expected = distance < window;
+                    assert_eq!(props.is_within_window(ack, *position, Box::new(&log)), expected);
+                }
+                // the window before can't be confused with the current one
+                for behind in 1..=window {
+                    assert!(!props.is_within_window(position.wrapping_sub(behind), *position, Box::new(&log)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn packet_must_fit_checksum() {
+        assert_eq!(negotiate((15, 1500, 64), (15, 79, 0)).err(), Some(TransferError::PacketTooSmall(79)));
+        assert!(negotiate((15, 1500, 64), (15, 80, 0)).is_ok());
+    }
+}
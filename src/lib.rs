@@ -1,15 +1,42 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
+
+#[cfg(feature = "std")]
 const DATE_FORMAT_STR: &'static str = "%Y-%m-%d %H:%M:%S.%N";
+#[cfg(feature = "std")]
 const BUFFER_SIZE: usize = 65535;
 
+#[cfg(feature = "std")]
 mod loggable;
+#[cfg(feature = "std")]
 use loggable::Loggable;
 
-mod packet;
+// the packet codec uses only core and alloc, so it can be shared without the networking parts
+pub mod packet;
+pub use packet::{checksum, verify};
+
+#[cfg(feature = "std")]
 mod connection_properties;
+#[cfg(feature = "std")]
+pub use connection_properties::{ConnectionProperties, TransferError};
+#[cfg(feature = "std")]
+pub use packet::ErrorReason;
 
+#[cfg(feature = "std")]
+mod seq_ring;
+
+#[cfg(feature = "std")]
 mod socket_manipulation;
-pub use socket_manipulation::recv_with_timeout;
+#[cfg(feature = "std")]
+pub use socket_manipulation::{recv_with_timeout, send_batch, send_parts};
 
+#[cfg(feature = "std")]
 pub mod broker;
+#[cfg(feature = "std")]
 pub mod sender;
+#[cfg(feature = "std")]
 pub mod receiver;
+
+// wiring of all the parts for the tests, that don't use fixed ports
+#[cfg(feature = "testing")]
+pub mod testing;
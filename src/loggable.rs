@@ -5,9 +5,24 @@ use crate::DATE_FORMAT_STR;
 pub trait Loggable {
     fn is_verbose(&self) -> bool;
 
+    fn is_quiet(&self) -> bool {
+        false
+    }
+
+    /// Print message for the user, unless the quiet mode is on.
+    fn info(&self, text: &str) -> () {
+        if !self.is_quiet() {
+            println!("{}", text);
+        }
+    }
+
+    fn log(&self, text: &str) -> () {
+        println!("{}: {}", OffsetDateTime::now_utc().format(DATE_FORMAT_STR),text);
+    }
+
     fn vlog(&self, text: &str) -> () {
         if self.is_verbose() {
-            println!("{}: {}", OffsetDateTime::now_utc().format(DATE_FORMAT_STR),text);
+            self.log(text);
         }
     }
 }
@@ -0,0 +1,73 @@
+use core::cmp::min;
+use super::{ToBin, Flag, ParsingError, PacketHeader};
+
+/// Checksum of the ack packet is at most this long, whatever the checksum size of the connection is.
+const ACK_CHECKSUM_SIZE: usize = 4;
+
+/// Compact acknowledge of the received data.
+/// It carries only the acknowledge number and a short checksum, so it is smaller than the data packet.
+#[derive(Debug)]
+pub struct AckPacket {
+    pub header: PacketHeader,
+}
+
+impl ToBin for AckPacket {
+    fn bin_size(&self) -> usize {
+        return self.header.bin_size();
+    }
+
+    fn to_bin_buff(&self, buff: &mut [u8]) -> usize {
+        return self.header.to_bin_buff(buff);
+    }
+
+    fn from_bin(memory: &[u8]) -> Result<Self, ParsingError> {
+        Ok(Self {
+            header: PacketHeader::from_bin(memory)?,
+        })
+    }
+}
+
+impl AckPacket {
+    pub fn new(connection_id: u32, ack: u16) -> Self {
+        return AckPacket {
+            header: PacketHeader {
+                id: connection_id,
+                seq: 0,
+                ack,
+                flag: Flag::Ack,
+            },
+        };
+    }
+
+    /// Size of the checksum of the ack packet in the connection with the `checksum_size`.
+    pub fn checksum_size(checksum_size: usize) -> usize {
+        return min(checksum_size, ACK_CHECKSUM_SIZE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packet::{AckPacket, DataPacket, Packet};
+
+    #[test]
+    fn ack_is_smaller_than_data_packet() {
+        let ack = Packet::from(AckPacket::new(7, 3)).to_bin(16);
+        let data = Packet::from(DataPacket::new_receiver(7, 0, 3)).to_bin(16);
+        assert_eq!(ack.len(), 9 + 4);
+        assert!(ack.len() < data.len());
+        match Packet::from_bin(&ack, 16) {
+            Ok(Packet::Ack(p)) => {
+                assert_eq!(p.header.id, 7);
+                assert_eq!(p.header.ack, 3);
+            }
+            p => panic!("{:?}", p),
+        };
+    }
+
+    #[test]
+    fn short_checksum_is_kept() {
+        let ack = Packet::from(AckPacket::new(7, 3)).to_bin(2);
+        assert_eq!(ack.len(), 9 + 2);
+        assert!(Packet::from_bin(&ack, 2).is_ok());
+    }
+}
@@ -1,3 +1,4 @@
+use alloc::{vec, vec::Vec};
 use crate::packet::{ToBin, ParsingError};
 
 pub struct Checksum {
@@ -51,11 +52,54 @@ impl Checksum {
         }
     }
 
+    /// Compute the checksum of the packet, that consists of the `parts` following each other.
+    pub fn from_packet_parts(parts: &[&[u8]], checksum_size: usize) -> Self {
+        let mut buffer = vec![0; checksum_size];
+
+        if checksum_size > 0 {
+            let mut position = 0;
+            for byte in parts.iter().flat_map(|part| part.iter()) {
+                buffer[position] ^= byte;
+                position = (position + 1) % checksum_size;
+            }
+        }
+
+        Self {
+            size: checksum_size,
+            checksum: buffer
+        }
+    }
+
     pub fn is_same(&self, second: &Self) -> bool {
         return self.size == second.size && self.checksum == second.checksum;
     }
 }
 
+/// Compute the checksum of `size` bytes for the `data`.
+/// Bytes of the `data` are split into blocks of `size` bytes, that are XORed together.
+///
+/// ```
+/// let sum = udp_transfer::checksum(&[1, 2, 8], 2);
+/// assert_eq!(sum, vec![1 ^ 8, 2]);
+/// ```
+pub fn checksum(data: &[u8], size: usize) -> Vec<u8> {
+    return Checksum::from_packet_content(data, size).checksum;
+}
+
+/// Verify the `data` matches the `expected` checksum.
+/// The size of the checksum is the length of the `expected` checksum.
+///
+/// Captured packet contains the checksum in its last bytes:
+/// ```
+/// // header of the end packet for connection 1 and 4 bytes of checksum
+/// let packet = [0, 0, 0, 1, 0, 0, 0, 0, 8, 8, 0, 0, 1];
+/// let (content, sum) = packet.split_at(packet.len() - 4);
+/// assert!(udp_transfer::verify(content, sum));
+/// assert!(!udp_transfer::verify(&packet[1..9], sum));
+/// ```
+pub fn verify(data: &[u8], expected: &[u8]) -> bool {
+    return checksum(data, expected.len()) == expected;
+}
 
 #[cfg(test)]
 mod tests {
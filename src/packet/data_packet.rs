@@ -1,4 +1,7 @@
-use super::{ToBin, Flag, ParsingError, PacketHeader};
+use alloc::vec::Vec;
+use byteorder::{NetworkEndian, ByteOrder};
+use alloc::vec;
+use super::{ToBin, Flag, ParsingError, PacketHeader, Checksum};
 
 #[derive(Debug)]
 pub struct DataPacket {
@@ -21,7 +24,21 @@ impl ToBin for DataPacket {
     fn from_bin(memory: &[u8]) -> Result<Self, ParsingError> {
         let header = PacketHeader::from_bin(memory)?;
         let header_size = header.bin_size();
-        let data = Vec::from(&memory[header_size..]);
+        let data = match header.flag {
+            // padded data ends with the length of the real data
+            Flag::Padded => {
+                if memory.len() < header_size + 2 {
+                    return Err(ParsingError::InvalidSize(header_size + 2, memory.len()));
+                }
+                let length_position = memory.len() - 2;
+                let length = NetworkEndian::read_u16(&memory[length_position..]) as usize;
+                if header_size + length > length_position {
+                    return Err(ParsingError::InvalidSize(header_size + length + 2, memory.len()));
+                }
+                Vec::from(&memory[header_size..header_size + length])
+            }
+            _ => Vec::from(&memory[header_size..]),
+        };
 
         Ok(Self {
             header,
@@ -43,6 +60,27 @@ impl DataPacket {
         };
     }
 
+    /// Create data packet, that has `size` bytes of data on the wire.
+    /// The `data` are followed by zeros and their length, the receiver reads only the `data`.
+    pub fn new_padded(data: Vec<u8>, connection_id: u32, seq: u16, ack: u16, size: usize) -> Self {
+        let length = data.len() as u16;
+        let mut packet = Self::new(data, connection_id, seq, ack);
+        packet.header.flag = Flag::Padded;
+        packet.data.resize(size - 2, 0);
+        packet.data.extend_from_slice(&length.to_be_bytes());
+        return packet;
+    }
+
+    /// Encode the `header` and the checksum of the data packet with `data`, without copying the `data`.
+    /// The packet on the wire consists of the returned header, the `data`, and the returned checksum.
+    pub fn framing(header: &PacketHeader, data: &[u8], checksum_size: usize) -> (Vec<u8>, Vec<u8>) {
+        let mut header_bin = vec![0; header.bin_size()];
+        header.to_bin_buff(&mut header_bin);
+        let mut checksum_bin = vec![0; checksum_size];
+        Checksum::from_packet_parts(&[&header_bin, data], checksum_size).to_bin_buff(&mut checksum_bin);
+        return (header_bin, checksum_bin);
+    }
+
     pub fn new_receiver(connection_id: u32, seq: u16, ack: u16) -> Self {
         return Self::new(Vec::new(), connection_id, seq, ack);
     }
@@ -55,4 +93,31 @@ impl From<(Vec<u8>, u32, u16, u16)> for DataPacket {
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use crate::packet::{DataPacket, Flag, Packet, PacketHeader};
+
+    #[test]
+    fn padded_data_are_truncated() {
+        let packet = Packet::from(DataPacket::new_padded(vec![1, 2, 3], 1, 5, 0, 10));
+        assert_eq!(packet.bin_size(), 9 + 10);
+        let mut memory = vec![0; 100];
+        let wrote = packet.to_bin_buff(&mut memory, 2);
+        match Packet::from_bin(&memory[..wrote], 2) {
+            Ok(Packet::Data(p)) => {
+                assert_eq!(p.header.flag, Flag::Padded);
+                assert_eq!(p.header.seq, 5);
+                assert_eq!(p.data, vec![1, 2, 3]);
+            }
+            p => panic!("{:?}", p),
+        };
+    }
+
+    #[test]
+    fn framing_matches_packet() {
+        let data = vec![1, 2, 3, 4, 5, 6, 7, 11, 13, 17];
+        let header = PacketHeader { id: 1 << 8, seq: 5, ack: 8, flag: Flag::Data };
+        let (header_bin, checksum_bin) = DataPacket::framing(&header, &data, 4);
+        let framed = [header_bin, data.clone(), checksum_bin].concat();
+        assert_eq!(framed, Packet::from(DataPacket::new(data, 1 << 8, 5, 8)).to_bin(4));
+    }
+}
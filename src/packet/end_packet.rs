@@ -1,22 +1,49 @@
+use byteorder::{NetworkEndian, ByteOrder};
 use super::{ToBin, Flag, ParsingError, PacketHeader};
 
 #[derive(Debug)]
 pub struct EndPacket {
     pub header: PacketHeader,
+    /// CRC-32 of the whole send content, the end packet without it is valid as well.
+    pub digest: Option<u32>,
+    /// Whether the receiver stored the content with the digest on the disk, it follows the digest in the answer.
+    pub stored: bool,
 }
 
 impl ToBin for EndPacket {
     fn bin_size(&self) -> usize {
-        return self.header.bin_size();
+        return match (self.digest, self.stored) {
+            (Some(_), true) => self.header.bin_size() + 5,
+            (Some(_), false) => self.header.bin_size() + 4,
+            (None, _) => self.header.bin_size(),
+        };
     }
 
     fn to_bin_buff(&self, buff: &mut [u8]) -> usize {
-        return self.header.to_bin_buff(buff);
+        let after_header = self.header.to_bin_buff(buff);
+        if let Some(digest) = self.digest {
+            NetworkEndian::write_u32(&mut buff[after_header..after_header + 4], digest);
+            if self.stored {
+                buff[after_header + 4] = 1;
+                return after_header + 5;
+            }
+            return after_header + 4;
+        }
+        return after_header;
     }
 
     fn from_bin(memory: &[u8]) -> Result<Self, ParsingError> {
+        let header = PacketHeader::from_bin(memory)?;
+        let header_size = header.bin_size();
+        let digest = match memory.len() >= header_size + 4 {
+            true => Some(NetworkEndian::read_u32(&memory[header_size..header_size + 4])),
+            false => None,
+        };
+        let stored = memory.len() >= header_size + 5 && memory[header_size + 4] != 0;
         Ok(Self {
-            header: PacketHeader::from_bin(memory)?,
+            header,
+            digest,
+            stored,
         })
     }
 }
@@ -30,6 +57,8 @@ impl EndPacket {
                 ack: seq_num,
                 flag: Flag::End,
             },
+            digest: None,
+            stored: false,
         };
     }
 }
@@ -39,3 +68,46 @@ impl From<(u32, u16)> for EndPacket {
         return Self::new(connection_id, seq_num);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::packet::{EndPacket, Packet};
+
+    #[test]
+    fn digest_round_trip() {
+        let mut packet = EndPacket::new(7, 3);
+        packet.digest = Some(0xCBF43926);
+        let bin = Packet::from(packet).to_bin(4);
+        assert_eq!(bin.len(), 9 + 4 + 4);
+        match Packet::from_bin(&bin, 4) {
+            Ok(Packet::End(x)) => assert_eq!(x.digest, Some(0xCBF43926)),
+            _ => panic!()
+        };
+    }
+
+    #[test]
+    fn stored_flag_follows_digest() {
+        let mut packet = EndPacket::new(7, 3);
+        packet.digest = Some(0xCBF43926);
+        packet.stored = true;
+        let bin = Packet::from(packet).to_bin(4);
+        assert_eq!(bin.len(), 9 + 5 + 4);
+        match Packet::from_bin(&bin, 4) {
+            Ok(Packet::End(x)) => {
+                assert_eq!(x.digest, Some(0xCBF43926));
+                assert!(x.stored);
+            }
+            _ => panic!()
+        };
+    }
+
+    #[test]
+    fn without_digest() {
+        let bin = Packet::from(EndPacket::new(7, 3)).to_bin(4);
+        assert_eq!(bin.len(), 9 + 4);
+        match Packet::from_bin(&bin, 4) {
+            Ok(Packet::End(x)) => assert_eq!(x.digest, None),
+            _ => panic!()
+        };
+    }
+}
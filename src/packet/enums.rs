@@ -1,3 +1,4 @@
+use alloc::{vec, vec::Vec};
 use crate::packet::enums::ParsingError::InvalidFlag;
 
 #[derive(Debug, PartialEq)]
@@ -29,6 +30,10 @@ pub enum Flag {
     Data,
     Error,
     End,
+    Gap,
+    Padded,
+    Ack,
+    Metadata,
 }
 
 impl ToBin for Flag {
@@ -46,6 +51,10 @@ impl ToBin for Flag {
             0x2 => Ok(Flag::Data),
             0x4 => Ok(Flag::Error),
             0x8 => Ok(Flag::End),
+            0x10 => Ok(Flag::Gap),
+            0x20 => Ok(Flag::Padded),
+            0x40 => Ok(Flag::Ack),
+            0x3 => Ok(Flag::Metadata),
             _ => Err(InvalidFlag(val[0])),
         }
     }
@@ -59,6 +68,10 @@ impl Flag {
             Flag::Data => 0x2,
             Flag::Error => 0x4,
             Flag::End => 0x8,
+            Flag::Gap => 0x10,
+            Flag::Padded => 0x20,
+            Flag::Ack => 0x40,
+            Flag::Metadata => 0x3,
         }
     }
 }
@@ -1,3 +1,4 @@
+use core::fmt::{self, Display, Formatter};
 use super::{ToBin, Flag, ParsingError, PacketHeader};
 
 #[derive(Debug)]
@@ -21,6 +22,98 @@ impl ToBin for ErrorPacket {
     }
 }
 
+/// Why the connection was closed by the error packet.
+/// The reason is stored in the sequence number, that error packets don't use otherwise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorReason {
+    /// The reason is not known, like in the error packets without any.
+    Unspecified,
+    /// The receiver doesn't know the connection (for example after the restart).
+    UnknownConnection,
+    /// The sender cancels the transfer, the receiver confirms it by the same reason.
+    Abort,
+    /// Nothing came in the connection for too long.
+    Timeout,
+    /// The end packet came before all the data.
+    MissingData,
+    /// The file would be bigger than the receiver allows.
+    FileTooBig,
+    /// The receiver needed the memory of the connection for the other ones.
+    OutOfMemory,
+    /// The stored content doesn't match the digest of the sender.
+    DigestMismatch,
+    /// The receiver can't write into the file, because the disk is full.
+    DiskFull,
+    /// The receiver can't write into the file for other reason.
+    WriteFailed,
+    /// The other side closed the connection by the error packet first.
+    ErrorReceived,
+    /// The receiver has no file to continue from the offset.
+    MissingTarget,
+    /// The receiver can't accept another connection now.
+    Busy,
+}
+
+impl ErrorReason {
+    /// Code of the reason in the sequence number of the packet.
+    pub fn code(&self) -> u16 {
+        return match self {
+            ErrorReason::Unspecified => 0,
+            ErrorReason::UnknownConnection => 1,
+            ErrorReason::Abort => 2,
+            ErrorReason::Timeout => 3,
+            ErrorReason::MissingData => 4,
+            ErrorReason::FileTooBig => 5,
+            ErrorReason::OutOfMemory => 6,
+            ErrorReason::DigestMismatch => 7,
+            ErrorReason::DiskFull => 8,
+            ErrorReason::WriteFailed => 9,
+            ErrorReason::ErrorReceived => 10,
+            ErrorReason::MissingTarget => 11,
+            ErrorReason::Busy => 12,
+        };
+    }
+
+    /// Reason with the `code`, the unknown codes are unspecified.
+    pub fn from_code(code: u16) -> Self {
+        return match code {
+            1 => ErrorReason::UnknownConnection,
+            2 => ErrorReason::Abort,
+            3 => ErrorReason::Timeout,
+            4 => ErrorReason::MissingData,
+            5 => ErrorReason::FileTooBig,
+            6 => ErrorReason::OutOfMemory,
+            7 => ErrorReason::DigestMismatch,
+            8 => ErrorReason::DiskFull,
+            9 => ErrorReason::WriteFailed,
+            10 => ErrorReason::ErrorReceived,
+            11 => ErrorReason::MissingTarget,
+            12 => ErrorReason::Busy,
+            _ => ErrorReason::Unspecified,
+        };
+    }
+}
+
+impl Display for ErrorReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return f.write_str(match self {
+            ErrorReason::Unspecified => "unspecified error",
+            ErrorReason::UnknownConnection => "unknown connection",
+            ErrorReason::Abort => "abort",
+            ErrorReason::Timeout => "timeout",
+            ErrorReason::MissingData => "end packet with some data left",
+            ErrorReason::FileTooBig => "file size limit",
+            ErrorReason::OutOfMemory => "memory limit",
+            ErrorReason::DigestMismatch => "content doesn't match the digest",
+            ErrorReason::DiskFull => "disk full",
+            ErrorReason::WriteFailed => "write failure",
+            ErrorReason::ErrorReceived => "error packet",
+            ErrorReason::MissingTarget => "no file to continue at the offset",
+            ErrorReason::Busy => "receiver busy",
+        });
+    }
+}
+
 impl ErrorPacket {
     pub fn new(connection_id: u32) -> Self {
         return Self {
@@ -32,6 +125,38 @@ impl ErrorPacket {
             },
         };
     }
+
+    /// Error packet closing the connection for the `reason`.
+    pub fn with_reason(connection_id: u32, reason: ErrorReason) -> Self {
+        let mut packet = Self::new(connection_id);
+        packet.header.seq = reason.code();
+        return packet;
+    }
+
+    /// Why the connection was closed.
+    pub fn reason(&self) -> ErrorReason {
+        return ErrorReason::from_code(self.header.seq);
+    }
+
+    /// Error packet for the connection, that the receiver doesn't know (for example after the restart).
+    pub fn unknown_connection(connection_id: u32) -> Self {
+        return Self::with_reason(connection_id, ErrorReason::UnknownConnection);
+    }
+
+    /// Whether the receiver doesn't know the connection of this packet.
+    pub fn is_unknown_connection(&self) -> bool {
+        return self.reason() == ErrorReason::UnknownConnection;
+    }
+
+    /// Error packet cancelling the transfer, the receiver answers by the same packet after it deleted the partial file.
+    pub fn abort(connection_id: u32) -> Self {
+        return Self::with_reason(connection_id, ErrorReason::Abort);
+    }
+
+    /// Whether this packet cancels the transfer or confirms the cancellation.
+    pub fn is_abort(&self) -> bool {
+        return self.reason() == ErrorReason::Abort;
+    }
 }
 
 impl From<u32> for ErrorPacket {
@@ -39,3 +164,20 @@ impl From<u32> for ErrorPacket {
         return Self::new(connection_id);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::packet::{ErrorPacket, ErrorReason, Packet};
+
+    #[test]
+    fn reason_round_trip() {
+        let bin = Packet::from(ErrorPacket::with_reason(7, ErrorReason::DiskFull)).to_bin(4);
+        match Packet::from_bin(&bin, 4) {
+            Ok(Packet::Error(x)) => assert_eq!(x.reason(), ErrorReason::DiskFull),
+            _ => panic!()
+        };
+        assert_eq!(ErrorPacket::new(7).reason(), ErrorReason::Unspecified);
+        assert!(ErrorPacket::abort(7).is_abort());
+        assert_eq!(ErrorReason::from_code(1000), ErrorReason::Unspecified);
+    }
+}
@@ -0,0 +1,79 @@
+use alloc::vec::Vec;
+use byteorder::{NetworkEndian, ByteOrder};
+use super::{ToBin, Flag, ParsingError, PacketHeader};
+
+/// Report of the parts the receiver is missing.
+/// Every range contains the first and the last missing sequence number.
+#[derive(Debug)]
+pub struct GapPacket {
+    pub header: PacketHeader,
+    pub ranges: Vec<(u16, u16)>,
+}
+
+impl ToBin for GapPacket {
+    fn bin_size(&self) -> usize {
+        return self.header.bin_size() + 4 * self.ranges.len();
+    }
+
+    fn to_bin_buff(&self, buff: &mut [u8]) -> usize {
+        let mut wrote = self.header.to_bin_buff(buff);
+        for (first, last) in &self.ranges {
+            NetworkEndian::write_u16(&mut buff[wrote..wrote + 2], *first);
+            NetworkEndian::write_u16(&mut buff[wrote + 2..wrote + 4], *last);
+            wrote += 4;
+        }
+        return wrote;
+    }
+
+    fn from_bin(memory: &[u8]) -> Result<Self, ParsingError> {
+        let header = PacketHeader::from_bin(memory)?;
+        let content = &memory[header.bin_size()..];
+        let incomplete = content.len() % 4;
+        if incomplete > 0 {
+            return Err(ParsingError::InvalidSize(memory.len() + 4 - incomplete, memory.len()));
+        }
+        let ranges = content.chunks(4).map(|range| {
+            (NetworkEndian::read_u16(&range[..2]), NetworkEndian::read_u16(&range[2..]))
+        }).collect();
+
+        Ok(Self {
+            header,
+            ranges,
+        })
+    }
+}
+
+impl GapPacket {
+    pub fn new(connection_id: u32, ack: u16, ranges: Vec<(u16, u16)>) -> Self {
+        return GapPacket {
+            header: PacketHeader {
+                id: connection_id,
+                seq: 0,
+                ack,
+                flag: Flag::Gap,
+            },
+            ranges,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packet::{GapPacket, Packet};
+
+    #[test]
+    fn ranges_are_transferred() {
+        let packet = Packet::from(GapPacket::new(7, 3, vec![(4, 4), (6, 10), (65535, 1)]));
+        let mut memory = vec![0; 100];
+        let wrote = packet.to_bin_buff(&mut memory, 4);
+        assert_eq!(wrote, 9 + 3 * 4 + 4);
+        match Packet::from_bin(&memory[..wrote], 4) {
+            Ok(Packet::Gap(p)) => {
+                assert_eq!(p.header.id, 7);
+                assert_eq!(p.header.ack, 3);
+                assert_eq!(p.ranges, vec![(4, 4), (6, 10), (65535, 1)]);
+            }
+            p => panic!("{:?}", p),
+        };
+    }
+}
@@ -7,6 +7,13 @@ pub struct InitPacket {
     pub window_size: u16,
     pub packet_size: u16,
     pub checksum_size: u16,
+    /// Position in the file where the transferred content starts.
+    /// It is stored only when the packet has room for it, otherwise it is zero.
+    pub offset: u64,
+    /// Number chosen by the receiver, that the sender echoes in its data packets until some is acknowledged.
+    /// In the request it is chosen by the sender, so the receiver recognizes the copies of the same request.
+    /// It is stored only when the packet has room for it, zero means it is not checked.
+    pub nonce: u16,
 }
 
 impl ToBin for InitPacket {
@@ -27,6 +34,13 @@ impl ToBin for InitPacket {
         for val in &mut buff[after_header+6..checksum_start] {
             *val = 0;
         }
+        // smaller packet can't hold the offset, the other side reads it as zero
+        if InitPacket::has_offset_room(self.packet_size, self.checksum_size) {
+            NetworkEndian::write_u64(&mut buff[after_header + 6..after_header + 14], self.offset);
+        }
+        if InitPacket::has_nonce_room(self.packet_size, self.checksum_size) {
+            NetworkEndian::write_u16(&mut buff[after_header + 14..after_header + 16], self.nonce);
+        }
 
         return checksum_start;
     }
@@ -57,14 +71,34 @@ impl InitPacket {
         let window_size = NetworkEndian::read_u16(&memory[header_size..header_size + 2]);
         let packet_size = NetworkEndian::read_u16(&memory[header_size + 2..header_size + 4]);
         let checksum_size = NetworkEndian::read_u16(&memory[header_size + 4..header_size + 6]);
+        let offset = match InitPacket::has_offset_room(packet_size, checksum_size) && memory.len() >= header_size + 14 {
+            true => NetworkEndian::read_u64(&memory[header_size + 6..header_size + 14]),
+            false => 0,
+        };
+        let nonce = match InitPacket::has_nonce_room(packet_size, checksum_size) && memory.len() >= header_size + 16 {
+            true => NetworkEndian::read_u16(&memory[header_size + 14..header_size + 16]),
+            false => 0,
+        };
 
         Ok(Self {
             header,
             window_size,
             packet_size,
             checksum_size,
+            offset,
+            nonce,
         })
     }
+
+    /// Whether the init packet of `packet_size` with `checksum_size` big checksum can hold the offset.
+    pub fn has_offset_room(packet_size: u16, checksum_size: u16) -> bool {
+        return packet_size as usize >= PacketHeader::bin_size() + 14 + checksum_size as usize;
+    }
+
+    /// Whether the init packet of `packet_size` with `checksum_size` big checksum can hold the nonce after the offset.
+    pub fn has_nonce_room(packet_size: u16, checksum_size: u16) -> bool {
+        return packet_size as usize >= PacketHeader::bin_size() + 16 + checksum_size as usize;
+    }
 }
 
 impl InitPacket {
@@ -79,6 +113,8 @@ impl InitPacket {
             window_size,
             packet_size,
             checksum_size,
+            offset: 0,
+            nonce: 0,
         };
     }
 }
@@ -138,6 +174,40 @@ mod tests {
         };
     }
 
+    #[test]
+    fn offset_round_trip() {
+        let mut packet = InitPacket::new(0x8, 0x32, 0x4);
+        packet.offset = 0x0102_0304_0506;
+        let bin = Packet::from(packet).to_bin(0x4);
+        assert_eq!(&bin[15..23], &[0, 0, 1, 2, 3, 4, 5, 6]);
+        match Packet::from_bin(&bin, 4) {
+            Ok(Packet::Init(x)) => assert_eq!(x.offset, 0x0102_0304_0506),
+            _ => panic!()
+        };
+        // small packet has no room for the offset
+        assert!(InitPacket::has_offset_room(27, 4));
+        assert!(!InitPacket::has_offset_room(26, 4));
+    }
+
+    #[test]
+    fn nonce_round_trip() {
+        let mut packet = InitPacket::new(0x8, 0x32, 0x4);
+        packet.offset = 7;
+        packet.nonce = 0xABCD;
+        let bin = Packet::from(packet).to_bin(0x4);
+        assert_eq!(&bin[23..25], &[0xAB, 0xCD]);
+        match Packet::from_bin(&bin, 4) {
+            Ok(Packet::Init(x)) => {
+                assert_eq!(x.offset, 7);
+                assert_eq!(x.nonce, 0xABCD);
+            }
+            _ => panic!()
+        };
+        // small packet has no room for the nonce
+        assert!(InitPacket::has_nonce_room(29, 4));
+        assert!(!InitPacket::has_nonce_room(28, 4));
+    }
+
     #[test]
     fn wrong_checksum() {
         let data = vec![
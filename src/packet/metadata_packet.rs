@@ -0,0 +1,130 @@
+use alloc::string::String;
+use byteorder::{NetworkEndian, ByteOrder};
+use super::{ToBin, Flag, ParsingError, PacketHeader};
+
+/// Size of the metadata before the path.
+const FIXED_SIZE: usize = 28;
+
+/// Description of the send file, that the receiver applies to the output file.
+/// The receiver answers with the same packet.
+#[derive(Debug, Clone)]
+pub struct MetadataPacket {
+    pub header: PacketHeader,
+    /// Number of bytes of the content, that will be transferred.
+    pub size: u64,
+    /// Permissions of the file, zero when they are not known.
+    pub mode: u32,
+    /// Seconds of the last modification since the Unix epoch.
+    pub mtime_secs: u64,
+    /// Nanoseconds of the last modification within the second.
+    pub mtime_nanos: u32,
+    /// CRC-32 of the content, that will be transferred.
+    pub crc: u32,
+    /// Original path of the file.
+    pub path: String,
+}
+
+impl ToBin for MetadataPacket {
+    fn bin_size(&self) -> usize {
+        return self.header.bin_size() + FIXED_SIZE + self.path.len();
+    }
+
+    fn to_bin_buff(&self, buff: &mut [u8]) -> usize {
+        let after_header = self.header.to_bin_buff(buff);
+        NetworkEndian::write_u64(&mut buff[after_header..after_header + 8], self.size);
+        NetworkEndian::write_u32(&mut buff[after_header + 8..after_header + 12], self.mode);
+        NetworkEndian::write_u64(&mut buff[after_header + 12..after_header + 20], self.mtime_secs);
+        NetworkEndian::write_u32(&mut buff[after_header + 20..after_header + 24], self.mtime_nanos);
+        NetworkEndian::write_u32(&mut buff[after_header + 24..after_header + 28], self.crc);
+        let path_start = after_header + FIXED_SIZE;
+        buff[path_start..path_start + self.path.len()].copy_from_slice(self.path.as_bytes());
+        return path_start + self.path.len();
+    }
+
+    fn from_bin(memory: &[u8]) -> Result<Self, ParsingError> {
+        let header = PacketHeader::from_bin(memory)?;
+        let header_size = header.bin_size();
+        if memory.len() < header_size + FIXED_SIZE {
+            return Err(ParsingError::InvalidSize(header_size + FIXED_SIZE, memory.len()));
+        }
+        Ok(Self {
+            header,
+            size: NetworkEndian::read_u64(&memory[header_size..header_size + 8]),
+            mode: NetworkEndian::read_u32(&memory[header_size + 8..header_size + 12]),
+            mtime_secs: NetworkEndian::read_u64(&memory[header_size + 12..header_size + 20]),
+            mtime_nanos: NetworkEndian::read_u32(&memory[header_size + 20..header_size + 24]),
+            crc: NetworkEndian::read_u32(&memory[header_size + 24..header_size + 28]),
+            path: String::from_utf8_lossy(&memory[header_size + FIXED_SIZE..]).into_owned(),
+        })
+    }
+}
+
+impl MetadataPacket {
+    pub fn new(connection_id: u32, size: u64, crc: u32) -> Self {
+        return MetadataPacket {
+            header: PacketHeader {
+                id: connection_id,
+                seq: 0,
+                ack: 0,
+                flag: Flag::Metadata,
+            },
+            size,
+            mode: 0,
+            mtime_secs: 0,
+            mtime_nanos: 0,
+            crc,
+            path: String::new(),
+        };
+    }
+
+    /// Shorten the path from the start, so the packet is at most `max_size` bytes long.
+    /// Returns false when even the packet without the path doesn't fit.
+    pub fn fit_into(&mut self, max_size: usize) -> bool {
+        let room = match max_size.checked_sub(self.header.bin_size() + FIXED_SIZE) {
+            Some(room) => room,
+            None => return false,
+        };
+        if self.path.len() > room {
+            let mut cut = self.path.len() - room;
+            while !self.path.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.path = String::from(&self.path[cut..]);
+        }
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::packet::{MetadataPacket, Packet};
+
+    #[test]
+    fn metadata_round_trip() {
+        let mut packet = MetadataPacket::new(7, 1234, 0xCBF43926);
+        packet.mode = 0o640;
+        packet.mtime_secs = 1_000_000_000;
+        packet.mtime_nanos = 5;
+        packet.path = String::from("dir/file.txt");
+        let bin = Packet::from(packet).to_bin(4);
+        assert_eq!(bin.len(), 9 + 28 + 12 + 4);
+        match Packet::from_bin(&bin, 4) {
+            Ok(Packet::Metadata(p)) => {
+                assert_eq!(p.header.id, 7);
+                assert_eq!((p.size, p.mode, p.crc), (1234, 0o640, 0xCBF43926));
+                assert_eq!((p.mtime_secs, p.mtime_nanos), (1_000_000_000, 5));
+                assert_eq!(p.path, "dir/file.txt");
+            }
+            p => panic!("{:?}", p),
+        };
+    }
+
+    #[test]
+    fn path_is_shortened_from_start() {
+        let mut packet = MetadataPacket::new(7, 0, 0);
+        packet.path = String::from("dir/žluťoučký.txt");
+        assert!(packet.fit_into(9 + 28 + 12));
+        assert_eq!(packet.path, "oučký.txt");
+        assert!(!packet.fit_into(9 + 27));
+    }
+}
@@ -4,8 +4,12 @@ mod init_packet;
 mod data_packet;
 mod error_packet;
 mod end_packet;
+mod gap_packet;
+mod ack_packet;
+mod metadata_packet;
 mod packet;
 mod checksum;
+mod crc32;
 
 
 pub use enums::{ParsingError, Flag};
@@ -13,7 +17,11 @@ pub use enums::ToBin;
 pub use packet_header::PacketHeader;
 pub use init_packet::InitPacket;
 pub use data_packet::DataPacket;
-pub use error_packet::ErrorPacket;
+pub use error_packet::{ErrorPacket, ErrorReason};
 pub use end_packet::EndPacket;
+pub use gap_packet::GapPacket;
+pub use ack_packet::AckPacket;
+pub use metadata_packet::MetadataPacket;
 pub use packet::Packet;
-pub use checksum::Checksum;
+pub use checksum::{Checksum, checksum, verify};
+pub use crc32::{Crc32, crc32};
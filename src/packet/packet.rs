@@ -1,5 +1,6 @@
+use alloc::{vec, vec::Vec};
 use super::{ToBin, Flag, ParsingError, PacketHeader, Checksum};
-use super::{InitPacket, DataPacket, ErrorPacket, EndPacket};
+use super::{InitPacket, DataPacket, ErrorPacket, EndPacket, GapPacket, AckPacket, MetadataPacket};
 
 #[derive(Debug)]
 pub enum Packet {
@@ -7,6 +8,9 @@ pub enum Packet {
     Data(DataPacket),
     Error(ErrorPacket),
     End(EndPacket),
+    Gap(GapPacket),
+    Ack(AckPacket),
+    Metadata(MetadataPacket),
 }
 
 impl ToBin for Packet {
@@ -16,6 +20,9 @@ impl ToBin for Packet {
             Self::Data(x) => x.bin_size(),
             Self::Error(x) => x.bin_size(),
             Self::End(x) => x.bin_size(),
+            Self::Gap(x) => x.bin_size(),
+            Self::Ack(x) => x.bin_size(),
+            Self::Metadata(x) => x.bin_size(),
         }
     }
 
@@ -25,6 +32,9 @@ impl ToBin for Packet {
             Self::Data(x) => x.to_bin_buff(buff),
             Self::Error(x) => x.to_bin_buff(buff),
             Self::End(x) => x.to_bin_buff(buff),
+            Self::Gap(x) => x.to_bin_buff(buff),
+            Self::Ack(x) => x.to_bin_buff(buff),
+            Self::Metadata(x) => x.to_bin_buff(buff),
         }
     }
 
@@ -35,7 +45,10 @@ impl ToBin for Packet {
             Flag::Init => Self::Init(InitPacket::from_bin(memory)?),
             Flag::Error => Self::Error(ErrorPacket::from_bin(memory)?),
             Flag::End => Self::End(EndPacket::from_bin(memory)?),
-            Flag::Data => Self::Data(DataPacket::from_bin(memory)?),
+            Flag::Data | Flag::Padded => Self::Data(DataPacket::from_bin(memory)?),
+            Flag::Gap => Self::Gap(GapPacket::from_bin(memory)?),
+            Flag::Ack => Self::Ack(AckPacket::from_bin(memory)?),
+            Flag::Metadata => Self::Metadata(MetadataPacket::from_bin(memory)?),
             Flag::None => return Err(ParsingError::InvalidFlag(memory[flag_pos])),
         })
     }
@@ -48,6 +61,9 @@ impl Packet {
             Packet::Data(p) => &p.header,
             Packet::Error(p) => &p.header,
             Packet::End(p) => &p.header,
+            Packet::Gap(p) => &p.header,
+            Packet::Ack(p) => &p.header,
+            Packet::Metadata(p) => &p.header,
         };
     }
 
@@ -55,14 +71,24 @@ impl Packet {
         return ToBin::bin_size(self);
     }
 
+    /// Size of the checksum of this packet in the connection with the `checksum_size`.
+    fn own_checksum_size(&self, checksum_size: usize) -> usize {
+        return match self {
+            Packet::Ack(_) => AckPacket::checksum_size(checksum_size),
+            _ => checksum_size,
+        };
+    }
+
     #[allow(dead_code)]
     pub fn to_bin(&self, checksum: usize) -> Vec<u8> {
+        let checksum = self.own_checksum_size(checksum);
         let mut memory = vec![0; self.bin_size() + checksum];
         self.to_bin_buff(&mut memory, checksum);
         return memory;
     }
 
     pub fn to_bin_buff(&self, memory: &mut [u8], checksum_size: usize) -> usize {
+        let checksum_size = self.own_checksum_size(checksum_size);
         let data_end = self.bin_size();
         let packet_size = data_end + checksum_size;
         debug_assert!(memory.len() >= packet_size);
@@ -76,6 +102,11 @@ impl Packet {
     }
 
     pub fn from_bin(memory: &[u8], checksum: usize) -> Result<Self, ParsingError> {
+        // the ack packet has its own checksum size
+        let checksum = match memory.get(PacketHeader::flag_position()) {
+            Some(flag) if *flag == Flag::Ack.value() => AckPacket::checksum_size(checksum),
+            _ => checksum,
+        };
         if checksum + PacketHeader::bin_size() > memory.len() {
             return Err(ParsingError::InvalidSize(checksum + PacketHeader::bin_size(), memory.len()));
         }
@@ -121,6 +152,24 @@ impl From<EndPacket> for Packet {
     }
 }
 
+impl From<GapPacket> for Packet {
+    fn from(packet: GapPacket) -> Self {
+        Packet::Gap(packet)
+    }
+}
+
+impl From<AckPacket> for Packet {
+    fn from(packet: AckPacket) -> Self {
+        Packet::Ack(packet)
+    }
+}
+
+impl From<MetadataPacket> for Packet {
+    fn from(packet: MetadataPacket) -> Self {
+        Packet::Metadata(packet)
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -322,4 +371,21 @@ mod tests {
             assert_eq!(actual, expected);
         }
     }
+
+    mod congestion {
+        use crate::packet::{DataPacket, PacketHeader, Packet, ParsingError};
+
+        #[test]
+        fn mark_is_outside_checksum() {
+            let mut memory = Packet::from(DataPacket::new(vec![1, 2, 3], 7, 5, 0)).to_bin(4);
+            PacketHeader::mark_congestion(&mut memory);
+            assert_eq!(Packet::from_bin(&memory, 4).unwrap_err(), ParsingError::InvalidFlag(0x82));
+            assert!(PacketHeader::take_congestion(&mut memory));
+            assert!(!PacketHeader::take_congestion(&mut memory));
+            match Packet::from_bin(&memory, 4) {
+                Ok(Packet::Data(packet)) => assert_eq!(packet.data, vec![1, 2, 3]),
+                rest => panic!("{:?}", rest),
+            }
+        }
+    }
 }
@@ -1,6 +1,10 @@
 use byteorder::{NetworkEndian, ByteOrder};
 use super::{ToBin, Flag, ParsingError};
 
+/// Bit of the flag byte, that marks the packet passed through the congested network.
+/// It is set after the checksum is computed and cleared before the packet is parsed.
+pub const CONGESTION_BIT: u8 = 0x80;
+
 #[derive(Debug, Clone)]
 pub struct PacketHeader {
     pub id: u32,
@@ -46,4 +50,23 @@ impl PacketHeader {
     pub fn flag_position() -> usize {
         return 8;
     }
+
+    /// Mark the serialized packet in the `memory` as congested.
+    pub fn mark_congestion(memory: &mut [u8]) {
+        if let Some(flag) = memory.get_mut(Self::flag_position()) {
+            *flag |= CONGESTION_BIT;
+        }
+    }
+
+    /// Remove the congestion mark from the serialized packet in the `memory`.
+    /// Returns whether the packet was marked.
+    pub fn take_congestion(memory: &mut [u8]) -> bool {
+        return match memory.get_mut(Self::flag_position()) {
+            Some(flag) if *flag & CONGESTION_BIT != 0 => {
+                *flag &= !CONGESTION_BIT;
+                true
+            }
+            _ => false,
+        };
+    }
 }
@@ -1,59 +1,244 @@
-use std::net::SocketAddrV4;
+use std::net::{SocketAddrV4, Ipv4Addr};
 use std::str::FromStr;
-use argparse::{ArgumentParser, StoreTrue, Store};
+use argparse::{ArgumentParser, StoreTrue, Store, StoreOption, Collect};
 use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::net::SocketAddr;
+use std::io;
 use crate::loggable::Loggable;
+use super::events::ReceiverEvent;
+use super::filename::sanitize_filename;
+
+/// When the receiver acknowledges the received data packets.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AckStrategy {
+    /// Every data packet is acknowledged.
+    Immediate,
+    /// Acknowledge is send after every N data packets.
+    EveryN(u16),
+    /// Acknowledge is send after the number of milliseconds from the first not acknowledged packet.
+    Delayed(u32),
+}
+
+impl FromStr for AckStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some("immediate"), None) => Ok(AckStrategy::Immediate),
+            (Some("every"), Some(n)) => match n.parse::<u16>() {
+                Ok(n) if n > 0 => Ok(AckStrategy::EveryN(n)),
+                _ => Err(format!("Invalid number of packets {}", n)),
+            },
+            (Some("delayed"), Some(ms)) => match ms.parse::<u32>() {
+                Ok(ms) => Ok(AckStrategy::Delayed(ms)),
+                Err(_) => Err(format!("Invalid delay {}", ms)),
+            },
+            _ => Err(format!("Unknown ack strategy {}", s)),
+        }
+    }
+}
+
+/// Hook, that may inspect every received datagram before it is parsed.
+pub type RecvHook = Mutex<Box<dyn FnMut(&[u8], SocketAddr) + Send>>;
+
+/// Hook, that is called before every write into the output file with the connection identifier and number of bytes.
+/// It runs on the writer threads, when there are any. The error it returns fails the write instead of the file system.
+pub type WriteHook = Arc<dyn Fn(u32, usize) -> io::Result<()> + Send + Sync>;
 
 pub struct Config {
     pub verbose: bool,
+    pub quiet: bool,
     pub bindaddr: String,
+    pub next_bindaddrs: Vec<String>,
     pub directory: String,
     pub max_packet_size: u16,
     pub max_window_size: u16,
     pub min_checksum: u16,
     pub timeout: u32,
+    pub multicast_group: Option<String>,
+    pub multicast_interface: String,
+    pub broadcast: bool,
+    pub coalesce_writes: bool,
+    pub stop_after: Option<usize>,
+    pub max_file_bytes: Option<u64>,
+    pub dedup: bool,
+    pub max_dedup_files: usize,
+    pub linger: u32,
+    pub max_lingering: usize,
+    pub max_filename_length: usize,
+    pub end_grace: u32,
+    pub nonce: bool,
+    pub gap_timeout: Option<u32>,
+    pub max_buffered_parts: Option<usize>,
+    pub max_total_buffer_bytes: Option<usize>,
+    pub seed: Option<u64>,
+    pub file_mode: Option<u32>,
+    pub writer_threads: usize,
+    pub write_retries: u32,
+    pub write_backoff: u32,
+    pub ack_strategy: AckStrategy,
+    pub gap_report: Option<u32>,
+    pub flush_idle: Option<u32>,
+    pub reject_unknown: bool,
+    pub fifo: Option<String>,
+    pub strict_order: bool,
+    pub quarantine_dir: Option<String>,
+    pub compact_acks: bool,
+    pub dry_run: bool,
+    pub events: Option<Sender<ReceiverEvent>>,
+    pub on_recv: Option<RecvHook>,
+    pub on_write: Option<WriteHook>,
 }
 
 impl Config {
     pub fn new() -> Self {
         return Config {
             verbose: false,
+            quiet: false,
             bindaddr: String::from("127.0.0.1:3003"),
+            next_bindaddrs: Vec::new(),
             directory: String::from("received"),
             max_packet_size: 1500,
             max_window_size: 15,
             min_checksum: 16,
             timeout: 5000,
+            multicast_group: None,
+            multicast_interface: String::from("0.0.0.0"),
+            broadcast: false,
+            coalesce_writes: false,
+            stop_after: None,
+            max_file_bytes: None,
+            dedup: false,
+            max_dedup_files: 1024,
+            linger: 2000,
+            max_lingering: 1024,
+            max_filename_length: 255,
+            end_grace: 200,
+            nonce: false,
+            gap_timeout: None,
+            max_buffered_parts: None,
+            max_total_buffer_bytes: None,
+            seed: None,
+            file_mode: None,
+            writer_threads: 0,
+            write_retries: 0,
+            write_backoff: 10,
+            ack_strategy: AckStrategy::Immediate,
+            gap_report: None,
+            flush_idle: None,
+            reject_unknown: false,
+            fifo: None,
+            strict_order: false,
+            quarantine_dir: None,
+            compact_acks: false,
+            dry_run: false,
+            events: None,
+            on_recv: None,
+            on_write: None,
         };
     }
 
     pub fn binding(&self) -> SocketAddrV4 {
         return SocketAddrV4::from_str(self.bindaddr.as_str()).expect("Invalid bind address");
     }
+    /// Whether the received content is stored into the files in the directory,
+    /// not into the pipe nor dropped by the dry run.
+    pub fn stores_files(&self) -> bool {
+        return self.fifo.is_none() && !self.dry_run;
+    }
+    /// All the addresses to bind to, the main one first.
+    pub fn bindings(&self) -> Vec<SocketAddrV4> {
+        let mut addrs = vec![self.binding()];
+        addrs.extend(self.next_bindaddrs.iter().map(|addr| {
+            SocketAddrV4::from_str(addr.as_str()).expect("Invalid bind address")
+        }));
+        return addrs;
+    }
+    pub fn multicast_group(&self) -> Option<Ipv4Addr> {
+        return self.multicast_group.as_ref().map(|group| {
+            Ipv4Addr::from_str(group.as_str()).expect("Invalid multicast group")
+        });
+    }
+    pub fn multicast_interface(&self) -> Ipv4Addr {
+        return Ipv4Addr::from_str(self.multicast_interface.as_str()).expect("Invalid multicast interface");
+    }
+    /// Whether the sender sends the data to the group of receivers (broadcast or multicast address),
+    /// the connection then continues under the identifier, that another member of the group confirmed.
+    pub fn is_group_member(&self) -> bool {
+        return self.broadcast || self.multicast_group.is_some();
+    }
 
     pub fn filename(&self, connection_id: u32) -> String {
+        return Self::path_in(&self.directory, connection_id);
+    }
+    /// Path where the file is held until it is verified, if the quarantine is used.
+    pub fn quarantine_filename(&self, connection_id: u32) -> Option<String> {
+        return self.quarantine_dir.as_ref().map(|directory| Self::path_in(directory, connection_id));
+    }
+    /// Path in the directory for the file `name` chosen by the sender, it can't lead outside of the directory.
+    /// Returns `None` when the name doesn't denote any file.
+    pub fn remote_filename(&self, name: &str) -> Option<String> {
+        return self.remote_path_in(&self.directory, name);
+    }
+    /// Path where the file `name` chosen by the sender is held until it is verified, if the quarantine is used.
+    pub fn remote_quarantine_filename(&self, name: &str) -> Option<String> {
+        return self.quarantine_dir.as_ref().and_then(|directory| self.remote_path_in(directory, name));
+    }
+    fn remote_path_in(&self, directory: &str, name: &str) -> Option<String> {
+        let name = sanitize_filename(name, self.max_filename_length)?;
+        let path = PathBuf::from(directory).join(name);
+        return path.to_str().map(String::from);
+    }
+    fn path_in(directory: &str, connection_id: u32) -> String {
         let mut path = PathBuf::new();
-        path.push(&self.directory);
+        path.push(directory);
         path.push(connection_id.to_string());
         let final_path = String::from(path.as_path().to_str().unwrap());
         return final_path;
     }
 
+    /// Pass the `event` to the observer of the receiver, if there is any.
+    pub fn emit(&self, event: ReceiverEvent) {
+        if let Some(events) = &self.events {
+            // the observer may be gone already, the receiver doesn't depend on it
+            let _ = events.send(event);
+        }
+    }
+
+    /// Pass the received `datagram` from the `addr` to the `on_recv` hook before it is parsed, if there is any.
+    pub fn after_receive(&self, datagram: &[u8], addr: SocketAddr) {
+        if let Some(on_recv) = &self.on_recv {
+            let mut on_recv = on_recv.lock().expect("Can't lock the receive hook");
+            on_recv(datagram, addr);
+        }
+    }
+
     pub fn vlog(&self, text: &str) {
         Loggable::vlog(self, &text)
     }
     pub fn is_verbose(&self) -> bool {
         Loggable::is_verbose(self)
     }
+    pub fn info(&self, text: &str) {
+        Loggable::info(self, text)
+    }
 
     pub fn from_command_line() -> Self {
         let mut config = Config::new();
+        let mut file_mode: Option<String> = None;
         {
             let mut parser = ArgumentParser::new();
             parser.refer(&mut config.verbose)
                 .add_option(&["-v", "--verbose"], StoreTrue, "Verbose output");
+            parser.refer(&mut config.quiet)
+                .add_option(&["-q", "--quiet"], StoreTrue, "Do not print any messages");
             parser.refer(&mut config.bindaddr)
                 .add_option(&["--addr"], Store, "Address to bind to in format IP:port");
+            parser.refer(&mut config.next_bindaddrs)
+                .add_option(&["--also_addr"], Collect, "Another address to bind to in format IP:port, may be repeated");
             parser.refer(&mut config.directory)
                 .add_option(&["-d", "--directory"], Store, "Directory where to store received files");
             parser.refer(&mut config.max_packet_size)
@@ -64,8 +249,69 @@ impl Config {
                 .add_option(&["-t", "--timeout"], Store, "Timeout after which resend the acknowledge packet");
             parser.refer(&mut config.min_checksum)
                 .add_option(&["-s", "--checksum"], Store, "Minimum size of checksum");
+            parser.refer(&mut config.multicast_group)
+                .add_option(&["--multicast_group"], StoreOption, "Multicast group to join");
+            parser.refer(&mut config.multicast_interface)
+                .add_option(&["--multicast_interface"], Store, "Address of the interface on which join the multicast group");
+            parser.refer(&mut config.broadcast)
+                .add_option(&["--broadcast"], StoreTrue, "Receive the data the sender sends to the broadcast address");
+            parser.refer(&mut config.coalesce_writes)
+                .add_option(&["--coalesce"], StoreTrue, "Write contiguous received parts into the file at once");
+            parser.refer(&mut config.stop_after)
+                .add_option(&["--stop_after"], StoreOption, "Exit after the number of files is received");
+            parser.refer(&mut config.max_file_bytes)
+                .add_option(&["--max_file_bytes"], StoreOption, "Maximum size of the received file in bytes");
+            parser.refer(&mut config.dedup)
+                .add_option(&["--dedup"], StoreTrue, "Replace received file by a hard link when the same file was already received");
+            parser.refer(&mut config.max_dedup_files)
+                .add_option(&["--max_dedup_files"], Store, "Maximum number of received files remembered for the deduplication, the oldest ones are forgotten");
+            parser.refer(&mut config.linger)
+                .add_option(&["--linger"], Store, "How long to confirm repeated end packets after the connection is closed");
+            parser.refer(&mut config.ack_strategy)
+                .add_option(&["--ack_strategy"], Store, "When to acknowledge data, immediate, every:N or delayed:MS");
+            parser.refer(&mut config.gap_report)
+                .add_option(&["--gap_report"], StoreOption, "Interval in milliseconds in which report missing parts to the sender");
+            parser.refer(&mut config.flush_idle)
+                .add_option(&["--flush_idle"], StoreOption, "Store received data on the disk after the connection is idle for the number of milliseconds");
+            parser.refer(&mut config.reject_unknown)
+                .add_option(&["--reject_unknown"], StoreTrue, "Answer packets of unknown connections by error packet");
+            parser.refer(&mut config.fifo)
+                .add_option(&["--fifo"], StoreOption, "Named pipe into which write the received data instead of the directory, it must have the reader and takes a single connection at a time");
+            parser.refer(&mut config.strict_order)
+                .add_option(&["--strict_order"], StoreTrue, "Drop data that are not the next expected part instead of buffering them");
+            parser.refer(&mut config.quarantine_dir)
+                .add_option(&["--quarantine"], StoreOption, "Directory where to hold the received files until they match the digest of the sender");
+            parser.refer(&mut config.compact_acks)
+                .add_option(&["--compact_acks"], StoreTrue, "Acknowledge the data by the compact ack packets with short checksum instead of the data packets");
+            parser.refer(&mut config.dry_run)
+                .add_option(&["--dry_run"], StoreTrue, "Receive and validate the files by the digest of the sender without storing them");
+            parser.refer(&mut config.max_lingering)
+                .add_option(&["--max_lingering"], Store, "Maximum number of closed connections confirming repeated end packets");
+            parser.refer(&mut config.max_filename_length)
+                .add_option(&["--max_filename"], Store, "Maximum length of the file name chosen by the sender");
+            parser.refer(&mut config.end_grace)
+                .add_option(&["--end_grace"], Store, "How long the end packet, that came before some data, waits for them in milliseconds");
+            parser.refer(&mut config.nonce)
+                .add_option(&["--nonce"], StoreTrue, "Accept the data only from the sender, that echoes the number from the init answer");
+            parser.refer(&mut config.gap_timeout)
+                .add_option(&["--gap_timeout"], StoreOption, "Repeat the acknowledge after the window is stuck before missing part for the number of milliseconds");
+            parser.refer(&mut config.max_buffered_parts)
+                .add_option(&["--max_buffered_parts"], StoreOption, "Maximum number of parts buffered per connection, other parts out of order are dropped");
+            parser.refer(&mut config.seed)
+                .add_option(&["--seed"], StoreOption, "Seed of the connection identifiers and nonces, so they repeat in every run");
+            parser.refer(&mut file_mode)
+                .add_option(&["--file_mode"], StoreOption, "Permissions of the created files in octal like 600, restricted by the umask, only on Unix");
+            parser.refer(&mut config.writer_threads)
+                .add_option(&["--writer_threads"], Store, "Number of threads writing the received data into the files, zero writes them in the main loop");
+            parser.refer(&mut config.max_total_buffer_bytes)
+                .add_option(&["--max_total_buffer_bytes"], StoreOption, "Maximum number of bytes buffered by all the connections, the least recently active ones are closed to make room");
+            parser.refer(&mut config.write_retries)
+                .add_option(&["--write_retries"], Store, "How many times to try again the write into the file, that failed with a transient error like the full disk");
+            parser.refer(&mut config.write_backoff)
+                .add_option(&["--write_backoff"], Store, "Pause in milliseconds before the first retry of the failed write, it doubles with every following one");
             parser.parse_args_or_exit();
         }
+        config.file_mode = file_mode.map(|mode| u32::from_str_radix(&mode, 8).expect("Invalid file mode"));
         return config;
     }
 }
@@ -74,4 +320,8 @@ impl Loggable for Config {
     fn is_verbose(&self) -> bool {
         self.verbose
     }
+
+    fn is_quiet(&self) -> bool {
+        self.quiet
+    }
 }
@@ -0,0 +1,142 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+
+/// Size of the chunks, in which the files are compared.
+const COMPARE_CHUNK: usize = 64 * 1024;
+
+/// Paths of the received files by the hash of their content.
+/// Only the `capacity` most recently received files are remembered, the oldest ones are forgotten.
+pub struct ReceivedFiles {
+    paths: HashMap<u64, String>,
+    /// Hashes in the order they were inserted.
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl ReceivedFiles {
+    pub fn new(capacity: usize) -> Self {
+        return ReceivedFiles {
+            paths: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        };
+    }
+
+    pub fn get(&self, hash: u64) -> Option<&String> {
+        return self.paths.get(&hash);
+    }
+
+    /// Remember the file at the `path` with the content `hash`, the oldest file is forgotten when there are too many.
+    pub fn insert(&mut self, hash: u64, path: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.paths.insert(hash, path).is_some() {
+            return;
+        }
+        self.order.push_back(hash);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.paths.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Whether the files at the `first` and the `second` path have the same content.
+/// The files are compared in chunks, so they are never loaded into the memory whole.
+pub fn same_content(first: &str, second: &str) -> io::Result<bool> {
+    if fs::metadata(first)?.len() != fs::metadata(second)?.len() {
+        return Ok(false);
+    }
+    let mut first = BufReader::new(File::open(first)?);
+    let mut second = BufReader::new(File::open(second)?);
+    let mut first_chunk = vec![0; COMPARE_CHUNK];
+    let mut second_chunk = vec![0; COMPARE_CHUNK];
+    loop {
+        let read = read_chunk(&mut first, &mut first_chunk)?;
+        if read != read_chunk(&mut second, &mut second_chunk)? || first_chunk[..read] != second_chunk[..read] {
+            return Ok(false);
+        }
+        if read == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Fill the `chunk` from the `reader`, shorter only at the end of the file.
+fn read_chunk(reader: &mut impl Read, chunk: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < chunk.len() {
+        match reader.read(&mut chunk[read..])? {
+            0 => break,
+            size => read += size,
+        }
+    }
+    return Ok(read);
+}
+
+/// Replace the file at the `path` by the hard link to the `original`.
+/// The link is created under temporary name and renamed over the file, so the file is kept when the link fails.
+pub fn link_over(original: &str, path: &str) -> io::Result<()> {
+    let temp_path = format!("{}.link", path);
+    // the link left by the previous failure would prevent creating the new one
+    let _ = fs::remove_file(&temp_path);
+    fs::hard_link(original, &temp_path)?;
+    return fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        e
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, read, remove_dir_all, write};
+    use std::path::Path;
+    use super::{link_over, same_content, ReceivedFiles};
+
+    #[test]
+    fn oldest_files_are_forgotten() {
+        let mut files = ReceivedFiles::new(2);
+        files.insert(1, String::from("first"));
+        files.insert(2, String::from("second"));
+        files.insert(1, String::from("first copy"));
+        files.insert(3, String::from("third"));
+        assert_eq!(files.get(1), None);
+        assert_eq!(files.get(2), Some(&String::from("second")));
+        assert_eq!(files.get(3), Some(&String::from("third")));
+    }
+
+    #[test]
+    fn files_are_compared_in_chunks() {
+        const DIR: &str = "dedup_compare";
+        create_dir_all(DIR).unwrap();
+        let content = (0..200_000u32).map(|i| i as u8).collect::<Vec<u8>>();
+        let mut changed = content.clone();
+        changed[150_000] ^= 1;
+        write(format!("{}/a", DIR), &content).unwrap();
+        write(format!("{}/b", DIR), &content).unwrap();
+        write(format!("{}/c", DIR), &changed).unwrap();
+        write(format!("{}/d", DIR), &content[..100]).unwrap();
+        assert!(same_content(&format!("{}/a", DIR), &format!("{}/b", DIR)).unwrap());
+        assert!(!same_content(&format!("{}/a", DIR), &format!("{}/c", DIR)).unwrap());
+        assert!(!same_content(&format!("{}/a", DIR), &format!("{}/d", DIR)).unwrap());
+        assert!(same_content(&format!("{}/a", DIR), &format!("{}/missing", DIR)).is_err());
+        remove_dir_all(DIR).unwrap();
+    }
+
+    #[test]
+    fn file_is_kept_when_link_fails() {
+        const DIR: &str = "dedup_link";
+        create_dir_all(DIR).unwrap();
+        write(format!("{}/copy", DIR), b"content").unwrap();
+        assert!(link_over(&format!("{}/missing", DIR), &format!("{}/copy", DIR)).is_err());
+        assert_eq!(read(format!("{}/copy", DIR)).unwrap(), b"content");
+        write(format!("{}/original", DIR), b"content").unwrap();
+        link_over(&format!("{}/original", DIR), &format!("{}/copy", DIR)).unwrap();
+        assert_eq!(read(format!("{}/copy", DIR)).unwrap(), b"content");
+        assert!(!Path::new(&format!("{}/copy.link", DIR)).exists());
+        remove_dir_all(DIR).unwrap();
+    }
+}
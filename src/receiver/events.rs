@@ -0,0 +1,33 @@
+use std::net::SocketAddr;
+use crate::connection_properties::ConnectionProperties;
+
+/// What happened in the receiver, for the application observing it.
+#[derive(Clone, Debug)]
+pub enum ReceiverEvent {
+    /// New connection with the identifier was accepted with these properties.
+    ConnectionAccepted(u32, ConnectionProperties),
+    /// Part with the sequence number and number of bytes was stored.
+    DataStored(u16, usize),
+    /// The receiver expects the part at the position.
+    WindowAdvanced(u16),
+    /// Number of bytes was written into the file.
+    FileWritten(usize),
+    /// Part that was already received came again.
+    DuplicateDropped,
+    /// Part that doesn't belong to the window was received.
+    OutOfWindowDropped,
+    /// Part within the window, that is not the next expected one, was dropped in the strict order mode.
+    OutOfOrderDropped(u16), // seq
+    /// Part out of order was dropped, because the connection buffers the maximum number of parts.
+    BufferFull(u16), // seq
+    /// Part with the sequence number was dropped, because it didn't echo the nonce of the init answer.
+    NonceRejected(u16), // seq
+    /// Number of datagrams read from the socket by single system call.
+    DatagramsRead(usize),
+    /// Packet that failed the checksum or size validation was dropped.
+    CorruptedDropped,
+    /// Connection was closed before the whole file was received, because of the reason.
+    ConnectionClosed(String),
+    /// File at the path was received completely with the number of bytes from the sender at the address.
+    Completed(String, u64, SocketAddr),
+}
@@ -0,0 +1,55 @@
+/// Make the file name received from the sender safe to be stored in the output directory.
+/// Only the last path component is kept, characters other than ASCII letters, digits, `.`, `-`, and `_`
+/// are replaced by `_`, and the name is shortened to `max_length` characters.
+/// Names, that don't denote any file in the directory, are refused.
+pub fn sanitize_filename(name: &str, max_length: usize) -> Option<String> {
+    let last_component = name.rsplit(&['/', '\\'][..]).next().unwrap_or("");
+    let sanitized: String = last_component.chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+            true => c,
+            false => '_',
+        })
+        .take(max_length)
+        .collect();
+    if sanitized.chars().all(|c| c == '.') {
+        return None;
+    }
+    return Some(sanitized);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use crate::receiver::config::Config;
+    use super::sanitize_filename;
+
+    #[test]
+    fn path_components_are_stripped() {
+        assert_eq!(sanitize_filename("../../etc/passwd", 255), Some(String::from("passwd")));
+        assert_eq!(sanitize_filename("/etc/passwd", 255), Some(String::from("passwd")));
+        assert_eq!(sanitize_filename("..\\..\\windows\\system.ini", 255), Some(String::from("system.ini")));
+        assert_eq!(sanitize_filename("report.txt", 255), Some(String::from("report.txt")));
+    }
+
+    #[test]
+    fn hostile_names_are_refused_or_replaced() {
+        assert_eq!(sanitize_filename("..", 255), None);
+        assert_eq!(sanitize_filename("dir/", 255), None);
+        assert_eq!(sanitize_filename("", 255), None);
+        assert_eq!(sanitize_filename("a\0b:c*d", 255), Some(String::from("a_b_c_d")));
+        assert_eq!(sanitize_filename("příliš", 255), Some(String::from("p__li_")));
+        assert_eq!(sanitize_filename("abcdef", 3), Some(String::from("abc")));
+        assert_eq!(sanitize_filename("...", 2), None);
+    }
+
+    #[test]
+    fn remote_names_stay_in_directory() {
+        let mut config = Config::new();
+        config.directory = String::from("received_remote");
+        for name in ["../../etc/passwd", "/etc/passwd", "a/../../b", "..\\x", "~/.bashrc", "C:\\boot.ini"].iter() {
+            let path = config.remote_filename(name).unwrap();
+            assert_eq!(Path::new(&path).parent(), Some(Path::new("received_remote")), "{}", name);
+        }
+        assert_eq!(config.remote_filename("../.."), None);
+    }
+}
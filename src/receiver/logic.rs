@@ -1,21 +1,31 @@
-use std::net::{UdpSocket};
+use std::net::{UdpSocket, SocketAddr};
 use std::result::Result::Ok;
-use std::cmp::{max, min};
-use std::collections::{HashMap as PropertiesMap};
-use rand::Rng;
+use std::cmp::{min, max};
+use std::collections::{HashMap as PropertiesMap, HashSet};
+use rand::{Rng, SeedableRng, rngs::StdRng};
 use itertools::Itertools;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::path::Path;
-use std::sync::Arc;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::JoinHandle;
 use std::thread;
-use super::config::Config;
-use crate::packet::{InitPacket, Packet, ParsingError, Flag, EndPacket, PacketHeader, ToBin, ErrorPacket, DataPacket};
+use super::config::{Config, AckStrategy};
+use crate::packet::{InitPacket, Packet, ParsingError, Flag, EndPacket, PacketHeader, ToBin, ErrorPacket, ErrorReason, DataPacket, GapPacket, AckPacket};
 use crate::connection_properties::ConnectionProperties;
-use crate::receiver::receiver_connection_properties::ReceiverConnectionProperties;
-use crate::{BUFFER_SIZE, recv_with_timeout};
+use crate::receiver::receiver_connection_properties::{ReceiverConnectionProperties, ConnectionSnapshot, CompletedTransfer};
+use crate::receiver::events::ReceiverEvent;
+use crate::receiver::writer::{FileWriter, WriterPool};
+use crate::receiver::dedup::{link_over, same_content, ReceivedFiles};
+use crate::socket_manipulation::{bind_shared, recv_batch};
 
+/// Number of datagrams, that may be read from the socket at once.
+#[cfg(feature = "batched")]
+const RECV_BATCH: usize = 32;
+#[cfg(not(feature = "batched"))]
+const RECV_BATCH: usize = 1;
 
 /// Creates the receiver.
 /// `brk` parameter should be set to `true` when the receiver should terminate.
@@ -24,273 +34,803 @@ pub fn breakable_logic(config: Config, brk: Arc<AtomicBool>) -> JoinHandle<Resul
     thread::Builder::new()
         .name(String::from("Receiver"))
         .spawn(move || {
-            receiver(config, brk)
+            receiver(config, brk, None, None).map(|_| ())
         }).expect("Can't create thread for the broker")
 }
 
+/// Creates the receiver, that publishes state of all its connections into the `status` every iteration.
+/// `brk` parameter should be set to `true` when the receiver should terminate.
+/// Returns handler to join the thread.
+pub fn monitored_logic(
+    config: Config,
+    brk: Arc<AtomicBool>,
+    status: Arc<Mutex<Vec<ConnectionSnapshot>>>,
+) -> JoinHandle<Result<(), String>> {
+    thread::Builder::new()
+        .name(String::from("Receiver"))
+        .spawn(move || {
+            receiver(config, brk, Some(status), None).map(|_| ())
+        }).expect("Can't create thread for the receiver")
+}
+
 /// Creates the receiver and keep running.
 /// There is no way how to terminate the execution.
 pub fn logic(config: Config) -> Result<(), String> {
     let brk = Arc::new(AtomicBool::new(false));
-    receiver(config, brk)
+    receiver(config, brk, None, None).map(|_| ())
 }
 
-fn receiver(config: Config, brk: Arc<AtomicBool>) -> Result<(), String> {
-    // create socket
-    let socket = UdpSocket::bind(config.binding()).expect("Can't bind socket");
-    socket.set_read_timeout(Some(Duration::from_millis(config.timeout as u64))).expect("Can't set read timeout");
-    config.vlog(&format!("Socket bind to {}", config.binding()));
+/// Runs the receiver until nothing was received for the `idle_timeout`.
+/// Returns the files received completely in the meantime.
+pub fn receive_all(config: Config, idle_timeout: Duration) -> Result<Vec<CompletedTransfer>, String> {
+    let brk = Arc::new(AtomicBool::new(false));
+    receiver(config, brk, None, Some(idle_timeout))
+}
+
+/// Receive the files until the `brk` is set or nothing is received for the `idle_timeout`.
+fn receiver(
+    config: Config,
+    brk: Arc<AtomicBool>,
+    status: Option<Arc<Mutex<Vec<ConnectionSnapshot>>>>,
+    idle_timeout: Option<Duration>,
+) -> Result<Vec<CompletedTransfer>, String> {
+    // the received files are stored into the directory, the pipe and the dry run don't need it
+    if config.stores_files() {
+        prepare_directory(&config.directory)?;
+        if let Some(quarantine) = &config.quarantine_dir {
+            prepare_directory(quarantine)?;
+        }
+    }
+    // create sockets, the connection is answered from the one it arrived on,
+    // the members of the group on the same host share the port
+    let sockets = config.bindings().into_iter()
+        .map(|addr| match config.is_group_member() {
+            true => bind_shared(addr),
+            false => UdpSocket::bind(addr),
+        }.expect("Can't bind socket"))
+        .collect::<Vec<UdpSocket>>();
+    let read_timeout = match config.ack_strategy {
+        AckStrategy::Delayed(delay) => min(config.timeout, max(delay, 1)),
+        _ => config.timeout,
+    };
+    let read_timeout = match config.gap_report {
+        Some(interval) => min(read_timeout, max(interval, 1)),
+        None => read_timeout,
+    };
+    let read_timeout = match config.gap_timeout {
+        Some(timeout) => min(read_timeout, max(timeout, 1)),
+        None => read_timeout,
+    };
+    let read_timeout = match config.flush_idle {
+        Some(idle) => min(read_timeout, max(idle, 1)),
+        None => read_timeout,
+    };
+    let read_timeout = match config.end_grace {
+        0 => read_timeout,
+        grace => min(read_timeout, grace),
+    };
+    let read_timeout = match idle_timeout {
+        Some(idle) => min(read_timeout, max(idle.as_millis() as u32, 1)),
+        None => read_timeout,
+    };
+    // the receiver, that stops after the number of files, ends as soon as the last connection stops lingering
+    let read_timeout = match config.stop_after {
+        Some(_) => min(read_timeout, max(config.linger, 1)),
+        None => read_timeout,
+    };
+    let read_timeout = Duration::from_millis(read_timeout as u64);
+    // more sockets are polled in turn, so every one of them is waited for only shortly
+    let socket_timeout = match sockets.len() {
+        1 => read_timeout,
+        _ => POLL_SLICE,
+    };
+    for socket in &sockets {
+        socket.set_read_timeout(Some(socket_timeout)).expect("Can't set read timeout");
+        config.vlog(&format!("Socket bind to {}", socket.local_addr().expect("Can't get address of the socket")));
+        if let Some(group) = config.multicast_group() {
+            socket.join_multicast_v4(&group, &config.multicast_interface()).expect("Can't join multicast group");
+            config.vlog(&format!("Joined multicast group {} on {}", group, config.multicast_interface()));
+        }
+    }
 
     // create structures
-    let mut random_generator = rand::thread_rng();
+    let mut random_generator = match config.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    // the writers outlive the connections, so the queued content is written before the receiver ends
+    let writers = match config.writer_threads {
+        0 => None,
+        threads => Some(WriterPool::new(threads, FileWriter::new(&config))),
+    };
     let mut properties = PropertiesMap::<u32, ReceiverConnectionProperties>::new();
-    let mut buffer = vec![0; BUFFER_SIZE];
+    // bigger packets are truncated and the sender is asked to use smaller ones
+    let mut buffers = vec![vec![0; config.max_packet_size as usize]; RECV_BATCH];
+    let mut response = vec![0; config.max_packet_size as usize];
+    let mut completed: usize = 0;
+    let mut completed_transfers = Vec::new();
+    let mut received_at = Instant::now();
+    let mut received_files = ReceivedFiles::new(config.max_dedup_files);
 
     while !brk.load(Ordering::SeqCst) {
         // filter connections timeout
         // TODO use heap
         let ids_to_disconnect = properties.iter()
-            .filter(|(_,prop)| prop.timeouted(config.timeout))
+            .filter(|(_,prop)| prop.timeouted(config.timeout) || prop.linger_expired(config.linger))
             .map(|(key,_)| *key)
             .collect_vec();
         for conn_id in ids_to_disconnect {
             let mut prop = properties.remove(&conn_id).expect("Connection is not in properties");
-            remove_connection(&mut prop, &config, &mut buffer, &socket, "timeout");
+            let socket = &sockets[prop.socket_index];
+            remove_connection(&mut prop, &config, &mut response, socket, ErrorReason::Timeout);
         }
-        // receive from socket
-        let result = recv_with_timeout(&socket, &mut buffer, Box::new(&config));
-        if let Err(_) = result {
-            continue;
+        // the data didn't come after the end packet in time
+        let ids_to_disconnect = properties.iter()
+            .filter(|(_, prop)| prop.end_grace_expired(config.end_grace))
+            .map(|(key, _)| *key)
+            .collect_vec();
+        for conn_id in ids_to_disconnect {
+            let mut prop = properties.remove(&conn_id).expect("Connection is not in properties");
+            let socket = &sockets[prop.socket_index];
+            remove_connection(&mut prop, &config, &mut response, socket, ErrorReason::MissingData);
         }
-        let (packet_size, received_from) = match result {
-            Err(_) => continue,
-            Ok(x) => x,
-        };
-        // get content
-        config.vlog(&format!("Received packet of size {}", packet_size));
-        let packet_content = &buffer[..packet_size];
-
-        // parse header
-        let header_result = PacketHeader::from_bin(packet_content);
-        let header = match header_result {
-            Err(e) => {
-                if config.is_verbose() {
-                    let header_in_bin = &buffer[..min(PacketHeader::bin_size(), packet_size)];
-                    let header_in_str = Itertools::intersperse(
-                        header_in_bin.iter().map(|num| { format!("{:02x}", num) }),
-                        String::from("")
-                    );
-                    let header_in_str: String = header_in_str.collect();
-                    config.vlog(&format!("Invalid header: {}; error: {:?}", header_in_str, e));
-                }
-                continue;
+        evict_lingering(&mut properties, &config);
+        // send postponed acknowledges
+        for prop in properties.values_mut() {
+            if !prop.is_closed() && prop.acknowledge_due(config.ack_strategy) {
+                let addr = prop.static_properties.socket_addr;
+                send_acknowledge(prop, &config, &mut response, &sockets[prop.socket_index], addr);
             }
-            Ok(h) => h,
+        }
+        // report the missing parts
+        if let Some(interval) = config.gap_report {
+            for prop in properties.values_mut().filter(|prop| prop.gap_report_due(interval)) {
+                // report only as many ranges as fit into the packet
+                let mut ranges = prop.missing_ranges();
+                let space = prop.static_properties.packet_size as usize - prop.static_properties.checksum_size as usize - PacketHeader::bin_size();
+                ranges.truncate(space / 4);
+                config.vlog(&format!("Connection {} misses parts {:?}", prop.static_properties.id, ranges));
+                let packet = Packet::from(GapPacket::new(prop.static_properties.id, prop.get_acknowledge(), ranges));
+                let report_size = packet.to_bin_buff(&mut response, prop.static_properties.checksum_size as usize);
+                sockets[prop.socket_index].send_to(&response[..report_size], prop.static_properties.socket_addr).expect("Can't send gap report");
+                prop.gap_reported();
+            }
+        }
+        // repeat the acknowledge of the stuck window, so the sender notices the missing part before its timeout
+        if let Some(timeout) = config.gap_timeout {
+            for prop in properties.values_mut().filter(|prop| prop.gap_timeout_due(timeout)) {
+                config.vlog(&format!("Connection {} stuck at position {}, repeating acknowledge", prop.static_properties.id, prop.window_position));
+                let addr = prop.static_properties.socket_addr;
+                send_acknowledge(prop, &config, &mut response, &sockets[prop.socket_index], addr);
+                prop.reacknowledged();
+            }
+        }
+        // store the data of the idle connections on the disk, the pipe has nothing to store
+        if let (Some(idle), None) = (config.flush_idle, &config.fifo) {
+            for prop in properties.values_mut().filter(|prop| prop.flush_due(idle)) {
+                match prop.flush() {
+                    Ok(_) => config.vlog(&format!("Connection {} idle, {}b stored on the disk", prop.static_properties.id, prop.synced_bytes)),
+                    Err(e) => config.info(&format!("Can't store data of connection {} on the disk: {}", prop.static_properties.id, e)),
+                };
+            }
+        }
+        // publish state of the connections
+        if let Some(status) = &status {
+            *status.lock().expect("Can't lock receiver status") = properties.values().map(|prop| prop.snapshot()).collect();
+        }
+        // end when enough files were received and remaining connections are finished,
+        // the closed ones linger to confirm the end packets, that are repeated when the confirmation is lost
+        if let Some(stop_after) = config.stop_after {
+            if completed >= stop_after && properties.is_empty() {
+                config.vlog(&format!("Received {} files, ending", completed));
+                break;
+            }
+        }
+        // end when no connection received anything for a while
+        if let Some(idle) = idle_timeout {
+            let connections_idle = properties.values().all(|prop| prop.last_receive_time.elapsed() >= idle);
+            if connections_idle && received_at.elapsed() >= idle {
+                config.vlog(&format!("Nothing received in {}ms, ending", idle.as_millis()));
+                break;
+            }
+        }
+        // receive from any socket, with the batched feature all the waiting datagrams at once
+        let (socket_index, received) = match recv_any(&sockets, &mut buffers, read_timeout, &config) {
+            Err(_) => continue,
+            Ok(received) => received,
         };
-        config.vlog(&format!("It is packet with flag {:?}", header.flag));
+        let socket = &sockets[socket_index];
+        received_at = Instant::now();
+        config.emit(ReceiverEvent::DatagramsRead(received.len()));
+        for (buffer, (packet_size, received_from)) in buffers.iter_mut().zip(received) {
+            // get content
+            config.vlog(&format!("Received packet of size {}", packet_size));
+            config.after_receive(&buffer[..packet_size], received_from);
+            let congested = PacketHeader::take_congestion(&mut buffer[..packet_size]);
+            let packet_content = &buffer[..packet_size];
 
-        // process init packet
-        if let Flag::Init = header.flag {
-            // Get content of init packet without checksum check, so it cat be used later
-            // (and mainly infer what the checksum size should be)
-            let init_content_result = InitPacket::from_bin_no_size_and_hash_check(&buffer[..packet_size]);
-            let init_content = match init_content_result {
+            // parse header
+            let header_result = PacketHeader::from_bin(packet_content);
+            let header = match header_result {
                 Err(e) => {
-                    config.vlog(&format!("Can't get content of init packet {:?}", e));
+                    if config.is_verbose() {
+                        let header_in_bin = &buffer[..min(PacketHeader::bin_size(), packet_size)];
+                        let header_in_str = Itertools::intersperse(
+                            header_in_bin.iter().map(|num| { format!("{:02x}", num) }),
+                            String::from("")
+                        );
+                        let header_in_str: String = header_in_str.collect();
+                        config.vlog(&format!("Invalid header: {}; error: {:?}", header_in_str, e));
+                    }
                     continue;
                 }
-                Ok(r) => r,
+                Ok(h) => h,
             };
-            config.vlog(&format!(
-                "Init packet properties, window size: {}, packet_size: {}, checksum: {}",
-                init_content.window_size,
-                init_content.packet_size,
-                init_content.checksum_size
-            ));
-            // parse as packet
-            let packet = Packet::from_bin(packet_content, init_content.checksum_size as usize);
-            match packet {
-                // everything OK, answer
-                Ok(Packet::Init(_)) => {
-                    // define properties
-                    let window_size = min(init_content.window_size, config.max_window_size);
-                    let packet_size = min(init_content.packet_size, config.max_packet_size);
-                    let checksum_size = max(init_content.checksum_size, config.min_checksum);
-                    let id: u32 = loop {
-                        let id = random_generator.gen();
-                        if !properties.contains_key(&id) && id > 0 {
-                            break id;
+            config.vlog(&format!("It is packet with flag {:?}", header.flag));
+
+            // process init packet
+            if let Flag::Init = header.flag {
+                // Get content of init packet without checksum check, so it cat be used later
+                // (and mainly infer what the checksum size should be)
+                let init_content_result = InitPacket::from_bin_no_size_and_hash_check(&buffer[..packet_size]);
+                let init_content = match init_content_result {
+                    Err(e) => {
+                        config.vlog(&format!("Can't get content of init packet {:?}", e));
+                        continue;
+                    }
+                    Ok(r) => r,
+                };
+                config.vlog(&format!(
+                    "Init packet properties, window size: {}, packet_size: {}, checksum: {}",
+                    init_content.window_size,
+                    init_content.packet_size,
+                    init_content.checksum_size
+                ));
+                // parse as packet
+                let packet = Packet::from_bin(packet_content, init_content.checksum_size as usize);
+                match packet {
+                    // everything OK, answer
+                    // the sender of the established connection asks for a bigger checksum
+                    Ok(Packet::Init(_)) if header.id != 0 && properties.contains_key(&header.id) => {
+                        let prop = properties.get_mut(&header.id).expect("Connection is not in properties");
+                        if prop.static_properties.socket_addr != received_from || prop.is_closed() {
+                            config.vlog(&format!("Checksum of connection {} can't be changed, ignoring", header.id));
+                            continue;
                         }
-                    };
-                    // create connection properties
-                    let props = ReceiverConnectionProperties::new(
-                        ConnectionProperties::new(id, checksum_size, window_size, packet_size, received_from)
-                    );
-                    config.vlog(&format!(
-                        "New connection {} with window_size: {}, packet_size: {}, checksum_size: {} created",
-                        props.static_properties.id,
-                        props.static_properties.window_size,
-                        props.static_properties.packet_size,
-                        props.static_properties.checksum_size,
-                    ));
-                    // store them
-                    if let Some(_) = properties.insert(id, props) {
-                        panic!("Connection with this ID already exists");
+                        let checksum_size = prop.renegotiate_checksum(init_content.checksum_size);
+                        config.vlog(&format!(
+                            "Connection {} asked for checksum of {}b, {}b agreed",
+                            header.id,
+                            init_content.checksum_size,
+                            checksum_size
+                        ));
+                        let answer = Packet::from(prop.checksum_answer(checksum_size));
+                        let answer_length = answer.to_bin_buff(&mut response, checksum_size as usize);
+                        socket.send_to(&response[..answer_length], received_from).expect("Can't answer checksum request");
+                    },
+                    // the copy of the request, that created the connection, is answered the same
+                    Ok(Packet::Init(_)) if header.id == 0 && properties.values().any(|prop| prop.init_answer(&init_content, received_from).is_some()) => {
+                        let (id, answer) = properties.values()
+                            .find_map(|prop| prop.init_answer(&init_content, received_from).map(|answer| (prop.static_properties.id, answer)))
+                            .expect("Connection of the init request is not in properties");
+                        socket.send_to(answer, received_from).expect("Can't answer repeated init packet");
+                        config.vlog(&format!("Init packet of connection {} repeated, answer send again", id));
+                    },
+                    // the pipe takes the content of a single connection at a time
+                    Ok(Packet::Init(_)) if config.fifo.is_some() && properties.values().any(|prop| !prop.is_closed()) => {
+                        config.info(&format!("Connection from {} refused, the pipe is written by another one", received_from));
+                        let refusal = Packet::from(ErrorPacket::with_reason(0, ErrorReason::Busy));
+                        let refusal_size = refusal.to_bin_buff(&mut response, 0);
+                        socket.send_to(&response[..refusal_size], received_from).expect("Can't refuse init packet");
+                    },
+                    Ok(Packet::Init(_)) => {
+                        // define properties
+                        let id: u32 = loop {
+                            let id = random_generator.gen();
+                            if !properties.contains_key(&id) && id > 0 {
+                                break id;
+                            }
+                        };
+                        let local = InitPacket::new(config.max_window_size, config.max_packet_size, config.min_checksum);
+                        let negotiated = match ConnectionProperties::negotiate(id, &local, &init_content, received_from) {
+                            Ok(negotiated) => negotiated,
+                            Err(e) => {
+                                config.vlog(&format!("Can't agree on the connection properties {:?}, ignoring", e));
+                                continue;
+                            }
+                        };
+                        let window_size = negotiated.window_size;
+                        let packet_size = negotiated.packet_size;
+                        let checksum_size = negotiated.checksum_size;
+                        // make sure no other active connection writes into the same file
+                        let active_paths = properties.values()
+                            .map(|prop| prop.output_path.clone())
+                            .collect::<HashSet<String>>();
+                        // create connection properties, the pipe can't store the data from the offset
+                        let offset = match &config.fifo {
+                            Some(_) => 0,
+                            None => init_content.offset,
+                        };
+                        // the resumed file continues in the final directory, where its beginning is
+                        let output_path = match (&config.fifo, config.quarantine_filename(id)) {
+                            (Some(fifo), _) => fifo.clone(),
+                            (None, Some(quarantined)) if offset == 0 => unique_output_path(quarantined, &active_paths),
+                            (None, _) => unique_output_path(config.filename(id), &active_paths),
+                        };
+                        let mut props = ReceiverConnectionProperties::new(negotiated, output_path);
+                        props.start_at(offset);
+                        props.socket_index = socket_index;
+                        // the sender proves it received the answer by echoing the nonce, the zero means no nonce
+                        let nonce = match config.nonce && InitPacket::has_nonce_room(packet_size, checksum_size) {
+                            true => random_generator.gen_range(1, u16::MAX),
+                            false => 0,
+                        };
+                        if nonce != 0 {
+                            props.expect_nonce(nonce);
+                        }
+                        if let Some(writers) = &writers {
+                            props.write_behind(writers.queue(id));
+                        }
+                        config.vlog(&format!(
+                            "New connection {} with window_size: {}, packet_size: {}, checksum_size: {} created",
+                            props.static_properties.id,
+                            props.static_properties.window_size,
+                            props.static_properties.packet_size,
+                            props.static_properties.checksum_size,
+                        ));
+                        config.emit(ReceiverEvent::ConnectionAccepted(id, props.static_properties.clone()));
+                        // answer the sender
+                        let mut answer_packet = InitPacket::new(window_size, packet_size, checksum_size);
+                        answer_packet.header.id = id;
+                        if InitPacket::has_offset_room(packet_size, checksum_size) {
+                            answer_packet.offset = offset;
+                        }
+                        answer_packet.nonce = nonce;
+                        let answer_length = Packet::from(answer_packet).to_bin_buff(&mut response, checksum_size as usize);
+                        props.answered_init(&init_content, received_from, Vec::from(&response[..answer_length]));
+                        // store them
+                        if let Some(_) = properties.insert(id, props) {
+                            panic!("Connection with this ID already exists");
+                        }
+                        socket.send_to(&response[..answer_length], received_from).expect("Can't answer with init packet");
+                        config.vlog("Answer init packet send");
+                    },
+                    // Not parsed init packet
+                    Ok(_) => {
+                        config.vlog("Expected init packet, but parsed something different");
                     }
-                    // answer the sender
-                    let mut answer_packet = InitPacket::new(window_size, packet_size, checksum_size);
-                    answer_packet.header.id = id;
-                    let answer_length = Packet::from(answer_packet).to_bin_buff(&mut buffer, checksum_size as usize);
-                    socket.send_to(&buffer[..answer_length], received_from).expect("Can't answer with init packet");
-                    config.vlog("Answer init packet send");
-                },
-                // Not parsed init packet
-                Ok(_) => {
-                    config.vlog("Expected init packet, but parsed something different");
-                }
-                // Checksum not match, can't infer content
-                Err(ParsingError::ChecksumNotMatch) => {
-                    config.vlog("Checksum of init packet not match, ignoring");
-                }
-                // Received smaller packet, therefore checksum (and validity of data) can't be checked
-                // Answer with receiver setting (and size that arrived) and let sender ask again
-                Err(ParsingError::InvalidSize(expect, actual)) => {
-                    config.vlog(&format!("Expected init packet of size {}, but received {}", expect, actual));
-                    let return_init = InitPacket::new(
-                        config.max_window_size,
-                        min(config.max_packet_size, packet_size as u16),
-                        config.min_checksum
-                    );
-                    config.vlog(&format!(
-                        "Return init packet with properties, window size: {}, packet_size: {}, checksum: {}",
-                        return_init.window_size,
-                        return_init.packet_size,
-                        return_init.checksum_size
-                    ));
-                    let answer_packet_size = Packet::from(return_init).to_bin_buff(buffer.as_mut_slice(), config.min_checksum as usize);
-                    socket.send_to(&buffer[..answer_packet_size], received_from).expect("Can't answer with init packet after invalid size");
-                    config.vlog("Return init packet send back");
-                }
-                // Other error
-                Err(e) => {
-                    config.vlog(&format!("Error parsing init packet {:?}", e));
-                }
-            };
-            continue;
-        }
-
-        // validate connection id and get the properties of the connection
-        let conn_id = header.id;
-        let prop = match properties.get_mut(&conn_id) {
-            Some(p) => p,
-            None => {
-                config.vlog(&format!("Received data packet for connection {}, but it doesn't exists", conn_id));
+                    // Checksum not match, can't infer content
+                    Err(ParsingError::ChecksumNotMatch) => {
+                        config.vlog("Checksum of init packet not match, ignoring");
+                    }
+                    // Received smaller packet, therefore checksum (and validity of data) can't be checked
+                    // Answer with receiver setting (and size that arrived) and let sender ask again
+                    Err(ParsingError::InvalidSize(expect, actual)) => {
+                        config.vlog(&format!("Expected init packet of size {}, but received {}", expect, actual));
+                        let return_init = InitPacket::new(
+                            config.max_window_size,
+                            min(config.max_packet_size, packet_size as u16),
+                            config.min_checksum
+                        );
+                        config.vlog(&format!(
+                            "Return init packet with properties, window size: {}, packet_size: {}, checksum: {}",
+                            return_init.window_size,
+                            return_init.packet_size,
+                            return_init.checksum_size
+                        ));
+                        let answer_packet_size = Packet::from(return_init).to_bin_buff(&mut response, config.min_checksum as usize);
+                        socket.send_to(&response[..answer_packet_size], received_from).expect("Can't answer with init packet after invalid size");
+                        config.vlog("Return init packet send back");
+                    }
+                    // Other error
+                    Err(e) => {
+                        config.vlog(&format!("Error parsing init packet {:?}", e));
+                    }
+                };
                 continue;
             }
-        };
-        // parse packet if possible
-        let packet = Packet::from_bin(&packet_content, prop.static_properties.checksum_size as usize);
 
-        // process the flag
-        match packet {
-            Err(ParsingError::InvalidFlag(f)) => {
-                config.vlog(&format!("Invalid flag {} received, ignoring packet", f));
-            }
-            Err(ParsingError::ChecksumNotMatch) => {
-                config.vlog("Checksum does not match, ignoring");
+            // validate connection id and get the properties of the connection
+            let conn_id = header.id;
+            // the sender sending to the group uses the identifier confirmed by another member,
+            // the connection from the sender, that got nothing since the init answer, continues under it
+            if config.is_group_member() && conn_id != 0 && !properties.contains_key(&conn_id) {
+                let awaiting = properties.values()
+                    .filter(|prop| prop.static_properties.socket_addr == received_from && prop.awaits_first_packet())
+                    .map(|prop| prop.static_properties.id)
+                    .collect::<Vec<u32>>();
+                if let [own_id] = awaiting[..] {
+                    let mut prop = properties.remove(&own_id).expect("Connection adopting the identifier is not in properties");
+                    prop.adopt_id(conn_id);
+                    if let Some(writers) = &writers {
+                        prop.write_behind(writers.queue(conn_id));
+                    }
+                    properties.insert(conn_id, prop);
+                    config.vlog(&format!("Connection {} continues as connection {} confirmed by another receiver of the group", own_id, conn_id));
+                }
             }
-            Err(ParsingError::InvalidSize(exp, act)) => {
-                config.vlog(&format!("Expected packet with size {}b, but only {}b received, ignoring", exp, act));
+            let prop = match properties.get_mut(&conn_id) {
+                Some(p) => p,
+                None => {
+                    config.vlog(&format!("Received data packet for connection {}, but it doesn't exists", conn_id));
+                    // the connection is gone already, so there is nothing to delete, the checksum size is not known
+                    let abort = ErrorPacket::from_bin(packet_content).ok()
+                        .filter(|packet| packet.header.flag == Flag::Error && packet.is_abort());
+                    if let Some(abort) = abort {
+                        let confirmation_size = Packet::from(abort).to_bin_buff(&mut response, 0);
+                        socket.send_to(&response[..confirmation_size], received_from).expect("Can't confirm abort of unknown connection");
+                        config.vlog(&format!("Abort of unknown connection {} confirmed", conn_id));
+                        continue;
+                    }
+                    // the checksum size of the connection is not known, the error is send without checksum
+                    if config.reject_unknown && header.flag != Flag::Error {
+                        let error_packet = Packet::from(ErrorPacket::unknown_connection(conn_id));
+                        let error_size = error_packet.to_bin_buff(&mut response, 0);
+                        socket.send_to(&response[..error_size], received_from).expect("Can't send error packet for unknown connection");
+                        config.vlog(&format!("Connection {} rejected", conn_id));
+                    }
+                    continue;
+                }
+            };
+            // make sure the packet comes from the sender of the connection
+            if prop.static_properties.socket_addr != received_from {
+                config.info(&format!(
+                    "Packet for connection {} received from {}, but the connection belongs to {}, ignoring",
+                    conn_id,
+                    received_from,
+                    prop.static_properties.socket_addr
+                ));
+                continue;
             }
-
-            // data packet
-            Ok(Packet::Data(packet)) => {
+            // single packet can't be bigger than agreed, such datagram merged more packets together
+            if packet_content.len() > prop.static_properties.packet_size as usize {
                 config.vlog(&format!(
-                    "Data packet for {} with seq {} and {}b of data, window at {} with size {}",
-                    prop.static_properties.id,
-                    packet.header.seq,
-                    packet.data.len(),
-                    prop.window_position,
-                    prop.static_properties.window_size
+                    "Received {}b for connection {}, that uses packets of at most {}b, ignoring",
+                    packet_content.len(),
+                    conn_id,
+                    prop.static_properties.packet_size
                 ));
-                // make sure it is within window
-                if !prop.is_within_window(packet.header.seq, &config) {
-                    config.vlog("Data packed is not within window");
+                continue;
+            }
+            // parse packet if possible
+            let packet = prop.parse(&packet_content);
+
+            // process the flag
+            match packet {
+                Err(ParsingError::InvalidFlag(f)) => {
+                    config.vlog(&format!("Invalid flag {} received, ignoring packet", f));
+                }
+                Err(ParsingError::ChecksumNotMatch) => {
+                    config.vlog("Checksum does not match, ignoring");
+                    config.emit(ReceiverEvent::CorruptedDropped);
                 }
-                else {
-                    // store it into structure
-                    prop.store_data(&packet.data, packet.header.seq, &config);
-                    // save it into file
-                    prop.save_into_file(&config);
+                Err(ParsingError::InvalidSize(exp, act)) => {
+                    config.vlog(&format!("Expected packet with size {}b, but only {}b received, ignoring", exp, act));
+                    config.emit(ReceiverEvent::CorruptedDropped);
                 }
-                // return response
-                let ack = prop.get_acknowledge();
-                let packet = DataPacket::new_receiver(
-                    prop.static_properties.id,
-                    packet.header.seq,
-                    ack
-                );
-                config.vlog(&format!("Answer with ack {}", packet.header.ack));
-                let packet = Packet::from(packet);
-                let response_size = packet.to_bin_buff(&mut buffer, prop.static_properties.checksum_size as usize);
-                socket.send_to(&buffer[..response_size], received_from).expect("Can't respond to data packet");
-                config.vlog("Answer data packet send");
-            },
-
-            // error packet
-            Ok(Packet::Error(_)) => {
-                let mut prop = properties.remove(&conn_id).expect("Can't remove connection property");
-                remove_connection(&mut prop, &config, &mut buffer, &socket, "error packet");
-                println!("Error received in connection {}", prop.static_properties.id);
-            },
-
-            // end packet
-            Ok(Packet::End(packet)) => {
-                if prop.parts_received.len() > 0 || prop.window_position != packet.header.seq {
-                    config.vlog("Attempt to end packet, that has some blocks not stored");
-                    let mut prop = properties.remove(&conn_id).expect("Can't remove connection properties for end packet with some data left");
-                    remove_connection(&mut prop, &config, &mut buffer, &socket, "end packet with some data left");
-                    continue;
+
+                // late data of the lingering connection, the unknown connections are rejected after the linger expires
+                Ok(Packet::Data(packet)) if prop.is_closed() => {
+                    config.vlog(&format!("Data packet with seq {} for closed connection {}, ignoring", packet.header.seq, conn_id));
+                    config.emit(ReceiverEvent::DuplicateDropped);
                 }
-                prop.close();
-                let response_packet = Packet::from(EndPacket::new(conn_id, prop.window_position));
-                let response_length = response_packet.to_bin_buff(&mut buffer, prop.static_properties.checksum_size as usize);
-                socket.send_to(&buffer[..response_length], received_from).expect("Can't send end packet");
-                config.vlog(&format!("End of connection {}", prop.static_properties.id));
-            },
-
-            Ok(_) => {
-                config.vlog("Received unexpected packet, ignoring");
-            }
-        }; // end of packet match
+
+                // the content from the offset can't be stored, until the metadata name the file it continues
+                Ok(Packet::Data(_)) if config.stores_files() && prop.awaits_file() => {
+                    config.info(&format!("Connection {} sends data from offset {} without the file to continue", conn_id, prop.start_offset()));
+                    let mut prop = properties.remove(&conn_id).expect("Can't remove connection properties without file to continue");
+                    remove_connection(&mut prop, &config, &mut response, &socket, ErrorReason::MissingTarget);
+                }
+
+                // data packet
+                Ok(Packet::Data(packet)) => {
+                    config.vlog(&format!(
+                        "Data packet for {} with seq {} and {}b of data, window at {} with size {}",
+                        prop.static_properties.id,
+                        packet.header.seq,
+                        packet.data.len(),
+                        prop.window_position,
+                        prop.static_properties.window_size
+                    ));
+                    // the part out of order makes room in the memory of all the connections, the in order one frees it
+                    let buffered = packet.header.seq != prop.window_position && prop.is_within_window(packet.header.seq, &config);
+                    let fits = match config.max_total_buffer_bytes {
+                        Some(max_total) if buffered => make_room(&mut properties, conn_id, packet.data.len(), max_total, &config, &mut response, &sockets),
+                        _ => true,
+                    };
+                    let prop = properties.get_mut(&conn_id).expect("Connection making room is not in properties");
+                    // make sure it is within window
+                    prop.register_unacknowledged(packet.header.seq);
+                    if congested {
+                        prop.mark_congestion();
+                    }
+                    let within_window = prop.is_within_window(packet.header.seq, &config);
+                    let mut stored = false;
+                    if !within_window {
+                        config.vlog("Data packed is not within window");
+                        // parts just before the window were stored already, their acknowledge was lost
+                        let behind = prop.window_position.wrapping_sub(packet.header.seq);
+                        // the repeated part shows the sender is alive, the paused sender repeats it to keep the connection
+                        match behind <= prop.static_properties.window_size {
+                            true => {
+                                prop.last_receive_time = Instant::now();
+                                config.emit(ReceiverEvent::DuplicateDropped);
+                            }
+                            false => config.emit(ReceiverEvent::OutOfWindowDropped),
+                        };
+                    }
+                    else if !fits {
+                        config.vlog(&format!("Data under seq {} dropped, no connection can make room for them", packet.header.seq));
+                        config.emit(ReceiverEvent::BufferFull(packet.header.seq));
+                    }
+                    else {
+                        // store it into structure
+                        stored = prop.store_data(&packet.data, packet.header.seq, packet.header.ack, &config);
+                        // save it into file, the connection can't continue without it
+                        if let Err(e) = prop.save_into_file(&config) {
+                            let reason = write_failure(&config, conn_id, &e);
+                            let mut prop = properties.remove(&conn_id).expect("Can't remove connection properties for failed write");
+                            remove_connection(&mut prop, &config, &mut response, &socket, reason);
+                            continue;
+                        }
+                        // make sure the file is not too big
+                        if let Some(max_file_bytes) = config.max_file_bytes {
+                            if prop.written_bytes > max_file_bytes {
+                                let mut prop = properties.remove(&conn_id).expect("Can't remove connection properties for too big file");
+                                remove_connection(&mut prop, &config, &mut response, &socket, ErrorReason::FileTooBig);
+                                continue;
+                            }
+                        }
+                        // the end packet, that overtook the data, is answered once they are stored
+                        if let Some(end) = prop.take_ready_end() {
+                            config.vlog(&format!("Data of connection {} before the end packet stored", conn_id));
+                            match end_connection(prop, &end, &config, &mut response, &socket, received_from, &mut received_files) {
+                                Ok(Some(transfer)) => {
+                                    if is_file_transfer(&transfer, &end) {
+                                        completed += 1;
+                                    }
+                                    completed_transfers.push(transfer);
+                                }
+                                Ok(None) => {}
+                                Err(reason) => {
+                                    let mut prop = properties.remove(&conn_id).expect("Can't remove connection properties for failed end");
+                                    remove_connection(&mut prop, &config, &mut response, &socket, reason);
+                                }
+                            };
+                            continue;
+                        }
+                    }
+                    // return response, repeated and dropped packets are acknowledged immediately
+                    if stored && !prop.acknowledge_due(config.ack_strategy) {
+                        config.vlog("Acknowledge postponed");
+                        continue;
+                    }
+                    send_acknowledge(prop, &config, &mut response, &socket, received_from);
+                },
+
+                // error packet
+                Ok(Packet::Error(packet)) if packet.is_abort() && !prop.is_closed() => {
+                    let mut prop = properties.remove(&conn_id).expect("Can't remove connection property for abort");
+                    discard_output(&mut prop, &config);
+                    config.info(&format!("Connection {} cancelled by the sender", conn_id));
+                    config.emit(ReceiverEvent::ConnectionClosed(ErrorReason::Abort.to_string()));
+                    let confirmation_size = Packet::from(ErrorPacket::abort(conn_id)).to_bin_buff(&mut response, prop.static_properties.checksum_size as usize);
+                    socket.send_to(&response[..confirmation_size], received_from).expect("Can't confirm abort");
+                },
+                Ok(Packet::Error(_)) => {
+                    let mut prop = properties.remove(&conn_id).expect("Can't remove connection property");
+                    remove_connection(&mut prop, &config, &mut response, &socket, ErrorReason::ErrorReceived);
+                    config.info(&format!("Error received in connection {}", prop.static_properties.id));
+                },
+
+                // metadata of the file, confirmed by sending them back
+                Ok(Packet::Metadata(packet)) if !prop.is_closed() => {
+                    config.vlog(&format!("Connection {} receives file {} of {}b", conn_id, packet.path, packet.size));
+                    let checksum_size = prop.static_properties.checksum_size as usize;
+                    let answer_size = Packet::from(packet.clone()).to_bin_buff(&mut response, checksum_size);
+                    let name = packet.path.clone();
+                    prop.describe(packet);
+                    // the file is named by the sender, but the name can't lead outside of the directory
+                    if config.stores_files() {
+                        let named = remote_output_path(&config, prop, &name);
+                        // make sure no other active connection writes into the same file, the closed ones wrote it already
+                        let active_paths = properties.iter()
+                            .filter(|(id, prop)| **id != conn_id && !prop.is_closed())
+                            .map(|(_, prop)| prop.output_path.clone())
+                            .collect::<HashSet<String>>();
+                        let prop = properties.get_mut(&conn_id).expect("Connection receiving metadata is not in properties");
+                        // the content from the offset continues the existing file with the same name
+                        if prop.awaits_file() {
+                            let continued = match named.filter(|path| !active_paths.contains(path)) {
+                                Some(path) => prop.continue_file(path.clone()).map_err(|e| format!("{}: {}", path, e)),
+                                None => Err(format!("{} doesn't denote any file, that is not written", name)),
+                            };
+                            if let Err(e) = continued {
+                                config.info(&format!("Connection {} can't continue file from offset {}, {}", conn_id, prop.start_offset(), e));
+                                let mut prop = properties.remove(&conn_id).expect("Can't remove connection properties without file to continue");
+                                remove_connection(&mut prop, &config, &mut response, &socket, ErrorReason::MissingTarget);
+                                continue;
+                            }
+                            config.vlog(&format!("Connection {} continues file {} from offset {}", conn_id, prop.output_path, prop.start_offset()));
+                        }
+                        else {
+                            match named.map(|path| unique_output_path(path, &active_paths)) {
+                                Some(path) if path != prop.output_path => {
+                                    let previous = prop.output_path.clone();
+                                    match prop.rename_output(path) {
+                                        true => config.vlog(&format!("Connection {} stores file {} instead of {}", conn_id, prop.output_path, previous)),
+                                        false => config.vlog(&format!("Connection {} wrote into {} already, keeping it", conn_id, previous)),
+                                    };
+                                }
+                                Some(_) => {}
+                                None => config.vlog(&format!("Name {} doesn't denote any file, connection {} keeps {}", name, conn_id, prop.output_path)),
+                            };
+                        }
+                    }
+                    socket.send_to(&response[..answer_size], received_from).expect("Can't confirm metadata");
+                },
+
+                // end packet
+                Ok(Packet::End(packet)) => {
+                    // the confirmation was lost on the way, send it again
+                    if let Some(confirmation) = prop.end_confirmation() {
+                        socket.send_to(confirmation, received_from).expect("Can't send end packet");
+                        config.vlog(&format!("Repeated end of connection {}, confirmation send again", prop.static_properties.id));
+                        continue;
+                    }
+                    if !prop.is_complete(&packet) {
+                        // the end packet may overtake the last data, it waits for them for a while
+                        if config.end_grace > 0 && !prop.is_closed() {
+                            config.vlog(&format!("End packet of connection {} came before some data, waiting for them", conn_id));
+                            prop.postpone_end(packet);
+                            continue;
+                        }
+                        config.vlog("Attempt to end packet, that has some blocks not stored");
+                        let mut prop = properties.remove(&conn_id).expect("Can't remove connection properties for end packet with some data left");
+                        remove_connection(&mut prop, &config, &mut response, &socket, ErrorReason::MissingData);
+                        continue;
+                    }
+                    match end_connection(prop, &packet, &config, &mut response, &socket, received_from, &mut received_files) {
+                        Ok(Some(transfer)) => {
+                            if is_file_transfer(&transfer, &packet) {
+                                completed += 1;
+                            }
+                            completed_transfers.push(transfer);
+                        }
+                        Ok(None) => {}
+                        Err(reason) => {
+                            let mut prop = properties.remove(&conn_id).expect("Can't remove connection properties for failed end");
+                            remove_connection(&mut prop, &config, &mut response, &socket, reason);
+                        }
+                    };
+                },
+
+                Ok(_) => {
+                    config.vlog("Received unexpected packet, ignoring");
+                }
+            }; // end of packet match
+        } // end of the received datagrams
     }; // end of the main loop
-    return Ok(());
+    return Ok(completed_transfers);
 } // end of the receiver method
 
 
+/// How long every socket is waited for, while the receiver polls more of them.
+const POLL_SLICE: Duration = Duration::from_millis(1);
+
+/// Receive datagrams from any of the `sockets` into the `buffers` within the `read_timeout`.
+/// The single socket is waited for by the system, more of them are polled in turn.
+/// Returns index of the socket and the datagrams received from it.
+fn recv_any(
+    sockets: &[UdpSocket],
+    buffers: &mut [Vec<u8>],
+    read_timeout: Duration,
+    config: &Config,
+) -> io::Result<(usize, Vec<(usize, SocketAddr)>)> {
+    if sockets.len() == 1 {
+        return recv_batch(&sockets[0], buffers, Box::new(config)).map(|received| (0, received));
+    }
+    let deadline = Instant::now() + read_timeout;
+    loop {
+        for (index, socket) in sockets.iter().enumerate() {
+            match recv_batch(socket, buffers, Box::new(config)) {
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+                result => return result.map(|received| (index, received)),
+            };
+        }
+        if Instant::now() >= deadline {
+            return Err(io::Error::from(ErrorKind::TimedOut));
+        }
+    }
+}
+
+/// Close the connection `prop` by the end `packet` from the `addr`, after all its data were stored.
+/// Returns the file, when it was received completely just now, or the reason why the connection must be removed.
+fn end_connection(
+    prop: &mut ReceiverConnectionProperties,
+    packet: &EndPacket,
+    config: &Config,
+    response: &mut [u8],
+    socket: &UdpSocket,
+    addr: SocketAddr,
+    received_files: &mut ReceivedFiles,
+) -> Result<Option<CompletedTransfer>, ErrorReason> {
+    let conn_id = prop.static_properties.id;
+    let newly_closed = !prop.is_closed();
+    let quarantined = newly_closed && config.quarantine_dir.is_some() && config.stores_files();
+    // the dry run validates the content only by the digest
+    let validated = quarantined || (newly_closed && config.dry_run);
+    if validated && packet.digest != Some(prop.digest()) {
+        config.info(&format!("Content of connection {} doesn't match the digest of the sender", conn_id));
+        return Err(ErrorReason::DigestMismatch);
+    }
+    // the content written behind is confirmed only after it is written and stored on the disk
+    if newly_closed {
+        prop.wait_for_writes().map_err(|e| write_failure(config, conn_id, &e))?;
+        if config.stores_files() {
+            prop.flush().map_err(|e| write_failure(config, conn_id, &e))?;
+        }
+    }
+    // the quarantined file is discarded, when it can't be moved
+    if quarantined {
+        let output_path = prop.output_path.clone();
+        prop.move_into(&config.directory).map_err(|e| write_failure(config, conn_id, &e))?;
+        config.vlog(&format!("Verified file {} moved to {}", output_path, prop.output_path));
+    }
+    // the sender may verify the content by the digest of the stored data,
+    // the pipe and the dry run confirm the digest without storing the content
+    let mut response_packet = EndPacket::new(conn_id, prop.window_position);
+    response_packet.digest = Some(prop.digest());
+    response_packet.stored = config.stores_files();
+    let response_packet = Packet::from(response_packet);
+    let response_length = response_packet.to_bin_buff(response, prop.static_properties.checksum_size as usize);
+    prop.linger(Vec::from(&response[..response_length]));
+    if newly_closed {
+        if let Err(e) = prop.apply_metadata(config) {
+            config.info(&format!("Can't apply metadata to file {}: {}", prop.output_path, e));
+        }
+    }
+    if newly_closed && config.dedup && config.stores_files() {
+        deduplicate(prop, config, received_files);
+    }
+    if newly_closed && config.dry_run {
+        config.info(&format!("Dry run of connection {} succeeded, {}b with digest {:08x}", conn_id, prop.written_bytes, prop.digest()));
+    }
+    let completed = match newly_closed {
+        true => {
+            config.emit(ReceiverEvent::Completed(prop.output_path.clone(), prop.written_bytes, prop.static_properties.socket_addr));
+            Some(prop.completed())
+        }
+        false => None,
+    };
+    socket.send_to(&response[..response_length], addr).expect("Can't send end packet");
+    config.vlog(&format!("End of connection {}", conn_id));
+    return Ok(completed);
+}
+
+/// Whether the `transfer` closed by the `end` packet carried a file.
+/// The connections of the packet size probes end without any data and without the digest of the content.
+fn is_file_transfer(transfer: &CompletedTransfer, end: &EndPacket) -> bool {
+    return transfer.bytes > 0 || end.digest.is_some();
+}
+
 fn remove_connection(
     prop: &mut ReceiverConnectionProperties,
     config: &Config,
     mut buffer: & mut Vec<u8>,
     socket: &UdpSocket,
-    reason: &str,
+    reason: ErrorReason,
 ) {
     // if the connection end successfully and now the structure is just deleted
     if prop.is_closed() {
         config.vlog(&format!("Connection {} definitely removed", prop.static_properties.id));
         return;
     }
-    // delete the temp file
-    prop.close();
-    let filename = config.filename(prop.static_properties.id);
-    let filepath = Path::new(&filename);
-    if filepath.exists() {
-        std::fs::remove_file(filepath).expect(&format!("Can't delete file for timeouted connection {}", prop.static_properties.id));
-        config.vlog(&format!("Deleted file {}", filename));
-    }
+    discard_output(prop, config);
     // send back the error packet
     config.vlog(&format!("Connection {} closed because of {}", prop.static_properties.id, reason));
-    let err_packet = Packet::from(ErrorPacket::new(prop.static_properties.id));
+    config.emit(ReceiverEvent::ConnectionClosed(reason.to_string()));
+    let err_packet = Packet::from(ErrorPacket::with_reason(prop.static_properties.id, reason));
     let bytes_to_write = err_packet.to_bin_buff(&mut buffer, prop.static_properties.checksum_size as usize);
     socket.send_to(&buffer[..bytes_to_write], prop.static_properties.socket_addr)
         .expect(&format!("Can't send error packet about the {}", reason));
@@ -300,3 +840,1011 @@ fn remove_connection(
         prop.static_properties.id
     ));
 }
+
+/// Reason to close the connection `conn_id`, whose content can't be written because of the `error`.
+fn write_failure(config: &Config, conn_id: u32, error: &io::Error) -> ErrorReason {
+    config.info(&format!("Can't write into file of connection {}: {}", conn_id, error));
+    return match error.kind() {
+        ErrorKind::StorageFull => ErrorReason::DiskFull,
+        _ => ErrorReason::WriteFailed,
+    };
+}
+
+/// Close the least recently active connections with buffered parts, until `size` more bytes for the connection `conn_id`
+/// fit into `max_total` bytes buffered by all the connections in the `properties`.
+/// Returns whether they fit, the connection `conn_id` itself is never closed.
+fn make_room(
+    properties: &mut PropertiesMap<u32, ReceiverConnectionProperties>,
+    conn_id: u32,
+    size: usize,
+    max_total: usize,
+    config: &Config,
+    response: &mut Vec<u8>,
+    sockets: &[UdpSocket],
+) -> bool {
+    loop {
+        let total = properties.values().map(|prop| prop.buffered_bytes()).sum::<usize>();
+        if total + size <= max_total {
+            return true;
+        }
+        let least_active = properties.iter()
+            .filter(|(id, prop)| **id != conn_id && prop.buffered_bytes() > 0)
+            .min_by_key(|(_, prop)| prop.last_receive_time)
+            .map(|(id, _)| *id);
+        match least_active {
+            Some(id) => {
+                config.vlog(&format!("{}b buffered by all the connections, closing connection {}", total, id));
+                let mut prop = properties.remove(&id).expect("Connection is not in properties");
+                let socket = &sockets[prop.socket_index];
+                remove_connection(&mut prop, config, response, socket, ErrorReason::OutOfMemory);
+            }
+            None => return false,
+        };
+    }
+}
+
+/// Remove the connections, that linger the longest, so at most `max_lingering` of them remain in the `properties`.
+fn evict_lingering(properties: &mut PropertiesMap<u32, ReceiverConnectionProperties>, config: &Config) {
+    let mut lingering = properties.iter()
+        .filter_map(|(id, prop)| prop.closed_at().map(|closed_at| (closed_at, *id)))
+        .collect_vec();
+    if lingering.len() <= config.max_lingering {
+        return;
+    }
+    lingering.sort();
+    for (_, conn_id) in &lingering[..lingering.len() - config.max_lingering] {
+        properties.remove(conn_id);
+        config.vlog(&format!("Connection {} stops lingering, too many connections linger", conn_id));
+    }
+}
+
+/// Close the connection `prop` and delete its partial file.
+fn discard_output(prop: &mut ReceiverConnectionProperties, config: &Config) {
+    // delete the temp file, the data written into the pipe can't be taken back
+    prop.close();
+    let filename = &prop.output_path;
+    let filepath = Path::new(filename);
+    if config.fifo.is_some() {
+        config.info(&format!("Connection {} failed, {}b already written into {}", prop.static_properties.id, prop.written_bytes, filename));
+    }
+    // the continued file existed before the connection
+    else if prop.start_offset() > 0 {
+        config.info(&format!("Connection {} failed, {}b already written into {} from offset {}", prop.static_properties.id, prop.written_bytes, filename, prop.start_offset()));
+    }
+    else if filepath.exists() {
+        std::fs::remove_file(filepath).expect(&format!("Can't delete file for timeouted connection {}", prop.static_properties.id));
+        config.vlog(&format!("Deleted file {}", filename));
+    }
+}
+
+/// Acknowledge all the data packets received in the connection `prop` to the `addr`.
+/// With `compact_acks` in the `config` the ack packet is used instead of the data packet.
+/// The acknowledge is marked as congested, when some of the data packets were.
+fn send_acknowledge(
+    prop: &mut ReceiverConnectionProperties,
+    config: &Config,
+    buffer: &mut [u8],
+    socket: &UdpSocket,
+    addr: SocketAddr,
+) {
+    let ack = prop.get_acknowledge();
+    config.vlog(&format!("Answer with ack {}", ack));
+    let packet = match config.compact_acks {
+        true => Packet::from(AckPacket::new(prop.static_properties.id, ack)),
+        false => Packet::from(DataPacket::new_receiver(prop.static_properties.id, prop.last_seq, ack)),
+    };
+    let response_size = packet.to_bin_buff(buffer, prop.static_properties.checksum_size as usize);
+    // the congestion is echoed outside of the checksum, the same way the network marks it
+    if prop.is_congested() {
+        PacketHeader::mark_congestion(&mut buffer[..response_size]);
+    }
+    socket.send_to(&buffer[..response_size], addr).expect("Can't respond to data packet");
+    prop.acknowledged();
+    config.vlog("Answer data packet send");
+}
+
+/// Replace the file received in the connection `prop` by a hard link, when the same content was already received.
+/// Paths of the received files are stored in `received_files` by the hash of their content.
+/// The file is kept as it is, when the link can't be created.
+fn deduplicate(
+    prop: &ReceiverConnectionProperties,
+    config: &Config,
+    received_files: &mut ReceivedFiles,
+) {
+    let path = &prop.output_path;
+    if !Path::new(path).exists() {
+        return;
+    }
+    let hash = prop.content_hash();
+    // make sure it is not just collision of the hashes
+    let original = received_files.get(hash)
+        .filter(|original| same_content(original, path).unwrap_or(false))
+        .cloned();
+    match original {
+        Some(original) => match link_over(&original, path) {
+            Ok(()) => config.vlog(&format!("File {} is the same as {}, replaced by link", path, original)),
+            Err(e) => config.vlog(&format!("Can't link {} to the same file {}, keeping the copy: {}", path, original, e)),
+        },
+        None => {
+            received_files.insert(hash, path.clone());
+        },
+    }
+}
+
+/// Make sure the `directory` for the received files exists, it is created when it is missing.
+/// Fails when the path exists, but it is not a directory.
+fn prepare_directory(directory: &str) -> Result<(), String> {
+    let path = Path::new(directory);
+    if path.exists() && !path.is_dir() {
+        return Err(format!("Output directory {} is not a directory", directory));
+    }
+    return fs::create_dir_all(path).map_err(|e| format!("Can't create output directory {}: {}", directory, e));
+}
+
+/// Path for the file `name` chosen by the sender of the connection `prop`, the resumed file isn't quarantined.
+/// Returns `None` when the name doesn't denote any file.
+fn remote_output_path(config: &Config, prop: &ReceiverConnectionProperties, name: &str) -> Option<String> {
+    return match prop.start_offset() {
+        0 if config.quarantine_dir.is_some() => config.remote_quarantine_filename(name),
+        _ => config.remote_filename(name),
+    };
+}
+
+/// Get path based on `path`, that is not within the `active_paths` and that is not taken by the file stored already.
+/// If the path is in use, numeric suffix is appended to it.
+fn unique_output_path(path: String, active_paths: &HashSet<String>) -> String {
+    let taken = |candidate: &String| active_paths.contains(candidate) || Path::new(candidate).exists();
+    if !taken(&path) {
+        return path;
+    }
+    let mut suffix = 1;
+    loop {
+        let candidate = format!("{}_{}", path, suffix);
+        if !taken(&candidate) {
+            return candidate;
+        }
+        suffix += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::fs::{create_dir_all, read, read_dir, remove_dir_all, remove_file, write};
+    use std::net::UdpSocket;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread::{self, sleep};
+    use std::time::Duration;
+    use std::time::Instant;
+    use crate::packet::{crc32, DataPacket, EndPacket, ErrorReason, InitPacket, Packet};
+    use crate::receiver::config::{Config, AckStrategy};
+    use super::{breakable_logic, monitored_logic, receive_all, unique_output_path};
+
+    #[test]
+    fn file_as_directory_is_reported_at_start() {
+        const TARGET: &str = "received_is_file";
+        write(TARGET, b"content").unwrap();
+        let mut config = Config::new();
+        config.bindaddr = String::from("127.0.0.1:3162");
+        config.directory = String::from(TARGET);
+        let result = breakable_logic(config, Arc::new(AtomicBool::new(false))).join().unwrap();
+        assert_eq!(result, Err(String::from("Output directory received_is_file is not a directory")));
+        remove_file(TARGET).unwrap();
+    }
+
+    #[test]
+    fn missing_directory_is_created() {
+        const TARGET: &str = "received_created/nested";
+        let mut config = Config::new();
+        config.bindaddr = String::from("127.0.0.1:3163");
+        config.directory = String::from(TARGET);
+        let brk = Arc::new(AtomicBool::new(true));
+        breakable_logic(config, brk).join().unwrap().unwrap();
+        assert!(Path::new(TARGET).is_dir());
+        remove_dir_all("received_created").unwrap();
+    }
+
+    #[test]
+    fn corrupted_file_stays_out_of_directory() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3165";
+        const TARGET_DIR: &str = "received_verified";
+        const QUARANTINE_DIR: &str = "received_quarantine";
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.quarantine_dir = Some(String::from(QUARANTINE_DIR));
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver = breakable_logic(config, brk.clone());
+        sleep(Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let mut buffer = vec![0; 2000];
+        let mut transfer = |content: &[u8], digest: u32| -> Packet {
+            let mut exchange = |packet: Packet| -> Packet {
+                let size = packet.to_bin_buff(&mut buffer, 16);
+                socket.send_to(&buffer[..size], RECEIVER_ADDR).unwrap();
+                let (size, _) = socket.recv_from(&mut buffer).unwrap();
+                Packet::from_bin(&buffer[..size], 16).unwrap()
+            };
+            let id = match exchange(Packet::from(InitPacket::new(1, 1000, 16))) {
+                Packet::Init(p) => p.header.id,
+                p => panic!("{:?}", p),
+            };
+            exchange(Packet::from(DataPacket::new(Vec::from(content), id, 0, 0)));
+            let mut end = EndPacket::new(id, 1);
+            end.digest = Some(digest);
+            exchange(Packet::from(end))
+        };
+        // the content was modified on the way
+        match transfer(&[1, 2, 3], crc32(&[1, 2, 4])) {
+            Packet::Error(_) => {},
+            p => panic!("{:?}", p),
+        };
+        assert_eq!(read_dir(TARGET_DIR).unwrap().count(), 0);
+        assert_eq!(read_dir(QUARANTINE_DIR).unwrap().count(), 0);
+        // the verified content is moved to the directory
+        match transfer(&[1, 2, 3], crc32(&[1, 2, 3])) {
+            Packet::End(_) => {},
+            p => panic!("{:?}", p),
+        };
+        let received = read_dir(TARGET_DIR).unwrap().next().unwrap().unwrap();
+        assert_eq!(read(received.path()).unwrap(), vec![1, 2, 3]);
+        assert_eq!(read_dir(QUARANTINE_DIR).unwrap().count(), 0);
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap().unwrap();
+        remove_dir_all(TARGET_DIR).unwrap();
+        remove_dir_all(QUARANTINE_DIR).unwrap();
+    }
+
+    #[test]
+    fn dry_run_validates_without_files() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3185";
+        const TARGET_DIR: &str = "received_dry_run";
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.dry_run = true;
+        config.quiet = true;
+        let receiver = thread::spawn(move || receive_all(config, Duration::from_millis(500)));
+        sleep(Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let mut buffer = vec![0; 2000];
+        let mut transfer = |parts: &[&[u8]], digest: u32| -> Packet {
+            let mut exchange = |packet: Packet| -> Packet {
+                let size = packet.to_bin_buff(&mut buffer, 16);
+                socket.send_to(&buffer[..size], RECEIVER_ADDR).unwrap();
+                let (size, _) = socket.recv_from(&mut buffer).unwrap();
+                Packet::from_bin(&buffer[..size], 16).unwrap()
+            };
+            let id = match exchange(Packet::from(InitPacket::new(2, 1000, 16))) {
+                Packet::Init(p) => p.header.id,
+                p => panic!("{:?}", p),
+            };
+            for (seq, part) in parts.iter().enumerate() {
+                exchange(Packet::from(DataPacket::new(Vec::from(*part), id, seq as u16, 0)));
+            }
+            let mut end = EndPacket::new(id, parts.len() as u16);
+            end.digest = Some(digest);
+            exchange(Packet::from(end))
+        };
+        // the content modified on the way fails the validation
+        match transfer(&[&[1, 2], &[3]], crc32(&[1, 2, 4])) {
+            Packet::Error(p) => assert_eq!(p.reason(), ErrorReason::DigestMismatch),
+            p => panic!("{:?}", p),
+        };
+        // the valid content is confirmed by its digest
+        match transfer(&[&[1, 2], &[3]], crc32(&[1, 2, 3])) {
+            Packet::End(p) => assert_eq!(p.digest, Some(crc32(&[1, 2, 3]))),
+            p => panic!("{:?}", p),
+        };
+
+        let completed = receiver.join().unwrap().unwrap();
+        assert_eq!(completed.len(), 1);
+        assert_eq!((completed[0].bytes, completed[0].digest), (3, crc32(&[1, 2, 3])));
+        assert!(!Path::new(TARGET_DIR).exists());
+        assert!(!Path::new(&completed[0].path).exists());
+    }
+
+    #[test]
+    fn end_waits_for_overtaken_data() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3177";
+        const TARGET_DIR: &str = "received_overtaken";
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.end_grace = 300;
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver = breakable_logic(config, brk.clone());
+        sleep(Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(2000))).unwrap();
+        let mut buffer = vec![0; 2000];
+        let mut send = |packet: Packet| {
+            let size = packet.to_bin_buff(&mut buffer, 16);
+            socket.send_to(&buffer[..size], RECEIVER_ADDR).unwrap();
+        };
+        let mut buffer = vec![0; 2000];
+        let mut receive = || -> Packet {
+            let (size, _) = socket.recv_from(&mut buffer).unwrap();
+            Packet::from_bin(&buffer[..size], 16).unwrap()
+        };
+        // the end packet comes just before the last data
+        send(Packet::from(InitPacket::new(4, 1000, 16)));
+        let id = match receive() {
+            Packet::Init(p) => p.header.id,
+            p => panic!("{:?}", p),
+        };
+        send(Packet::from(DataPacket::new(vec![1, 2, 3], id, 0, 0)));
+        receive();
+        send(Packet::from(EndPacket::new(id, 2)));
+        send(Packet::from(DataPacket::new(vec![4, 5], id, 1, 0)));
+        match receive() {
+            Packet::End(p) => assert_eq!(p.header.seq, 2),
+            p => panic!("{:?}", p),
+        };
+        let received = read_dir(TARGET_DIR).unwrap().next().unwrap().unwrap();
+        assert_eq!(read(received.path()).unwrap(), vec![1, 2, 3, 4, 5]);
+        // the data never come
+        send(Packet::from(InitPacket::new(4, 1000, 16)));
+        let id = match receive() {
+            Packet::Init(p) => p.header.id,
+            p => panic!("{:?}", p),
+        };
+        send(Packet::from(EndPacket::new(id, 1)));
+        match receive() {
+            Packet::Error(_) => {},
+            p => panic!("{:?}", p),
+        };
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap().unwrap();
+        remove_dir_all(TARGET_DIR).unwrap();
+    }
+
+    #[test]
+    fn data_without_nonce_are_rejected() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3178";
+        const TARGET_DIR: &str = "received_nonce";
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.nonce = true;
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver = breakable_logic(config, brk.clone());
+        sleep(Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let mut buffer = vec![0; 2000];
+        let mut exchange = |packet: Packet| -> Packet {
+            let size = packet.to_bin_buff(&mut buffer, 16);
+            socket.send_to(&buffer[..size], RECEIVER_ADDR).unwrap();
+            let (size, _) = socket.recv_from(&mut buffer).unwrap();
+            Packet::from_bin(&buffer[..size], 16).unwrap()
+        };
+        let (id, nonce) = match exchange(Packet::from(InitPacket::new(4, 1000, 16))) {
+            Packet::Init(p) => (p.header.id, p.nonce),
+            p => panic!("{:?}", p),
+        };
+        assert_ne!(nonce, 0);
+        // the data without the nonce are not stored
+        for ack in &[0, nonce.wrapping_add(1)] {
+            match exchange(Packet::from(DataPacket::new(vec![1, 2, 3], id, 0, *ack))) {
+                Packet::Data(p) => assert_eq!(p.header.ack, u16::MAX),
+                p => panic!("{:?}", p),
+            };
+        }
+        // the sender, that got the answer, echoes the nonce
+        match exchange(Packet::from(DataPacket::new(vec![1, 2, 3], id, 0, nonce))) {
+            Packet::Data(p) => assert_eq!(p.header.ack, 0),
+            p => panic!("{:?}", p),
+        };
+        match exchange(Packet::from(DataPacket::new(vec![4], id, 1, 0))) {
+            Packet::Data(p) => assert_eq!(p.header.ack, 1),
+            p => panic!("{:?}", p),
+        };
+        match exchange(Packet::from(EndPacket::new(id, 2))) {
+            Packet::End(_) => {},
+            p => panic!("{:?}", p),
+        };
+        let received = read_dir(TARGET_DIR).unwrap().next().unwrap().unwrap();
+        assert_eq!(read(received.path()).unwrap(), vec![1, 2, 3, 4]);
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap().unwrap();
+        remove_dir_all(TARGET_DIR).unwrap();
+    }
+
+    #[test]
+    fn unused_path_is_kept() {
+        let active = HashSet::new();
+        assert_eq!(unique_output_path(String::from("received/file"), &active), "received/file");
+    }
+
+    #[test]
+    fn same_path_gets_distinct_names() {
+        let mut active = HashSet::new();
+        let first = unique_output_path(String::from("received/file"), &active);
+        active.insert(first.clone());
+        let second = unique_output_path(String::from("received/file"), &active);
+        active.insert(second.clone());
+        let third = unique_output_path(String::from("received/file"), &active);
+        assert_eq!(first, "received/file");
+        assert_eq!(second, "received/file_1");
+        assert_eq!(third, "received/file_2");
+    }
+
+    #[test]
+    fn stored_file_is_not_reused() {
+        const TARGET_DIR: &str = "received_unique";
+        create_dir_all(TARGET_DIR).unwrap();
+        write(format!("{}/file", TARGET_DIR), b"stored").unwrap();
+        let mut active = HashSet::new();
+        active.insert(format!("{}/file_1", TARGET_DIR));
+        let path = unique_output_path(format!("{}/file", TARGET_DIR), &active);
+        assert_eq!(path, format!("{}/file_2", TARGET_DIR));
+        remove_dir_all(TARGET_DIR).unwrap();
+    }
+
+    #[test]
+    fn repeated_end_is_confirmed_while_lingering() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3150";
+        const TARGET_DIR: &str = "received_linger";
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.timeout = 1000;
+        config.linger = 1000;
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver = breakable_logic(config, brk.clone());
+        sleep(Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let mut buffer = vec![0; 2000];
+        let mut exchange = |packet: Packet, checksum: usize| -> Option<Packet> {
+            let size = packet.to_bin_buff(&mut buffer, checksum);
+            socket.send_to(&buffer[..size], RECEIVER_ADDR).unwrap();
+            let (size, _) = socket.recv_from(&mut buffer).ok()?;
+            Some(Packet::from_bin(&buffer[..size], checksum).unwrap())
+        };
+        // establish connection and send single part
+        let id = match exchange(Packet::from(InitPacket::new(1, 1000, 16)), 16) {
+            Some(Packet::Init(p)) => p.header.id,
+            p => panic!("{:?}", p),
+        };
+        exchange(Packet::from(DataPacket::new(vec![1, 2, 3], id, 0, 0)), 16).unwrap();
+        // first confirmation is dropped
+        exchange(Packet::from(EndPacket::new(id, 1)), 16).unwrap();
+        match exchange(Packet::from(EndPacket::new(id, 1)), 16) {
+            Some(Packet::End(p)) => assert_eq!(p.header.id, id),
+            p => panic!("{:?}", p),
+        };
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap().unwrap();
+        remove_dir_all(TARGET_DIR).unwrap();
+    }
+
+    #[test]
+    fn stop_after_waits_for_lingering_file() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3190";
+        const TARGET_DIR: &str = "received_stop_after";
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.timeout = 5000;
+        config.linger = 500;
+        config.stop_after = Some(1);
+        config.quiet = true;
+        let receiver = breakable_logic(config, Arc::new(AtomicBool::new(false)));
+        sleep(Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let mut buffer = vec![0; 2000];
+        let mut exchange = |packet: Packet| -> Option<Packet> {
+            let size = packet.to_bin_buff(&mut buffer, 16);
+            socket.send_to(&buffer[..size], RECEIVER_ADDR).unwrap();
+            let (size, _) = socket.recv_from(&mut buffer).ok()?;
+            Some(Packet::from_bin(&buffer[..size], 16).unwrap())
+        };
+        // the connection of the packet size probe is not a file
+        let probe = match exchange(Packet::from(InitPacket::new(1, 1000, 16))) {
+            Some(Packet::Init(p)) => p.header.id,
+            p => panic!("{:?}", p),
+        };
+        exchange(Packet::from(EndPacket::new(probe, 0))).unwrap();
+        let id = match exchange(Packet::from(InitPacket::new(1, 1000, 16))) {
+            Some(Packet::Init(p)) => p.header.id,
+            p => panic!("{:?}", p),
+        };
+        exchange(Packet::from(DataPacket::new(vec![1, 2, 3], id, 0, 0))).unwrap();
+        let end = || {
+            let mut end = EndPacket::new(id, 1);
+            end.digest = Some(crc32(&[1, 2, 3]));
+            Packet::from(end)
+        };
+        exchange(end()).unwrap();
+        // the first confirmation was lost, the receiver still lingers
+        sleep(Duration::from_millis(200));
+        assert!(!receiver.is_finished());
+        match exchange(end()) {
+            Some(Packet::End(p)) => assert_eq!(p.header.id, id),
+            p => panic!("{:?}", p),
+        };
+        // ends after the linger
+        let waiting_since = Instant::now();
+        while !receiver.is_finished() && waiting_since.elapsed() < Duration::from_secs(3) {
+            sleep(Duration::from_millis(10));
+        }
+        assert!(receiver.is_finished());
+        receiver.join().unwrap().unwrap();
+        remove_dir_all(TARGET_DIR).unwrap();
+    }
+
+    #[test]
+    fn lingering_connections_are_bounded() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3175";
+        const TARGET_DIR: &str = "received_lingering";
+        const MAX_LINGERING: usize = 5;
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.timeout = 2000;
+        config.linger = 10000;
+        config.max_lingering = MAX_LINGERING;
+        let brk = Arc::new(AtomicBool::new(false));
+        let status = Arc::new(Mutex::new(Vec::new()));
+        let receiver = monitored_logic(config, brk.clone(), status.clone());
+        sleep(Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let mut buffer = vec![0; 2000];
+        let mut exchange = |packet: Packet| -> Option<Packet> {
+            let size = packet.to_bin_buff(&mut buffer, 16);
+            socket.send_to(&buffer[..size], RECEIVER_ADDR).unwrap();
+            let (size, _) = socket.recv_from(&mut buffer).ok()?;
+            Some(Packet::from_bin(&buffer[..size], 16).unwrap())
+        };
+        // open and immediately close many empty connections
+        let mut ids = Vec::new();
+        for _ in 0..4 * MAX_LINGERING {
+            let id = match exchange(Packet::from(InitPacket::new(1, 1000, 16))) {
+                Some(Packet::Init(p)) => p.header.id,
+                p => panic!("{:?}", p),
+            };
+            match exchange(Packet::from(EndPacket::new(id, 0))) {
+                Some(Packet::End(p)) => assert_eq!(p.header.id, id),
+                p => panic!("{:?}", p),
+            };
+            ids.push(id);
+            assert!(status.lock().unwrap().len() <= MAX_LINGERING + 1);
+        }
+        sleep(Duration::from_millis(50));
+        // only the most recently closed connections linger
+        let mut lingering = status.lock().unwrap().iter().map(|snapshot| snapshot.id).collect::<Vec<u32>>();
+        lingering.sort();
+        let mut expected = ids[ids.len() - MAX_LINGERING..].to_vec();
+        expected.sort();
+        assert_eq!(lingering, expected);
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap().unwrap();
+        remove_dir_all(TARGET_DIR).unwrap();
+    }
+
+    #[test]
+    fn late_data_is_dropped_while_lingering() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3176";
+        const TARGET_DIR: &str = "received_late";
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.timeout = 200;
+        config.linger = 100;
+        config.reject_unknown = true;
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver = breakable_logic(config, brk.clone());
+        sleep(Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+        let mut buffer = vec![0; 2000];
+        let mut exchange = |packet: Packet| -> Option<Vec<u8>> {
+            let size = packet.to_bin_buff(&mut buffer, 16);
+            socket.send_to(&buffer[..size], RECEIVER_ADDR).unwrap();
+            let (size, _) = socket.recv_from(&mut buffer).ok()?;
+            Some(Vec::from(&buffer[..size]))
+        };
+        // transfer single part and close the connection
+        let id = match exchange(Packet::from(InitPacket::new(1, 1000, 16))).map(|answer| Packet::from_bin(&answer, 16)) {
+            Some(Ok(Packet::Init(p))) => p.header.id,
+            p => panic!("{:?}", p),
+        };
+        exchange(Packet::from(DataPacket::new(vec![1, 2, 3], id, 0, 0))).unwrap();
+        exchange(Packet::from(EndPacket::new(id, 1))).unwrap();
+        // the repeated part is not answered while the connection lingers
+        assert_eq!(exchange(Packet::from(DataPacket::new(vec![1, 2, 3], id, 0, 0))), None);
+        // the connection is unknown after the linger
+        sleep(Duration::from_millis(400));
+        match exchange(Packet::from(DataPacket::new(vec![1, 2, 3], id, 0, 0))).map(|answer| Packet::from_bin(&answer, 0)) {
+            Some(Ok(Packet::Error(p))) => assert_eq!(p.header.id, id),
+            p => panic!("{:?}", p),
+        };
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap().unwrap();
+        remove_dir_all(TARGET_DIR).unwrap();
+    }
+
+    #[test]
+    fn merged_datagram_is_rejected() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3164";
+        const TARGET_DIR: &str = "received_merged";
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.timeout = 1000;
+        config.linger = 0;
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver = breakable_logic(config, brk.clone());
+        sleep(Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+        let mut buffer = vec![0; 2000];
+        let mut exchange = |content: &[u8]| -> Option<Packet> {
+            socket.send_to(content, RECEIVER_ADDR).unwrap();
+            let (size, _) = socket.recv_from(&mut buffer).ok()?;
+            Some(Packet::from_bin(&buffer[..size], 16).unwrap())
+        };
+        // packets of 48b are aligned to the checksum, so the merged datagram has valid checksum
+        let id = match exchange(&Packet::from(InitPacket::new(2, 48, 16)).to_bin(16)) {
+            Some(Packet::Init(p)) => p.header.id,
+            p => panic!("{:?}", p),
+        };
+        let first = Packet::from(DataPacket::new(vec![1; 23], id, 0, 0)).to_bin(16);
+        let second = Packet::from(DataPacket::new(vec![2; 23], id, 1, 0)).to_bin(16);
+        let merged = [first.clone(), second.clone()].concat();
+        assert!(Packet::from_bin(&merged, 16).is_ok());
+        // the merged datagram is ignored, the parts are accepted when they come separately
+        assert!(exchange(&merged).is_none());
+        match exchange(&first) {
+            Some(Packet::Data(p)) => assert_eq!(p.header.ack, 0),
+            p => panic!("{:?}", p),
+        };
+        match exchange(&second) {
+            Some(Packet::Data(p)) => assert_eq!(p.header.ack, 1),
+            p => panic!("{:?}", p),
+        };
+        match exchange(&Packet::from(EndPacket::new(id, 2)).to_bin(16)) {
+            Some(Packet::End(p)) => assert_eq!(p.header.id, id),
+            p => panic!("{:?}", p),
+        };
+        assert_eq!(read(format!("{}/{}", TARGET_DIR, id)).unwrap(), [vec![1; 23], vec![2; 23]].concat());
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap().unwrap();
+        remove_dir_all(TARGET_DIR).unwrap();
+    }
+
+    #[test]
+    fn every_n_strategy_sends_less_acks() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3151";
+        const TARGET_DIR: &str = "received_ack";
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.timeout = 1000;
+        config.ack_strategy = AckStrategy::EveryN(4);
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver = breakable_logic(config, brk.clone());
+        sleep(Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+        let mut buffer = vec![0; 2000];
+        let mut send = |packet: Packet| {
+            let size = packet.to_bin_buff(&mut buffer, 16);
+            socket.send_to(&buffer[..size], RECEIVER_ADDR).unwrap();
+        };
+        let receive = || -> Vec<Packet> {
+            let mut buffer = vec![0; 2000];
+            let mut packets = Vec::new();
+            while let Ok((size, _)) = socket.recv_from(&mut buffer) {
+                packets.push(Packet::from_bin(&buffer[..size], 16).unwrap());
+            }
+            return packets;
+        };
+        // establish connection
+        send(Packet::from(InitPacket::new(15, 1000, 16)));
+        let id = match receive().pop() {
+            Some(Packet::Init(p)) => p.header.id,
+            p => panic!("{:?}", p),
+        };
+        // ten parts are acknowledged only twice
+        for seq in 0..10 {
+            send(Packet::from(DataPacket::new(vec![seq as u8], id, seq, 0)));
+        }
+        let acks = receive().into_iter().map(|p| match p {
+            Packet::Data(p) => p.header.ack,
+            p => panic!("{:?}", p),
+        }).collect::<Vec<_>>();
+        assert_eq!(acks, vec![3, 7]);
+        // repeated part is acknowledged immediately
+        send(Packet::from(DataPacket::new(vec![9], id, 9, 0)));
+        match receive().pop() {
+            Some(Packet::Data(p)) => assert_eq!(p.header.ack, 9),
+            p => panic!("{:?}", p),
+        };
+        // transfer is completed
+        send(Packet::from(EndPacket::new(id, 10)));
+        match receive().pop() {
+            Some(Packet::End(p)) => assert_eq!(p.header.id, id),
+            p => panic!("{:?}", p),
+        };
+        let content = read(format!("{}/{}", TARGET_DIR, id)).unwrap();
+        assert_eq!(content, (0..10).collect::<Vec<u8>>());
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap().unwrap();
+        remove_dir_all(TARGET_DIR).unwrap();
+    }
+
+    #[test]
+    fn compact_acks_are_smaller() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3174";
+        const TARGET_DIR: &str = "received_compact";
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.compact_acks = true;
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver = breakable_logic(config, brk.clone());
+        sleep(Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let mut buffer = vec![0; 2000];
+        let mut exchange = |packet: Packet| -> (usize, Packet) {
+            let size = packet.to_bin_buff(&mut buffer, 16);
+            socket.send_to(&buffer[..size], RECEIVER_ADDR).unwrap();
+            let (size, _) = socket.recv_from(&mut buffer).unwrap();
+            (size, Packet::from_bin(&buffer[..size], 16).unwrap())
+        };
+        let id = match exchange(Packet::from(InitPacket::new(1, 1000, 16))) {
+            (_, Packet::Init(p)) => p.header.id,
+            (_, p) => panic!("{:?}", p),
+        };
+        // the data are acknowledged by the ack packet without the full checksum
+        match exchange(Packet::from(DataPacket::new(vec![1, 2, 3], id, 0, 0))) {
+            (size, Packet::Ack(p)) => {
+                assert_eq!(p.header.ack, 0);
+                assert!(size < Packet::from(DataPacket::new_receiver(id, 0, 0)).to_bin(16).len());
+            }
+            (_, p) => panic!("{:?}", p),
+        };
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap().unwrap();
+        remove_dir_all(TARGET_DIR).unwrap();
+    }
+
+    #[test]
+    fn packet_from_different_address_is_ignored() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3152";
+        const TARGET_DIR: &str = "received_spoof";
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.timeout = 1000;
+        config.quiet = true;
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver = breakable_logic(config, brk.clone());
+        sleep(Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+        let spoofer = UdpSocket::bind("127.0.0.1:0").unwrap();
+        spoofer.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+        let exchange = |socket: &UdpSocket, packet: Packet| -> Option<Packet> {
+            let mut buffer = vec![0; 2000];
+            let size = packet.to_bin_buff(&mut buffer, 16);
+            socket.send_to(&buffer[..size], RECEIVER_ADDR).unwrap();
+            let (size, _) = socket.recv_from(&mut buffer).ok()?;
+            Some(Packet::from_bin(&buffer[..size], 16).unwrap())
+        };
+        // establish connection
+        let id = match exchange(&socket, Packet::from(InitPacket::new(1, 1000, 16))) {
+            Some(Packet::Init(p)) => p.header.id,
+            p => panic!("{:?}", p),
+        };
+        // packet with the valid id from another address is not answered
+        assert!(exchange(&spoofer, Packet::from(DataPacket::new(vec![9, 9], id, 0, 0))).is_none());
+        // the sender continues as nothing happened
+        match exchange(&socket, Packet::from(DataPacket::new(vec![1, 2, 3], id, 0, 0))) {
+            Some(Packet::Data(p)) => assert_eq!(p.header.ack, 0),
+            p => panic!("{:?}", p),
+        };
+        assert!(exchange(&spoofer, Packet::from(EndPacket::new(id, 1))).is_none());
+        match exchange(&socket, Packet::from(EndPacket::new(id, 1))) {
+            Some(Packet::End(p)) => assert_eq!(p.header.id, id),
+            p => panic!("{:?}", p),
+        };
+        assert_eq!(read(format!("{}/{}", TARGET_DIR, id)).unwrap(), vec![1, 2, 3]);
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap().unwrap();
+        remove_dir_all(TARGET_DIR).unwrap();
+    }
+
+    #[test]
+    fn missing_parts_are_reported_at_once() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3156";
+        const TARGET_DIR: &str = "received_gap";
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.timeout = 1000;
+        config.gap_report = Some(400);
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver = breakable_logic(config, brk.clone());
+        sleep(Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+        let mut buffer = vec![0; 2000];
+        let mut send = |packet: Packet| {
+            let size = packet.to_bin_buff(&mut buffer, 16);
+            socket.send_to(&buffer[..size], RECEIVER_ADDR).unwrap();
+        };
+        let receive = |duration: Duration| -> Vec<Packet> {
+            let mut buffer = vec![0; 2000];
+            let mut packets = Vec::new();
+            let start = Instant::now();
+            while start.elapsed() < duration {
+                if let Ok((size, _)) = socket.recv_from(&mut buffer) {
+                    packets.push(Packet::from_bin(&buffer[..size], 16).unwrap());
+                }
+            }
+            return packets;
+        };
+        // establish connection
+        send(Packet::from(InitPacket::new(15, 1000, 16)));
+        let id = match receive(Duration::from_millis(100)).pop() {
+            Some(Packet::Init(p)) => p.header.id,
+            p => panic!("{:?}", p),
+        };
+        // send parts with several gaps
+        for seq in [0, 2, 3, 6, 9].iter() {
+            send(Packet::from(DataPacket::new(vec![*seq as u8], id, *seq, 0)));
+        }
+        // only single report within the interval
+        let reports = receive(Duration::from_millis(500)).into_iter().filter_map(|p| match p {
+            Packet::Gap(p) => Some(p),
+            _ => None,
+        }).collect::<Vec<_>>();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].header.id, id);
+        assert_eq!(reports[0].header.ack, 0);
+        assert_eq!(reports[0].ranges, vec![(1, 1), (4, 5), (7, 8)]);
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap().unwrap();
+        remove_dir_all(TARGET_DIR).unwrap();
+    }
+
+    #[test]
+    fn status_describes_running_connection() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3159";
+        const TARGET_DIR: &str = "received_status";
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.timeout = 1000;
+        let brk = Arc::new(AtomicBool::new(false));
+        let status = Arc::new(Mutex::new(Vec::new()));
+        let receiver = monitored_logic(config, brk.clone(), status.clone());
+        sleep(Duration::from_millis(100));
+        assert!(status.lock().unwrap().is_empty());
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+        let exchange = |packet: Packet| -> Option<Packet> {
+            let mut buffer = vec![0; 2000];
+            let size = packet.to_bin_buff(&mut buffer, 16);
+            socket.send_to(&buffer[..size], RECEIVER_ADDR).unwrap();
+            let (size, _) = socket.recv_from(&mut buffer).ok()?;
+            Some(Packet::from_bin(&buffer[..size], 16).unwrap())
+        };
+        // establish connection and send parts with a gap
+        let id = match exchange(Packet::from(InitPacket::new(15, 1000, 16))) {
+            Some(Packet::Init(p)) => p.header.id,
+            p => panic!("{:?}", p),
+        };
+        for seq in [0, 1, 3, 5].iter() {
+            exchange(Packet::from(DataPacket::new(vec![*seq as u8], id, *seq, 0))).unwrap();
+        }
+        sleep(Duration::from_millis(50));
+        // first two parts are written, the others wait for the missing ones
+        {
+            let snapshots = status.lock().unwrap();
+            assert_eq!(snapshots.len(), 1);
+            assert_eq!(snapshots[0].id, id);
+            assert_eq!(snapshots[0].window_position, 2);
+            assert_eq!(snapshots[0].next_write_position, 2);
+            assert_eq!(snapshots[0].buffered_parts, 2);
+            assert!(snapshots[0].idle < Duration::from_millis(1000));
+        }
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap().unwrap();
+        remove_dir_all(TARGET_DIR).unwrap();
+    }
+
+    #[test]
+    fn small_buffer_asks_for_smaller_packets() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3160";
+        const TARGET_DIR: &str = "received_small_buffer";
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut config = Config::new();
+        config.bindaddr = String::from(RECEIVER_ADDR);
+        config.directory = String::from(TARGET_DIR);
+        config.timeout = 1000;
+        config.max_packet_size = 64;
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver = breakable_logic(config, brk.clone());
+        sleep(Duration::from_millis(100));
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+        let exchange = |packet: Packet, checksum: usize| -> Option<Packet> {
+            let mut buffer = vec![0; 2000];
+            let size = packet.to_bin_buff(&mut buffer, checksum);
+            socket.send_to(&buffer[..size], RECEIVER_ADDR).unwrap();
+            let (size, _) = socket.recv_from(&mut buffer).ok()?;
+            Some(Packet::from_bin(&buffer[..size], checksum).unwrap())
+        };
+        // too big init packet is truncated by the receiver and it answers with its packet size
+        match exchange(Packet::from(InitPacket::new(15, 1000, 16)), 16) {
+            Some(Packet::Init(p)) => {
+                assert_eq!(p.header.id, 0);
+                assert_eq!(p.packet_size, 64);
+            }
+            p => panic!("{:?}", p),
+        };
+        let id = match exchange(Packet::from(InitPacket::new(15, 64, 16)), 16) {
+            Some(Packet::Init(p)) => p.header.id,
+            p => panic!("{:?}", p),
+        };
+        // the whole packet fits into the buffer
+        let content = (0..64 - 9 - 16).collect::<Vec<u8>>();
+        match exchange(Packet::from(DataPacket::new(content.clone(), id, 0, 0)), 16) {
+            Some(Packet::Data(p)) => assert_eq!(p.header.ack, 0),
+            p => panic!("{:?}", p),
+        };
+        match exchange(Packet::from(EndPacket::new(id, 1)), 16) {
+            Some(Packet::End(p)) => assert_eq!(p.header.id, id),
+            p => panic!("{:?}", p),
+        };
+        assert_eq!(read(format!("{}/{}", TARGET_DIR, id)).unwrap(), content);
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap().unwrap();
+        remove_dir_all(TARGET_DIR).unwrap();
+    }
+}
@@ -1,5 +1,12 @@
 pub mod config;
 mod logic;
 mod receiver_connection_properties;
+mod events;
+mod filename;
+mod dedup;
+mod writer;
 
-pub use logic::{logic, breakable_logic};
+pub use logic::{logic, breakable_logic, monitored_logic, receive_all};
+pub use receiver_connection_properties::{ConnectionSnapshot, CompletedTransfer};
+pub use events::ReceiverEvent;
+pub use filename::sanitize_filename;
@@ -1,11 +1,53 @@
-use std::collections::BTreeMap;
+use std::collections::hash_map::DefaultHasher;
 use std::fs::{File, OpenOptions};
-use std::io::Write;
+use std::hash::Hasher;
+use std::fs;
+use std::io::{self, Seek, SeekFrom};
 use std::num::Wrapping;
-use std::path::Path;
-use std::time::{Duration, Instant};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, UNIX_EPOCH};
 use crate::connection_properties::ConnectionProperties;
-use crate::receiver::config::Config;
+use crate::receiver::config::{Config, AckStrategy};
+use crate::receiver::events::ReceiverEvent;
+use crate::receiver::writer::{FileWriter, WriteBehind};
+use crate::seq_ring::SeqRing;
+use crate::packet::{Crc32, EndPacket, InitPacket, MetadataPacket, Packet, PacketHeader, ParsingError};
+
+/// State of the connection at some point of time, for monitoring of the running receiver.
+#[derive(Clone, Debug)]
+pub struct ConnectionSnapshot {
+    /// Connection identifier.
+    pub id: u32,
+    /// Sequence number of the part the receiver expects.
+    pub window_position: u16,
+    /// Sequence number of the part that will be written into the file next.
+    pub next_write_position: u16,
+    /// Number of received parts waiting to be written.
+    pub buffered_parts: usize,
+    /// Number of bytes of the received parts waiting to be written.
+    pub buffered_bytes: usize,
+    /// How long the receiver didn't receive anything in this connection.
+    pub idle: Duration,
+}
+
+/// File the receiver received completely.
+#[derive(Clone, Debug)]
+pub struct CompletedTransfer {
+    /// Connection identifier.
+    pub id: u32,
+    /// Path where the file is stored.
+    pub path: String,
+    /// Number of bytes written into the file.
+    pub bytes: u64,
+    /// Address the sender sent the file from.
+    pub source: SocketAddr,
+    /// Path of the file at the sender, when it sent the metadata.
+    pub original_path: Option<String>,
+    /// CRC-32 of the received content, the same as the digest of the sender.
+    pub digest: u32,
+}
 
 /// Properties that the receiver stores per connection.
 pub struct ReceiverConnectionProperties {
@@ -17,26 +59,270 @@ pub struct ReceiverConnectionProperties {
     pub next_write_position: u16,
     /// Temporary storage of parts received from the sender.
     /// This variable is freed when corresponding part is written into the file.
-    pub parts_received: BTreeMap<u16, Vec<u8>>,
+    /// The ring starts at the write position and holds two windows, as the parts are written after every store.
+    parts_received: SeqRing<Vec<u8>>,
+    /// Number of bytes of the parts in the cache memory.
+    buffered_bytes: usize,
     /// When was last time receiver get packet from the sender.
     pub last_receive_time: Instant,
     /// Whether this connection received all the data and is closed by the sender (successfully).
     is_closed: bool,
-    /// File into which store the received content.
-    file: Option<File>,
+    /// Number of bytes written into the file.
+    pub written_bytes: u64,
+    /// Path of the file into which store the received content.
+    pub output_path: String,
+    /// File into which store the received content, shared with the writer thread.
+    file: Option<Arc<File>>,
+    /// Writer thread, that writes the content instead of the main loop.
+    write_behind: Option<WriteBehind>,
+    /// Number of write calls issued into the file.
+    write_calls: usize,
+    /// Confirmation of the end packet, that is send again when the sender repeats the end packet.
+    end_confirmation: Option<Vec<u8>>,
+    /// When the connection started to linger after it was closed.
+    closed_at: Option<Instant>,
+    /// Number of data packets received since the last acknowledge.
+    unacknowledged: u16,
+    /// When the first not acknowledged data packet was received.
+    unacknowledged_since: Option<Instant>,
+    /// Whether a data packet marked as congested was received since the last acknowledge.
+    congested: bool,
+    /// Sequence number of the last received data packet.
+    pub last_seq: u16,
+    /// When were the missing parts reported to the sender for the last time.
+    last_gap_report: Instant,
+    /// Hash of the content written into the file so far.
+    content_hash: DefaultHasher,
+    /// CRC-32 of the content written into the file so far.
+    crc: Crc32,
+    /// Number of bytes of the file, that are stored on the disk.
+    pub synced_bytes: u64,
+    /// Position in the file where the received content is stored.
+    start_offset: u64,
+    /// Bigger checksum size agreed with the sender, used once the sender starts to use it.
+    pending_checksum: Option<u16>,
+    /// End packet, that came before all the data, and when it came.
+    pending_end: Option<(EndPacket, Instant)>,
+    /// Nonce from the init answer, that the first stored data must echo.
+    nonce: Option<u16>,
+    /// When the window moved or its acknowledge was repeated for the last time.
+    stuck_since: Instant,
+    /// Metadata of the file from the sender.
+    metadata: Option<MetadataPacket>,
+    /// Whether the content from the start offset continues the existing file.
+    continues_file: bool,
+    /// Whether the output file was allocated to the size from the metadata.
+    preallocated: bool,
+    /// Index of the receiver socket the connection arrived on, the answers are send from it.
+    pub socket_index: usize,
+    /// Init request, that created the connection, and the answer to it, so the copies of the request are answered the same.
+    init_exchange: Option<(InitRequest, Vec<u8>)>,
 }
 
+/// Sender address and the properties requested by the init packet, the copies of the same request are equal.
+type InitRequest = (SocketAddr, u16, u16, u16, u64, u16);
+
 impl ReceiverConnectionProperties {
-    pub fn new(conn_props: ConnectionProperties) -> Self {
+    pub fn new(conn_props: ConnectionProperties, output_path: String) -> Self {
+        let parts_received = SeqRing::new(2 * conn_props.window_size as usize);
         Self {
             static_properties: conn_props,
             next_write_position: 0,
             window_position: 0,
-            parts_received: BTreeMap::new(),
+            parts_received,
+            buffered_bytes: 0,
             last_receive_time: Instant::now(),
             is_closed: false,
+            written_bytes: 0,
+            output_path,
             file: None,
+            write_behind: None,
+            write_calls: 0,
+            end_confirmation: None,
+            closed_at: None,
+            unacknowledged: 0,
+            unacknowledged_since: None,
+            congested: false,
+            last_seq: 0,
+            last_gap_report: Instant::now(),
+            content_hash: DefaultHasher::new(),
+            crc: Crc32::new(),
+            synced_bytes: 0,
+            start_offset: 0,
+            pending_checksum: None,
+            pending_end: None,
+            nonce: None,
+            stuck_since: Instant::now(),
+            metadata: None,
+            continues_file: false,
+            preallocated: false,
+            socket_index: 0,
+            init_exchange: None,
+        }
+    }
+
+    /// Remember the `answer` to the init `request` received `from` the sender.
+    /// Only the requests with the nonce of the sender can be recognized, when they come again.
+    pub fn answered_init(&mut self, request: &InitPacket, from: SocketAddr, answer: Vec<u8>) {
+        if request.nonce != 0 {
+            self.init_exchange = Some((init_request(request, from), answer));
+        }
+    }
+
+    /// Answer to the copy of the init `request` received `from` the sender, that created this connection.
+    pub fn init_answer(&self, request: &InitPacket, from: SocketAddr) -> Option<&[u8]> {
+        return match &self.init_exchange {
+            Some((created_by, answer)) if request.nonce != 0 && *created_by == init_request(request, from) => Some(answer),
+            _ => None,
+        };
+    }
+
+    /// Accept the data only after the sender echoes the `nonce` from the init answer.
+    pub fn expect_nonce(&mut self, nonce: u16) {
+        self.nonce = Some(nonce);
+    }
+
+    /// Whether the connection got nothing from the sender since the init answer.
+    pub fn awaits_first_packet(&self) -> bool {
+        return !self.is_closed
+            && self.window_position == 0
+            && self.parts_received.is_empty()
+            && self.metadata.is_none()
+            && self.pending_end.is_none();
+    }
+
+    /// Continue the connection under the `id`, that another receiver of the group confirmed to the sender.
+    /// The sender echoes the nonce of that receiver, so the nonce of this connection is not expected anymore.
+    pub fn adopt_id(&mut self, id: u32) {
+        self.static_properties.id = id;
+        self.nonce = None;
+    }
+
+    /// Apply the `metadata` from the sender to the output file, its size is allocated when the file is created.
+    pub fn describe(&mut self, metadata: MetadataPacket) {
+        self.metadata = Some(metadata);
+    }
+
+    /// Set the modification time and permissions from the metadata on the stored file.
+    /// The permissions from the `config` take precedence over the ones from the sender.
+    pub fn apply_metadata(&self, config: &Config) -> io::Result<()> {
+        let metadata = match &self.metadata {
+            Some(metadata) if config.stores_files() => metadata,
+            _ => return Ok(()),
+        };
+        let modified = UNIX_EPOCH + Duration::new(metadata.mtime_secs, metadata.mtime_nanos);
+        File::open(&self.output_path)?.set_modified(modified)?;
+        if metadata.mode != 0 && config.file_mode.is_none() {
+            set_mode(&self.output_path, metadata.mode)?;
+        }
+        return Ok(());
+    }
+
+    /// Write the content by the writer thread of the `writes` instead of the main loop.
+    pub fn write_behind(&mut self, writes: WriteBehind) {
+        self.write_behind = Some(writes);
+    }
+
+    /// Store the received content into the file from the `offset`.
+    pub fn start_at(&mut self, offset: u64) {
+        self.start_offset = offset;
+        // the same content at different position is a different file
+        self.content_hash.write_u64(offset);
+    }
+
+    /// Position in the file where the received content is stored.
+    pub fn start_offset(&self) -> u64 {
+        return self.start_offset;
+    }
+
+    /// Store the content into the file at the `path` instead, unless some content was written already.
+    /// Returns whether the output was renamed.
+    pub fn rename_output(&mut self, path: String) -> bool {
+        if self.file.is_some() || self.written_bytes > 0 {
+            return false;
+        }
+        self.output_path = path;
+        return true;
+    }
+
+    /// Continue the existing file at the `path` from the start offset, it must be at least as long as the offset.
+    pub fn continue_file(&mut self, path: String) -> io::Result<()> {
+        let length = fs::metadata(&path)?.len();
+        if length < self.start_offset {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("the file has only {}b", length)));
+        }
+        if !self.rename_output(path) {
+            return Err(io::Error::new(io::ErrorKind::AlreadyExists, "some content was written already"));
+        }
+        self.continues_file = true;
+        return Ok(());
+    }
+
+    /// Whether the content from the start offset waits for the file, that it continues.
+    pub fn awaits_file(&self) -> bool {
+        return self.start_offset > 0 && !self.continues_file;
+    }
+
+    /// Current state of the connection.
+    pub fn snapshot(&self) -> ConnectionSnapshot {
+        return ConnectionSnapshot {
+            id: self.static_properties.id,
+            window_position: self.window_position,
+            next_write_position: self.next_write_position,
+            buffered_parts: self.parts_received.len(),
+            buffered_bytes: self.buffered_bytes,
+            idle: self.last_receive_time.elapsed(),
+        };
+    }
+
+    /// Agree on the checksum of `requested` bytes, if the init packet with it fits into the packet
+    /// and the sender already uses the previously agreed one.
+    /// Returns the checksum size the sender should use.
+    pub fn renegotiate_checksum(&mut self, requested: u16) -> u16 {
+        // the sender must start to use the agreed checksum, before it asks for another one
+        if let Some(pending) = self.pending_checksum {
+            return pending;
+        }
+        let current = self.static_properties.checksum_size;
+        let fits = (self.static_properties.packet_size as usize) > PacketHeader::bin_size() + 6 + requested as usize;
+        if requested <= current || !fits {
+            return current;
+        }
+        self.pending_checksum = Some(requested);
+        return requested;
+    }
+
+    /// Parse the packet of this connection from the `content`.
+    /// The first packet, that uses the agreed bigger checksum, switches the connection to it.
+    pub fn parse(&mut self, content: &[u8]) -> Result<Packet, ParsingError> {
+        // the bigger checksum is tried first, the packet with it may pass the smaller one as well
+        if let Some(pending) = self.pending_checksum {
+            if let Ok(packet) = Packet::from_bin(content, pending as usize) {
+                self.static_properties.checksum_size = pending;
+                self.pending_checksum = None;
+                return Ok(packet);
+            }
         }
+        return Packet::from_bin(content, self.static_properties.checksum_size as usize);
+    }
+
+    /// Init packet confirming the checksum of `checksum_size` bytes to the sender.
+    pub fn checksum_answer(&self, checksum_size: u16) -> InitPacket {
+        let mut answer = InitPacket::new(self.static_properties.window_size, self.static_properties.packet_size, checksum_size);
+        answer.header.id = self.static_properties.id;
+        return answer;
+    }
+
+    /// Record of the completely received file.
+    pub fn completed(&self) -> CompletedTransfer {
+        return CompletedTransfer {
+            id: self.static_properties.id,
+            path: self.output_path.clone(),
+            bytes: self.written_bytes,
+            source: self.static_properties.socket_addr,
+            original_path: self.metadata.as_ref().map(|metadata| metadata.path.clone()),
+            digest: self.digest(),
+        };
     }
 
     /// Check whether this connection end successfully and is closed.
@@ -47,7 +333,64 @@ impl ReceiverConnectionProperties {
     /// Mark the connection as closed and flush content of the temp file.
     pub fn close(&mut self) {
         self.is_closed = true;
-        self.file.take();
+        self.release_file();
+    }
+
+    /// Release the output file once the content written behind is in it.
+    fn release_file(&mut self) {
+        // the failed writes were reported while the connection was open, the rest of the content is kept
+        let _ = self.wait_for_writes();
+        // the allocated file of the incomplete transfer keeps only the written content
+        if let Some(file) = self.file.take().filter(|_| self.preallocated) {
+            file.set_len(self.written_bytes).expect("Can't shrink the allocated file");
+        }
+    }
+
+    /// Close the connection and keep answering repeated end packets with the `confirmation` for a while.
+    pub fn linger(&mut self, confirmation: Vec<u8>) {
+        self.close();
+        self.end_confirmation = Some(confirmation);
+        self.closed_at = Some(Instant::now());
+    }
+
+    /// Whether all the data before the end `packet` were stored.
+    pub fn is_complete(&self, packet: &EndPacket) -> bool {
+        return self.parts_received.is_empty() && self.window_position == packet.header.seq;
+    }
+
+    /// Wait with the end `packet` until the data before it are stored.
+    /// The repeated end packet doesn't prolong the waiting.
+    pub fn postpone_end(&mut self, packet: EndPacket) {
+        let since = self.pending_end.take().map_or_else(Instant::now, |(_, since)| since);
+        self.pending_end = Some((packet, since));
+    }
+
+    /// The postponed end packet, once all the data before it are stored.
+    pub fn take_ready_end(&mut self) -> Option<EndPacket> {
+        return match &self.pending_end {
+            Some((packet, _)) if self.is_complete(packet) => self.pending_end.take().map(|(packet, _)| packet),
+            _ => None,
+        };
+    }
+
+    /// Whether the postponed end packet waits for the data longer than the `grace` milliseconds.
+    pub fn end_grace_expired(&self, grace: u32) -> bool {
+        return matches!(&self.pending_end, Some((_, since)) if since.elapsed() >= Duration::from_millis(grace as u64));
+    }
+
+    /// Confirmation of the end packet, if the connection is lingering.
+    pub fn end_confirmation(&self) -> Option<&Vec<u8>> {
+        self.end_confirmation.as_ref()
+    }
+
+    /// When the connection started to linger, if it is lingering.
+    pub fn closed_at(&self) -> Option<Instant> {
+        self.closed_at
+    }
+
+    /// Check whether the connection lingers for more than `linger` milliseconds and can be removed.
+    pub fn linger_expired(&self, linger: u32) -> bool {
+        return self.closed_at.map_or(false, |closed_at| closed_at.elapsed() >= Duration::from_millis(linger as u64));
     }
 
     /// Check whether the connection timeouted.
@@ -62,16 +405,56 @@ impl ReceiverConnectionProperties {
     }
 
     /// Store `data` received from the sender in packet with sequential number `seq` into cache memory.
-    pub fn store_data(&mut self, data: &Vec<u8>, seq: u16, config: &Config) {
+    /// With `strict_order` in the `config` only the part at the window position is accepted.
+    /// Other parts are not accepted, when `max_buffered_parts` in the `config` are buffered already.
+    /// Until the nonce is echoed, only the packet with the nonce in its `ack` is accepted.
+    /// Returns whether the data were stored.
+    pub fn store_data(&mut self, data: &Vec<u8>, seq: u16, ack: u16, config: &Config) -> bool {
         // register new data
         self.last_receive_time = Instant::now();
         // validate if data are within window
         if !self.is_within_window(seq, &config) {
             config.vlog("Not storing data, as they are outside of the window");
-            return;
+            return false;
+        }
+        // the sender must prove it received the init answer, before any data are accepted
+        if let Some(nonce) = self.nonce {
+            if ack != nonce {
+                config.vlog(&format!("Data under seq {} don't echo the nonce of connection {}", seq, self.static_properties.id));
+                config.emit(ReceiverEvent::NonceRejected(seq));
+                return false;
+            }
+            config.vlog(&format!("Sender of connection {} echoed the nonce", self.static_properties.id));
+            self.nonce = None;
+        }
+        // in the strict mode the parts are not buffered, the sender must repeat them in order
+        if config.strict_order && seq != self.window_position {
+            config.vlog(&format!("Data under seq {} are out of order, expected {}", seq, self.window_position));
+            config.emit(ReceiverEvent::OutOfOrderDropped(seq));
+            return false;
+        }
+        // the part may be received already, while it waits for the missing ones
+        if self.parts_received.contains(seq) {
+            config.vlog(&format!("Data under seq {} are stored already", seq));
+            config.emit(ReceiverEvent::DuplicateDropped);
+            return false;
+        }
+        // the memory of the connection is bounded, the sender repeats the part later
+        if let Some(max_buffered_parts) = config.max_buffered_parts {
+            if seq != self.window_position && self.parts_received.len() >= max_buffered_parts {
+                config.vlog(&format!("Data under seq {} dropped, connection {} buffers {} parts", seq, self.static_properties.id, self.parts_received.len()));
+                config.emit(ReceiverEvent::BufferFull(seq));
+                return false;
+            }
+        }
+        // store them, unless the parts before the window are still waiting to be written
+        if !self.parts_received.insert(seq, Clone::clone(data)) {
+            config.vlog(&format!("Data under seq {} dropped, connection {} didn't write the parts from seq {}", seq, self.static_properties.id, self.next_write_position));
+            config.emit(ReceiverEvent::BufferFull(seq));
+            return false;
         }
-        // store them
-        self.parts_received.insert(seq, Clone::clone(data));
+        self.buffered_bytes += data.len();
+        config.emit(ReceiverEvent::DataStored(seq, data.len()));
         config.vlog(&format!(
             "Connection {} stored {}b of data under seq {}",
             self.static_properties.id,
@@ -79,48 +462,260 @@ impl ReceiverConnectionProperties {
             seq
         ));
         // move window if necessary
-        while self.parts_received.contains_key(&self.window_position) {
+        let previous_position = self.window_position;
+        while self.parts_received.contains(self.window_position) {
             let new_pos = Wrapping::<u16>(self.window_position) + Wrapping::<u16>(1);
             self.window_position = new_pos.0;
         }
+        if self.window_position != previous_position {
+            self.stuck_since = Instant::now();
+            config.emit(ReceiverEvent::WindowAdvanced(self.window_position));
+        }
         config.vlog(&format!(
             "Window moved to position {} for connection {}",
             self.window_position,
             self.static_properties.id
         ));
+        return true;
     }
 
     /// Write data from the cache memory into the file if present.
-    pub fn save_into_file(&mut self, config: &Config) {
-        // path to the file
-        let path_str = config.filename(self.static_properties.id);
-        let path = Path::new(&path_str);
+    /// When `coalesce_writes` is set in the `config`, all the contiguous parts are written at once.
+    /// Fails when the content can't be written, the write behind fails by some of the previous writes.
+    pub fn save_into_file(&mut self, config: &Config) -> std::io::Result<()> {
+        let start_offset = self.start_offset;
+        // the new file gets the size from the metadata at once
+        let preallocate = self.metadata.as_ref()
+            .map(|metadata| metadata.size)
+            .filter(|_| self.file.is_none() && start_offset == 0 && config.fifo.is_none());
 
         // while there are packets to write
         while self.next_write_position != self.window_position {
-            // get the following one and remove it from the cache memory
-            let buffer = self.parts_received.remove(&self.next_write_position).expect("Part to write is not within the map");
-            // make sure the file is open
-            self.file = Some(match self.file.take() {
-                Some(f) => f,
-                None => OpenOptions::new().write(true)
-                                          .append(true)
-                                          .create(true)
-                                          .open(path).expect("Can't open file for write")
-            });
-            let file = self.file.as_mut().unwrap();
-            // write the content
-            let wrote = file.write(&buffer).expect("Can't write to the output file");
+            // get the following ones and remove them from the cache memory
+            let (buffer, parts) = match config.coalesce_writes {
+                true => self.take_contiguous_parts(),
+                false => (self.parts_received.pop().expect("Part to write is not stored"), 1),
+            };
+            self.buffered_bytes -= buffer.len();
+            self.content_hash.write(&buffer);
+            self.crc.update(&buffer);
+            let size = buffer.len();
+            // the dry run only validates the content, nothing is stored
+            if !config.dry_run {
+                // make sure the file is open, the pipe must exist already and have the reader
+                self.preallocated |= preallocate.is_some();
+                if self.file.is_none() {
+                    self.file = Some(Arc::new(self.open_unused_output(preallocate, config)?));
+                }
+                let file = self.file.as_mut().expect("Output file is not open");
+                // write the content, the writer thread writes it while the main loop continues
+                match &mut self.write_behind {
+                    Some(writes) => writes.write(file, buffer)?,
+                    None => FileWriter::new(config).write(self.static_properties.id, file, &buffer)?,
+                };
+            }
+            self.write_calls += 1;
+            self.written_bytes += size as u64;
+            config.emit(ReceiverEvent::FileWritten(size));
             config.vlog(&format!(
-                "Connection {} wrote {}b into file for packet seq {}",
+                "Connection {} wrote {}b into file for {} packets starting at seq {}, {} writes so far",
                 self.static_properties.id,
-                wrote,
-                self.next_write_position
+                size,
+                parts,
+                self.next_write_position,
+                self.write_calls
             ));
             // move to the following packet
-            let new_write_pos = Wrapping(self.next_write_position) + Wrapping::<u16>(1);
+            let new_write_pos = Wrapping(self.next_write_position) + Wrapping(parts);
             self.next_write_position = new_write_pos.0;
         }
+        return Ok(());
+    }
+
+    /// Open the output file, the file stored under its name is kept and numeric suffix is appended to the name instead.
+    /// The continued file and the pipe are opened as they are.
+    fn open_unused_output(&mut self, preallocate: Option<u64>, config: &Config) -> io::Result<File> {
+        let name = self.output_path.clone();
+        let mut suffix = 1;
+        loop {
+            match open_output(Path::new(&self.output_path), preallocate, self.start_offset, config) {
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    self.output_path = format!("{}_{}", name, suffix);
+                    suffix += 1;
+                }
+                result => return result,
+            };
+        }
+    }
+
+    /// Number of bytes of the received parts waiting to be written.
+    pub fn buffered_bytes(&self) -> usize {
+        return self.buffered_bytes;
+    }
+
+    /// Check whether the written data should be stored on the disk,
+    /// because the connection didn't receive anything for `idle` milliseconds.
+    pub fn flush_due(&self, idle: u32) -> bool {
+        return !self.is_closed && self.synced_bytes < self.written_bytes
+            && self.last_receive_time.elapsed() >= Duration::from_millis(idle as u64);
+    }
+
+    /// Store the data written into the file on the disk, so they are not lost when the system crashes.
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.wait_for_writes()?;
+        if let Some(file) = &self.file {
+            file.sync_data()?;
+        }
+        self.synced_bytes = self.written_bytes;
+        return Ok(());
+    }
+
+    /// Wait until the writer thread writes all the content of this connection.
+    /// Fails when some of the writes failed.
+    pub fn wait_for_writes(&mut self) -> std::io::Result<()> {
+        if let Some(writes) = &mut self.write_behind {
+            return writes.wait();
+        }
+        return Ok(());
+    }
+
+    /// Hash of the content written into the file.
+    pub fn content_hash(&self) -> u64 {
+        return self.content_hash.finish();
+    }
+
+    /// CRC-32 of the content written into the file, to compare with the digest of the sender.
+    pub fn digest(&self) -> u32 {
+        return self.crc.finish();
+    }
+
+    /// Move the output file into the `directory`, keeping its name.
+    /// The file already stored in the directory is kept, numeric suffix is appended to the name of the moved one.
+    pub fn move_into(&mut self, directory: &str) -> std::io::Result<()> {
+        self.release_file();
+        // the continued file is in the directory already
+        if Path::new(&self.output_path).parent() == Some(Path::new(directory)) {
+            return Ok(());
+        }
+        let mut target = PathBuf::from(directory);
+        target.push(Path::new(&self.output_path).file_name().expect("Output path without file name"));
+        let target = String::from(target.to_str().unwrap());
+        let mut candidate = target.clone();
+        let mut suffix = 1;
+        loop {
+            let moved = match Path::new(&self.output_path).exists() {
+                true => move_new(&self.output_path, &candidate),
+                // nothing was written, so there is no file to move
+                false if Path::new(&candidate).exists() => Err(io::Error::from(io::ErrorKind::AlreadyExists)),
+                false => Ok(()),
+            };
+            match moved {
+                Ok(()) => break,
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    candidate = format!("{}_{}", target, suffix);
+                    suffix += 1;
+                }
+                Err(e) => return Err(e),
+            };
+        }
+        self.output_path = candidate;
+        return Ok(());
+    }
+
+    /// Remove all the parts between the write position and the window position from the cache memory.
+    /// Returns their concatenated content and number of the parts.
+    fn take_contiguous_parts(&mut self) -> (Vec<u8>, u16) {
+        let mut content = Vec::new();
+        let mut position = Wrapping(self.next_write_position);
+        while position.0 != self.window_position {
+            let part = self.parts_received.pop().expect("Part to write is not stored");
+            content.extend_from_slice(&part);
+            position += Wrapping::<u16>(1);
+        }
+        let parts = position - Wrapping(self.next_write_position);
+        return (content, parts.0);
+    }
+
+    /// Register data packet with sequential number `seq`, that was not acknowledged yet.
+    pub fn register_unacknowledged(&mut self, seq: u16) {
+        self.unacknowledged = self.unacknowledged.saturating_add(1);
+        self.unacknowledged_since.get_or_insert(Instant::now());
+        self.last_seq = seq;
+    }
+
+    /// Check whether the acknowledge should be send according to the `strategy`.
+    pub fn acknowledge_due(&self, strategy: AckStrategy) -> bool {
+        if self.unacknowledged == 0 {
+            return false;
+        }
+        return match strategy {
+            AckStrategy::Immediate => true,
+            AckStrategy::EveryN(n) => self.unacknowledged >= n,
+            AckStrategy::Delayed(ms) => match self.unacknowledged_since {
+                Some(since) => since.elapsed() >= Duration::from_millis(ms as u64),
+                None => false,
+            },
+        };
+    }
+
+    /// Mark all the received data packets as acknowledged.
+    pub fn acknowledged(&mut self) {
+        self.unacknowledged = 0;
+        self.unacknowledged_since = None;
+        self.congested = false;
+    }
+
+    /// Remember the data packet marked as congested was received, so the next acknowledge echoes it.
+    pub fn mark_congestion(&mut self) {
+        self.congested = true;
+    }
+
+    /// Whether the next acknowledge should echo the congestion.
+    pub fn is_congested(&self) -> bool {
+        self.congested
+    }
+
+    /// Ranges of the parts missing between the window position and the furthest received part.
+    /// Every range contains the first and the last missing sequence number.
+    pub fn missing_ranges(&self) -> Vec<(u16, u16)> {
+        let mut ranges = Vec::new();
+        // the window never moves past the stored parts, the last of them is the furthest one
+        let stored_ahead = (Wrapping(self.parts_received.end()) - Wrapping(self.window_position)).0;
+        let mut first_missing = None;
+        for offset in 0..stored_ahead {
+            let seq = (Wrapping(self.window_position) + Wrapping(offset)).0;
+            match (self.parts_received.contains(seq), first_missing) {
+                (false, None) => first_missing = Some(seq),
+                (true, Some(first)) => {
+                    ranges.push((first, (Wrapping(seq) - Wrapping::<u16>(1)).0));
+                    first_missing = None;
+                }
+                _ => {}
+            };
+        }
+        return ranges;
+    }
+
+    /// Check whether the missing parts should be reported after `interval` milliseconds since the last report.
+    pub fn gap_report_due(&self, interval: u32) -> bool {
+        return !self.is_closed && !self.parts_received.is_empty()
+            && self.last_gap_report.elapsed() >= Duration::from_millis(interval as u64);
+    }
+
+    /// Mark the missing parts as reported.
+    pub fn gap_reported(&mut self) {
+        self.last_gap_report = Instant::now();
+    }
+
+    /// Check whether the window is stuck before the missing part for `timeout` milliseconds, while the later parts were received.
+    pub fn gap_timeout_due(&self, timeout: u32) -> bool {
+        return !self.is_closed && self.stuck_since.elapsed() >= Duration::from_millis(timeout as u64)
+            && !self.missing_ranges().is_empty();
+    }
+
+    /// Mark the acknowledge of the stuck window as repeated.
+    pub fn reacknowledged(&mut self) {
+        self.stuck_since = Instant::now();
     }
 
     /// Get acknowledge number that the receiver should respond with.
@@ -128,4 +723,433 @@ impl ReceiverConnectionProperties {
         let ack = Wrapping(self.window_position) - Wrapping::<u16>(1);
         return ack.0;
     }
-}
\ No newline at end of file
+}
+
+/// Properties of the init `request` received `from` the sender, that identify it.
+fn init_request(request: &InitPacket, from: SocketAddr) -> InitRequest {
+    return (from, request.window_size, request.packet_size, request.checksum_size, request.offset, request.nonce);
+}
+
+/// Move the file at the `source` path to the `target` path, that must not exist yet.
+fn move_new(source: &str, target: &str) -> io::Result<()> {
+    // the link doesn't replace the existing file, but it can't lead to other file system
+    match fs::hard_link(source, target) {
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Err(e),
+        Err(_) => {
+            let mut copy = OpenOptions::new().write(true).create_new(true).open(target)?;
+            let copied = io::copy(&mut File::open(source)?, &mut copy).and_then(|_| copy.sync_data());
+            if let Err(e) = copied {
+                let _ = fs::remove_file(target);
+                return Err(e);
+            }
+        }
+        Ok(()) => {}
+    };
+    return fs::remove_file(source);
+}
+
+/// Open the output at the `path` for the content from the `start_offset`, the new file has the `preallocate` size.
+/// The pipe from the `config` is opened instead, it must exist already and have the reader.
+fn open_output(path: &Path, preallocate: Option<u64>, start_offset: u64, config: &Config) -> io::Result<File> {
+    if config.fifo.is_some() {
+        return open_fifo(path);
+    }
+    if start_offset > 0 {
+        let mut file = output_options(config).truncate(false).open(path)?;
+        file.seek(SeekFrom::Start(start_offset))?;
+        return Ok(file);
+    }
+    // the new file never replaces the one stored already
+    let file = output_options(config).create_new(true).open(path)?;
+    if let Some(size) = preallocate {
+        file.set_len(size)?;
+    }
+    return Ok(file);
+}
+
+/// Open the pipe at the `path` for write without waiting for the reader, it fails when there is none.
+/// Only the opening doesn't wait, the writes wait for the reader as before.
+#[cfg(unix)]
+fn open_fifo(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::AsRawFd;
+    let file = OpenOptions::new().write(true).custom_flags(libc::O_NONBLOCK).open(path)?;
+    let fd = file.as_raw_fd();
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags & !libc::O_NONBLOCK) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    return Ok(file);
+}
+
+/// Open the pipe at the `path` for write, it must exist already.
+#[cfg(not(unix))]
+fn open_fifo(path: &Path) -> io::Result<File> {
+    return OpenOptions::new().write(true).open(path);
+}
+
+/// Options creating the output file for write, with the permissions from the `config`.
+#[cfg(unix)]
+fn output_options(config: &Config) -> OpenOptions {
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut options = OpenOptions::new();
+    options.write(true).create(true);
+    if let Some(mode) = config.file_mode {
+        options.mode(mode);
+    }
+    return options;
+}
+
+/// Change permissions of the file at the `path` to the `mode`.
+#[cfg(unix)]
+fn set_mode(path: &str, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    return fs::set_permissions(path, fs::Permissions::from_mode(mode));
+}
+
+/// Change permissions of the file at the `path`, they are not supported on this platform.
+#[cfg(not(unix))]
+fn set_mode(_path: &str, _mode: u32) -> io::Result<()> {
+    return Ok(());
+}
+
+/// Options creating the output file for write, the permissions are not supported on this platform.
+#[cfg(not(unix))]
+fn output_options(_config: &Config) -> OpenOptions {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true);
+    return options;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, metadata, read, remove_dir_all, write};
+    use std::net::SocketAddr;
+    use std::path::Path;
+    use std::str::FromStr;
+    use std::thread::sleep;
+    use std::time::Duration;
+    use crate::connection_properties::ConnectionProperties;
+    use crate::packet::{DataPacket, EndPacket, MetadataPacket, Packet};
+    use crate::receiver::config::Config;
+    use super::ReceiverConnectionProperties;
+
+    fn write_tiny_parts(config: &Config) -> (usize, Vec<u8>) {
+        create_dir_all(&config.directory).unwrap();
+        let addr = SocketAddr::from_str("127.0.0.1:3000").unwrap();
+        let mut props = ReceiverConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 15, 100, addr),
+            config.filename(1),
+        );
+        // deliver parts in reverse order, so all of them are written at once
+        for seq in (0..10).rev() {
+            props.store_data(&vec![seq as u8; 2], seq, 0, config);
+            props.save_into_file(config).unwrap();
+        }
+        props.close();
+        let content = read(&props.output_path).unwrap();
+        remove_dir_all(&config.directory).unwrap();
+        return (props.write_calls, content);
+    }
+
+    #[test]
+    fn coalesced_writes_issue_less_write_calls() {
+        let mut config = Config::new();
+        config.directory = String::from("received_coalesce_off");
+        let (separate_calls, separate_content) = write_tiny_parts(&config);
+        config.directory = String::from("received_coalesce_on");
+        config.coalesce_writes = true;
+        let (coalesced_calls, coalesced_content) = write_tiny_parts(&config);
+
+        assert_eq!(separate_calls, 10);
+        assert_eq!(coalesced_calls, 1);
+        assert_eq!(separate_content, coalesced_content);
+        assert_eq!(coalesced_content, (0..10u8).flat_map(|i| vec![i; 2]).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn idle_connection_is_flushed() {
+        let mut config = Config::new();
+        config.directory = String::from("received_flush_idle");
+        create_dir_all(&config.directory).unwrap();
+        let addr = SocketAddr::from_str("127.0.0.1:3000").unwrap();
+        let mut props = ReceiverConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 15, 100, addr),
+            config.filename(1),
+        );
+        // the part 2 is missing, so the part 3 stays buffered
+        for seq in [0, 1, 3].iter() {
+            props.store_data(&vec![*seq as u8; 5], *seq, 0, &config);
+            props.save_into_file(&config).unwrap();
+        }
+        assert!(!props.flush_due(50));
+        sleep(Duration::from_millis(60));
+        assert!(props.flush_due(50));
+        props.flush().unwrap();
+        assert!(!props.flush_due(50));
+        assert_eq!(props.synced_bytes, 10);
+        assert_eq!(props.parts_received.len(), 1);
+        remove_dir_all(&config.directory).unwrap();
+    }
+    #[test]
+    fn metadata_size_is_preallocated() {
+        let mut config = Config::new();
+        config.directory = String::from("received_preallocate");
+        create_dir_all(&config.directory).unwrap();
+        let addr = SocketAddr::from_str("127.0.0.1:3000").unwrap();
+        let mut props = ReceiverConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 15, 100, addr),
+            config.filename(1),
+        );
+        props.describe(MetadataPacket::new(1, 1000, 0));
+        // the file has the size from the metadata since the first write
+        for seq in 0..2 {
+            props.store_data(&vec![seq as u8 + 1; 10], seq, 0, &config);
+            props.save_into_file(&config).unwrap();
+        }
+        props.flush().unwrap();
+        assert_eq!(metadata(&props.output_path).unwrap().len(), 1000);
+        // the incomplete file keeps only the written content
+        props.close();
+        assert_eq!(read(&props.output_path).unwrap(), [vec![1; 10], vec![2; 10]].concat());
+        remove_dir_all(&config.directory).unwrap();
+    }
+
+    #[test]
+    fn stuck_window_is_reacknowledged() {
+        let mut config = Config::new();
+        config.directory = String::from("received_stuck_window");
+        create_dir_all(&config.directory).unwrap();
+        let addr = SocketAddr::from_str("127.0.0.1:3000").unwrap();
+        let mut props = ReceiverConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 15, 100, addr),
+            config.filename(1),
+        );
+        // the window waits for the next part, but nothing is missing
+        assert!(props.store_data(&vec![0; 5], 0, 0, &config));
+        props.save_into_file(&config).unwrap();
+        sleep(Duration::from_millis(30));
+        assert!(!props.gap_timeout_due(20));
+        // the part 2 overtook the part 1, the window is stuck since it moved
+        assert!(props.store_data(&vec![2; 5], 2, 0, &config));
+        assert!(props.gap_timeout_due(20));
+        props.reacknowledged();
+        assert!(!props.gap_timeout_due(20));
+        // the missing part came
+        assert!(props.store_data(&vec![1; 5], 1, 0, &config));
+        props.save_into_file(&config).unwrap();
+        sleep(Duration::from_millis(30));
+        assert!(!props.gap_timeout_due(20));
+        props.close();
+        remove_dir_all(&config.directory).unwrap();
+    }
+
+    #[test]
+    fn stored_file_is_not_overwritten() {
+        let mut config = Config::new();
+        config.directory = String::from("received_create_new");
+        create_dir_all(&config.directory).unwrap();
+        write(config.filename(1), b"stored").unwrap();
+        let addr = SocketAddr::from_str("127.0.0.1:3000").unwrap();
+        let mut props = ReceiverConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 15, 100, addr),
+            config.filename(1),
+        );
+        assert!(props.store_data(&vec![1; 5], 0, 0, &config));
+        props.save_into_file(&config).unwrap();
+        props.close();
+        // the content is written next to the stored file
+        assert_eq!(props.output_path, format!("{}_1", config.filename(1)));
+        assert_eq!(read(config.filename(1)).unwrap(), b"stored");
+        assert_eq!(read(&props.output_path).unwrap(), vec![1; 5]);
+        remove_dir_all(&config.directory).unwrap();
+    }
+
+    #[test]
+    fn moved_file_keeps_existing_one() {
+        let mut config = Config::new();
+        config.directory = String::from("received_move_into");
+        let quarantine = "received_move_into_quarantine";
+        create_dir_all(&config.directory).unwrap();
+        create_dir_all(quarantine).unwrap();
+        write(Path::new(&config.directory).join("file"), b"first").unwrap();
+        let addr = SocketAddr::from_str("127.0.0.1:3000").unwrap();
+        let mut props = ReceiverConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 15, 100, addr),
+            format!("{}/file", quarantine),
+        );
+        assert!(props.store_data(&vec![1; 5], 0, 0, &config));
+        props.save_into_file(&config).unwrap();
+        // the directory, that doesn't exist, keeps the file in the quarantine
+        assert!(props.move_into("received_move_into_missing").is_err());
+        assert_eq!(props.output_path, format!("{}/file", quarantine));
+        // the moved file gets the suffix
+        props.move_into(&config.directory).unwrap();
+        assert_eq!(props.output_path, format!("{}/file_1", config.directory));
+        assert_eq!(read(Path::new(&config.directory).join("file")).unwrap(), b"first");
+        assert_eq!(read(&props.output_path).unwrap(), vec![1; 5]);
+        assert!(!Path::new(quarantine).join("file").exists());
+        props.close();
+        remove_dir_all(&config.directory).unwrap();
+        remove_dir_all(quarantine).unwrap();
+    }
+
+    #[test]
+    fn group_member_adopts_confirmed_id() {
+        let config = Config::new();
+        let addr = SocketAddr::from_str("127.0.0.1:3000").unwrap();
+        let mut props = ReceiverConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 15, 100, addr),
+            config.filename(1),
+        );
+        props.expect_nonce(7);
+        assert!(props.awaits_first_packet());
+        // the sender echoes the nonce of the confirming receiver
+        props.adopt_id(2);
+        assert_eq!(props.static_properties.id, 2);
+        assert!(props.store_data(&vec![0; 5], 0, 3, &config));
+        assert!(!props.awaits_first_packet());
+    }
+
+    #[test]
+    fn strict_order_drops_out_of_order_parts() {
+        let mut config = Config::new();
+        config.directory = String::from("received_strict_order");
+        config.strict_order = true;
+        create_dir_all(&config.directory).unwrap();
+        let addr = SocketAddr::from_str("127.0.0.1:3000").unwrap();
+        let mut props = ReceiverConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 15, 100, addr),
+            config.filename(1),
+        );
+        // the part 2 overtook the part 1, it is dropped and the part 1 is requested
+        assert!(props.store_data(&vec![0; 5], 0, 0, &config));
+        assert!(!props.store_data(&vec![2; 5], 2, 0, &config));
+        assert!(props.parts_received.get(2).is_none());
+        assert_eq!(props.get_acknowledge(), 0);
+        // the parts repeated in order are stored
+        for seq in [1, 2, 3].iter() {
+            assert!(props.store_data(&vec![*seq as u8; 5], *seq, 0, &config));
+            props.save_into_file(&config).unwrap();
+            assert!(props.parts_received.is_empty());
+        }
+        assert_eq!(props.get_acknowledge(), 3);
+        props.close();
+        let content = read(&props.output_path).unwrap();
+        assert_eq!(content, (0..4u8).flat_map(|i| vec![i; 5]).collect::<Vec<u8>>());
+        remove_dir_all(&config.directory).unwrap();
+    }
+
+    #[test]
+    fn parts_are_stored_across_wraparound() {
+        let mut config = Config::new();
+        config.directory = String::from("received_wraparound");
+        config.coalesce_writes = true;
+        create_dir_all(&config.directory).unwrap();
+        let addr = SocketAddr::from_str("127.0.0.1:3000").unwrap();
+        let mut props = ReceiverConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 15, 100, addr),
+            config.filename(1),
+        );
+        // move the window just before the wraparound, the parts are written once per window
+        for seq in 0..65530u16 {
+            assert!(props.store_data(&vec![seq as u8], seq, 0, &config));
+            if seq % 15 == 14 {
+                props.save_into_file(&config).unwrap();
+            }
+        }
+        props.save_into_file(&config).unwrap();
+        assert_eq!((props.window_position, props.next_write_position), (65530, 65530));
+        // the parts after the wraparound overtook the ones before it
+        for seq in [2, 65535, 0].iter() {
+            assert!(props.store_data(&vec![*seq as u8], *seq, 0, &config));
+            props.save_into_file(&config).unwrap();
+        }
+        assert!(!props.store_data(&vec![0], 0, 0, &config));
+        assert_eq!(props.missing_ranges(), vec![(65530, 65534), (1, 1)]);
+        assert!(!props.is_complete(&EndPacket::new(1, 3)));
+        // the missing parts move the window over the wraparound
+        for seq in (65530..65535u16).rev() {
+            assert!(props.store_data(&vec![seq as u8], seq, 0, &config));
+            props.save_into_file(&config).unwrap();
+        }
+        assert_eq!(props.window_position, 1);
+        assert_eq!(props.missing_ranges(), vec![(1, 1)]);
+        assert!(props.store_data(&vec![1], 1, 0, &config));
+        props.save_into_file(&config).unwrap();
+        assert_eq!((props.window_position, props.next_write_position), (3, 3));
+        // all the parts are written, so the end packet is accepted
+        assert!(props.parts_received.is_empty());
+        assert!(props.is_complete(&EndPacket::new(1, 3)));
+        props.close();
+        let content = read(&props.output_path).unwrap();
+        assert_eq!(content.len(), 65539);
+        assert_eq!(content[65528..], (65528..65539u32).map(|seq| seq as u16 as u8).collect::<Vec<u8>>()[..]);
+        remove_dir_all(&config.directory).unwrap();
+    }
+
+    #[test]
+    fn parts_are_not_stored_before_the_previous_are_written() {
+        let mut config = Config::new();
+        config.directory = String::from("received_unwritten_parts");
+        create_dir_all(&config.directory).unwrap();
+        let addr = SocketAddr::from_str("127.0.0.1:3000").unwrap();
+        let mut props = ReceiverConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 4, 100, addr),
+            config.filename(1),
+        );
+        // the window moves over the parts, that wait to be written
+        for seq in 0..6u16 {
+            assert!(props.store_data(&vec![seq as u8; 5], seq, 0, &config));
+        }
+        // the part within the window is dropped, the sender repeats it
+        assert!(!props.store_data(&vec![9; 5], 9, 0, &config));
+        assert!(props.store_data(&vec![7; 5], 7, 0, &config));
+        props.save_into_file(&config).unwrap();
+        assert!(props.store_data(&vec![9; 5], 9, 0, &config));
+        assert_eq!(props.missing_ranges(), vec![(6, 6), (8, 8)]);
+        assert_eq!(props.parts_received.len(), 2);
+        props.close();
+        remove_dir_all(&config.directory).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pipe_without_reader_fails_the_write() {
+        use std::fs::remove_file;
+        use std::io::ErrorKind;
+        use std::process::Command;
+        const FIFO: &str = "received_fifo_no_reader";
+        let mut config = Config::new();
+        config.fifo = Some(String::from(FIFO));
+        let addr = SocketAddr::from_str("127.0.0.1:3000").unwrap();
+        let mut props = ReceiverConnectionProperties::new(ConnectionProperties::new(1, 0, 15, 100, addr), String::from(FIFO));
+        // the missing pipe
+        let _ = remove_file(FIFO);
+        assert!(props.store_data(&vec![1; 5], 0, 0, &config));
+        assert_eq!(props.save_into_file(&config).unwrap_err().kind(), ErrorKind::NotFound);
+        // nobody reads the pipe, the receiver doesn't wait for the reader
+        assert!(Command::new("mkfifo").arg(FIFO).status().unwrap().success());
+        assert!(props.store_data(&vec![2; 5], 1, 0, &config));
+        assert!(props.save_into_file(&config).is_err());
+        assert!(props.file.is_none());
+        remove_file(FIFO).unwrap();
+    }
+
+    #[test]
+    fn bigger_checksum_is_used_once_the_sender_uses_it() {
+        let addr = SocketAddr::from_str("127.0.0.1:3000").unwrap();
+        let mut props = ReceiverConnectionProperties::new(ConnectionProperties::new(1, 4, 15, 100, addr), String::from("unused"));
+        assert_eq!(props.renegotiate_checksum(8), 8);
+        // the sender must use the agreed checksum before asking for another one
+        assert_eq!(props.renegotiate_checksum(16), 8);
+        let old = Packet::from(DataPacket::new(vec![1; 20], 1, 0, 0)).to_bin(4);
+        assert!(props.parse(&old).is_ok());
+        assert_eq!(props.static_properties.checksum_size, 4);
+        // the packet with the bigger checksum switches the connection
+        let new = Packet::from(DataPacket::new(vec![1; 20], 1, 1, 0)).to_bin(8);
+        assert!(props.parse(&new).is_ok());
+        assert_eq!(props.static_properties.checksum_size, 8);
+        assert!(props.parse(&old).is_err());
+    }
+}
@@ -0,0 +1,219 @@
+use std::fs::File;
+use std::io::{self, ErrorKind, Write};
+use std::sync::Arc;
+use std::sync::mpsc::{channel, sync_channel, Receiver, Sender, SyncSender};
+use std::thread::{self, sleep, JoinHandle};
+use std::time::Duration;
+use super::config::{Config, WriteHook};
+
+/// Maximum number of writes waiting for single writer thread, the receiver waits for the disk when it is reached.
+const QUEUE_SIZE: usize = 1024;
+
+/// How to write into the output files.
+#[derive(Clone)]
+pub struct FileWriter {
+    /// Hook called before every attempt to write.
+    on_write: Option<WriteHook>,
+    /// How many times to try again the write, that failed with a transient error.
+    retries: u32,
+    /// Pause before the first retry, it doubles with every following one.
+    backoff: Duration,
+}
+
+impl FileWriter {
+    pub fn new(config: &Config) -> Self {
+        return FileWriter {
+            on_write: config.on_write.clone(),
+            retries: config.write_retries,
+            backoff: Duration::from_millis(config.write_backoff as u64),
+        };
+    }
+
+    /// Write the whole `content` into the `file` of the `connection`.
+    /// The write failing with a transient error continues where it stopped after the pause, until the retries run out.
+    pub fn write(&self, connection: u32, mut file: &File, content: &[u8]) -> io::Result<()> {
+        let mut written = 0;
+        let mut failures = 0;
+        let mut pause = self.backoff;
+        while written < content.len() {
+            let result = match &self.on_write {
+                Some(on_write) => on_write(connection, content.len() - written),
+                None => Ok(()),
+            }.and_then(|_| file.write(&content[written..]));
+            match result {
+                Ok(0) => return Err(io::Error::from(ErrorKind::WriteZero)),
+                Ok(size) => {
+                    written += size;
+                    failures = 0;
+                    pause = self.backoff;
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) if is_transient(&e) && failures < self.retries => {
+                    failures += 1;
+                    sleep(pause);
+                    pause *= 2;
+                }
+                Err(e) => return Err(e),
+            };
+        }
+        return Ok(());
+    }
+}
+
+/// Whether the write, that failed with the `error`, may succeed later.
+fn is_transient(error: &io::Error) -> bool {
+    return matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::StorageFull);
+}
+
+/// Content to write into the file of the connection.
+struct WriteJob {
+    connection: u32,
+    file: Arc<File>,
+    content: Vec<u8>,
+    done: Sender<io::Result<()>>,
+}
+
+/// Threads, that write the received content into the files in the background.
+/// Every connection sticks to single thread, so its content is written in order.
+pub struct WriterPool {
+    queues: Vec<SyncSender<WriteJob>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl WriterPool {
+    /// Start `threads` writer threads, that write by the `writer`.
+    pub fn new(threads: usize, writer: FileWriter) -> Self {
+        let mut pool = WriterPool {
+            queues: Vec::with_capacity(threads),
+            threads: Vec::with_capacity(threads),
+        };
+        for _ in 0..threads {
+            let (queue, jobs) = sync_channel::<WriteJob>(QUEUE_SIZE);
+            let writer = writer.clone();
+            pool.queues.push(queue);
+            pool.threads.push(thread::spawn(move || {
+                for job in jobs {
+                    // the connection may be gone already, nobody waits for the result then
+                    let _ = job.done.send(writer.write(job.connection, &job.file, &job.content));
+                }
+            }));
+        }
+        return pool;
+    }
+
+    /// Queue of the writer thread for the `connection`.
+    pub fn queue(&self, connection: u32) -> WriteBehind {
+        let (done, finished) = channel();
+        return WriteBehind {
+            connection,
+            queue: self.queues[connection as usize % self.queues.len()].clone(),
+            done,
+            finished,
+            pending: 0,
+        };
+    }
+}
+
+impl Drop for WriterPool {
+    /// Write the remaining content and stop the threads.
+    fn drop(&mut self) {
+        self.queues.clear();
+        for thread in self.threads.drain(..) {
+            thread.join().expect("Writer thread panicked");
+        }
+    }
+}
+
+/// Writes of single connection, that are done by the writer thread.
+pub struct WriteBehind {
+    connection: u32,
+    queue: SyncSender<WriteJob>,
+    done: Sender<io::Result<()>>,
+    finished: Receiver<io::Result<()>>,
+    pending: usize,
+}
+
+impl WriteBehind {
+    /// Queue the `content` to write into the `file`.
+    /// Fails when some of the previous writes failed.
+    pub fn write(&mut self, file: &Arc<File>, content: Vec<u8>) -> io::Result<()> {
+        let job = WriteJob {
+            connection: self.connection,
+            file: file.clone(),
+            content,
+            done: self.done.clone(),
+        };
+        self.queue.send(job).expect("Writer thread is gone");
+        self.pending += 1;
+        return self.collect();
+    }
+
+    /// Account the writes, that are done already.
+    /// Returns the first of them, that failed.
+    fn collect(&mut self) -> io::Result<()> {
+        let mut result = Ok(());
+        while let Ok(written) = self.finished.try_recv() {
+            result = result.and(written);
+            self.pending -= 1;
+        }
+        return result;
+    }
+
+    /// Wait until all the queued content is written.
+    /// Returns the first of the writes, that failed.
+    pub fn wait(&mut self) -> io::Result<()> {
+        let mut result = Ok(());
+        while self.pending > 0 {
+            result = result.and(self.finished.recv().expect("Writer thread is gone"));
+            self.pending -= 1;
+        }
+        return result;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{read, remove_file};
+
+    #[test]
+    fn writes_of_connection_keep_order() {
+        const PATH: &str = "writer_order.bin";
+        let file = Arc::new(File::create(PATH).unwrap());
+        let pool = WriterPool::new(3, FileWriter::new(&Config::new()));
+        let mut writes = pool.queue(7);
+        for i in 0..200u8 {
+            writes.write(&file, vec![i; 10]).unwrap();
+        }
+        writes.wait().unwrap();
+        let expected: Vec<u8> = (0..200u8).flat_map(|i| vec![i; 10]).collect();
+        assert_eq!(read(PATH).unwrap(), expected);
+        remove_file(PATH).unwrap();
+    }
+
+    /// Writer, that fails the first `failures` attempts as if the disk was full.
+    fn failing_writer(failures: usize, retries: u32) -> FileWriter {
+        let attempts = std::sync::atomic::AtomicUsize::new(0);
+        return FileWriter {
+            on_write: Some(Arc::new(move |_, _| {
+                match attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst) < failures {
+                    true => Err(io::Error::from(ErrorKind::StorageFull)),
+                    false => Ok(()),
+                }
+            })),
+            retries,
+            backoff: Duration::from_millis(1),
+        };
+    }
+
+    #[test]
+    fn transient_errors_are_retried() {
+        const PATH: &str = "writer_retry.bin";
+        let file = File::create(PATH).unwrap();
+        failing_writer(3, 3).write(1, &file, &[1, 2, 3]).unwrap();
+        let e = failing_writer(4, 3).write(1, &file, &[4, 5]).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::StorageFull);
+        assert_eq!(read(PATH).unwrap(), vec![1, 2, 3]);
+        remove_file(PATH).unwrap();
+    }
+}
@@ -1,33 +1,211 @@
 use std::net::{SocketAddrV4, SocketAddr};
 use std::str::FromStr;
-use argparse::{ArgumentParser, StoreTrue, Store};
+use std::cmp::{max, min};
+use std::time::Duration;
+use rand::Rng;
+use std::sync::Mutex;
+use std::sync::mpsc::Sender;
+use argparse::{ArgumentParser, StoreTrue, StoreFalse, Store, StoreOption, Collect};
 use crate::loggable::Loggable;
+use crate::connection_properties::{TransferError, MAX_WINDOW_SIZE};
+use super::events::SenderEvent;
+
+/// Hook, that may inspect or change every serialized packet before it is send.
+pub type SendHook = Mutex<Box<dyn FnMut(&mut [u8]) + Send>>;
 
 pub struct Config {
     pub verbose: bool,
+    pub quiet: bool,
     pub bind_addr: String,
     pub file: String,
     pub packet_size: u16,
     pub send_addr: String,
     pub window_size: u16,
     pub timeout: u32,
+    pub timeout_jitter: f32,
     pub repetition: u16,
     pub checksum_size: u16,
+    pub broadcast: bool,
+    pub multicast_ttl: Option<u32>,
+    pub confirmer_addr: Option<String>,
+    pub mtu_probe: bool,
+    pub mtu_floor: u16,
+    pub min_packet_size: Option<u16>,
+    pub max_packet_size: Option<u16>,
+    pub trace: bool,
+    pub warmup_packets: u32,
+    pub manifest_path: Option<String>,
+    pub manifest_interval: u32,
+    pub auto_window: bool,
+    pub pacing: bool,
+    pub stuck_timeouts: u16,
+    pub stuck_abort: Option<u16>,
+    pub pad_last: bool,
+    pub chunk_bytes: Option<u64>,
+    pub append_files: Vec<String>,
+    pub coalesce_acks: bool,
+    pub progress_interval: Option<u32>,
+    pub start_offset: u64,
+    pub redundancy: u16,
+    pub abort_timeout: u32,
+    pub pipeline_depth: u16,
+    pub print_hash: bool,
+    pub min_send_interval: Option<u32>,
+    pub checksum_escalation: Option<f32>,
+    pub perf_log_path: Option<String>,
+    pub timeline_path: Option<String>,
+    pub deadline: Option<u32>,
+    pub next_send_addrs: Vec<String>,
+    pub fast_retransmit: Option<u16>,
+    pub param_cache_path: Option<String>,
+    pub max_stray_inits: Option<u32>,
+    pub fast_verify: bool,
+    pub seed: Option<u64>,
+    pub ssthresh: Option<u16>,
+    pub max_cwnd: Option<u16>,
+    pub delete_after_verify: bool,
+    pub wait_for_receiver: Option<u32>,
+    pub send_metadata: bool,
+    pub bandwidth: Option<u64>,
+    pub expected_rtt: Option<u32>,
+    pub events: Option<Sender<SenderEvent>>,
+    pub on_send: Option<SendHook>,
 }
 
 impl Config {
     pub fn new() -> Self {
         return Config {
             verbose: false,
+            quiet: false,
             bind_addr: String::from("127.0.0.1:3000"),
             file: String::from("input.txt"),
             packet_size: 1500,
             send_addr: String::from("127.0.0.1:3001"),
             window_size: 15,
             timeout: 100,
+            timeout_jitter: 0.0,
             repetition: 20,
             checksum_size: 64,
+            broadcast: false,
+            multicast_ttl: None,
+            confirmer_addr: None,
+            mtu_probe: false,
+            mtu_floor: 128,
+            min_packet_size: None,
+            max_packet_size: None,
+            trace: false,
+            warmup_packets: 0,
+            manifest_path: None,
+            manifest_interval: 1000,
+            auto_window: false,
+            pacing: false,
+            stuck_timeouts: 5,
+            stuck_abort: None,
+            pad_last: false,
+            chunk_bytes: None,
+            append_files: Vec::new(),
+            coalesce_acks: true,
+            progress_interval: None,
+            start_offset: 0,
+            redundancy: 1,
+            abort_timeout: 500,
+            pipeline_depth: 1,
+            print_hash: false,
+            min_send_interval: None,
+            checksum_escalation: None,
+            perf_log_path: None,
+            timeline_path: None,
+            deadline: None,
+            next_send_addrs: Vec::new(),
+            fast_retransmit: None,
+            param_cache_path: None,
+            max_stray_inits: None,
+            fast_verify: false,
+            seed: None,
+            ssthresh: None,
+            max_cwnd: None,
+            delete_after_verify: false,
+            wait_for_receiver: None,
+            send_metadata: false,
+            bandwidth: None,
+            expected_rtt: None,
+            events: None,
+            on_send: None,
+        };
+    }
+
+    /// Whether the handshake still waits for the receiver to start listening `elapsed` time after it started.
+    /// The zero `wait_for_receiver` waits forever.
+    pub fn waits_for_receiver(&self, elapsed: Duration) -> bool {
+        return match self.wait_for_receiver {
+            None => false,
+            Some(0) => true,
+            Some(wait) => elapsed < Duration::from_millis(wait as u64),
+        };
+    }
+
+    /// Timeout after which resend the part, prolonged by random fraction of it up to the `timeout_jitter`.
+    pub fn retransmit_timeout(&self, rng: &mut impl Rng) -> Duration {
+        let timeout = Duration::from_millis(self.timeout as u64);
+        if self.timeout_jitter <= 0.0 {
+            return timeout;
+        }
+        return timeout.mul_f32(1.0 + rng.gen_range(0.0, self.timeout_jitter));
+    }
+
+    /// How long to wait for the answer at once, so the heartbeat is send in time.
+    pub fn answer_timeout(&self) -> Duration {
+        let timeout = match self.min_send_interval {
+            Some(interval) => min(self.timeout, max(interval, 1)),
+            None => self.timeout,
         };
+        return Duration::from_millis(timeout as u64);
+    }
+
+    /// Checksum requested from the receiver, the `fast_verify` mode relies on the digest at the end instead.
+    pub fn requested_checksum(&self) -> u16 {
+        if self.fast_verify {
+            return 0;
+        }
+        return self.checksum_size;
+    }
+
+    /// Window requested from the receiver.
+    /// The window computed from the bandwidth and the round trip time is known after the negotiation,
+    /// until then the largest window is requested.
+    pub fn requested_window(&self) -> u16 {
+        if self.bandwidth.is_some() && self.expected_rtt.is_some() {
+            return MAX_WINDOW_SIZE;
+        }
+        return self.window_size;
+    }
+
+    /// Number of parts carrying `payload_size` bytes each, that fill the link with the bandwidth-delay product.
+    /// The window is computed only when both the `bandwidth` and the `expected_rtt` are configured.
+    pub fn bdp_window(&self, payload_size: usize) -> Option<u16> {
+        let bdp = self.bandwidth? * self.expected_rtt? as u64 / 1000;
+        let parts = (bdp + payload_size as u64 - 1) / max(payload_size as u64, 1);
+        return Some(min(max(parts, 1), u16::MAX as u64) as u16);
+    }
+
+    /// The packet `size` lowered to the `max_packet_size`.
+    pub fn packet_ceiling(&self, size: u16) -> u16 {
+        return self.max_packet_size.map_or(size, |ceiling| size.min(ceiling));
+    }
+
+    /// Fail when the packet `size` is smaller than the `min_packet_size`.
+    pub fn check_packet_floor(&self, size: u16) -> Result<u16, TransferError> {
+        return match self.min_packet_size {
+            Some(floor) if size < floor => Err(TransferError::PacketBelowFloor(size, floor)),
+            _ => Ok(size),
+        };
+    }
+
+    /// All the files to send, in the order they are send.
+    pub fn input_files(&self) -> Vec<String> {
+        let mut files = vec![self.file.clone()];
+        files.extend(self.append_files.iter().cloned());
+        return files;
     }
 
     pub fn bind_addr(&self) -> SocketAddrV4 {
@@ -36,6 +214,39 @@ impl Config {
     pub fn send_addr(&self) -> SocketAddr {
         return SocketAddr::from_str(self.send_addr.as_str()).expect("Send address is invalid");
     }
+    /// Addresses of all the receivers, to which send the file in turn.
+    pub fn send_addrs(&self) -> Vec<SocketAddr> {
+        let mut addrs = vec![self.send_addr()];
+        addrs.extend(self.next_send_addrs.iter().map(|addr| {
+            SocketAddr::from_str(addr.as_str()).expect("Send address is invalid")
+        }));
+        return addrs;
+    }
+    pub fn confirmer_addr(&self) -> Option<SocketAddr> {
+        return self.confirmer_addr.as_ref().map(|addr| {
+            SocketAddr::from_str(addr.as_str()).expect("Confirmer address is invalid")
+        });
+    }
+    /// Whether the data are send to a group of receivers (broadcast or multicast address).
+    pub fn is_group_send(&self) -> bool {
+        return self.broadcast || self.multicast_ttl.is_some();
+    }
+
+    /// Pass the `event` to the observer of the transfer, if there is any.
+    pub fn emit(&self, event: SenderEvent) {
+        if let Some(events) = &self.events {
+            // the observer may be gone already, the transfer doesn't depend on it
+            let _ = events.send(event);
+        }
+    }
+
+    /// Pass the serialized `packet` to the `on_send` hook just before it is send, if there is any.
+    pub fn before_send(&self, packet: &mut [u8]) {
+        if let Some(on_send) = &self.on_send {
+            let mut on_send = on_send.lock().expect("Can't lock the send hook");
+            on_send(packet);
+        }
+    }
 
     pub fn vlog(&self, text: &str) {
         Loggable::vlog(self, &text)
@@ -43,6 +254,14 @@ impl Config {
     pub fn is_verbose(&self) -> bool {
         Loggable::is_verbose(self)
     }
+    pub fn info(&self, text: &str) {
+        Loggable::info(self, text)
+    }
+    pub fn trace_log(&self, text: &str) {
+        if self.trace {
+            Loggable::log(self, text)
+        }
+    }
 
     pub fn from_command_line() -> Self {
         let mut config = Config::new();
@@ -50,6 +269,8 @@ impl Config {
             let mut parser = ArgumentParser::new();
             parser.refer(&mut config.verbose)
                 .add_option(&["-v", "--verbose"], StoreTrue, "Verbose output");
+            parser.refer(&mut config.quiet)
+                .add_option(&["-q", "--quiet"], StoreTrue, "Do not print any messages");
             parser.refer(&mut config.bind_addr)
                 .add_option(&["--bind"], Store, "Address to bind to in format IP:port");
             parser.refer(&mut config.file)
@@ -67,6 +288,94 @@ impl Config {
                 .add_option(&["-r", "--repetition"], Store, "Maximum number of timeouts per packet");
             parser.refer(&mut config.checksum_size)
                 .add_option(&["-s", "--sum_size"], Store, "Size of the checksum");
+            parser.refer(&mut config.broadcast)
+                .add_option(&["--broadcast"], StoreTrue, "Allow sending to the broadcast address");
+            parser.refer(&mut config.multicast_ttl)
+                .add_option(&["--multicast_ttl"], StoreOption, "Send to the multicast group with the given TTL");
+            parser.refer(&mut config.confirmer_addr)
+                .add_option(&["--confirmer"], StoreOption, "Address of the receiver that confirms the data when sending to a group");
+            parser.refer(&mut config.mtu_probe)
+                .add_option(&["--mtu_probe"], StoreTrue, "Find the largest packet size that reaches the receiver before sending");
+            parser.refer(&mut config.mtu_floor)
+                .add_option(&["--mtu_floor"], Store, "Smallest packet size the MTU probe considers");
+            parser.refer(&mut config.min_packet_size)
+                .add_option(&["--min_packet"], StoreOption, "Fail when the packet size would be lowered below this size");
+            parser.refer(&mut config.max_packet_size)
+                .add_option(&["--max_packet"], StoreOption, "Never use packets bigger than this size");
+            parser.refer(&mut config.trace)
+                .add_option(&["--trace"], StoreTrue, "Print every send data packet with the reason of sending");
+            parser.refer(&mut config.warmup_packets)
+                .add_option(&["--warmup"], Store, "Number of packets send one by one before using the whole window");
+            parser.refer(&mut config.manifest_path)
+                .add_option(&["--manifest"], StoreOption, "File where to store progress of the transfer, so it can be resumed");
+            parser.refer(&mut config.manifest_interval)
+                .add_option(&["--manifest_interval"], Store, "Minimal interval in milliseconds between the updates of the manifest");
+            parser.refer(&mut config.auto_window)
+                .add_option(&["--auto_window"], StoreTrue, "Grow the window while no packet is lost and shrink it on loss");
+            parser.refer(&mut config.pacing)
+                .add_option(&["--pacing"], StoreTrue, "Spread packets of the window over the round trip time");
+            parser.refer(&mut config.stuck_timeouts)
+                .add_option(&["--stuck_timeouts"], Store, "Number of timeouts without moving the window after which print the waiting parts");
+            parser.refer(&mut config.stuck_abort)
+                .add_option(&["--stuck_abort"], StoreOption, "Number of timeouts without moving the window after which fail the transfer");
+            parser.refer(&mut config.pad_last)
+                .add_option(&["--pad_last"], StoreTrue, "Pad the last packet to the full packet size");
+            parser.refer(&mut config.chunk_bytes)
+                .add_option(&["--chunk_bytes"], StoreOption, "Size of the chunk of the file, that must be acknowledged before sending the next one");
+            parser.refer(&mut config.append_files)
+                .add_option(&["--append"], Collect, "File to send after the previous ones into the same output, may be repeated");
+            parser.refer(&mut config.coalesce_acks)
+                .add_option(&["--one_ack_per_send"], StoreFalse, "Process only one answer of the receiver before sending again");
+            parser.refer(&mut config.progress_interval)
+                .add_option(&["--progress"], StoreOption, "Interval in milliseconds in which print the progress of the transfer");
+            parser.refer(&mut config.start_offset)
+                .add_option(&["--start_offset"], Store, "Position in the file from which send the data, the receiver stores them at the same position of its file with the same name");
+            parser.refer(&mut config.redundancy)
+                .add_option(&["--redundancy"], Store, "Number of copies of the init and end packets send at once, so a single loss doesn't wait for the timeout");
+            parser.refer(&mut config.abort_timeout)
+                .add_option(&["--abort_timeout"], Store, "How long to wait in milliseconds for the receiver to confirm it deleted the partial file after cancellation");
+            parser.refer(&mut config.timeout_jitter)
+                .add_option(&["--timeout_jitter"], Store, "Maximum random prolongation of the timeout as a fraction of it, so more senders don't resend at once");
+            parser.refer(&mut config.pipeline_depth)
+                .add_option(&["--pipeline"], Store, "Number of windows, that may be in flight at once");
+            parser.refer(&mut config.print_hash)
+                .add_option(&["--print_hash"], StoreTrue, "Print CRC-32 of the send content, so it can be compared with the received file");
+            parser.refer(&mut config.min_send_interval)
+                .add_option(&["--min_send_interval"], StoreOption, "Interval in milliseconds in which send at least one packet, so the connection doesn't look idle");
+            parser.refer(&mut config.checksum_escalation)
+                .add_option(&["--checksum_escalation"], StoreOption, "Fraction of the answers failing the checksum, above which ask the receiver for a bigger checksum");
+            parser.refer(&mut config.perf_log_path)
+                .add_option(&["--perf_log"], StoreOption, "File where to write the statistics of the transfer in stable format, so the runs can be compared");
+            parser.refer(&mut config.timeline_path)
+                .add_option(&["--timeline"], StoreOption, "CSV file where to write when every part was send and acknowledged");
+            parser.refer(&mut config.deadline)
+                .add_option(&["--deadline"], StoreOption, "Time in milliseconds in which the data should be send, the sender is more aggressive when it is behind the schedule");
+            parser.refer(&mut config.next_send_addrs)
+                .add_option(&["--then_send_to"], Collect, "Address of another receiver, to which send the file after the previous ones, may be repeated");
+            parser.refer(&mut config.fast_retransmit)
+                .add_option(&["--fast_retransmit"], StoreOption, "Number of repeated acknowledges, after which send the first unacknowledged part again without waiting for the timeout");
+            parser.refer(&mut config.param_cache_path)
+                .add_option(&["--param_cache"], StoreOption, "File where to store the properties negotiated with the receivers, the next connections start from them");
+            parser.refer(&mut config.max_stray_inits)
+                .add_option(&["--max_stray_inits"], StoreOption, "Number of init packets received after the connection was established, after which the transfer fails");
+            parser.refer(&mut config.fast_verify)
+                .add_option(&["--fast_verify"], StoreTrue, "Send the parts without checksum and fail the transfer, when the receiver doesn't confirm the digest of the whole file");
+            parser.refer(&mut config.seed)
+                .add_option(&["--seed"], StoreOption, "Seed of the timeout jitter, so it repeats in every run");
+            parser.refer(&mut config.ssthresh)
+                .add_option(&["--ssthresh"], StoreOption, "Number of parts in flight from which the automatic window grows by one part instead of doubling");
+            parser.refer(&mut config.max_cwnd)
+                .add_option(&["--max_cwnd"], StoreOption, "Maximum number of parts in flight, independent of the negotiated window size");
+            parser.refer(&mut config.delete_after_verify)
+                .add_option(&["--delete_after_verify"], StoreTrue, "Delete the source files after every receiver confirmed the digest of the whole file and that it stored it");
+            parser.refer(&mut config.wait_for_receiver)
+                .add_option(&["--wait_for_receiver"], StoreOption, "How long in milliseconds to repeat the init packet with growing pauses until the receiver starts, 0 waits forever");
+            parser.refer(&mut config.send_metadata)
+                .add_option(&["--metadata"], StoreTrue, "Send the name, size, permissions and modification time of the file after the handshake, so the receiver applies them");
+            parser.refer(&mut config.bandwidth)
+                .add_option(&["--bandwidth"], StoreOption, "Target bandwidth in bytes per second, together with the round trip time it replaces the window size");
+            parser.refer(&mut config.expected_rtt)
+                .add_option(&["--rtt"], StoreOption, "Expected round trip time in milliseconds, the window holds the bandwidth-delay product");
             parser.parse_args_or_exit();
         }
         return config;
@@ -77,4 +386,8 @@ impl Loggable for Config {
     fn is_verbose(&self) -> bool {
         self.verbose
     }
+
+    fn is_quiet(&self) -> bool {
+        self.quiet
+    }
 }
@@ -0,0 +1,66 @@
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Handle of the running sender, that pauses, resumes, or cancels the transfer from another thread.
+/// The clones of the handle control the same sender.
+#[derive(Clone)]
+pub struct Control {
+    /// Set when the sender should terminate, the same flag as the one of the breakable sender.
+    brk: Arc<AtomicBool>,
+    /// Set while the sender shouldn't send new parts of the file.
+    paused: Arc<AtomicBool>,
+    /// Why the transfer was cancelled.
+    reason: Arc<Mutex<Option<String>>>,
+}
+
+impl Control {
+    pub fn new() -> Self {
+        return Control::from(Arc::new(AtomicBool::new(false)));
+    }
+
+    /// Stop sending new parts of the file, the connection is kept alive.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Continue sending the parts of the file.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    /// Terminate the transfer for the `reason`.
+    pub fn cancel(&self, reason: &str) {
+        *self.reason.lock().expect("Can't lock the cancel reason") = Some(String::from(reason));
+        self.brk.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        return self.paused.load(Ordering::SeqCst);
+    }
+
+    /// Whether the sender should terminate, because it was cancelled or its flag was set.
+    pub fn is_cancelled(&self) -> bool {
+        return self.brk.load(Ordering::SeqCst);
+    }
+
+    /// Why the transfer was cancelled, unless it was terminated only by the flag.
+    pub fn reason(&self) -> Option<String> {
+        return self.reason.lock().expect("Can't lock the cancel reason").clone();
+    }
+
+    /// Flag set when the sender should terminate.
+    pub(crate) fn flag(&self) -> Arc<AtomicBool> {
+        return self.brk.clone();
+    }
+}
+
+impl From<Arc<AtomicBool>> for Control {
+    /// Control of the sender, that terminates when the `brk` is set.
+    fn from(brk: Arc<AtomicBool>) -> Self {
+        return Control {
+            brk,
+            paused: Arc::new(AtomicBool::new(false)),
+            reason: Arc::new(Mutex::new(None)),
+        };
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
+use crate::connection_properties::ConnectionProperties;
+
+/// Summary of the finished transfer.
+#[derive(Clone, Debug)]
+pub struct TransferStats {
+    /// Number of bytes the receiver acknowledged in the connection, the content before the start offset is not counted.
+    pub bytes: u64,
+    /// Number of parts of the file the receiver acknowledged.
+    pub parts: u32,
+    /// Number of data packets send again, after timeout or on request of the receiver.
+    pub retransmissions: u32,
+    /// Number of bytes of the file send in the data packets, including the retransmissions.
+    pub payload_bytes: u64,
+    /// Number of bytes of the data packets send, including the headers and checksums.
+    pub wire_bytes: u64,
+    /// How long the transfer took.
+    pub elapsed: Duration,
+    /// Shortest and longest measured round trip time, if any was measured.
+    pub rtt_range: Option<(Duration, Duration)>,
+    /// Smoothed round trip time at the end of the transfer.
+    pub rtt: Option<Duration>,
+}
+
+impl TransferStats {
+    /// Achieved throughput in megabytes (10^6 bytes) per second.
+    pub fn throughput(&self) -> f64 {
+        let seconds = f64::max(self.elapsed.as_secs_f64(), f64::EPSILON);
+        return self.bytes as f64 / 1_000_000.0 / seconds;
+    }
+
+    /// Part of the send data packets, that were send again.
+    pub fn retransmit_ratio(&self) -> f64 {
+        let sent = self.parts + self.retransmissions;
+        return match sent {
+            0 => 0.0,
+            sent => self.retransmissions as f64 / sent as f64,
+        };
+    }
+
+    /// How many bytes were send over the wire for every byte the receiver acknowledged.
+    pub fn overhead_ratio(&self) -> f64 {
+        return match self.bytes {
+            0 => 0.0,
+            bytes => self.wire_bytes as f64 / bytes as f64,
+        };
+    }
+
+    /// Human readable summary of the transfer.
+    pub fn summary(&self) -> String {
+        return format!("{}b send in {:.3}s, {:.2} MB/s", self.bytes, self.elapsed.as_secs_f64(), self.throughput());
+    }
+
+    /// Summary in the stable format with single `name=value` per line, so the runs can be compared.
+    pub fn perf_log(&self) -> String {
+        let micros = |rtt: Option<Duration>| match rtt {
+            Some(rtt) => rtt.as_micros().to_string(),
+            None => String::from("unknown"),
+        };
+        return [
+            format!("bytes={}", self.bytes),
+            format!("parts={}", self.parts),
+            format!("packets={}", self.parts + self.retransmissions),
+            format!("retransmissions={}", self.retransmissions),
+            format!("duration_ms={}", self.elapsed.as_millis()),
+            format!("rtt_min_us={}", micros(self.rtt_range.map(|(min, _)| min))),
+            format!("rtt_smoothed_us={}", micros(self.rtt)),
+            format!("rtt_max_us={}", micros(self.rtt_range.map(|(_, max)| max))),
+        ].iter().map(|line| format!("{}\n", line)).collect();
+    }
+}
+
+/// State of the running transfer, that is periodically reported.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    /// Sequence number of the first part not acknowledged yet.
+    pub window_position: u16,
+    /// Number of bytes the receiver acknowledged in the connection, the content before the start offset is not counted.
+    pub bytes: u64,
+    /// Estimated round trip time, if it was measured already.
+    pub rtt: Option<Duration>,
+    /// Number of data packets send again.
+    pub retransmissions: u32,
+}
+
+impl Display for Progress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let rtt = match self.rtt {
+            Some(rtt) => format!("{:.1}ms", rtt.as_secs_f64() * 1000.0),
+            None => String::from("unknown"),
+        };
+        write!(f, "progress position={} acked={}b rtt={} retransmissions={}",
+               self.window_position, self.bytes, rtt, self.retransmissions)
+    }
+}
+
+/// What happened during the transfer, for the application observing the sender.
+#[derive(Clone, Debug)]
+pub enum SenderEvent {
+    /// The receiver accepted the connection with these properties.
+    ConnectionEstablished(ConnectionProperties),
+    /// The receiver acknowledged the parts before the position.
+    WindowAdvanced(u16),
+    /// Part with the sequence number was send again.
+    Retransmit(u16),
+    /// Part with the sequence number is send again, because the receiver repeated the acknowledge before it.
+    FastRetransmit(u16),
+    /// The whole file fits into the first window, it is send in the number of parts.
+    SmallFile(u16),
+    /// No answer came from the receiver in time.
+    Timeout,
+    /// Answer from the receiver was damaged.
+    ChecksumFailure,
+    /// The connection started to use the bigger checksum of the size, because of many damaged answers.
+    ChecksumIncreased(u16),
+    /// The receiver echoed the congestion, the number of parts in flight was reduced to the size.
+    CongestionReduced(u16),
+    /// The transfer is behind the schedule of the deadline, the sender uses the whole window and shorter timeout.
+    BehindSchedule,
+    /// The transfer was paused through its control, no new parts are send.
+    Paused,
+    /// The paused transfer continues.
+    Resumed,
+    /// Something the user should know about, that doesn't stop the transfer.
+    Warning(String),
+    /// Periodic report of the transfer state.
+    Progress(Progress),
+    /// CRC-32 of the content send in the connection, when the hash should be printed.
+    ContentHash(u32),
+    /// The receiver confirmed the whole file.
+    Completed(TransferStats),
+    /// The transfer failed because of the reason.
+    Failed(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::{Progress, TransferStats};
+
+    #[test]
+    fn throughput_is_bytes_per_second() {
+        let stats = TransferStats {
+            bytes: 3_000_000,
+            parts: 90,
+            retransmissions: 10,
+            payload_bytes: 3_300_000,
+            wire_bytes: 3_600_000,
+            elapsed: Duration::from_millis(1500),
+            rtt_range: None,
+            rtt: None,
+        };
+        assert!((stats.throughput() - 2.0).abs() < 1e-9);
+        assert!((stats.retransmit_ratio() - 0.1).abs() < 1e-9);
+        assert!((stats.overhead_ratio() - 1.2).abs() < 1e-9);
+        assert_eq!(stats.summary(), "3000000b send in 1.500s, 2.00 MB/s");
+    }
+
+    #[test]
+    fn perf_log_has_line_per_value() {
+        let stats = TransferStats {
+            bytes: 3000,
+            parts: 2,
+            retransmissions: 1,
+            payload_bytes: 4500,
+            wire_bytes: 4575,
+            elapsed: Duration::from_millis(15),
+            rtt_range: Some((Duration::from_micros(800), Duration::from_micros(2100))),
+            rtt: Some(Duration::from_micros(1200)),
+        };
+        assert_eq!(
+            stats.perf_log(),
+            "bytes=3000\nparts=2\npackets=3\nretransmissions=1\nduration_ms=15\nrtt_min_us=800\nrtt_smoothed_us=1200\nrtt_max_us=2100\n"
+        );
+        let stats = TransferStats { rtt_range: None, rtt: None, ..stats };
+        assert!(stats.perf_log().ends_with("rtt_min_us=unknown\nrtt_smoothed_us=unknown\nrtt_max_us=unknown\n"));
+    }
+
+    #[test]
+    fn progress_is_single_line() {
+        let progress = Progress {
+            window_position: 12,
+            bytes: 18000,
+            rtt: Some(Duration::from_micros(2500)),
+            retransmissions: 3,
+        };
+        assert_eq!(progress.to_string(), "progress position=12 acked=18000b rtt=2.5ms retransmissions=3");
+        let progress = Progress { rtt: None, ..progress };
+        assert_eq!(progress.to_string(), "progress position=12 acked=18000b rtt=unknown retransmissions=3");
+    }
+}
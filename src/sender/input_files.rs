@@ -0,0 +1,135 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+use std::time::SystemTime;
+use crate::connection_properties::TransferError;
+use crate::packet::Crc32;
+
+/// Files read one after another as a single stream of data.
+pub struct InputFiles {
+    files: Vec<File>,
+    /// Index of the file that is read now.
+    current: usize,
+    /// Position within the whole stream.
+    position: u64,
+}
+
+impl InputFiles {
+    pub fn open(paths: &[String]) -> std::result::Result<Self, TransferError> {
+        let files = paths.iter()
+            .map(|path| File::open(path).map_err(|e| TransferError::Io(path.clone(), e.kind())))
+            .collect::<std::result::Result<Vec<File>, TransferError>>()?;
+        return Ok(InputFiles {
+            files,
+            current: 0,
+            position: 0,
+        });
+    }
+
+    /// Size and modification time of every file, that change when someone writes into the file.
+    pub fn state(&self) -> Vec<Option<(u64, SystemTime)>> {
+        return self.files.iter()
+            .map(|file| file.metadata().and_then(|metadata| Ok((metadata.len(), metadata.modified()?))).ok())
+            .collect();
+    }
+
+    /// Size of the whole stream.
+    pub fn size(&self) -> Result<u64> {
+        let mut size = 0;
+        for file in &self.files {
+            size += file.metadata()?.len();
+        }
+        return Ok(size);
+    }
+
+    /// CRC-32 of the stream from the `offset` to the end.
+    pub fn digest_from(&mut self, offset: u64) -> Result<u32> {
+        self.seek(SeekFrom::Start(offset))?;
+        let mut crc = Crc32::new();
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            match self.read(&mut buffer)? {
+                0 => return Ok(crc.finish()),
+                read => crc.update(&buffer[..read]),
+            };
+        }
+    }
+}
+
+impl Read for InputFiles {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        // move to the following file when the current one ends
+        while self.current < self.files.len() {
+            let read = self.files[self.current].read(buf)?;
+            if read > 0 || buf.is_empty() {
+                self.position += read as u64;
+                return Ok(read);
+            }
+            self.current += 1;
+        }
+        return Ok(0);
+    }
+}
+
+impl Seek for InputFiles {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let lengths = self.files.iter().map(|file| file.metadata().map(|m| m.len())).collect::<Result<Vec<u64>>>()?;
+        let total: u64 = lengths.iter().sum();
+        let target = match pos {
+            SeekFrom::Start(offset) => offset as i128,
+            SeekFrom::Current(offset) => self.position as i128 + offset as i128,
+            SeekFrom::End(offset) => total as i128 + offset as i128,
+        };
+        if target < 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "Seek before the start of the files"));
+        }
+        // find the file containing the target position
+        let mut skipped = 0;
+        self.current = 0;
+        while self.current < lengths.len() && skipped + lengths[self.current] <= target as u64 {
+            self.files[self.current].seek(SeekFrom::End(0))?;
+            skipped += lengths[self.current];
+            self.current += 1;
+        }
+        if self.current < lengths.len() {
+            self.files[self.current].seek(SeekFrom::Start(target as u64 - skipped))?;
+            for file in &mut self.files[self.current + 1..] {
+                file.seek(SeekFrom::Start(0))?;
+            }
+        }
+        self.position = target as u64;
+        return Ok(self.position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{File, remove_file};
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use super::InputFiles;
+
+    #[test]
+    fn files_are_read_as_single_stream() {
+        let paths = vec![
+            String::from("input_files_a.txt"),
+            String::from("input_files_b.txt"),
+            String::from("input_files_c.txt"),
+        ];
+        File::create(&paths[0]).unwrap().write_all(&[1, 2, 3]).unwrap();
+        File::create(&paths[1]).unwrap();
+        File::create(&paths[2]).unwrap().write_all(&[4, 5]).unwrap();
+        let mut files = InputFiles::open(&paths).unwrap();
+
+        let mut content = Vec::new();
+        files.read_to_end(&mut content).unwrap();
+        assert_eq!(content, vec![1, 2, 3, 4, 5]);
+        // seeking continues within the right file
+        assert_eq!(files.seek(SeekFrom::Start(2)).unwrap(), 2);
+        let mut content = Vec::new();
+        files.read_to_end(&mut content).unwrap();
+        assert_eq!(content, vec![3, 4, 5]);
+
+        for path in paths {
+            remove_file(path).unwrap();
+        }
+    }
+}
@@ -1,13 +1,22 @@
 use std::cmp::{max, min};
-use std::fs::File;
+use std::fs::{self, remove_file, write, Metadata};
+use std::io::{self, ErrorKind, Seek, SeekFrom};
 use std::net::{SocketAddr, UdpSocket};
 use std::result::Result::Ok;
-use std::time::Duration;
-use crate::connection_properties::ConnectionProperties;
-use crate::packet::{EndPacket, ErrorPacket, InitPacket, Packet, PacketHeader, ParsingError, Flag};
+use rand::Rng;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use crate::connection_properties::{ConnectionProperties, TransferError};
+use crate::packet::{EndPacket, ErrorPacket, InitPacket, MetadataPacket, Packet, PacketHeader, ParsingError, Flag, ToBin};
 use super::config::Config;
 use super::sender_connection_properties::SenderConnectionProperties;
+use super::manifest::Manifest;
+use super::param_cache::{CachedParams, ParamCache};
+use super::input_files::InputFiles;
+use super::events::{SenderEvent, TransferStats};
+use super::control::Control;
+use super::sender_connection_properties::SendReason;
 use crate::{recv_with_timeout, BUFFER_SIZE};
+use crate::socket_manipulation::{is_message_too_long, recv_available};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
@@ -15,67 +24,405 @@ use std::thread::JoinHandle;
 
 /// Creates the sender.
 /// `brk` parameter should be set to `true` when the sender should terminate.
-/// Returns handler to join the thread.
-pub fn breakable_logic(config: Config, brk: Arc<AtomicBool>) -> JoinHandle<Result<(), String>> {
+/// Returns handler to join the thread, the thread returns the properties negotiated with the receiver.
+pub fn breakable_logic(config: Config, brk: Arc<AtomicBool>) -> JoinHandle<Result<ConnectionProperties, TransferError>> {
+    controlled_logic(config, Control::from(brk))
+}
+
+/// Creates the sender, that is paused, resumed, or cancelled by the `control`.
+/// Returns handler to join the thread, the thread returns the properties negotiated with the receiver.
+pub fn controlled_logic(config: Config, control: Control) -> JoinHandle<Result<ConnectionProperties, TransferError>> {
     thread::Builder::new()
         .name(String::from("Broker"))
         .spawn(move || {
-            sender(config, brk)
+            sender(config, control)
         }).expect("Can't create thread for the broker")
 }
 
 /// Creates the sender and keep running.
 /// There is no way how to terminate the execution.
-pub fn logic(config: Config) -> Result<(), String> {
-    let brk = Arc::new(AtomicBool::new(false));
-    sender(config, brk)
+/// Returns the properties negotiated with the receiver.
+pub fn logic(config: Config) -> Result<ConnectionProperties, TransferError> {
+    sender(config, Control::new())
+}
+
+pub fn sender(config: Config, control: Control) -> Result<ConnectionProperties, TransferError> {
+    let result = transfer(&config, &control);
+    if let Err(e) = &result {
+        config.emit(SenderEvent::Failed(e.to_string()));
+    }
+    return result;
 }
 
-pub fn sender(config: Config, brk: Arc<AtomicBool>) -> Result<(), String> {
+/// Transfer the file described by the `config` to every receiver in turn.
+/// Returns the properties negotiated with the last receiver.
+fn transfer(config: &Config, control: &Control) -> Result<ConnectionProperties, TransferError> {
     // open file
-    let mut input_file = File::open(&config.file).expect("Couldn't open file");
+    let mut input_file = InputFiles::open(&config.input_files())?;
+    let source_state = input_file.state();
     config.vlog(&format!("File {} opened", &config.file));
     // connect socket
     let socket = UdpSocket::bind(config.bind_addr()).expect("Can't bind socket");
-    config.vlog(&format!("Socket bind to {}", config.bind_addr()));
+    config.vlog(&format!("Socket bind to {}", socket.local_addr().expect("Can't get address of the socket")));
     socket.set_read_timeout(Option::Some(Duration::from_millis(config.timeout as u64))).expect("Can't set timeout on the socket");
+    // allow sending to the group of receivers
+    if config.broadcast {
+        socket.set_broadcast(true).expect("Can't enable broadcast on the socket");
+        config.vlog("Broadcast enabled");
+    }
+    if let Some(ttl) = config.multicast_ttl {
+        socket.set_multicast_ttl_v4(ttl).expect("Can't set multicast TTL on the socket");
+        config.vlog(&format!("Multicast TTL set to {}", ttl));
+    }
+
+    // send the file to every receiver in turn, the connection to the next one starts at the beginning of the file
+    let mut negotiated = None;
+    let mut verified = true;
+    for (index, addr) in config.send_addrs().into_iter().enumerate() {
+        if index > 0 {
+            config.vlog(&format!("Sending to the next receiver {}", addr));
+        }
+        let (props, confirmed) = match transfer_to(config, &mut input_file, &source_state, &socket, addr, index == 0, control) {
+            Ok(transfer) => transfer,
+            // the file and the socket are released before the failure is reported
+            Err(e) => {
+                drop(input_file);
+                drop(socket);
+                return Err(e);
+            }
+        };
+        negotiated = Some(props);
+        verified &= confirmed;
+    }
+    // the source is moved only when every receiver stored the same content
+    if config.delete_after_verify {
+        drop(input_file);
+        match verified {
+            true => delete_sources(config)?,
+            false => config.info(&format!("Digest of {} not confirmed, the source is kept", config.input_files().join(", "))),
+        };
+    }
+    return Ok(negotiated.expect("There is at least one receiver"));
+}
+
+/// Delete the source files after their transfer was verified.
+fn delete_sources(config: &Config) -> Result<(), TransferError> {
+    for path in config.input_files() {
+        remove_file(&path).map_err(|e| TransferError::Io(path.clone(), e.kind()))?;
+        config.vlog(&format!("Verified source {} deleted", path));
+    }
+    return Ok(());
+}
+
+/// Transfer the `input_file` over the `socket` to the receiver at `addr`.
+/// The transfer fails, when the files changed since they had the `source_state`.
+/// Only the `first` receiver may continue in the transfer stored in the manifest.
+/// Returns the negotiated properties and whether the receiver confirmed the digest of the stored content.
+fn transfer_to(
+    config: &Config,
+    input_file: &mut InputFiles,
+    source_state: &[Option<(u64, SystemTime)>],
+    socket: &UdpSocket,
+    addr: SocketAddr,
+    first: bool,
+    control: &Control,
+) -> Result<(ConnectionProperties, bool), TransferError> {
+    let brk = control.flag();
+    let started_at = Instant::now();
+    // resume the transfer if there is a manifest for this file, only the first receiver may be resumed
+    let resumed = config.manifest_path.as_ref()
+        .filter(|_| first)
+        .and_then(|path| Manifest::load(path))
+        .filter(|manifest| manifest.file == config.file);
+    let mut props = match resumed {
+        Some(manifest) => {
+            input_file.seek(SeekFrom::Start(manifest.offset)).expect("Can't seek in the file to resume");
+            config.vlog(&format!(
+                "Resuming connection {} at position {} and offset {}",
+                manifest.id,
+                manifest.position,
+                manifest.offset
+            ));
+            manifest.to_properties()
+        }
+        None => {
+            // start from the properties negotiated with the receiver last time
+            let cached = config.param_cache_path.as_ref()
+                .and_then(|path| ParamCache::load(path).get(&addr));
+            // find packet size that reaches the receiver, unless it is known already
+            let packet_size = match config.mtu_probe && cached.is_none() {
+                true => probe_packet_size(config, socket, addr, brk.clone()),
+                false => config.packet_size,
+            };
+            // init connection
+            let props = create_connection(config, socket, addr, packet_size, cached, brk.clone())?;
+            store_params(config, addr, &props);
+            // the content before the start offset is not send, the receiver finds the file to continue by its name
+            input_file.seek(SeekFrom::Start(config.start_offset)).map_err(|e| TransferError::Io(config.file.clone(), e.kind()))?;
+            if config.send_metadata || config.start_offset > 0 {
+                send_metadata(config, socket, input_file, &props, brk.clone())?;
+            }
+            store_manifest(config, &props);
+            props
+        }
+    };
+
+    config.emit(SenderEvent::ConnectionEstablished(props.static_properties.clone()));
+    props.pipeline(config.pipeline_depth);
+    if config.auto_window {
+        props.auto_tune_window();
+    }
+    props.limit_growth(config.ssthresh, config.max_cwnd);
+    if config.timeline_path.is_some() {
+        props.record_timeline();
+    }
+
+    // send data, the cancelled transfer is cleaned up on the receiver unless it can be resumed
+    if let Err(e) = send_data(config, input_file, socket, &mut props, control) {
+        if control.is_cancelled() && config.manifest_path.is_none() {
+            return Err(match (abort(config, socket, &props), control.reason()) {
+                (TransferError::Cancelled(confirmed), Some(reason)) => TransferError::CancelledFor(reason, confirmed),
+                (e, _) => e,
+            });
+        }
+        return Err(e);
+    }
+
+    // the receiver must not keep the file, if the content changed while it was read
+    if input_file.state() != source_state {
+        let error_packet = Packet::from(ErrorPacket::new(props.static_properties.id));
+        let mut buffer = vec![0; BUFFER_SIZE];
+        let length = error_packet.to_bin_buff(&mut buffer, props.static_properties.checksum_size as usize);
+        socket.send_to(&buffer[..length], props.static_properties.socket_addr).expect("Can't send error packet");
+        let e = format!("Source file {} changed during the transfer", config.input_files().join(", "));
+        config.info(&e);
+        return Err(TransferError::Failed(e));
+    }
+
+    // the receiver compares the digest with the content it stored
+    let digest = input_file.digest_from(config.start_offset)
+        .map_err(|e| TransferError::Io(config.file.clone(), e.kind()))?;
+    let verified = send_end(config, socket, &mut props, digest, brk.clone())?;
+    let stats = TransferStats {
+        bytes: props.transferred_bytes(),
+        parts: props.acknowledged_parts,
+        retransmissions: props.retransmissions,
+        payload_bytes: props.payload_bytes,
+        wire_bytes: props.wire_bytes,
+        elapsed: started_at.elapsed(),
+        rtt_range: props.rtt_range(),
+        rtt: props.progress().rtt,
+    };
+    match config.send_addrs().len() {
+        1 => config.info(&stats.summary()),
+        _ => config.info(&format!("{}: {}", addr, stats.summary())),
+    };
+    if let Some(path) = &config.perf_log_path {
+        if let Err(e) = write(path, stats.perf_log()) {
+            config.info(&format!("Can't write performance log {}: {}", path, e));
+        }
+    }
+    if let (Some(path), Some(timeline)) = (&config.timeline_path, props.timeline_csv()) {
+        if let Err(e) = write(path, timeline) {
+            config.info(&format!("Can't write timeline {}: {}", path, e));
+        }
+    }
+    if config.print_hash {
+        let hash = props.content_crc();
+        config.info(&format!("crc32 {:08x} {}", hash, config.input_files().join(", ")));
+        config.emit(SenderEvent::ContentHash(hash));
+    }
+    config.vlog(&format!("{} of {} data packets send again, retransmit ratio {:.3}",
+                         stats.retransmissions,
+                         stats.parts + stats.retransmissions,
+                         stats.retransmit_ratio()));
+    config.vlog(&format!("{}b of the file send in {}b of data packets, overhead ratio {:.3}",
+                         stats.payload_bytes,
+                         stats.wire_bytes,
+                         stats.overhead_ratio()));
+    config.emit(SenderEvent::Completed(stats));
+
+    // transfer is complete, the manifest is not needed anymore
+    if let Some(path) = &config.manifest_path {
+        let _ = remove_file(path);
+    }
+    return Ok((props.static_properties, verified));
+}
+
+/// Remember the properties negotiated with the receiver at `addr` in the cache, if required.
+fn store_params(config: &Config, addr: SocketAddr, props: &SenderConnectionProperties) {
+    if let Some(path) = &config.param_cache_path {
+        let mut cache = ParamCache::load(path);
+        cache.insert(addr, &props.static_properties);
+        if let Err(e) = cache.store(path) {
+            config.vlog(&format!("Can't store negotiated properties into {}: {}", path, e));
+        }
+    }
+}
 
-    // init connection
-    let mut props =
-        create_connection(&config, &socket, config.send_addr(), brk.clone())
-            .expect("Can't create init connection");
+/// Store the progress of the transfer into the manifest, if required.
+fn store_manifest(config: &Config, props: &SenderConnectionProperties) {
+    if let Some(path) = &config.manifest_path {
+        if let Err(e) = Manifest::from_properties(&config.file, props).store(path) {
+            config.vlog(&format!("Can't store manifest {}: {}", path, e));
+        }
+    }
+}
 
-    // send data
-    send_data(&config, &mut input_file, &socket, &mut props, brk.clone())?;
+/// Number of init packets send for every probed packet size.
+const MTU_PROBE_ATTEMPTS: u16 = 3;
 
-    send_end(&config, &socket, &mut props, brk.clone())
+/// Find the largest packet size between `mtu_floor` and `packet_size` from the `config`,
+/// that the receiver at `addr` accepts without truncation.
+/// Every probe is a connection establishment, that is immediately closed after it succeeds.
+pub fn probe_packet_size(
+    config: &Config,
+    socket: &UdpSocket,
+    addr: SocketAddr,
+    brk: Arc<AtomicBool>,
+) -> u16 {
+    // the smallest size must fit the init packet and checksum
+    let min_size = (PacketHeader::bin_size() + 7) as u16 + config.requested_checksum();
+    let mut low = max(config.mtu_floor, min_size);
+    let mut high = max(config.packet_ceiling(config.packet_size), low);
+    // binary search for the largest size that round-trips
+    while low < high && !brk.load(Ordering::SeqCst) {
+        let size = low + (high - low + 1) / 2;
+        config.vlog(&format!("Probing packet size {}, searching between {} and {}", size, low, high));
+        match probe_size(config, socket, addr, size) {
+            Ok(accepted) => {
+                low = max(low, accepted);
+                // the receiver accepted smaller packet than probed, it can't accept bigger ones
+                if accepted < size {
+                    high = low;
+                }
+            }
+            Err(Some(received)) => high = max(low, min(size - 1, received)),
+            Err(None) => high = size - 1,
+        };
+    }
+    config.vlog(&format!("Probed packet size {}", low));
+    return low;
+}
+
+/// Send init packet of the given `size` to the receiver at `addr`.
+/// Returns packet size the receiver accepted, or the size that it received when the packet was truncated.
+fn probe_size(config: &Config, socket: &UdpSocket, addr: SocketAddr, size: u16) -> Result<u16, Option<u16>> {
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let init_packet = InitPacket::new(config.requested_window(), size, config.requested_checksum());
+    for _ in 0..MTU_PROBE_ATTEMPTS {
+        // send the probe
+        let wrote = Packet::from(Clone::clone(&init_packet)).to_bin_buff(&mut buffer, config.requested_checksum() as usize);
+        match socket.send_to(&buffer[..wrote], addr) {
+            Err(e) if is_message_too_long(&e) => {
+                config.vlog(&format!("Probe of size {} is too long for the system", size));
+                return Err(None);
+            }
+            result => result.expect("Can't send probe packet"),
+        };
+        // wait for answer
+        let (data_size, received_from) = match recv_with_timeout(&socket, &mut buffer, Box::new(config)) {
+            Err(_) => continue,
+            Ok(x) => x,
+        };
+        let init_content = match InitPacket::from_bin_no_size_and_hash_check(&buffer[..data_size]) {
+            Err(e) => {
+                config.vlog(&format!("Can't read init content of the probe answer {:?}", e));
+                continue;
+            }
+            Ok(x) => x,
+        };
+        match Packet::from_bin(&buffer[..data_size], init_content.checksum_size as usize) {
+            Ok(Packet::Init(packet)) if packet.header.id == 0 => {
+                config.vlog(&format!("Probe of size {} truncated to {}", size, packet.packet_size));
+                return Err(Some(packet.packet_size));
+            }
+            Ok(Packet::Init(packet)) => {
+                // close the connection created by the probe
+                let end_packet = Packet::from(EndPacket::new(packet.header.id, 0));
+                let wrote = end_packet.to_bin_buff(&mut buffer, packet.checksum_size as usize);
+                socket.send_to(&buffer[..wrote], received_from).expect("Can't close probe connection");
+                let _ = recv_with_timeout(&socket, &mut buffer, Box::new(config));
+                config.vlog(&format!("Probe of size {} accepted with size {}", size, packet.packet_size));
+                return Ok(packet.packet_size);
+            }
+            Err(ParsingError::InvalidSize(_, actual)) => {
+                config.vlog(&format!("Probe answer truncated to {}", actual));
+                return Err(Some(actual as u16));
+            }
+            _ => {
+                config.vlog("Invalid answer to the probe, ignoring");
+            }
+        };
+    }
+    return Err(None);
 }
 
 /// Connect to the receiver and agree on the connection properties.
 /// It uses `socket` and expect receiver at the `addr` address.
+/// The connection uses at most `packet_size` big packets, limited by the packet size bounds in the `config`.
+/// Fails when the packet size would be lowered below the minimal packet size.
+/// The connection starts from the `cached` properties, when the receiver agreed on them last time.
+/// The receiver is asked to store the data from the start offset and it must confirm it.
+/// The user is warned, when the agreed properties differ from the requested ones.
 fn create_connection(
     config: &Config,
     socket: &UdpSocket,
     addr: SocketAddr,
+    packet_size: u16,
+    cached: Option<CachedParams>,
     brk: Arc<AtomicBool>,
-) -> Result<SenderConnectionProperties, ()> {
+) -> Result<SenderConnectionProperties, TransferError> {
     // create buffer
     let mut buffer = vec![0; BUFFER_SIZE];
     // create my init packet
     let mut init_packet = InitPacket::new(
-        config.window_size,
-        config.packet_size,
-        config.checksum_size,
+        config.requested_window(),
+        config.check_packet_floor(config.packet_ceiling(packet_size))?,
+        config.requested_checksum(),
     );
+    init_packet.offset = config.start_offset;
+    // the receiver answers the copies and the repeats of the request by the same connection
+    if InitPacket::has_nonce_room(init_packet.packet_size, init_packet.checksum_size) {
+        init_packet.nonce = rand::thread_rng().gen_range(1, u16::MAX);
+    }
+    let mut requested = Clone::clone(&init_packet);
+    // the receiver agreed on these properties last time, the user is still warned about the configured ones
+    if let Some(cached) = cached {
+        init_packet.packet_size = min(init_packet.packet_size, cached.packet_size);
+        init_packet.window_size = min(init_packet.window_size, cached.window_size);
+        if !config.fast_verify {
+            init_packet.checksum_size = max(init_packet.checksum_size, cached.checksum_size);
+        }
+        config.vlog(&format!("Starting from the properties negotiated with {} last time", addr));
+    }
 
-    // for specified number of retries
+    // for specified number of retries, not counting the ones while waiting for the receiver to start
+    let started_at = Instant::now();
+    let mut pause = Duration::from_millis(config.timeout as u64);
     let mut attempts = 0;
+    let mut too_small = None;
     while attempts < config.repetition && !brk.load(Ordering::SeqCst) {
         // send packet
         config.vlog(&format!("Attempt {} to establish connection", attempts + 1));
         let packet = Packet::from(Clone::clone(&init_packet));
         let wrote = packet.to_bin_buff(&mut buffer, init_packet.checksum_size as usize);
-        socket.send_to(&buffer[..wrote], addr).expect("Can't send data and establish connection");
+        config.before_send(&mut buffer[..wrote]);
+        match send_copies(config, socket, &buffer[..wrote], addr) {
+            // the system can't send such long packets, try smaller ones
+            Err(e) if is_message_too_long(&e) => {
+                let min_size = (PacketHeader::bin_size() + 7) as u16 + init_packet.checksum_size;
+                if init_packet.packet_size / 2 < min_size {
+                    let reason = format!("Packet of {}b can't be send by the system", init_packet.packet_size);
+                    config.info(&reason);
+                    return Err(TransferError::Failed(reason));
+                }
+                init_packet.packet_size = config.check_packet_floor(init_packet.packet_size / 2)?;
+                config.vlog(&format!("Init packet is too long for the system, lowering packet size to {}", init_packet.packet_size));
+                continue;
+            }
+            result => result.expect("Can't send data and establish connection"),
+        };
         config.vlog(&format!(
             "Init packet send - packet size: {}, checksum size: {}, window_size: {}",
             init_packet.packet_size,
@@ -83,19 +430,35 @@ fn create_connection(
             init_packet.window_size
         ));
         // wait for answer
-        let recv_result = recv_with_timeout(&socket, &mut buffer, Box::new(config));
+        let recv_result = recv_breakable(config, socket, &mut buffer, &brk);
         if let Err(_) = recv_result {
+            if config.waits_for_receiver(started_at.elapsed()) {
+                config.vlog(&format!("No answer from {}, repeating init packet in {}ms", addr, pause.as_millis()));
+                pause_breakable(pause, &brk);
+                pause = min(pause * 2, MAX_HANDSHAKE_PAUSE);
+                continue;
+            }
             attempts += 1;
             continue;
         };
         // get raw data
         let (data_size, received_from) = recv_result.unwrap();
         config.vlog(&format!("Received {} data from {}", data_size, received_from));
+        if !from_confirmer(config, received_from) {
+            continue;
+        }
         if data_size < PacketHeader::bin_size() {
             config.vlog("Received less data than header, ignoring");
             attempts += 1;
             continue;
         }
+        // the receiver refused the connection, its error packet has no checksum
+        let refusal = ErrorPacket::from_bin(&buffer[..data_size]).ok()
+            .filter(|packet| packet.header.flag == Flag::Error && packet.header.id == 0);
+        if let Some(refusal) = refusal {
+            config.info(&format!("Receiver refused the connection, {}", refusal.reason()));
+            return Err(TransferError::ReceiverClosed(refusal.reason()));
+        }
         // parse init packet without exception
         let init_content_result = InitPacket::from_bin_no_size_and_hash_check(&buffer[..data_size]);
         if let Err(e) = init_content_result {
@@ -108,32 +471,80 @@ fn create_connection(
         // decide what to do with the packet
         match packet_result {
             Ok(Packet::Init(packet)) => {
-                init_packet.packet_size = min(init_packet.packet_size, packet.packet_size);
-                init_packet.window_size = min(init_packet.window_size, packet.window_size);
-                init_packet.checksum_size = max(init_packet.checksum_size, packet.checksum_size);
+                // the receiver may only lower the requested sizes, the window has at least one packet
+                let oversized = packet.packet_size > init_packet.packet_size || packet.window_size > max(init_packet.window_size, 1);
+                if packet.header.id != 0 && oversized {
+                    config.info(&format!(
+                        "Receiver advertised packet size {} and window size {}, but at most {} and {} were requested, ignoring",
+                        packet.packet_size,
+                        packet.window_size,
+                        init_packet.packet_size,
+                        init_packet.window_size
+                    ));
+                    attempts += 1;
+                    continue;
+                }
+                // when sending to the group, data go to the group address and only the acknowledges come from the receiver
+                let data_addr = match config.is_group_send() {
+                    true => addr,
+                    false => received_from,
+                };
+                let mut negotiated = match ConnectionProperties::negotiate(packet.header.id, &init_packet, &packet, data_addr) {
+                    Ok(negotiated) => negotiated,
+                    Err(e) => {
+                        config.vlog(&format!("Can't agree on the connection properties {:?}", e));
+                        if let TransferError::PacketTooSmall(size) = e {
+                            too_small = Some(size);
+                        }
+                        attempts += 1;
+                        continue;
+                    }
+                };
+                init_packet.packet_size = config.check_packet_floor(negotiated.packet_size)?;
+                init_packet.window_size = negotiated.window_size;
+                init_packet.checksum_size = negotiated.checksum_size;
                 if packet.header.id == 0 {
                     config.vlog("Received init packet with 0 id, receiver couldn't receive whole packet, repeating");
                     continue;
                 }
-                let props = SenderConnectionProperties::new(ConnectionProperties::new(
-                    packet.header.id,
-                    init_packet.checksum_size,
-                    init_packet.window_size,
-                    init_packet.packet_size,
-                    received_from,
-                ));
+                // the window holds the bandwidth-delay product, but no more than the receiver agreed on
+                let payload_size = negotiated.packet_size as usize - PacketHeader::bin_size() - negotiated.checksum_size as usize;
+                if let Some(window) = config.bdp_window(payload_size) {
+                    config.vlog(&format!("Bandwidth-delay product fills {} parts of {}b", window, payload_size));
+                    requested.window_size = window;
+                    negotiated.window_size = min(window, negotiated.window_size);
+                }
+                if packet.offset != config.start_offset {
+                    let reason = format!("Receiver stores the data from {}, but {} was requested", packet.offset, config.start_offset);
+                    config.info(&reason);
+                    return Err(TransferError::Failed(reason));
+                }
+                // the content before the start offset is considered acknowledged, so the manifest stores the position in the file
+                let mut props = SenderConnectionProperties::resume(negotiated, 0, config.start_offset);
+                props.echo_nonce(packet.nonce);
+                if let Some(seed) = config.seed {
+                    props.seed(seed);
+                }
                 config.vlog(&format!("Connection {} established, window_size: {}, packet_size: {}, checksum_size: {}",
                                      props.static_properties.id,
                                      props.static_properties.window_size,
                                      props.static_properties.packet_size,
                                      props.static_properties.checksum_size));
+                warn_overridden(config, &requested, &props.static_properties);
                 return Ok(props);
             }
             Ok(_) => {
                 config.vlog("Not init packet received, dropping");
             }
             Err(ParsingError::InvalidSize(expected, actual)) => {
-                init_packet.packet_size = actual as u16;
+                // the path truncated the packet, so that no data would fit, the packet size is kept in case it was random
+                if actual < ConnectionProperties::min_packet_size(init_packet.checksum_size) {
+                    config.vlog(&format!("Expected to received {} bytes, but {} only received, too few to carry any data", expected, actual));
+                    too_small = Some(actual as u16);
+                    attempts += 1;
+                    continue;
+                }
+                init_packet.packet_size = config.check_packet_floor(actual as u16)?;
                 config.vlog(&format!("Expected to received {} bytes, but {} only received, repeating with new configuration", expected, actual));
                 continue;
             }
@@ -143,132 +554,576 @@ fn create_connection(
             }
         };
     }
+    // the path kept shrinking the packets below any payload
+    if let Some(size) = too_small {
+        let e = TransferError::PacketTooSmall(size);
+        config.info(&format!("Can't establish connection with the server after {} attempts: {}", config.repetition, e));
+        return Err(e);
+    }
     // didn't receive init packet after specified number of retries
-    println!("Can't establish connection with the server after {} attempts", config.repetition);
-    return Err(());
+    let reason = format!("Can't establish connection with the server after {} attempts", config.repetition);
+    config.info(&reason);
+    return Err(TransferError::Failed(reason));
 }
 
+/// How often the termination is checked while waiting for the answer to the init packet.
+const BREAK_POLL: Duration = Duration::from_millis(50);
+/// Longest pause between the init packets, while waiting for the receiver to start.
+const MAX_HANDSHAKE_PAUSE: Duration = Duration::from_secs(2);
+
+/// Wait for the `pause`, the wait ends soon after the `brk` is set.
+fn pause_breakable(pause: Duration, brk: &AtomicBool) {
+    let deadline = Instant::now() + pause;
+    while !brk.load(Ordering::SeqCst) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.as_nanos() == 0 {
+            break;
+        }
+        thread::sleep(min(left, BREAK_POLL));
+    }
+}
+
+/// Receive packet from the `socket` into the `buffer` within the timeout from the `config`.
+/// The wait ends soon after the `brk` is set, even when the timeout is long.
+fn recv_breakable(config: &Config, socket: &UdpSocket, buffer: &mut Vec<u8>, brk: &AtomicBool) -> io::Result<(usize, SocketAddr)> {
+    let deadline = Instant::now() + Duration::from_millis(config.timeout as u64);
+    let result = loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        socket.set_read_timeout(Some(max(min(left, BREAK_POLL), Duration::from_millis(1)))).expect("Can't set timeout on the socket");
+        match recv_with_timeout(socket, buffer, Box::new(config)) {
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                if brk.load(Ordering::SeqCst) || Instant::now() >= deadline {
+                    break Err(e);
+                }
+            }
+            result => break result,
+        };
+    };
+    socket.set_read_timeout(Some(Duration::from_millis(config.timeout as u64))).expect("Can't set timeout on the socket");
+    return result;
+}
+
+/// Warn the user about the `agreed` connection properties, that differ from the `requested` ones.
+fn warn_overridden(config: &Config, requested: &InitPacket, agreed: &ConnectionProperties) {
+    let changes = [
+        ("checksum size", requested.checksum_size, agreed.checksum_size),
+        ("window size", requested.window_size, agreed.window_size),
+        ("packet size", requested.packet_size, agreed.packet_size),
+    ];
+    let changes = changes.iter()
+        .filter(|(_, requested, agreed)| requested != agreed)
+        .map(|(name, requested, agreed)| format!("{} {} requested, {} agreed", name, requested, agreed))
+        .collect::<Vec<String>>();
+    if changes.is_empty() {
+        return;
+    }
+    let warning = format!("Warning: connection {} differs from the configuration, {}", agreed.id, changes.join(", "));
+    config.info(&warning);
+    config.emit(SenderEvent::Warning(warning));
+}
 
 /// Send the data after connection has been established.
 /// It send `input_file` file via `socket` using the `props` connection.
 fn send_data(
     config: &Config,
-    mut input_file: &mut File,
+    mut input_file: &mut InputFiles,
     socket: &UdpSocket,
     props: &mut SenderConnectionProperties,
-    brk: Arc<AtomicBool>,
-) -> Result<(), String> {
+    control: &Control,
+) -> Result<(), TransferError> {
     // prepare variables
     let mut attempts = 0;
+    let mut paused = false;
     let mut buffer = vec![0; BUFFER_SIZE];
+    let mut window_moved_at = Instant::now();
+    let mut stuck_reported = false;
+    let mut manifest_at = Instant::now();
+    let mut progress_at = Instant::now();
+    let mut answered_at = Instant::now();
+    // the progress is compared with the deadline from now on
+    let started_at = Instant::now();
+    let initial_bytes = props.transferred_bytes();
+    let bytes_to_send = match config.deadline {
+        Some(_) => input_file.size()
+            .map_err(|e| TransferError::Io(config.file.clone(), e.kind()))?
+            .saturating_sub(props.acknowledged_bytes),
+        None => 0,
+    };
+    // wait shorter for the answers, so the heartbeat is send in time
+    socket.set_read_timeout(Some(config.answer_timeout())).expect("Can't set timeout on the socket");
     // process data
-    while attempts < config.repetition && !props.is_complete() && !brk.load(Ordering::SeqCst) {
-        // load data to fill rest of the window
-        props.load_window(&mut input_file, &config);
+    while attempts < config.repetition && !props.is_complete() && !control.is_cancelled() {
+        // the paused transfer waits for the resume, the receiver not answering the keepalive is not counted
+        if control.is_paused() != paused {
+            paused = control.is_paused();
+            config.vlog(&format!("Connection {} {}", props.static_properties.id, if paused { "paused" } else { "resumed" }));
+            config.emit(if paused { SenderEvent::Paused } else { SenderEvent::Resumed });
+        }
+        if paused {
+            attempts = 0;
+            window_moved_at = Instant::now();
+        }
+        // report the progress, even when the output is not verbose
+        if let Some(interval) = config.progress_interval {
+            if progress_at.elapsed().as_millis() >= interval as u128 {
+                let progress = props.progress();
+                config.info(&progress.to_string());
+                config.emit(SenderEvent::Progress(progress));
+                progress_at = Instant::now();
+            }
+        }
+        // check the window moves, the receiver may acknowledge only the parts before it
+        let stalled_timeouts = window_moved_at.elapsed().as_millis() / max(config.timeout, 1) as u128;
+        if !stuck_reported && stalled_timeouts >= config.stuck_timeouts as u128 {
+            config.info(&props.stuck_diagnostic());
+            stuck_reported = true;
+        }
+        if config.stuck_abort.map_or(false, |abort| stalled_timeouts >= abort as u128) {
+            config.vlog(&format!("Window didn't move in {} timeouts", stalled_timeouts));
+            break;
+        }
+        // be more aggressive, when the transfer wouldn't end before the deadline
+        if let Some(deadline) = config.deadline {
+            let elapsed = started_at.elapsed();
+            let behind = elapsed.as_millis() >= config.timeout as u128
+                && behind_schedule(props.transferred_bytes() - initial_bytes, bytes_to_send, elapsed, deadline);
+            if props.hurry(behind, config) {
+                config.emit(SenderEvent::BehindSchedule);
+            }
+        }
+        // switch to the bigger checksum, when the receiver agreed on it
+        if let Some(checksum_size) = props.apply_checksum() {
+            config.info(&format!("Connection {} uses checksum of {}b", props.static_properties.id, checksum_size));
+            config.emit(SenderEvent::ChecksumIncreased(checksum_size));
+        }
+        // the receiver stored more than the manifest knows, continue where it is
+        if props.is_behind() {
+            props.skip_acknowledged(input_file, config)?;
+        }
+        // load data to fill rest of the window, the paused transfer sends only the parts loaded already
+        if !paused {
+            props.load_window(&mut input_file, &config);
+        }
+        // the end of the file may be found only after all the parts were acknowledged
+        if props.is_complete() {
+            continue;
+        }
+        // keep the connection alive, when nothing was send for a while
+        if paused && props.keepalive(&socket, config) {
+            config.vlog("Paused connection kept alive");
+        }
+        if props.heartbeat(config) {
+            config.vlog(&format!("Nothing send in {}ms, sending heartbeat", config.min_send_interval.unwrap_or(0)));
+        }
         // send data
-        props.send_data(&socket, &config);
+        for trace in props.send_data(&socket, &config) {
+            config.trace_log(&trace.to_string());
+            if trace.reason != SendReason::First {
+                config.emit(SenderEvent::Retransmit(trace.seq));
+            }
+        }
+        // the packets are too long for the system, send smaller ones
+        if props.is_oversized() {
+            props.shrink_packets(input_file, config)?;
+            continue;
+        }
         // receive response
         let content_result = recv_with_timeout(&socket, &mut buffer, Box::new(config));
         // process errors for receive
         if let Err(_) = content_result {
+            // the shorter waits for the heartbeat count together as a single timeout
+            if config.min_send_interval.is_some() && answered_at.elapsed().as_millis() < config.timeout as u128 {
+                continue;
+            }
+            answered_at = Instant::now();
             attempts += 1;
             config.vlog(&format!("Recv timeout, increased number of attempts to {}", attempts));
+            config.emit(SenderEvent::Timeout);
             continue;
         }
-        // read received content
-        let (recived_len, recived_from) = content_result.unwrap();
-        config.vlog(&format!("Received {}b of data from {}", recived_len, recived_from));
-        let packet = Packet::from_bin(&buffer[..recived_len], props.static_properties.checksum_size as usize);
-        // validate the packet
-        let packet = match packet {
-            Err(ParsingError::ChecksumNotMatch) => {
-                config.vlog("Invalid sum, ignoring");
-                continue;
-            }
-            Err(ParsingError::InvalidFlag(f)) => {
-                config.vlog(&format!("Invalid flag {}, ignoring", f));
-                continue;
-            }
-            Err(ParsingError::InvalidSize(expected, actual)) => {
-                config.vlog(&format!("Expected {}b but received {}b, ignoring", expected, actual));
-                continue;
-            }
-            Ok(packet) => {
-                if packet.header().id != props.static_properties.id {
-                    config.vlog("Wrong connection ID, ignoring");
-                    continue;
+        // process the answer and all the answers that are already waiting
+        answered_at = Instant::now();
+        let mut received = content_result.ok();
+        while let Some((recived_len, recived_from)) = received {
+            config.vlog(&format!("Received {}b of data from {}", recived_len, recived_from));
+            if process_answer(config, &mut buffer, recived_len, recived_from, socket, props)? {
+                config.emit(SenderEvent::WindowAdvanced(props.window_position));
+                attempts = 0;
+                window_moved_at = Instant::now();
+                stuck_reported = false;
+                // the manifest is rewritten only periodically, not on every acknowledge
+                if manifest_at.elapsed().as_millis() >= config.manifest_interval as u128 {
+                    store_manifest(config, props);
+                    manifest_at = Instant::now();
                 }
-                packet
             }
-        };
-        // process the packet
-        match packet {
-            Packet::Init(_) => {
-                config.vlog("Init packet received, but connection already established");
-                continue;
-            }
-            Packet::End(_) => {
-                config.vlog("End packet received, but hasn't been expected");
-                let error_packet = ErrorPacket::new(props.static_properties.id);
-                let answer_length = Packet::from(error_packet).to_bin_buff(&mut buffer, props.static_properties.checksum_size as usize);
-                socket.send_to(&buffer[..answer_length], config.send_addr()).expect("Can't send error packet");
-                return Err(String::from("Unexpected end packet"));
-            }
-            Packet::Error(_) => {
-                config.vlog("Error packet received");
-                println!("Failed because error packet received");
-                return Err(String::from("Error packet received"));
-            }
-            Packet::Data(packet) => {
-                if props.acknowledge(packet.header.ack, &config) {
-                    attempts = 0;
-                }
-            }
-        };
+            received = match config.coalesce_acks {
+                true => recv_available(socket, &mut buffer),
+                false => None,
+            };
+        }
     };
+    socket.set_read_timeout(Some(Duration::from_millis(config.timeout as u64))).expect("Can't set timeout on the socket");
     // validate whether the loop does not end because of the timeout
     if !props.is_complete() {
-        let e = format!("Connection lost after {} attempts or because of termination", attempts);
+        // the progress since the last update of the manifest is not lost
+        store_manifest(config, props);
+        let mut e = format!("Connection lost after {} attempts or because of termination", attempts);
+        if stuck_reported {
+            e = format!("{}; {}", e, props.stuck_diagnostic());
+        }
         config.vlog(&e);
-        return Err(e);
+        return Err(TransferError::Failed(e));
     }
     // other end peacefully
     config.vlog("All data send");
     return Ok(());
 }
 
+/// Whether smaller part of the `bytes` was `acknowledged`, than the part of the `deadline` in milliseconds `elapsed`.
+fn behind_schedule(acknowledged: u64, bytes: u64, elapsed: Duration, deadline: u32) -> bool {
+    return (acknowledged as u128) * (deadline as u128) < (bytes as u128) * elapsed.as_millis();
+}
+
+/// Fail when the `content` is the error packet of the receiver, that doesn't know the connection `props`.
+/// The receiver that doesn't know the connection doesn't know its checksum size either.
+fn receiver_gone(config: &Config, content: &[u8], props: &SenderConnectionProperties) -> Result<(), TransferError> {
+    if content.len() == PacketHeader::bin_size() {
+        if let Ok(Packet::Error(packet)) = Packet::from_bin(content, 0) {
+            if packet.header.id == props.static_properties.id && packet.is_unknown_connection() {
+                config.info(&format!("Receiver doesn't know connection {}", packet.header.id));
+                return Err(TransferError::ReceiverGone(packet.header.id));
+            }
+        }
+    }
+    return Ok(());
+}
+
+/// Whether the packet `received_from` comes from the receiver, that confirms the data when sending to the group.
+/// Without the confirming receiver the packets from any address are processed.
+fn from_confirmer(config: &Config, received_from: SocketAddr) -> bool {
+    return match config.confirmer_addr() {
+        Some(confirmer) if confirmer != received_from => {
+            config.vlog(&format!("Packet is not from the confirming receiver {}, ignoring", confirmer));
+            false
+        }
+        _ => true,
+    };
+}
+
+/// Process answer of `length` bytes in the `buffer` received `from` the receiver in the connection `props`.
+/// Returns whether the window moved.
+fn process_answer(
+    config: &Config,
+    buffer: &mut [u8],
+    length: usize,
+    from: SocketAddr,
+    socket: &UdpSocket,
+    props: &mut SenderConnectionProperties,
+) -> Result<bool, TransferError> {
+    if !from_confirmer(config, from) {
+        return Ok(false);
+    }
+    receiver_gone(config, &buffer[..length], props)?;
+    let congested = PacketHeader::take_congestion(&mut buffer[..length]);
+    // the receiver agrees on the bigger checksum with the init packet, that has its own checksum size
+    let init = InitPacket::from_bin_no_size_and_hash_check(&buffer[..length]).ok()
+        .filter(|init| init.packet_size as usize == length && PacketHeader::bin_size() + 6 + (init.checksum_size as usize) < length);
+    if let Some(init) = init {
+        if let Ok(Packet::Init(packet)) = Packet::from_bin(&buffer[..length], init.checksum_size as usize) {
+            if packet.header.id == props.static_properties.id {
+                // the answer without bigger checksum is the init answer delayed on the way
+                if packet.checksum_size <= props.static_properties.checksum_size {
+                    return stray_init(config, props);
+                }
+                config.vlog(&format!("Receiver agreed on checksum of {}b", packet.checksum_size));
+                props.checksum_agreed(packet.checksum_size);
+                return Ok(false);
+            }
+        }
+    }
+    let packet = Packet::from_bin(&buffer[..length], props.static_properties.checksum_size as usize);
+    // validate the packet
+    let packet = match packet {
+        Err(ParsingError::ChecksumNotMatch) => {
+            config.vlog("Invalid sum, ignoring");
+            config.emit(SenderEvent::ChecksumFailure);
+            if let Some(checksum_size) = props.answer_checked(false, config) {
+                request_checksum(config, socket, props, checksum_size);
+            }
+            return Ok(false);
+        }
+        Err(ParsingError::InvalidFlag(f)) => {
+            config.vlog(&format!("Invalid flag {}, ignoring", f));
+            return Ok(false);
+        }
+        Err(ParsingError::InvalidSize(expected, actual)) => {
+            config.vlog(&format!("Expected {}b but received {}b, ignoring", expected, actual));
+            return Ok(false);
+        }
+        Ok(packet) => {
+            if packet.header().id != props.static_properties.id {
+                config.vlog("Wrong connection ID, ignoring");
+                return Ok(false);
+            }
+            if let Some(checksum_size) = props.answer_checked(true, config) {
+                request_checksum(config, socket, props, checksum_size);
+            }
+            packet
+        }
+    };
+    // the receiver echoes the congestion marked by the network
+    if congested {
+        if let Some(window) = props.congestion_experienced(config) {
+            config.emit(SenderEvent::CongestionReduced(window));
+        }
+    }
+    // process the packet
+    return match packet {
+        Packet::Init(_) => {
+            stray_init(config, props)
+        }
+        Packet::End(_) => {
+            config.vlog("End packet received, but hasn't been expected");
+            let error_packet = ErrorPacket::new(props.static_properties.id);
+            let answer_length = Packet::from(error_packet).to_bin_buff(buffer, props.static_properties.checksum_size as usize);
+            socket.send_to(&buffer[..answer_length], props.static_properties.socket_addr).expect("Can't send error packet");
+            Err(TransferError::Failed(String::from("Unexpected end packet")))
+        }
+        Packet::Error(packet) => {
+            config.vlog("Error packet received");
+            config.info(&format!("Failed because receiver closed the connection, {}", packet.reason()));
+            Err(TransferError::ReceiverClosed(packet.reason()))
+        }
+        Packet::Data(packet) => {
+            Ok(acknowledge(config, props, packet.header.ack))
+        }
+        Packet::Ack(packet) => {
+            Ok(acknowledge(config, props, packet.header.ack))
+        }
+        Packet::Gap(packet) => {
+            let moved = acknowledge(config, props, packet.header.ack);
+            props.request_resend(&packet.ranges, config);
+            Ok(moved)
+        }
+        Packet::Metadata(_) => {
+            config.vlog("Repeated confirmation of the metadata, ignoring");
+            Ok(false)
+        }
+    };
+}
+
+/// Register the `ack` of the receiver in the connection `props`, the repeated acknowledges may send the part again.
+/// Returns whether the window moved.
+fn acknowledge(config: &Config, props: &mut SenderConnectionProperties, ack: u16) -> bool {
+    let moved = props.acknowledge(ack, config);
+    if !moved {
+        if let Some(seq) = props.duplicate_ack(ack, config) {
+            config.emit(SenderEvent::FastRetransmit(seq));
+        }
+    }
+    return moved;
+}
+
+/// Ignore the init packet received in the established connection `props`.
+/// Fails when more of them came, than the `max_stray_inits` in the `config` tolerates, as the sender and receiver are out of sync.
+fn stray_init(config: &Config, props: &mut SenderConnectionProperties) -> Result<bool, TransferError> {
+    let count = props.stray_init();
+    config.vlog("Init packet received, but connection already established, ignoring");
+    if let Some(max_stray_inits) = config.max_stray_inits {
+        if count > max_stray_inits {
+            let reason = format!("Received {} init packets after the connection was established", count);
+            config.info(&reason);
+            return Err(TransferError::Failed(reason));
+        }
+    }
+    return Ok(false);
+}
+
+/// Ask the receiver to use the checksum of `checksum_size` bytes in the connection `props`.
+/// The init packet with the identifier of the connection carries the request.
+fn request_checksum(config: &Config, socket: &UdpSocket, props: &SenderConnectionProperties, checksum_size: u16) {
+    let static_properties = &props.static_properties;
+    let mut request = InitPacket::new(static_properties.window_size, static_properties.packet_size, checksum_size);
+    request.header.id = static_properties.id;
+    let mut buffer = vec![0; static_properties.packet_size as usize];
+    let size = Packet::from(request).to_bin_buff(&mut buffer, checksum_size as usize);
+    socket.send_to(&buffer[..size], static_properties.socket_addr).expect("Can't send checksum request");
+    config.vlog(&format!("Connection {} asked for checksum of {}b", static_properties.id, checksum_size));
+}
+
+/// Cancel the connection `props` on the receiver and wait until it confirms it deleted the partial file.
+/// The abort is repeated after every timeout until the `abort_timeout` in the `config` passes.
+fn abort(config: &Config, socket: &UdpSocket, props: &SenderConnectionProperties) -> TransferError {
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let checksum_size = props.static_properties.checksum_size as usize;
+    let abort_packet = Packet::from(ErrorPacket::abort(props.static_properties.id));
+    let deadline = Instant::now() + Duration::from_millis(config.abort_timeout as u64);
+    while Instant::now() < deadline {
+        let size = abort_packet.to_bin_buff(&mut buffer, checksum_size);
+        socket.send_to(&buffer[..size], props.static_properties.socket_addr).expect("Can't send abort packet");
+        config.vlog("Abort packet send");
+        let size = match recv_with_timeout(socket, &mut buffer, Box::new(config)) {
+            Ok((size, from)) if from_confirmer(config, from) => size,
+            _ => continue,
+        };
+        // the receiver, that doesn't know the connection anymore, confirms without checksum
+        let confirmation = Packet::from_bin(&buffer[..size], checksum_size)
+            .or_else(|_| Packet::from_bin(&buffer[..size], 0));
+        if let Ok(Packet::Error(packet)) = confirmation {
+            if packet.header.id == props.static_properties.id && packet.is_abort() {
+                config.info("Transfer cancelled, the receiver deleted the partial file");
+                return TransferError::Cancelled(true);
+            }
+        }
+    }
+    config.info("Transfer cancelled, the receiver didn't confirm the cleanup");
+    return TransferError::Cancelled(false);
+}
+
+/// Send the `content` to the `addr` as many times as the redundancy in the `config` specifies.
+/// The receiver answers each copy, the copies after the first answer are ignored.
+fn send_copies(config: &Config, socket: &UdpSocket, content: &[u8], addr: SocketAddr) -> std::io::Result<usize> {
+    for _ in 1..config.redundancy {
+        socket.send_to(content, addr)?;
+    }
+    return socket.send_to(content, addr);
+}
+
+/// Describe the content from the current position of the `input_file` to its end,
+/// with the permissions and modification time of the file in the `config`.
+fn file_metadata(config: &Config, input_file: &mut InputFiles, connection_id: u32) -> io::Result<MetadataPacket> {
+    let position = input_file.stream_position()?;
+    let crc = input_file.digest_from(position)?;
+    input_file.seek(SeekFrom::Start(position))?;
+    let mut packet = MetadataPacket::new(connection_id, input_file.size()? - position, crc);
+    let metadata = fs::metadata(&config.file)?;
+    let modified = metadata.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default();
+    packet.mode = file_mode(&metadata);
+    packet.mtime_secs = modified.as_secs();
+    packet.mtime_nanos = modified.subsec_nanos();
+    packet.path = config.file.clone();
+    return Ok(packet);
+}
+
+/// Permissions of the file with the `metadata`.
+#[cfg(unix)]
+fn file_mode(metadata: &Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    return metadata.permissions().mode() & 0o7777;
+}
+
+/// Permissions of the file with the `metadata`, they are not known on this platform.
+#[cfg(not(unix))]
+fn file_mode(_metadata: &Metadata) -> u32 {
+    return 0;
+}
+
+/// Send the metadata of the `input_file` right after the handshake, so the receiver applies them to the output file.
+/// The receiver confirms them by sending the same packet back.
+fn send_metadata(
+    config: &Config,
+    socket: &UdpSocket,
+    input_file: &mut InputFiles,
+    props: &SenderConnectionProperties,
+    brk: Arc<AtomicBool>,
+) -> Result<(), TransferError> {
+    let id = props.static_properties.id;
+    let checksum_size = props.static_properties.checksum_size as usize;
+    let mut metadata = file_metadata(config, input_file, id).map_err(|e| TransferError::Io(config.file.clone(), e.kind()))?;
+    let mut buffer = vec![0; BUFFER_SIZE];
+    if !metadata.fit_into((props.static_properties.packet_size as usize).saturating_sub(checksum_size)) {
+        // the receiver finds the file to continue from the offset only by the name in the metadata
+        if config.start_offset > 0 {
+            let length = Packet::from(ErrorPacket::new(id)).to_bin_buff(&mut buffer, checksum_size);
+            socket.send_to(&buffer[..length], props.static_properties.socket_addr).expect("Can't send error packet");
+            let e = format!("Negotiated packet size is too small for the metadata naming the file to continue from offset {}", config.start_offset);
+            config.info(&e);
+            return Err(TransferError::Failed(e));
+        }
+        config.info("Negotiated packet size is too small for the metadata, the file is send without them");
+        return Ok(());
+    }
+    let packet = Packet::from(metadata);
+    let mut attempts = 0;
+    while attempts < config.repetition && !brk.load(Ordering::SeqCst) {
+        let size = packet.to_bin_buff(&mut buffer, checksum_size);
+        config.before_send(&mut buffer[..size]);
+        send_copies(config, socket, &buffer[..size], props.static_properties.socket_addr).expect("Can't send metadata packet");
+        config.vlog("Send metadata packet");
+        let recv_size = match recv_with_timeout(socket, &mut buffer, Box::new(config)) {
+            Ok((recv_size, from)) if from_confirmer(config, from) => recv_size,
+            Ok(_) => continue,
+            Err(_) => {
+                attempts += 1;
+                config.emit(SenderEvent::Timeout);
+                continue;
+            }
+        };
+        match Packet::from_bin(&buffer[..recv_size], checksum_size) {
+            Ok(Packet::Metadata(answer)) if answer.header.id == id => {
+                config.vlog(&format!("Metadata of {} confirmed", config.file));
+                return Ok(());
+            }
+            Ok(Packet::Error(answer)) if answer.header.id == id => {
+                config.info(&format!("Failed because receiver closed the connection, {}", answer.reason()));
+                return Err(TransferError::ReceiverClosed(answer.reason()));
+            }
+            Err(ParsingError::ChecksumNotMatch) => {
+                config.emit(SenderEvent::ChecksumFailure);
+            }
+            _ => {
+                config.vlog("Received other packet than the confirmation of the metadata, ignoring");
+            }
+        };
+    }
+    return Err(TransferError::Failed(String::from("Metadata packet timeout or termination")));
+}
+
 /// Ends the connection after the file has been received.
 /// It sends data using `socket` and closes connection specified by `props`.
+/// The end packet carries the `digest` of the send content.
+/// Returns whether the receiver answered with the same digest of the content and that it stored the content.
 fn send_end(
     config: &Config,
     socket: &UdpSocket,
     props: &mut SenderConnectionProperties,
+    digest: u32,
     brk: Arc<AtomicBool>,
-) -> Result<(), String> {
+) -> Result<bool, TransferError> {
     // creates variables
     let mut buffer = vec![0; BUFFER_SIZE];
-    let packet = Packet::from(EndPacket::new(
-        props.static_properties.id,
-        props.window_position,
-    ));
+    let mut end_packet = EndPacket::new(props.static_properties.id, props.window_position);
+    end_packet.digest = Some(digest);
+    let packet = Packet::from(end_packet);
     // wait for end packet
     let mut attempts = 0;
     while attempts < config.repetition && !brk.load(Ordering::SeqCst) {
         // send end packet
         let size = packet.to_bin_buff(&mut buffer, props.static_properties.checksum_size as usize);
-        socket.send_to(&buffer[..size], props.static_properties.socket_addr).expect("Can't send end packet");
+        config.before_send(&mut buffer[..size]);
+        send_copies(config, socket, &buffer[..size], props.static_properties.socket_addr).expect("Can't send end packet");
         config.vlog("Send end packet");
         // receive response
         let recv_result = recv_with_timeout(&socket, &mut buffer, Box::new(config));
         if let Err(_) = recv_result {
             attempts += 1;
+            config.emit(SenderEvent::Timeout);
+            continue;
+        }
+        let (recv_size, received_from) = recv_result.unwrap();
+        if !from_confirmer(config, received_from) {
             continue;
         }
-        let (recv_size, _) = recv_result.unwrap();
+        // the receiver restarted before it confirmed the end
+        receiver_gone(config, &buffer[..recv_size], props)?;
         // parse packet
         let packet = Packet::from_bin(&buffer[..recv_size], props.static_properties.checksum_size as usize);
         if let Err(e) = packet {
             config.vlog(&format!("Error parsing end packet {:?}", e));
+            if e == ParsingError::ChecksumNotMatch {
+                config.emit(SenderEvent::ChecksumFailure);
+            }
             continue;
         }
         let packet = packet.unwrap();
@@ -278,7 +1133,7 @@ fn send_end(
             if Flag::Init == packet.header().flag {
                 continue; // init flag delay on the way with not established connection
             }
-            return Err(String::from("Received packet with invalid connection number"));
+            return Err(TransferError::Failed(String::from("Received packet with invalid connection number")));
         }
         // handle end packet
         match packet {
@@ -289,17 +1144,26 @@ fn send_end(
                     config.vlog("Received invalid end packet");
                     let error_packet = ErrorPacket::new(props.static_properties.id);
                     let answer_length = Packet::from(error_packet).to_bin_buff(&mut buffer, props.static_properties.checksum_size as usize);
-                    socket.send_to(&buffer[..answer_length], config.send_addr()).expect("Can't send error packet");
-                    return Err(String::from("Invalid end packet"));
+                    socket.send_to(&buffer[..answer_length], props.static_properties.socket_addr).expect("Can't send error packet");
+                    return Err(TransferError::Failed(String::from("Invalid end packet")));
                 }
-                // else end peacefully
-                println!("File receive confirmed");
-                return Ok(());
+                // without the checksum only the digest proves the receiver stored the same content
+                if config.fast_verify && packet.digest != Some(digest) {
+                    config.info("Receiver didn't confirm the digest of the file");
+                    let error_packet = ErrorPacket::new(props.static_properties.id);
+                    let answer_length = Packet::from(error_packet).to_bin_buff(&mut buffer, props.static_properties.checksum_size as usize);
+                    socket.send_to(&buffer[..answer_length], props.static_properties.socket_addr).expect("Can't send error packet");
+                    return Err(TransferError::Failed(String::from("Received content doesn't match the digest")));
+                }
+                // else end peacefully, the digest of the answer confirms the receiver stored the same content,
+                // unless the receiver only validated it without storing
+                config.info("File receive confirmed");
+                return Ok(packet.digest == Some(digest) && packet.stored);
             }
             // error on the receiver part, ending
-            Packet::Error(_) => {
+            Packet::Error(packet) => {
                 config.vlog("Received error packet instead of end packet");
-                return Err(String::from("Error packet received"));
+                return Err(TransferError::Failed(format!("Error packet received, {}", packet.reason())));
             }
             // data or init packet delayed on the way, ignoring
             _ => {
@@ -308,6 +1172,524 @@ fn send_end(
             }
         };
     }
-    return Err(String::from("End packet timeout or terminatioln"));
+    return Err(TransferError::Failed(String::from("End packet timeout or terminatioln")));
 }
 
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{File, remove_file};
+    use std::io::Write;
+    use std::net::UdpSocket;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use crate::packet::{DataPacket, EndPacket, ErrorPacket, Flag, InitPacket, Packet, PacketHeader};
+    use std::io::ErrorKind;
+    use crate::connection_properties::TransferError;
+    use crate::sender::config::Config;
+    use crate::sender::control::Control;
+    use crate::sender::manifest::Manifest;
+    use super::{behind_schedule, create_connection, sender};
+
+    #[test]
+    fn progress_is_compared_with_deadline() {
+        assert!(!behind_schedule(0, 1000, Duration::from_millis(0), 2000));
+        assert!(behind_schedule(400, 1000, Duration::from_millis(1000), 2000));
+        assert!(!behind_schedule(500, 1000, Duration::from_millis(1000), 2000));
+        assert!(behind_schedule(999, 1000, Duration::from_millis(2500), 2000));
+    }
+
+    #[test]
+    fn missing_file_is_reported() {
+        let config = Config {
+            bind_addr: String::from("127.0.0.1:3161"),
+            file: String::from("missing_source.txt"),
+            quiet: true,
+            ..Config::new()
+        };
+        let result = sender(config, Control::new());
+        assert_eq!(result.unwrap_err(), TransferError::Io(String::from("missing_source.txt"), ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn stuck_window_is_diagnosed() {
+        const SOURCE_FILE: &str = "stuck_source.txt";
+        const RECEIVER_ADDR: &str = "127.0.0.1:3157";
+        const DROPPED_SEQ: u16 = 3;
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 1000]).unwrap();
+        // receiver that never gets the part with DROPPED_SEQ
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver_brk = brk.clone();
+        let receiver = thread::spawn(move || {
+            let socket = UdpSocket::bind(RECEIVER_ADDR).unwrap();
+            socket.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+            let mut buffer = vec![0; 2000];
+            let mut next_seq = 0;
+            while !receiver_brk.load(Ordering::SeqCst) {
+                let (size, from) = match socket.recv_from(&mut buffer) {
+                    Ok(x) => x,
+                    Err(_) => continue,
+                };
+                let answer = match Packet::from_bin(&buffer[..size], 0).unwrap() {
+                    Packet::Init(_) => {
+                        let mut answer = InitPacket::new(4, 100, 0);
+                        answer.header.id = 5;
+                        Packet::from(answer)
+                    }
+                    Packet::Data(p) => {
+                        if p.header.seq == next_seq && p.header.seq != DROPPED_SEQ {
+                            next_seq += 1;
+                        }
+                        Packet::from(DataPacket::new_receiver(5, p.header.seq, next_seq.wrapping_sub(1)))
+                    }
+                    _ => continue,
+                };
+                let size = answer.to_bin_buff(&mut buffer, 0);
+                socket.send_to(&buffer[..size], from).unwrap();
+            }
+        });
+
+        let mut config = Config::new();
+        config.bind_addr = String::from("127.0.0.1:3158");
+        config.send_addr = String::from(RECEIVER_ADDR);
+        config.file = String::from(SOURCE_FILE);
+        config.packet_size = 100;
+        config.window_size = 4;
+        config.checksum_size = 0;
+        config.timeout = 50;
+        config.repetition = 10;
+        config.stuck_timeouts = 3;
+        config.stuck_abort = Some(10);
+        config.quiet = true;
+        let result = sender(config, Control::new());
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap();
+        remove_file(SOURCE_FILE).unwrap();
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("stuck at position 3"), "{}", error);
+        assert!(error.contains("waiting parts: 3 "), "{}", error);
+    }
+
+    #[test]
+    fn restarted_receiver_fails_end_fast() {
+        const SOURCE_FILE: &str = "restarted_end_source.txt";
+        const RECEIVER_ADDR: &str = "127.0.0.1:3186";
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 1000]).unwrap();
+        // receiver that forgets the connection before the end packet comes
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver_brk = brk.clone();
+        let receiver = thread::spawn(move || {
+            let socket = UdpSocket::bind(RECEIVER_ADDR).unwrap();
+            socket.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+            let mut buffer = vec![0; 2000];
+            let mut next_seq = 0;
+            while !receiver_brk.load(Ordering::SeqCst) {
+                let (size, from) = match socket.recv_from(&mut buffer) {
+                    Ok(x) => x,
+                    Err(_) => continue,
+                };
+                let answer = match Packet::from_bin(&buffer[..size], 0).unwrap() {
+                    Packet::Init(_) => {
+                        let mut answer = InitPacket::new(4, 100, 0);
+                        answer.header.id = 5;
+                        Packet::from(answer)
+                    }
+                    Packet::Data(p) => {
+                        if p.header.seq == next_seq {
+                            next_seq += 1;
+                        }
+                        Packet::from(DataPacket::new_receiver(5, p.header.seq, next_seq.wrapping_sub(1)))
+                    }
+                    Packet::End(_) => Packet::from(ErrorPacket::unknown_connection(5)),
+                    _ => continue,
+                };
+                let size = answer.to_bin_buff(&mut buffer, 0);
+                socket.send_to(&buffer[..size], from).unwrap();
+            }
+        });
+
+        let mut config = Config::new();
+        config.bind_addr = String::from("127.0.0.1:3187");
+        config.send_addr = String::from(RECEIVER_ADDR);
+        config.file = String::from(SOURCE_FILE);
+        config.packet_size = 100;
+        config.window_size = 4;
+        config.checksum_size = 0;
+        config.timeout = 200;
+        config.repetition = 10;
+        config.quiet = true;
+        let started_at = Instant::now();
+        let result = sender(config, Control::new());
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap();
+        remove_file(SOURCE_FILE).unwrap();
+        // the end packet isn't repeated until all the attempts time out
+        assert_eq!(result.unwrap_err(), TransferError::ReceiverGone(5));
+        assert!(started_at.elapsed() < Duration::from_millis(1000), "{:?}", started_at.elapsed());
+    }
+
+    #[test]
+    fn offset_without_metadata_fails() {
+        const SOURCE_FILE: &str = "offset_metadata_source.txt";
+        const RECEIVER_ADDR: &str = "127.0.0.1:3191";
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 1000]).unwrap();
+        // receiver that agrees on packets too small for the metadata
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver_brk = brk.clone();
+        let receiver = thread::spawn(move || {
+            let socket = UdpSocket::bind(RECEIVER_ADDR).unwrap();
+            socket.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+            let mut buffer = vec![0; 2000];
+            loop {
+                let (size, from) = match socket.recv_from(&mut buffer) {
+                    Ok(x) => x,
+                    Err(_) if receiver_brk.load(Ordering::SeqCst) => return None,
+                    Err(_) => continue,
+                };
+                match Packet::from_bin(&buffer[..size], 0).unwrap() {
+                    Packet::Init(_) => {
+                        let mut answer = InitPacket::new(4, 30, 0);
+                        answer.header.id = 5;
+                        answer.offset = 100;
+                        let size = Packet::from(answer).to_bin_buff(&mut buffer, 0);
+                        socket.send_to(&buffer[..size], from).unwrap();
+                    }
+                    packet => return Some(packet.header().flag.clone()),
+                };
+            }
+        });
+
+        let mut config = Config::new();
+        config.bind_addr = String::from("127.0.0.1:3192");
+        config.send_addr = String::from(RECEIVER_ADDR);
+        config.file = String::from(SOURCE_FILE);
+        config.packet_size = 30;
+        config.window_size = 4;
+        config.checksum_size = 0;
+        config.start_offset = 100;
+        config.timeout = 200;
+        config.quiet = true;
+        let result = sender(config, Control::new());
+
+        brk.store(true, Ordering::SeqCst);
+        let received = receiver.join().unwrap();
+        remove_file(SOURCE_FILE).unwrap();
+        // the receiver wouldn't know which file to continue, so the connection is closed before any data
+        assert!(matches!(&result, Err(TransferError::Failed(e)) if e.contains("too small for the metadata")), "{:?}", result);
+        assert_eq!(received, Some(Flag::Error));
+    }
+
+    #[test]
+    fn manifest_is_updated_periodically() {
+        const SOURCE_FILE: &str = "periodic_manifest_source.txt";
+        const MANIFEST_FILE: &str = "periodic_manifest";
+        const RECEIVER_ADDR: &str = "127.0.0.1:3188";
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 10000]).unwrap();
+        // receiver that acknowledges slowly every part in order
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver_brk = brk.clone();
+        let receiver = thread::spawn(move || {
+            let socket = UdpSocket::bind(RECEIVER_ADDR).unwrap();
+            socket.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+            let mut buffer = vec![0; 2000];
+            let mut next_seq = 0;
+            while !receiver_brk.load(Ordering::SeqCst) {
+                let (size, from) = match socket.recv_from(&mut buffer) {
+                    Ok(x) => x,
+                    Err(_) => continue,
+                };
+                let answer = match Packet::from_bin(&buffer[..size], 0).unwrap() {
+                    Packet::Init(_) => {
+                        let mut answer = InitPacket::new(4, 100, 0);
+                        answer.header.id = 5;
+                        Packet::from(answer)
+                    }
+                    Packet::Data(p) => {
+                        thread::sleep(Duration::from_millis(10));
+                        if p.header.seq == next_seq {
+                            next_seq += 1;
+                        }
+                        Packet::from(DataPacket::new_receiver(5, p.header.seq, next_seq.wrapping_sub(1)))
+                    }
+                    _ => continue,
+                };
+                let size = answer.to_bin_buff(&mut buffer, 0);
+                socket.send_to(&buffer[..size], from).unwrap();
+            }
+        });
+
+        let mut config = Config::new();
+        config.bind_addr = String::from("127.0.0.1:3189");
+        config.send_addr = String::from(RECEIVER_ADDR);
+        config.file = String::from(SOURCE_FILE);
+        config.packet_size = 100;
+        config.window_size = 4;
+        config.checksum_size = 0;
+        config.manifest_path = Some(String::from(MANIFEST_FILE));
+        config.manifest_interval = 60_000;
+        config.quiet = true;
+        let control = Control::new();
+        let sender_control = control.clone();
+        let st = thread::spawn(move || sender(config, sender_control));
+
+        // the acknowledged parts don't rewrite the manifest stored after the handshake
+        thread::sleep(Duration::from_millis(400));
+        assert_eq!(Manifest::load(MANIFEST_FILE).unwrap().position, 0);
+        // the progress is stored when the transfer is terminated
+        control.cancel("test");
+        assert!(st.join().unwrap().is_err());
+        assert!(Manifest::load(MANIFEST_FILE).unwrap().position > 0);
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap();
+        remove_file(SOURCE_FILE).unwrap();
+        remove_file(MANIFEST_FILE).unwrap();
+    }
+
+    #[test]
+    fn stray_inits_are_bounded() {
+        const SOURCE_FILE: &str = "stray_inits_source.txt";
+        const RECEIVER_ADDR: &str = "127.0.0.1:3179";
+        const STRAY_INITS: usize = 3;
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 1000]).unwrap();
+        // receiver that repeats its init answer before every acknowledge
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver_brk = brk.clone();
+        let receiver = thread::spawn(move || {
+            let socket = UdpSocket::bind(RECEIVER_ADDR).unwrap();
+            socket.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+            let mut buffer = vec![0; 2000];
+            let mut init_answer = InitPacket::new(4, 100, 0);
+            init_answer.header.id = 5;
+            let mut next_seq = 0;
+            while !receiver_brk.load(Ordering::SeqCst) {
+                let (size, from) = match socket.recv_from(&mut buffer) {
+                    Ok(x) => x,
+                    Err(_) => continue,
+                };
+                let answer = match Packet::from_bin(&buffer[..size], 0).unwrap() {
+                    Packet::Init(_) => {
+                        next_seq = 0;
+                        Packet::from(init_answer.clone())
+                    }
+                    Packet::Data(p) => {
+                        for _ in 0..STRAY_INITS {
+                            let size = Packet::from(init_answer.clone()).to_bin_buff(&mut buffer, 0);
+                            socket.send_to(&buffer[..size], from).unwrap();
+                        }
+                        if p.header.seq == next_seq {
+                            next_seq += 1;
+                        }
+                        Packet::from(DataPacket::new_receiver(5, p.header.seq, next_seq.wrapping_sub(1)))
+                    }
+                    Packet::End(p) => Packet::from(EndPacket::new(5, p.header.seq)),
+                    _ => continue,
+                };
+                let size = answer.to_bin_buff(&mut buffer, 0);
+                socket.send_to(&buffer[..size], from).unwrap();
+            }
+        });
+
+        let send = |max_stray_inits: Option<u32>| {
+            let mut config = Config::new();
+            config.bind_addr = String::from("127.0.0.1:3180");
+            config.send_addr = String::from(RECEIVER_ADDR);
+            config.file = String::from(SOURCE_FILE);
+            config.packet_size = 100;
+            config.window_size = 4;
+            config.checksum_size = 0;
+            config.timeout = 100;
+            config.repetition = 10;
+            config.max_stray_inits = max_stray_inits;
+            config.quiet = true;
+            sender(config, Control::new())
+        };
+        // the stray init packets are ignored, unless there are too many of them
+        let tolerated = send(None);
+        let bounded = send(Some(5));
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap();
+        remove_file(SOURCE_FILE).unwrap();
+        assert!(tolerated.is_ok(), "{:?}", tolerated);
+        let error = bounded.unwrap_err().to_string();
+        assert!(error.contains("Received 6 init packets"), "{}", error);
+    }
+
+    #[test]
+    fn redundant_init_avoids_timeouts() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3166";
+        const LOSS: u32 = 4;
+        // receiver that gets only every fourth init packet
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver_brk = brk.clone();
+        let receiver = thread::spawn(move || {
+            let socket = UdpSocket::bind(RECEIVER_ADDR).unwrap();
+            socket.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+            let mut buffer = vec![0; 2000];
+            let mut received = 0;
+            while !receiver_brk.load(Ordering::SeqCst) {
+                let (_, from) = match socket.recv_from(&mut buffer) {
+                    Ok(x) => x,
+                    Err(_) => continue,
+                };
+                received += 1;
+                if received % LOSS != 0 {
+                    continue;
+                }
+                let mut answer = InitPacket::new(4, 100, 0);
+                answer.header.id = received;
+                let size = Packet::from(answer).to_bin_buff(&mut buffer, 0);
+                socket.send_to(&buffer[..size], from).unwrap();
+            }
+        });
+
+        let connect = |bind_addr: &str, redundancy: u16| {
+            let mut config = Config::new();
+            config.bind_addr = String::from(bind_addr);
+            config.send_addr = String::from(RECEIVER_ADDR);
+            config.packet_size = 100;
+            config.window_size = 4;
+            config.checksum_size = 0;
+            config.timeout = 200;
+            config.redundancy = redundancy;
+            config.quiet = true;
+            let socket = UdpSocket::bind(config.bind_addr()).unwrap();
+            socket.set_read_timeout(Some(Duration::from_millis(config.timeout as u64))).unwrap();
+            let start = Instant::now();
+            create_connection(&config, &socket, config.send_addr(), config.packet_size, None, Arc::new(AtomicBool::new(false))).unwrap();
+            start.elapsed()
+        };
+        // single init packet waits for three timeouts, the burst gets through at once
+        let single = connect("127.0.0.1:3167", 1);
+        let burst = connect("127.0.0.1:3167", LOSS as u16);
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap();
+        assert!(single >= Duration::from_millis(600), "{:?}", single);
+        assert!(burst < Duration::from_millis(200), "{:?}", burst);
+    }
+
+    #[test]
+    fn establishment_is_cancelled_promptly() {
+        // receiver that never answers
+        let receiver = UdpSocket::bind("127.0.0.1:3181").unwrap();
+
+        let mut config = Config::new();
+        config.bind_addr = String::from("127.0.0.1:3182");
+        config.send_addr = String::from("127.0.0.1:3181");
+        config.packet_size = 100;
+        config.window_size = 4;
+        config.checksum_size = 0;
+        config.timeout = 5000;
+        config.quiet = true;
+        let socket = UdpSocket::bind(config.bind_addr()).unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(config.timeout as u64))).unwrap();
+        let brk = Arc::new(AtomicBool::new(false));
+        let canceller_brk = brk.clone();
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            canceller_brk.store(true, Ordering::SeqCst);
+        });
+        let start = Instant::now();
+        let result = create_connection(&config, &socket, config.send_addr(), config.packet_size, None, brk);
+        let elapsed = start.elapsed();
+
+        canceller.join().unwrap();
+        drop(receiver);
+        assert!(result.is_err());
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn window_holds_bandwidth_delay_product() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3183";
+        const MAX_WINDOW: u16 = 50;
+        // receiver that accepts the requested packets, but at most MAX_WINDOW of them in flight
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver_brk = brk.clone();
+        let receiver = thread::spawn(move || {
+            let socket = UdpSocket::bind(RECEIVER_ADDR).unwrap();
+            socket.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+            let mut buffer = vec![0; 2000];
+            while !receiver_brk.load(Ordering::SeqCst) {
+                let (size, from) = match socket.recv_from(&mut buffer) {
+                    Ok(x) => x,
+                    Err(_) => continue,
+                };
+                let request = match Packet::from_bin(&buffer[..size], 0) {
+                    Ok(Packet::Init(request)) => request,
+                    _ => continue,
+                };
+                let mut answer = InitPacket::new(request.window_size.min(MAX_WINDOW), request.packet_size, 0);
+                answer.header.id = 7;
+                let size = Packet::from(answer).to_bin_buff(&mut buffer, 0);
+                socket.send_to(&buffer[..size], from).unwrap();
+            }
+        });
+
+        let connect = |bandwidth: u64, rtt: u32| {
+            let mut config = Config::new();
+            config.bind_addr = String::from("127.0.0.1:3184");
+            config.send_addr = String::from(RECEIVER_ADDR);
+            config.packet_size = 100;
+            config.window_size = 4;
+            config.checksum_size = 0;
+            config.timeout = 200;
+            config.bandwidth = Some(bandwidth);
+            config.expected_rtt = Some(rtt);
+            config.quiet = true;
+            let socket = UdpSocket::bind(config.bind_addr()).unwrap();
+            socket.set_read_timeout(Some(Duration::from_millis(config.timeout as u64))).unwrap();
+            let props = create_connection(&config, &socket, config.send_addr(), config.packet_size, None, Arc::new(AtomicBool::new(false))).unwrap();
+            props.static_properties.window_size
+        };
+        // 2000b in flight are split into the parts of 91b, the window of 220 parts is clamped by the receiver
+        let payload_size = 100 - PacketHeader::bin_size() as u64;
+        let small = connect(100_000, 20);
+        let large = connect(100_000, 200);
+
+        brk.store(true, Ordering::SeqCst);
+        receiver.join().unwrap();
+        assert_eq!(small as u64, (100_000 * 20 / 1000 + payload_size - 1) / payload_size);
+        assert_eq!(small, 22);
+        assert_eq!(large, MAX_WINDOW);
+    }
+
+    #[test]
+    fn oversized_answer_is_ignored() {
+        const RECEIVER_ADDR: &str = "127.0.0.1:3172";
+        // receiver that first advertises bigger packets than requested
+        let receiver = thread::spawn(move || {
+            let socket = UdpSocket::bind(RECEIVER_ADDR).unwrap();
+            let mut buffer = vec![0; 10000];
+            for (id, packet_size) in [(1, 9000), (2, 80)] {
+                let (_, from) = socket.recv_from(&mut buffer).unwrap();
+                let mut answer = InitPacket::new(4, packet_size, 0);
+                answer.header.id = id;
+                let size = Packet::from(answer).to_bin_buff(&mut buffer, 0);
+                socket.send_to(&buffer[..size], from).unwrap();
+            }
+        });
+
+        let mut config = Config::new();
+        config.bind_addr = String::from("127.0.0.1:3173");
+        config.send_addr = String::from(RECEIVER_ADDR);
+        config.packet_size = 100;
+        config.window_size = 4;
+        config.checksum_size = 0;
+        config.timeout = 100;
+        config.quiet = true;
+        let socket = UdpSocket::bind(config.bind_addr()).unwrap();
+        socket.set_read_timeout(Some(Duration::from_millis(config.timeout as u64))).unwrap();
+        let props = create_connection(&config, &socket, config.send_addr(), config.packet_size, None, Arc::new(AtomicBool::new(false))).unwrap();
+
+        receiver.join().unwrap();
+        assert_eq!(props.static_properties.id, 2);
+        assert_eq!(props.static_properties.packet_size, 80);
+    }
+}
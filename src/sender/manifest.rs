@@ -0,0 +1,142 @@
+use std::fs::{read_to_string, rename, write};
+use std::io;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use crate::connection_properties::ConnectionProperties;
+use crate::packet::crc32;
+use super::sender_connection_properties::SenderConnectionProperties;
+
+/// State of the transfer persisted on the disk, so the sender can resume it after restart.
+#[derive(Debug, PartialEq)]
+pub struct Manifest {
+    /// Path of the send file.
+    pub file: String,
+    /// Connection identifier.
+    pub id: u32,
+    pub checksum_size: u16,
+    pub window_size: u16,
+    pub packet_size: u16,
+    /// Address of the receiver.
+    pub socket_addr: SocketAddr,
+    /// Sequence number of the first not acknowledged part.
+    pub position: u16,
+    /// Number of bytes of the file the receiver acknowledged.
+    pub offset: u64,
+}
+
+impl Manifest {
+    pub fn from_properties(file: &str, props: &SenderConnectionProperties) -> Self {
+        return Manifest {
+            file: String::from(file),
+            id: props.static_properties.id,
+            checksum_size: props.static_properties.checksum_size,
+            window_size: props.static_properties.window_size,
+            packet_size: props.static_properties.packet_size,
+            socket_addr: props.static_properties.socket_addr,
+            position: props.window_position,
+            offset: props.acknowledged_bytes,
+        };
+    }
+
+    /// Create properties of the connection that continue at the stored position.
+    pub fn to_properties(&self) -> SenderConnectionProperties {
+        let props = ConnectionProperties::new(
+            self.id,
+            self.checksum_size,
+            self.window_size,
+            self.packet_size,
+            self.socket_addr,
+        );
+        return SenderConnectionProperties::restore(props, self.position, self.offset);
+    }
+
+    /// Store the manifest into the `path`.
+    /// The content is written into temporary file first, so the manifest is never half written.
+    pub fn store(&self, path: &str) -> io::Result<()> {
+        let content = format!(
+            "file={}\nid={}\nchecksum_size={}\nwindow_size={}\npacket_size={}\naddr={}\nposition={}\noffset={}\n",
+            self.file, self.id, self.checksum_size, self.window_size, self.packet_size, self.socket_addr, self.position, self.offset
+        );
+        let content = format!("{}crc={:08x}\n", content, crc32(content.as_bytes()));
+        let temp_path = format!("{}.tmp", path);
+        write(&temp_path, content)?;
+        return rename(&temp_path, path);
+    }
+
+    /// Load the manifest from the `path`.
+    /// Returns `None` if the file doesn't exist, can't be parsed, or its checksum doesn't match.
+    pub fn load(path: &str) -> Option<Self> {
+        let content = read_to_string(path).ok()?;
+        let crc_start = content.rfind("crc=")?;
+        let (data, crc_line) = content.split_at(crc_start);
+        let crc = u32::from_str_radix(crc_line["crc=".len()..].trim(), 16).ok()?;
+        if crc != crc32(data.as_bytes()) {
+            return None;
+        }
+        let value = |key: &str| {
+            data.lines()
+                .find(|line| line.starts_with(&format!("{}=", key)))
+                .map(|line| &line[key.len() + 1..])
+        };
+        return Some(Manifest {
+            file: String::from(value("file")?),
+            id: value("id")?.parse().ok()?,
+            checksum_size: value("checksum_size")?.parse().ok()?,
+            window_size: value("window_size")?.parse().ok()?,
+            packet_size: value("packet_size")?.parse().ok()?,
+            socket_addr: SocketAddr::from_str(value("addr")?).ok()?,
+            position: value("position")?.parse().ok()?,
+            offset: value("offset")?.parse().ok()?,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{read_to_string, remove_file, write};
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+    use crate::packet::crc32;
+    use super::Manifest;
+
+    fn manifest() -> Manifest {
+        Manifest {
+            file: String::from("input.txt"),
+            id: 42,
+            checksum_size: 16,
+            window_size: 15,
+            packet_size: 1500,
+            socket_addr: SocketAddr::from_str("127.0.0.1:3003").unwrap(),
+            position: 7,
+            offset: 7 * 1475,
+        }
+    }
+
+    #[test]
+    fn crc_of_known_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+    }
+
+    #[test]
+    fn store_and_load() {
+        const PATH: &str = "manifest_roundtrip";
+        manifest().store(PATH).unwrap();
+        assert_eq!(Manifest::load(PATH), Some(manifest()));
+        remove_file(PATH).unwrap();
+    }
+
+    #[test]
+    fn corrupted_manifest_is_ignored() {
+        const PATH: &str = "manifest_corrupted";
+        manifest().store(PATH).unwrap();
+        let content = read_to_string(PATH).unwrap().replace("position=7", "position=8");
+        write(PATH, content).unwrap();
+        assert_eq!(Manifest::load(PATH), None);
+        remove_file(PATH).unwrap();
+    }
+
+    #[test]
+    fn missing_manifest() {
+        assert_eq!(Manifest::load("manifest_not_existing"), None);
+    }
+}
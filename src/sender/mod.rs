@@ -1,5 +1,12 @@
 pub mod config;
 mod logic;
 mod sender_connection_properties;
+mod manifest;
+mod param_cache;
+mod input_files;
+mod events;
+mod control;
 
-pub use logic::{logic, breakable_logic};
+pub use logic::{logic, breakable_logic, controlled_logic, probe_packet_size};
+pub use control::Control;
+pub use events::{Progress, SenderEvent, TransferStats};
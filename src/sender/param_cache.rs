@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::fs::{read_to_string, rename, write};
+use std::io;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use crate::connection_properties::ConnectionProperties;
+
+/// Properties negotiated with the receiver, the next connection to it starts from them.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CachedParams {
+    pub checksum_size: u16,
+    pub window_size: u16,
+    pub packet_size: u16,
+}
+
+/// Properties negotiated with the receivers persisted on the disk, one line per receiver address.
+#[derive(Debug, PartialEq)]
+pub struct ParamCache {
+    receivers: HashMap<SocketAddr, CachedParams>,
+}
+
+impl ParamCache {
+    /// Load the cache from the `path`.
+    /// Missing file means empty cache, the lines that can't be parsed are ignored.
+    pub fn load(path: &str) -> Self {
+        let content = read_to_string(path).unwrap_or_default();
+        let receivers = content.lines()
+            .filter_map(ParamCache::parse_line)
+            .collect();
+        return ParamCache { receivers };
+    }
+
+    /// Parse line in format `addr checksum_size window_size packet_size`.
+    fn parse_line(line: &str) -> Option<(SocketAddr, CachedParams)> {
+        let mut values = line.split_whitespace();
+        let addr = SocketAddr::from_str(values.next()?).ok()?;
+        let params = CachedParams {
+            checksum_size: values.next()?.parse().ok()?,
+            window_size: values.next()?.parse().ok()?,
+            packet_size: values.next()?.parse().ok()?,
+        };
+        if values.next().is_some() {
+            return None;
+        }
+        return Some((addr, params));
+    }
+
+    /// Properties negotiated with the receiver at `addr` last time.
+    pub fn get(&self, addr: &SocketAddr) -> Option<CachedParams> {
+        return self.receivers.get(addr).copied();
+    }
+
+    /// Remember the properties negotiated with the receiver at `addr`.
+    pub fn insert(&mut self, addr: SocketAddr, props: &ConnectionProperties) {
+        self.receivers.insert(addr, CachedParams {
+            checksum_size: props.checksum_size,
+            window_size: props.window_size,
+            packet_size: props.packet_size,
+        });
+    }
+
+    /// Store the cache into the `path`.
+    /// The content is written into temporary file first, so the cache is never half written.
+    pub fn store(&self, path: &str) -> io::Result<()> {
+        let mut lines = self.receivers.iter()
+            .map(|(addr, params)| format!("{} {} {} {}\n", addr, params.checksum_size, params.window_size, params.packet_size))
+            .collect::<Vec<String>>();
+        lines.sort();
+        let temp_path = format!("{}.tmp", path);
+        write(&temp_path, lines.concat())?;
+        return rename(&temp_path, path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{remove_file, write};
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+    use crate::connection_properties::ConnectionProperties;
+    use super::{CachedParams, ParamCache};
+
+    #[test]
+    fn store_and_load() {
+        const PATH: &str = "param_cache_roundtrip";
+        let addr = SocketAddr::from_str("127.0.0.1:3003").unwrap();
+        let mut cache = ParamCache::load(PATH);
+        assert_eq!(cache.get(&addr), None);
+        cache.insert(addr, &ConnectionProperties::new(42, 16, 15, 800, addr));
+        cache.store(PATH).unwrap();
+        let expected = CachedParams { checksum_size: 16, window_size: 15, packet_size: 800 };
+        assert_eq!(ParamCache::load(PATH).get(&addr), Some(expected));
+        remove_file(PATH).unwrap();
+    }
+
+    #[test]
+    fn broken_lines_are_ignored() {
+        const PATH: &str = "param_cache_broken";
+        write(PATH, "127.0.0.1:3003 16 15\nnot_address 16 15 800\n127.0.0.1:3004 16 15 800\n").unwrap();
+        let cache = ParamCache::load(PATH);
+        assert_eq!(cache.get(&SocketAddr::from_str("127.0.0.1:3003").unwrap()), None);
+        assert!(cache.get(&SocketAddr::from_str("127.0.0.1:3004").unwrap()).is_some());
+        remove_file(PATH).unwrap();
+    }
+}
@@ -1,13 +1,22 @@
-use crate::connection_properties::ConnectionProperties;
-use std::fs::File;
+use crate::connection_properties::{ConnectionProperties, MAX_WINDOW_SIZE};
 use std::net::UdpSocket;
-use std::collections::BTreeMap;
 use crate::sender::config::Config;
+use crate::sender::events::{Progress, SenderEvent};
 use std::time::{Instant, Duration};
-use std::io::Read;
-use crate::packet::{Packet, DataPacket, PacketHeader};
+use std::io::{Read, Seek, SeekFrom};
+use crate::packet::{DataPacket, PacketHeader, Flag, Crc32};
+use crate::{send_batch, send_parts};
+use crate::socket_manipulation::is_message_too_long;
+use crate::seq_ring::SeqRing;
 use std::num::Wrapping;
-use std::cmp::min;
+use std::cmp::{max, min};
+use std::fmt;
+use std::borrow::Cow;
+use std::thread::sleep;
+use rand::{SeedableRng, rngs::StdRng};
+
+/// Number of answers from which the rate of the checksum failures is computed.
+const CHECKSUM_SAMPLE: u32 = 20;
 
 /// Part of the content that should be send.
 struct Part {
@@ -19,6 +28,56 @@ struct Part {
     pub seq: u16,
     /// Whether the part was send (not necessarily received).
     pub send: bool,
+    /// How many times was the part send.
+    pub attempts: u16,
+    /// Whether the receiver reported the part as missing.
+    pub requested: bool,
+    /// Whether the part is send again, because nothing was send for the minimum send interval.
+    pub heartbeat: bool,
+    /// Timeout of the last send, after which the part is send again.
+    pub timeout: Duration,
+}
+
+/// Why was the part send.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SendReason {
+    /// The part is send for the first time.
+    First,
+    /// The part wasn't acknowledged in time.
+    Timeout,
+    /// The receiver reported the part as missing.
+    Requested,
+    /// Nothing was send for the minimum send interval.
+    Heartbeat,
+}
+
+/// Record about single data packet send to the receiver.
+#[derive(Debug, Clone)]
+pub struct SendTrace {
+    pub connection_id: u32,
+    pub seq: u16,
+    pub reason: SendReason,
+    pub attempt: u16,
+}
+
+impl fmt::Display for SendTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.reason {
+            SendReason::First => "first",
+            SendReason::Timeout => "timeout",
+            SendReason::Requested => "requested",
+            SendReason::Heartbeat => "heartbeat",
+        };
+        write!(f, "trace conn={} seq={} reason={} attempt={}", self.connection_id, self.seq, reason, self.attempt)
+    }
+}
+
+/// Transmissions and acknowledges of the parts in the order they happened.
+struct Timeline {
+    /// When the recording started, the events are relative to it.
+    started_at: Instant,
+    /// Sequence number, event, attempt and time of the event.
+    events: Vec<(u16, &'static str, u16, Duration)>,
 }
 
 /// Properties that the receiver stores per connection.
@@ -28,30 +87,259 @@ pub struct SenderConnectionProperties {
     /// Current position of the window. This number specified sequence number of the part the sender should send.
     pub window_position: u16,
     /// Cache memory of the parts sender should send.
-    loaded_parts: BTreeMap<u16, Part>,
+    loaded_parts: SeqRing<Part>,
     /// Flag whether the sender read the whole file already.
     file_read: bool,
+    /// Number of parts the receiver acknowledged.
+    pub acknowledged_parts: u32,
+    /// Position in the file, up to which the receiver acknowledged the content.
+    pub acknowledged_bytes: u64,
+    /// Position in the file, from which this connection sends the content.
+    start_offset: u64,
+    /// Whether the number of parts in flight is tuned during the transfer.
+    auto_window: bool,
+    /// Number of parts that can be in flight, at most the pipeline window.
+    effective_window: u16,
+    /// Number of parts that can be unacknowledged, the negotiated window times the pipeline depth.
+    pipeline_window: u16,
+    /// Number of parts acknowledged since the effective window changed.
+    acknowledged_since_tune: u16,
+    /// Size of the auto tuned window, from which it grows by one part instead of doubling.
+    ssthresh: u16,
+    /// Maximum number of parts in flight, independent of the negotiated window size.
+    max_cwnd: u16,
+    /// Smoothed round trip time measured from the parts send only once.
+    rtt: Option<Duration>,
+    /// Shortest and longest round trip time measured.
+    rtt_range: Option<(Duration, Duration)>,
+    /// When the window was reduced because of the congestion for the last time.
+    congestion_reduced_at: Option<Instant>,
+    /// When the last data packet was send.
+    last_send_at: Instant,
+    /// Whether the system refused to send the data packet, because it was too long.
+    oversized: bool,
+    /// Number of data packets send more than once.
+    pub retransmissions: u32,
+    /// Number of bytes of the file send in the data packets, including the retransmissions.
+    pub payload_bytes: u64,
+    /// Number of bytes of the data packets send, including the headers and checksums.
+    pub wire_bytes: u64,
+    /// CRC of the parts the receiver acknowledged, in the order of the file.
+    crc: Crc32,
+    /// Number of valid answers since the checksum failure rate was computed.
+    valid_answers: u32,
+    /// Number of answers failing the checksum since the checksum failure rate was computed.
+    failed_answers: u32,
+    /// Bigger checksum size the receiver agreed on, used once the parts with the current one are acknowledged.
+    pending_checksum: Option<u16>,
+    /// Whether the transfer is behind the schedule of the deadline.
+    hurried: bool,
+    /// Nonce from the init answer, echoed in the data packets until some part is acknowledged.
+    nonce: u16,
+    /// Number of acknowledges, that repeated the last one since the window moved.
+    duplicate_acks: u16,
+    /// Number of init packets received after the connection was established.
+    stray_inits: u32,
+    /// Whether the position comes from the manifest, that may be stored before the last acknowledges.
+    from_manifest: bool,
+    /// Acknowledge of the receiver ahead of the manifest, up to which the parts are skipped.
+    skip_to: Option<u16>,
+    /// Random generator of the timeout jitter.
+    rng: StdRng,
+    /// Timeline of the transmissions, when it is recorded.
+    timeline: Option<Timeline>,
 }
 
 impl SenderConnectionProperties {
     pub fn new(props: ConnectionProperties) -> Self {
         Self {
-            static_properties: props,
             window_position: 0,
-            loaded_parts: BTreeMap::new(),
+            loaded_parts: SeqRing::new(props.window_size as usize),
             file_read: false,
+            acknowledged_parts: 0,
+            acknowledged_bytes: 0,
+            start_offset: 0,
+            auto_window: false,
+            effective_window: props.window_size,
+            pipeline_window: props.window_size,
+            acknowledged_since_tune: 0,
+            ssthresh: u16::MAX,
+            max_cwnd: u16::MAX,
+            rtt: None,
+            rtt_range: None,
+            congestion_reduced_at: None,
+            last_send_at: Instant::now(),
+            oversized: false,
+            retransmissions: 0,
+            payload_bytes: 0,
+            wire_bytes: 0,
+            crc: Crc32::new(),
+            valid_answers: 0,
+            failed_answers: 0,
+            pending_checksum: None,
+            hurried: false,
+            nonce: 0,
+            duplicate_acks: 0,
+            stray_inits: 0,
+            from_manifest: false,
+            skip_to: None,
+            rng: StdRng::from_entropy(),
+            timeline: None,
+            static_properties: props,
         }
     }
 
+    /// Continue in the connection, where the receiver already acknowledged parts before `window_position`,
+    /// that ended at the position `acknowledged_bytes` of the file.
+    pub fn resume(props: ConnectionProperties, window_position: u16, acknowledged_bytes: u64) -> Self {
+        let mut props = Self::new(props);
+        props.window_position = window_position;
+        props.acknowledged_bytes = acknowledged_bytes;
+        props.start_offset = acknowledged_bytes;
+        return props;
+    }
+
+    /// Continue at the position from the manifest, the receiver may have stored the parts after it already.
+    /// Until some part is acknowledged, the acknowledge ahead of the position skips the parts up to it.
+    pub fn restore(props: ConnectionProperties, window_position: u16, acknowledged_bytes: u64) -> Self {
+        let mut props = Self::resume(props, window_position, acknowledged_bytes);
+        props.from_manifest = true;
+        return props;
+    }
+
+    /// Echo the `nonce` from the init answer, so the receiver knows the data come from the one it answered.
+    pub fn echo_nonce(&mut self, nonce: u16) {
+        self.nonce = nonce;
+    }
+
+    /// Repeat the same timeout jitter in every run with the `seed`.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Record when every part was send and acknowledged.
+    pub fn record_timeline(&mut self) {
+        self.timeline = Some(Timeline {
+            started_at: Instant::now(),
+            events: Vec::new(),
+        });
+    }
+
+    /// Recorded timeline in CSV, with a line per transmission and acknowledge of the part.
+    pub fn timeline_csv(&self) -> Option<String> {
+        let timeline = self.timeline.as_ref()?;
+        let mut csv = String::from("seq,event,attempt,micros\n");
+        for (seq, event, attempt, at) in &timeline.events {
+            csv.push_str(&format!("{},{},{},{}\n", seq, event, attempt, at.as_micros()));
+        }
+        return Some(csv);
+    }
+
+    /// Start with single part in flight and tune the number of parts based on the losses.
+    /// The window doubles every time the whole window is acknowledged and halves when a part timeouts.
+    pub fn auto_tune_window(&mut self) {
+        self.auto_window = true;
+        self.effective_window = 1;
+        self.acknowledged_since_tune = 0;
+    }
+
+    /// Keep up to `depth` negotiated windows unacknowledged, so the link is full while the acknowledges travel back.
+    /// The receiver moves its window as the parts arrive, so it accepts them unless some part before is lost.
+    /// Must be called before any part is loaded.
+    pub fn pipeline(&mut self, depth: u16) {
+        let window = self.static_properties.window_size.saturating_mul(max(depth, 1));
+        self.pipeline_window = min(window, MAX_WINDOW_SIZE);
+        self.loaded_parts = SeqRing::new(self.pipeline_window as usize);
+        self.effective_window = self.window_cap();
+    }
+
+    /// Let the auto tuned window double only until the `ssthresh` and never exceed the `max_cwnd` parts in flight.
+    pub fn limit_growth(&mut self, ssthresh: Option<u16>, max_cwnd: Option<u16>) {
+        self.ssthresh = max(ssthresh.unwrap_or(u16::MAX), 1);
+        self.max_cwnd = max(max_cwnd.unwrap_or(u16::MAX), 1);
+        self.effective_window = min(self.effective_window, self.window_cap());
+    }
+
+    /// Largest number of parts that may be in flight.
+    fn window_cap(&self) -> u16 {
+        return min(self.pipeline_window, self.max_cwnd);
+    }
+
+    /// Change the behavior by whether the transfer is `behind` the schedule of the deadline.
+    /// Behind the schedule the whole window is used and the parts are send again after half of the timeout.
+    /// Returns whether the sender started to hurry just now.
+    pub fn hurry(&mut self, behind: bool, config: &Config) -> bool {
+        if behind == self.hurried {
+            return false;
+        }
+        self.hurried = behind;
+        if !behind {
+            config.vlog(&format!("Connection {} caught up with the schedule", self.static_properties.id));
+            return false;
+        }
+        self.effective_window = self.window_cap();
+        self.acknowledged_since_tune = 0;
+        config.vlog(&format!("Connection {} is behind the schedule, window grows to {}", self.static_properties.id, self.effective_window));
+        return true;
+    }
+
     /// Whether the whole file was send and confirmed.
     pub fn is_complete(&self) -> bool {
-        return self.file_read && self.loaded_parts.len() == 0;
+        return self.file_read && self.loaded_parts.is_empty();
     }
 
 
-    /// Check whether the `ack` number is within windows of this connection.
+    /// Number of bytes the receiver acknowledged since this connection started sending.
+    pub fn transferred_bytes(&self) -> u64 {
+        return self.acknowledged_bytes - self.start_offset;
+    }
+
+    /// Current state of the transfer.
+    pub fn progress(&self) -> Progress {
+        return Progress {
+            window_position: self.window_position,
+            bytes: self.transferred_bytes(),
+            rtt: self.rtt,
+            retransmissions: self.retransmissions,
+        };
+    }
+
+    /// Shortest and longest round trip time measured in this connection.
+    pub fn rtt_range(&self) -> Option<(Duration, Duration)> {
+        return self.rtt_range;
+    }
+
+    /// CRC-32 of the content the receiver acknowledged in this connection.
+    pub fn content_crc(&self) -> u32 {
+        return self.crc.finish();
+    }
+
+    /// Describe the window waiting for the acknowledge, with the parts it still holds and how many times they were send.
+    pub fn stuck_diagnostic(&self) -> String {
+        let parts = (0..self.loaded_parts.len() as u16)
+            .filter_map(|i| self.loaded_parts.get((Wrapping(self.window_position) + Wrapping(i)).0))
+            .map(|part| format!("{} (send {}x)", part.seq, part.attempts))
+            .collect::<Vec<String>>();
+        return format!(
+            "Window of connection {} stuck at position {}, waiting parts: {}",
+            self.static_properties.id,
+            self.window_position,
+            parts.join(", ")
+        );
+    }
+
+    /// Check whether the `ack` number is within the unacknowledged parts of this connection.
+    /// The parts of all the pipelined windows are acknowledged by the same cumulative acknowledge.
     fn is_within_window(&self, ack: u16, config: &Config) -> bool {
-        self.static_properties.is_within_window(ack, self.window_position, Box::new(config))
+        let is_within = ack.wrapping_sub(self.window_position) < self.pipeline_window;
+        config.vlog(&format!(
+            "Check whether {} is within pipeline window starting at {} of size {}: {}",
+            ack,
+            self.window_position,
+            self.pipeline_window,
+            is_within
+        ));
+        return is_within;
     }
 
     /// Register acknowledge packet from the receiver with `ack` number.
@@ -64,63 +352,475 @@ impl SenderConnectionProperties {
             self.window_position,
             self.static_properties.window_size
         ));
+        // the manifest may be stored before the last acknowledges, the receiver continues after them
+        let ahead = ack.wrapping_sub(self.window_position);
+        if self.from_manifest && self.acknowledged_parts == 0 && ahead < u16::MAX / 2
+            && !matches!(self.loaded_parts.get(ack), Some(part) if part.send) {
+            config.vlog(&format!("Acknowledge {} for connection {} is ahead of the manifest", ack, self.static_properties.id));
+            self.skip_to = Some(ack);
+            return false;
+        }
         // check if it is valid packet for current window
         if !self.is_within_window(ack, &config){
             return false;
         }
+        // the parts are send in order, the acknowledge ahead of the last send part is malformed
+        if !matches!(self.loaded_parts.get(ack), Some(part) if part.send) {
+            config.vlog(&format!("Acknowledge {} for connection {} is ahead of the send parts", ack, self.static_properties.id));
+            return false;
+        }
+        // measure round trip time, only from parts that were not resend
+        if let Some(part) = self.loaded_parts.get(ack).filter(|part| part.attempts == 1) {
+            let sample = part.last_transition.elapsed();
+            self.rtt = Some(match self.rtt {
+                Some(rtt) => (rtt * 7 + sample) / 8,
+                None => sample,
+            });
+            self.rtt_range = Some(match self.rtt_range {
+                Some((shortest, longest)) => (min(shortest, sample), max(longest, sample)),
+                None => (sample, sample),
+            });
+        }
         // free cache memory for acknowledge packets
         let mut current_pos = Wrapping(self.window_position);
         let end_pos = Wrapping(ack) + Wrapping::<u16>(1);
         while current_pos != end_pos {
-            self.loaded_parts.remove(&current_pos.0).expect("Can't remove entry for acknowledge");
+            let part = self.loaded_parts.pop().filter(|part| part.seq == current_pos.0).expect("Can't remove entry for acknowledge");
+            self.acknowledged_parts = self.acknowledged_parts.saturating_add(1);
+            self.acknowledged_bytes += part.content.len() as u64;
+            self.crc.update(&part.content);
+            if let Some(timeline) = &mut self.timeline {
+                timeline.events.push((part.seq, "ack", part.attempts, timeline.started_at.elapsed()));
+            }
             current_pos += Wrapping::<u16>(1);
+            self.acknowledged_since_tune = self.acknowledged_since_tune.saturating_add(1);
+        }
+        // grow the window when the whole window was acknowledged without loss, linearly after the slow start
+        if self.auto_window && self.acknowledged_since_tune >= self.effective_window {
+            let grown = match self.effective_window < self.ssthresh {
+                true => min(self.effective_window.saturating_mul(2), self.ssthresh),
+                false => self.effective_window.saturating_add(1),
+            };
+            self.effective_window = min(grown, self.window_cap());
+            self.acknowledged_since_tune = 0;
+            config.vlog(&format!("Connection {} grows window to {}", self.static_properties.id, self.effective_window));
         }
         // does the window moved?
         let moved = current_pos.0 != self.window_position;
+        if moved {
+            self.duplicate_acks = 0;
+        }
         // move window if necessary.
         self.window_position = current_pos.0;
         // return value
         return moved;
     }
 
+    /// Halve the number of parts in flight, because the receiver echoed the congestion marked by the network.
+    /// The window is reduced at most once per round trip time and grows again only when it is auto tuned.
+    /// Returns the new window, when it was reduced.
+    pub fn congestion_experienced(&mut self, config: &Config) -> Option<u16> {
+        let interval = self.rtt.unwrap_or_else(|| Duration::from_millis(config.timeout as u64));
+        if matches!(self.congestion_reduced_at, Some(reduced_at) if reduced_at.elapsed() < interval) {
+            return None;
+        }
+        self.congestion_reduced_at = Some(Instant::now());
+        self.effective_window = max(1, self.effective_window / 2);
+        self.acknowledged_since_tune = 0;
+        config.vlog(&format!("Connection {} is congested, window shrinks to {}", self.static_properties.id, self.effective_window));
+        return Some(self.effective_window);
+    }
+
+    /// Count the `ack` of the receiver, that repeats the last acknowledge.
+    /// Returns the part at the window position, when it should be send again because the acknowledge was repeated `fast_retransmit` times.
+    pub fn duplicate_ack(&mut self, ack: u16, config: &Config) -> Option<u16> {
+        let threshold = config.fast_retransmit?;
+        if ack != self.window_position.wrapping_sub(1) {
+            return None;
+        }
+        let part = self.loaded_parts.get_mut(self.window_position).filter(|part| part.send)?;
+        self.duplicate_acks = self.duplicate_acks.saturating_add(1);
+        if self.duplicate_acks != threshold {
+            return None;
+        }
+        part.requested = true;
+        config.vlog(&format!("Connection {} got {} duplicate acknowledges, part {} is send again", self.static_properties.id, threshold, part.seq));
+        return Some(part.seq);
+    }
+
+    /// Count the init packet received after the connection was established.
+    /// Returns the number of such packets so far.
+    pub fn stray_init(&mut self) -> u32 {
+        self.stray_inits = self.stray_inits.saturating_add(1);
+        return self.stray_inits;
+    }
+
+    /// Mark the parts within the `ranges` reported by the receiver as missing, so they are send again.
+    pub fn request_resend(&mut self, ranges: &[(u16, u16)], config: &Config) {
+        for (first, last) in ranges {
+            let mut seq = Wrapping(*first);
+            loop {
+                if let Some(part) = self.loaded_parts.get_mut(seq.0).filter(|part| part.send) {
+                    part.requested = true;
+                }
+                if seq.0 == *last {
+                    break;
+                }
+                seq += Wrapping::<u16>(1);
+            }
+            config.vlog(&format!("Connection {} misses parts {} to {}", self.static_properties.id, first, last));
+        }
+    }
+
+    /// Force the loaded parts with the `seqs` to be send on the next pass, as if they were never send.
+    /// Parts that are not loaded are ignored.
+    #[cfg(test)]
+    pub fn force_resend(&mut self, seqs: &[u16]) {
+        for seq in seqs {
+            if let Some(part) = self.loaded_parts.get_mut(*seq) {
+                part.send = false;
+                part.last_transition = Instant::now();
+            }
+        }
+    }
+
+    /// Count the answer of the receiver, that was `valid` or failed the checksum.
+    /// Returns the bigger checksum size to ask the receiver for, when more answers than the `checksum_escalation` failed.
+    pub fn answer_checked(&mut self, valid: bool, config: &Config) -> Option<u16> {
+        let threshold = config.checksum_escalation?;
+        match valid {
+            true => self.valid_answers += 1,
+            false => self.failed_answers += 1,
+        };
+        let answers = self.valid_answers + self.failed_answers;
+        if answers < CHECKSUM_SAMPLE {
+            return None;
+        }
+        let rate = self.failed_answers as f32 / answers as f32;
+        self.valid_answers = 0;
+        self.failed_answers = 0;
+        if rate <= threshold {
+            return None;
+        }
+        // the checksum may take at most half of the packet
+        let current = max(self.static_properties.checksum_size, self.pending_checksum.unwrap_or(0));
+        let requested = min(max(current.saturating_mul(2), 1), self.static_properties.packet_size / 2);
+        if requested <= current {
+            return None;
+        }
+        config.vlog(&format!(
+            "Connection {} has {:.0}% answers failing the checksum, asking for checksum of {}b",
+            self.static_properties.id,
+            rate * 100.0,
+            requested
+        ));
+        return Some(requested);
+    }
+
+    /// The receiver agreed on the checksum of `checksum_size` bytes.
+    pub fn checksum_agreed(&mut self, checksum_size: u16) {
+        if checksum_size > max(self.static_properties.checksum_size, self.pending_checksum.unwrap_or(0)) {
+            self.pending_checksum = Some(checksum_size);
+        }
+    }
+
+    /// Use the bigger checksum the receiver agreed on, when no part with the current one waits for the acknowledge.
+    /// Returns the new checksum size, when it changed.
+    pub fn apply_checksum(&mut self) -> Option<u16> {
+        if !self.loaded_parts.is_empty() {
+            return None;
+        }
+        let checksum_size = self.pending_checksum.take()?;
+        self.static_properties.checksum_size = checksum_size;
+        return Some(checksum_size);
+    }
+
+    /// Send the oldest unacknowledged part again on the next pass, when nothing was send for the `min_send_interval`,
+    /// so the receiver doesn't consider the connection idle.
+    /// Returns whether the heartbeat is due.
+    pub fn heartbeat(&mut self, config: &Config) -> bool {
+        let interval = match config.min_send_interval {
+            Some(interval) => interval,
+            None => return false,
+        };
+        if self.last_send_at.elapsed().as_millis() < interval as u128 {
+            return false;
+        }
+        return match self.loaded_parts.get_mut(self.window_position).filter(|part| part.send) {
+            Some(part) => {
+                part.heartbeat = true;
+                true
+            }
+            None => false,
+        };
+    }
+
+    /// Send the empty data packet just behind the window over `socket`, when nothing was send for the answer timeout,
+    /// so the receiver doesn't consider the paused connection idle. The receiver drops it as the repeated part.
+    /// Returns whether the packet was send.
+    pub fn keepalive(&mut self, socket: &UdpSocket, config: &Config) -> bool {
+        if self.last_send_at.elapsed() < config.answer_timeout() {
+            return false;
+        }
+        let header = PacketHeader {
+            id: self.static_properties.id,
+            seq: self.window_position.wrapping_sub(1),
+            ack: self.window_position,
+            flag: Flag::Data,
+        };
+        let (header, checksum) = DataPacket::framing(&header, &[], self.static_properties.checksum_size as usize);
+        let mut packet = [&header[..], &checksum[..]].concat();
+        config.before_send(&mut packet);
+        socket.send_to(&packet, self.static_properties.socket_addr).expect("Can't send keepalive packet");
+        self.last_send_at = Instant::now();
+        return true;
+    }
+
     /// Sends data over `socket` to the receiver of this connection.
-    pub fn send_data(&mut self, socket: &UdpSocket, config: &Config){
+    /// With the `batched` feature the window is send by single system call, unless the packets are paced.
+    /// Returns record for every part that was send.
+    pub fn send_data(&mut self, socket: &UdpSocket, config: &Config) -> Vec<SendTrace> {
         // create buffer
         let mut buffer = vec![0;self.static_properties.packet_size as usize];
-        // for each part of the message
-        for i in 0..min(self.static_properties.window_size, self.loaded_parts.len() as u16) {
-            // get the part from the cache
-            let current_index = Wrapping(self.window_position) + Wrapping(i);
-            let part = self.loaded_parts.get_mut(&current_index.0).expect("Part is not within the map");
-            // do not send if the timeout time doesn't exceed
-            if part.send && Instant::now() - part.last_transition < Duration::from_millis(config.timeout as u64){
-                continue;
+        let mut traces = Vec::new();
+        let load_size = self.payload_size();
+        // send only one part at the time until the warmup is over
+        let in_flight = match self.acknowledged_parts < config.warmup_packets {
+            true => 1,
+            false => self.effective_window,
+        };
+        // spread the packets of the window over the round trip time
+        let gap = match config.pacing {
+            true => self.rtt.map(|rtt| rtt / in_flight as u32),
+            false => None,
+        };
+        // parts never send or with the exceeded timeout
+        let due = (0..min(in_flight, self.loaded_parts.len() as u16))
+            .map(|i| (Wrapping(self.window_position) + Wrapping(i)).0)
+            .filter(|seq| self.is_due(*seq))
+            .collect::<Vec<u16>>();
+        // send the whole window at once
+        if cfg!(feature = "batched") && gap.is_none() && due.len() > 1 {
+            let packets = due.iter().map(|seq| {
+                let (header, content, checksum) = self.framing(*seq, load_size, config);
+                let mut packet = [&header[..], &content, &checksum[..]].concat();
+                config.before_send(&mut packet);
+                packet
+            }).collect::<Vec<Vec<u8>>>();
+            match send_batch(socket, &packets, self.static_properties.socket_addr) {
+                Ok(calls) => config.vlog(&format!("{} data packets send by {} system calls", packets.len(), calls)),
+                Err(e) if is_message_too_long(&e) => {
+                    config.vlog(&format!("Packet of {}b is too long for the system", self.static_properties.packet_size));
+                    self.oversized = true;
+                    return traces;
+                }
+                Err(e) => panic!("Can't send part of data: {}", e),
+            };
+            self.last_send_at = Instant::now();
+            for (seq, packet) in due.into_iter().zip(&packets) {
+                traces.push(self.record_send(seq, packet.len(), config));
+            }
+        }
+        // or part after part
+        else {
+            for seq in due {
+                let (header, content, checksum) = self.framing(seq, load_size, config);
+                let wire_size = header.len() + content.len() + checksum.len();
+                // wait for the gap after the previous packet
+                if let Some(gap) = gap {
+                    let wait = (self.last_send_at + gap).saturating_duration_since(Instant::now());
+                    if wait.as_nanos() > 0 {
+                        sleep(wait);
+                    }
+                }
+                // send the packet, the send hook needs the whole packet in the single buffer
+                let sent = match &config.on_send {
+                    Some(_) => {
+                        let mut packet = [&header[..], &content, &checksum[..]].concat();
+                        config.before_send(&mut packet);
+                        socket.send_to(&packet, self.static_properties.socket_addr)
+                    }
+                    None => send_parts(socket, &[&header, &content, &checksum], &mut buffer, self.static_properties.socket_addr),
+                };
+                match sent {
+                    Ok(_) => {}
+                    Err(e) if is_message_too_long(&e) => {
+                        config.vlog(&format!("Packet of {}b is too long for the system", self.static_properties.packet_size));
+                        self.oversized = true;
+                        break;
+                    }
+                    Err(e) => panic!("Can't send part of data: {}", e),
+                };
+                self.last_send_at = Instant::now();
+                traces.push(self.record_send(seq, wire_size, config));
             }
-            config.vlog(&format!(
-                "Connection {} will send data packet with seq {} and {}b of data",
-                self.static_properties.id,
-                part.seq,
-                part.content.len()
-            ));
-            // create the packet for the part
-            let data_packet = DataPacket::new(
-                Clone::clone(&part.content),
-                self.static_properties.id,
-                part.seq,
-                self.window_position,
-            );
-            // send the packet
-            let response_size = Packet::from(data_packet).to_bin_buff(&mut buffer, self.static_properties.checksum_size as usize);
-            socket.send_to(&buffer[..response_size], self.static_properties.socket_addr).expect("Can't send part of data");
-            // update attributes of the part
-            part.last_transition = Instant::now();
-            part.send = true;
-            config.vlog("Data packet send");
         }
+        // shrink the window when some part was lost, the heartbeat doesn't mean a loss
+        let lost = traces.iter().any(|trace| trace.reason != SendReason::First && trace.reason != SendReason::Heartbeat);
+        if self.auto_window && lost && !self.hurried {
+            self.effective_window = max(1, self.effective_window / 2);
+            self.acknowledged_since_tune = 0;
+            config.vlog(&format!("Connection {} shrinks window to {}", self.static_properties.id, self.effective_window));
+        }
+        return traces;
+    }
+
+    /// Whether the part with the `seq` was never send or its timeout exceeded.
+    fn is_due(&self, seq: u16) -> bool {
+        let part = self.loaded_parts.get(seq).expect("Part is not loaded");
+        return !part.send || part.requested || part.heartbeat || Instant::now() - part.last_transition >= part.timeout;
+    }
+
+    /// Header, content, and checksum of the data packet with the part `seq`.
+    /// The shorter last part may be padded to the `load_size`, otherwise the content is not copied.
+    fn framing(&self, seq: u16, load_size: usize, config: &Config) -> (Vec<u8>, Cow<'_, [u8]>, Vec<u8>) {
+        let part = self.loaded_parts.get(seq).expect("Part is not loaded");
+        config.vlog(&format!(
+            "Connection {} will send data packet with seq {} and {}b of data",
+            self.static_properties.id,
+            part.seq,
+            part.content.len()
+        ));
+        let header = PacketHeader {
+            id: self.static_properties.id,
+            seq: part.seq,
+            ack: match self.nonce != 0 && self.acknowledged_parts == 0 {
+                true => self.nonce,
+                false => self.window_position,
+            },
+            flag: Flag::Data,
+        };
+        let (header, content) = match config.pad_last && part.content.len() + 2 <= load_size {
+            true => {
+                let packet = DataPacket::new_padded(Clone::clone(&part.content), header.id, header.seq, header.ack, load_size);
+                (packet.header, Cow::Owned(packet.data))
+            }
+            false => (header, Cow::Borrowed(&part.content[..])),
+        };
+        let (header, checksum) = DataPacket::framing(&header, &content, self.static_properties.checksum_size as usize);
+        return (header, content, checksum);
+    }
+
+    /// Update the part `seq` after it was send in the packet of `wire_size` bytes.
+    /// Returns record of the send.
+    fn record_send(&mut self, seq: u16, wire_size: usize, config: &Config) -> SendTrace {
+        let part = self.loaded_parts.get_mut(seq).expect("Part is not loaded");
+        let trace = SendTrace {
+            connection_id: self.static_properties.id,
+            seq: part.seq,
+            reason: match (part.send, part.requested, part.heartbeat) {
+                (false, _, _) => SendReason::First,
+                (true, true, _) => SendReason::Requested,
+                (true, false, true) => SendReason::Heartbeat,
+                (true, false, false) => SendReason::Timeout,
+            },
+            attempt: part.attempts + 1,
+        };
+        if part.send {
+            self.retransmissions += 1;
+        }
+        self.payload_bytes += part.content.len() as u64;
+        self.wire_bytes += wire_size as u64;
+        part.last_transition = Instant::now();
+        part.timeout = match self.hurried {
+            true => config.retransmit_timeout(&mut self.rng) / 2,
+            false => config.retransmit_timeout(&mut self.rng),
+        };
+        part.send = true;
+        part.requested = false;
+        part.heartbeat = false;
+        part.attempts += 1;
+        if let Some(timeline) = &mut self.timeline {
+            timeline.events.push((part.seq, "send", part.attempts, timeline.started_at.elapsed()));
+        }
+        config.vlog("Data packet send");
+        return trace;
+    }
+
+    /// Whether the system refused to send the data packet, because it was too long.
+    pub fn is_oversized(&self) -> bool {
+        return self.oversized;
+    }
+
+    /// Halve the packet size after the system refused to send the data packet.
+    /// The loaded parts are read again from the `file`, as none of them was send yet.
+    /// The receiver accepts the smaller packets without the new negotiation.
+    pub fn shrink_packets(&mut self, file: &mut impl Seek, config: &Config) -> Result<(), String> {
+        let min_size = (PacketHeader::bin_size() + 1) as u16 + self.static_properties.checksum_size;
+        let new_size = self.static_properties.packet_size / 2;
+        if new_size < min_size {
+            return Err(format!("Packet of {}b can't be send by the system", self.static_properties.packet_size));
+        }
+        if self.loaded_parts.values().any(|part| part.send) {
+            return Err(format!("Packet of {}b can't be send by the system after some data were send", self.static_properties.packet_size));
+        }
+        file.seek(SeekFrom::Start(self.acknowledged_bytes)).map_err(|e| format!("Can't seek in the file: {}", e))?;
+        self.loaded_parts.clear();
+        self.file_read = false;
+        self.oversized = false;
+        self.static_properties.packet_size = new_size;
+        config.info(&format!("Packet size of connection {} lowered to {}b", self.static_properties.id, new_size));
+        return Ok(());
+    }
+
+    /// Whether the receiver acknowledged parts after the position from the manifest, that must be skipped.
+    pub fn is_behind(&self) -> bool {
+        return self.skip_to.is_some();
+    }
+
+    /// Skip the parts the receiver acknowledged ahead of the manifest, it stored them before the sender restarted.
+    /// The skipped parts are read from the `file` again, so the acknowledged bytes and their CRC follow the receiver.
+    pub fn skip_acknowledged(&mut self, file: &mut (impl Read + Seek), config: &Config) -> Result<(), String> {
+        let ack = match self.skip_to.take() {
+            Some(ack) => ack,
+            None => return Ok(()),
+        };
+        file.seek(SeekFrom::Start(self.acknowledged_bytes)).map_err(|e| format!("Can't seek in the file: {}", e))?;
+        self.loaded_parts.clear();
+        self.file_read = false;
+        // the parts are split the same way they were loaded before the restart
+        let load_size = self.payload_size();
+        let mut buffer = vec![0; load_size];
+        let end_pos = Wrapping(ack) + Wrapping::<u16>(1);
+        let mut current_pos = Wrapping(self.window_position);
+        while current_pos != end_pos {
+            let read_limit = match config.chunk_bytes {
+                Some(chunk) if chunk > 0 => min(load_size as u64, chunk - self.acknowledged_bytes % chunk) as usize,
+                _ => load_size,
+            };
+            let read_size = file.read(&mut buffer[..read_limit]).map_err(|e| format!("Can't read file: {}", e))?;
+            if read_size == 0 {
+                return Err(format!("Receiver acknowledged part {} after the end of the file", ack));
+            }
+            self.acknowledged_parts = self.acknowledged_parts.saturating_add(1);
+            self.acknowledged_bytes += read_size as u64;
+            self.crc.update(&buffer[..read_size]);
+            current_pos += Wrapping::<u16>(1);
+        }
+        config.info(&format!(
+            "Connection {} skipped to position {} and offset {}, the receiver stored the parts before",
+            self.static_properties.id,
+            current_pos.0,
+            self.acknowledged_bytes
+        ));
+        self.window_position = current_pos.0;
+        return Ok(());
+    }
+
+    /// How many bytes of the file fit into single data packet.
+    /// The negotiation makes sure some bytes fit, the packet too small for the framing carries nothing.
+    fn payload_size(&self) -> usize {
+        let framing = self.static_properties.checksum_size as usize + PacketHeader::bin_size();
+        return (self.static_properties.packet_size as usize).saturating_sub(framing);
     }
 
     /// Load content from the `file` to fill up the window.
-    pub fn load_window(&mut self, file: &mut File, config: &Config){
+    pub fn load_window(&mut self, file: &mut impl Read, config: &Config){
+        // the parts using the bigger checksum are loaded once the current ones are acknowledged
+        if self.pending_checksum.is_some() {
+            config.vlog(&format!("Connection {} waits for the acknowledge before changing the checksum", self.static_properties.id));
+            return;
+        }
         // if it read the whole file, do nothing
         if self.file_read {
             config.vlog("No more parts to read, as EOF occured");
@@ -130,26 +830,45 @@ impl SenderConnectionProperties {
         // compute indices of parts to load
         let loaded_parts = Wrapping(self.loaded_parts.len() as u16);
         let mut load_index = Wrapping(self.window_position) + loaded_parts;
-        let end_index = Wrapping(self.window_position) + Wrapping(self.static_properties.window_size);
+        let end_index = Wrapping(self.window_position) + Wrapping(self.pipeline_window);
         // decide how much data to load per packet
-        let load_size = self.static_properties.packet_size - self.static_properties.checksum_size;
-        let load_size = load_size as usize - PacketHeader::bin_size();
+        let load_size = self.payload_size();
         config.vlog(&format!(
-            "Connection {} has {} loaded parts, window size is {}, gonna be loaded {} parts, each of size {}",
+            "Connection {} has {} loaded parts, pipeline window size is {}, gonna be loaded {} parts, each of size {}",
             self.static_properties.id,
             loaded_parts.0,
-            self.static_properties.window_size,
-            self.static_properties.window_size - loaded_parts.0,
+            self.pipeline_window,
+            self.pipeline_window - loaded_parts.0,
             load_size
         ));
 
         // load data
         let mut buffer = vec![0;load_size];
+        let mut offset = self.acknowledged_bytes + self.loaded_parts.values().map(|part| part.content.len() as u64).sum::<u64>();
         while load_index != end_index {
-            let read_size = file.read(buffer.as_mut_slice()).expect("Can't read file");
+            // parts never cross the chunk boundary and the next chunk starts after the whole chunk is acknowledged
+            let read_limit = match config.chunk_bytes {
+                Some(chunk) if chunk > 0 => {
+                    let chunk_left = chunk - offset % chunk;
+                    if chunk_left == chunk && !self.loaded_parts.is_empty() {
+                        config.vlog(&format!("Connection {} waits for the chunk ending at offset {}", self.static_properties.id, offset));
+                        break;
+                    }
+                    min(load_size as u64, chunk_left) as usize
+                }
+                _ => load_size,
+            };
+            let read_size = file.read(&mut buffer[..read_limit]).expect("Can't read file");
+            offset += read_size as u64;
             config.vlog(&format!("Read {}b from file", read_size));
             if read_size == 0 { // if nothing read then it is end of the file
                 self.file_read = true;
+                // the window size doesn't matter, when the whole file fits into the first window
+                if self.window_position == 0 && self.acknowledged_parts == 0 {
+                    let parts = self.loaded_parts.len() as u16;
+                    config.vlog(&format!("File smaller than window; sending {} parts total", parts));
+                    config.emit(SenderEvent::SmallFile(parts));
+                }
                 break;
             }
             let part = Part {
@@ -157,12 +876,481 @@ impl SenderConnectionProperties {
                 last_transition: Instant::now(),
                 seq: load_index.0,
                 send: false,
+                attempts: 0,
+                requested: false,
+                heartbeat: false,
+                timeout: Duration::from_millis(config.timeout as u64),
             };
             config.vlog(&format!("Stored as part with seq {} and {}b of data", part.seq, part.content.len()));
-            if let Some(_) = self.loaded_parts.insert(load_index.0, part){
+            if !self.loaded_parts.push(load_index.0, part) {
                 panic!("Part with this number os already loaded");
             }
             load_index += Wrapping::<u16>(1);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{File, remove_file};
+    use std::io::Write;
+    use std::net::{SocketAddr, UdpSocket};
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread::sleep;
+    use std::time::Duration;
+    use crate::broker;
+    use crate::broker::config::DelayModel;
+    use crate::connection_properties::ConnectionProperties;
+    use crate::packet::PacketHeader;
+    use crate::sender::config::Config;
+    use super::{SenderConnectionProperties, SendReason};
+
+    #[test]
+    fn retransmissions_are_labeled() {
+        const SOURCE_FILE: &str = "trace_source.txt";
+        // four parts of data
+        let packet_size = 100;
+        let payload_size = packet_size - PacketHeader::bin_size();
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 4 * payload_size]).unwrap();
+        // receiver that drops everything
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut config = Config::new();
+        config.timeout = 0;
+        let mut props = SenderConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 2, packet_size as u16, sink.local_addr().unwrap())
+        );
+        let mut file = File::open(SOURCE_FILE).unwrap();
+
+        props.load_window(&mut file, &config);
+        let first = props.send_data(&socket, &config);
+        assert_eq!(first.iter().map(|t| (t.seq, t.reason, t.attempt)).collect::<Vec<_>>(),
+                   vec![(0, SendReason::First, 1), (1, SendReason::First, 1)]);
+
+        // only the first part is acknowledged, the second one was dropped
+        props.acknowledge(0, &config);
+        props.load_window(&mut file, &config);
+        let second = props.send_data(&socket, &config);
+        assert_eq!(second.iter().map(|t| (t.seq, t.reason, t.attempt)).collect::<Vec<_>>(),
+                   vec![(1, SendReason::Timeout, 2), (2, SendReason::First, 1)]);
+        assert_eq!(format!("{}", second[0]), "trace conn=1 seq=1 reason=timeout attempt=2");
+
+        remove_file(SOURCE_FILE).unwrap();
+    }
+
+    #[test]
+    fn forced_part_is_send_again() {
+        const SOURCE_FILE: &str = "forced_source.txt";
+        let packet_size = 100;
+        let payload_size = packet_size - PacketHeader::bin_size();
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 8 * payload_size]).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let config = Config::new();
+        let mut props = SenderConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 8, packet_size as u16, sink.local_addr().unwrap())
+        );
+        let mut file = File::open(SOURCE_FILE).unwrap();
+        props.load_window(&mut file, &config);
+        assert_eq!(props.send_data(&socket, &config).len(), 8);
+
+        // nothing timeouts yet, only the forced part is send
+        props.force_resend(&[5, 100]);
+        let traces = props.send_data(&socket, &config);
+        assert_eq!(traces.iter().map(|t| (t.seq, t.attempt)).collect::<Vec<_>>(), vec![(5, 2)]);
+        assert!(props.send_data(&socket, &config).is_empty());
+
+        remove_file(SOURCE_FILE).unwrap();
+    }
+
+    #[test]
+    fn acknowledge_ahead_of_send_parts_is_ignored() {
+        const SOURCE_FILE: &str = "ack_ahead_source.txt";
+        let packet_size = 100;
+        let payload_size = packet_size - PacketHeader::bin_size();
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 4 * payload_size]).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut config = Config::new();
+        config.warmup_packets = 1;
+        let mut props = SenderConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 4, packet_size as u16, sink.local_addr().unwrap())
+        );
+        let mut file = File::open(SOURCE_FILE).unwrap();
+        props.load_window(&mut file, &config);
+        // only the first part is send during the warmup
+        assert_eq!(props.send_data(&socket, &config).len(), 1);
+
+        assert!(!props.acknowledge(2, &config));
+        assert_eq!(props.window_position, 0);
+        assert_eq!(props.acknowledged_parts, 0);
+        assert!(props.acknowledge(0, &config));
+        assert_eq!(props.window_position, 1);
+
+        remove_file(SOURCE_FILE).unwrap();
+    }
+
+    #[test]
+    fn heartbeat_resends_oldest_part() {
+        const SOURCE_FILE: &str = "heartbeat_source.txt";
+        let packet_size = 100;
+        let payload_size = packet_size - PacketHeader::bin_size();
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 4 * payload_size]).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut config = Config::new();
+        config.timeout = 10000;
+        config.min_send_interval = Some(50);
+        let mut props = SenderConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 4, packet_size as u16, sink.local_addr().unwrap())
+        );
+        let mut file = File::open(SOURCE_FILE).unwrap();
+        props.load_window(&mut file, &config);
+        assert_eq!(props.send_data(&socket, &config).len(), 4);
+
+        // the heartbeat is due only after the interval
+        assert!(!props.heartbeat(&config));
+        sleep(Duration::from_millis(60));
+        assert!(props.heartbeat(&config));
+        let traces = props.send_data(&socket, &config);
+        assert_eq!(traces.iter().map(|t| (t.seq, t.reason)).collect::<Vec<_>>(), vec![(0, SendReason::Heartbeat)]);
+        assert!(!props.heartbeat(&config));
+
+        remove_file(SOURCE_FILE).unwrap();
+    }
+
+    #[test]
+    fn warmup_sends_one_by_one() {
+        const SOURCE_FILE: &str = "warmup_source.txt";
+        let packet_size = 100;
+        let payload_size = packet_size - PacketHeader::bin_size();
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 8 * payload_size]).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut config = Config::new();
+        config.timeout = 0;
+        config.warmup_packets = 2;
+        let mut props = SenderConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 4, packet_size as u16, sink.local_addr().unwrap())
+        );
+        let mut file = File::open(SOURCE_FILE).unwrap();
+        let mut send = |props: &mut SenderConnectionProperties| {
+            props.load_window(&mut file, &config);
+            props.send_data(&socket, &config).iter().map(|t| t.seq).collect::<Vec<u16>>()
+        };
+
+        assert_eq!(send(&mut props), vec![0]);
+        assert_eq!(send(&mut props), vec![0]);
+        props.acknowledge(0, &config);
+        assert_eq!(send(&mut props), vec![1]);
+        props.acknowledge(1, &config);
+        assert_eq!(send(&mut props), vec![2, 3, 4, 5]);
+
+        remove_file(SOURCE_FILE).unwrap();
+    }
+
+    #[test]
+    fn chunks_are_not_in_flight_together() {
+        const SOURCE_FILE: &str = "chunk_source.txt";
+        let packet_size = 101;
+        let payload_size = packet_size - PacketHeader::bin_size();
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 6 * payload_size]).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut config = Config::new();
+        config.timeout = 60000;
+        config.chunk_bytes = Some((2 * payload_size + payload_size / 2) as u64);
+        let mut props = SenderConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 8, packet_size as u16, sink.local_addr().unwrap())
+        );
+        let mut file = File::open(SOURCE_FILE).unwrap();
+        let mut send = |props: &mut SenderConnectionProperties| {
+            props.load_window(&mut file, &config);
+            props.send_data(&socket, &config).iter().map(|t| t.seq).collect::<Vec<u16>>()
+        };
+
+        // the first chunk ends within the third part
+        assert_eq!(send(&mut props), vec![0, 1, 2]);
+        props.acknowledge(1, &config);
+        assert_eq!(send(&mut props), vec![]);
+        props.acknowledge(2, &config);
+        assert_eq!(props.acknowledged_bytes, config.chunk_bytes.unwrap());
+        // the second chunk starts with the rest of the third part
+        assert_eq!(send(&mut props), vec![3, 4, 5]);
+        props.acknowledge(5, &config);
+        assert_eq!(props.acknowledged_bytes, 2 * config.chunk_bytes.unwrap());
+        assert_eq!(send(&mut props), vec![6]);
+        props.acknowledge(6, &config);
+        assert_eq!(props.acknowledged_bytes, 6 * payload_size as u64);
+        send(&mut props);
+        assert!(props.is_complete());
+
+        remove_file(SOURCE_FILE).unwrap();
+    }
+
+    #[test]
+    fn acknowledge_ahead_of_manifest_skips_parts() {
+        const SOURCE_FILE: &str = "stale_manifest_source.txt";
+        let packet_size = 101;
+        let payload_size = packet_size - PacketHeader::bin_size();
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 40 * payload_size]).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut config = Config::new();
+        config.timeout = 60000;
+        let properties = || ConnectionProperties::new(1, 0, 4, packet_size as u16, sink.local_addr().unwrap());
+        let mut file = File::open(SOURCE_FILE).unwrap();
+
+        // the fresh connection ignores the acknowledge ahead of the send parts
+        let mut props = SenderConnectionProperties::resume(properties(), 2, 2 * payload_size as u64);
+        props.load_window(&mut file, &config);
+        props.send_data(&socket, &config);
+        assert!(!props.acknowledge(20, &config));
+        assert!(!props.is_behind());
+
+        // the manifest one continues after the acknowledge of the receiver
+        let mut props = SenderConnectionProperties::restore(properties(), 2, 2 * payload_size as u64);
+        props.load_window(&mut file, &config);
+        props.send_data(&socket, &config);
+        assert!(!props.acknowledge(20, &config));
+        assert!(props.is_behind());
+        props.skip_acknowledged(&mut file, &config).unwrap();
+        assert_eq!(props.window_position, 21);
+        assert_eq!(props.acknowledged_bytes, 21 * payload_size as u64);
+        props.load_window(&mut file, &config);
+        assert_eq!(props.send_data(&socket, &config).iter().map(|t| t.seq).collect::<Vec<u16>>(), vec![21, 22, 23, 24]);
+        // once some part is acknowledged, the acknowledge ahead is malformed again
+        assert!(props.acknowledge(22, &config));
+        assert!(!props.acknowledge(30, &config));
+        assert!(!props.is_behind());
+
+        remove_file(SOURCE_FILE).unwrap();
+    }
+
+    #[test]
+    fn oversized_packets_are_shrinked() {
+        const SOURCE_FILE: &str = "oversized_source.txt";
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 100000]).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut config = Config::new();
+        config.quiet = true;
+        let mut props = SenderConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 2, 65535, sink.local_addr().unwrap())
+        );
+        let mut file = File::open(SOURCE_FILE).unwrap();
+
+        // the system refuses to send the datagram bigger than 65507 bytes
+        props.load_window(&mut file, &config);
+        assert!(props.send_data(&socket, &config).is_empty());
+        assert!(props.is_oversized());
+        // the parts are read again with the smaller size
+        props.shrink_packets(&mut file, &config).unwrap();
+        assert_eq!(props.static_properties.packet_size, 32767);
+        props.load_window(&mut file, &config);
+        assert_eq!(props.send_data(&socket, &config).iter().map(|t| t.seq).collect::<Vec<u16>>(), vec![0, 1]);
+        assert!(!props.is_oversized());
+        let mut buffer = vec![0; 65535];
+        assert_eq!(sink.recv(&mut buffer).unwrap(), 32767);
+
+        remove_file(SOURCE_FILE).unwrap();
+    }
+
+    #[test]
+    fn auto_window_grows_without_loss() {
+        const SOURCE_FILE: &str = "auto_window_source.txt";
+        let packet_size = 100;
+        let payload_size = packet_size - PacketHeader::bin_size();
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 32 * payload_size]).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut config = Config::new();
+        config.timeout = 60000;
+        let mut props = SenderConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 8, packet_size as u16, sink.local_addr().unwrap())
+        );
+        props.auto_tune_window();
+        let mut file = File::open(SOURCE_FILE).unwrap();
+
+        // every send part is acknowledged
+        let mut windows = Vec::new();
+        for _ in 0..5 {
+            props.load_window(&mut file, &config);
+            let send = props.send_data(&socket, &config);
+            assert_eq!(send.len() as u16, props.effective_window);
+            windows.push(props.effective_window);
+            props.acknowledge(send.last().unwrap().seq, &config);
+        }
+        assert_eq!(windows, vec![1, 2, 4, 8, 8]);
+
+        // the lost part shrinks the window
+        config.timeout = 0;
+        props.load_window(&mut file, &config);
+        props.send_data(&socket, &config);
+        props.send_data(&socket, &config);
+        assert_eq!(props.effective_window, 4);
+
+        remove_file(SOURCE_FILE).unwrap();
+    }
+
+    #[test]
+    fn pipelined_windows_are_in_flight_together() {
+        const SOURCE_FILE: &str = "pipeline_source.txt";
+        let packet_size = 100;
+        let payload_size = packet_size - PacketHeader::bin_size();
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 32 * payload_size]).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut config = Config::new();
+        config.timeout = 60000;
+        let mut props = SenderConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 4, packet_size as u16, sink.local_addr().unwrap())
+        );
+        props.pipeline(3);
+        let mut file = File::open(SOURCE_FILE).unwrap();
+
+        // three negotiated windows are send before any acknowledge
+        props.load_window(&mut file, &config);
+        let send = props.send_data(&socket, &config);
+        assert_eq!(send.iter().map(|t| t.seq).collect::<Vec<u16>>(), (0..12).collect::<Vec<u16>>());
+
+        // the cumulative acknowledge beyond the first window moves the window and more parts are send
+        assert!(!props.acknowledge(12, &config));
+        assert!(props.acknowledge(9, &config));
+        assert_eq!(props.window_position, 10);
+        props.load_window(&mut file, &config);
+        let send = props.send_data(&socket, &config);
+        assert_eq!(send.iter().map(|t| t.seq).collect::<Vec<u16>>(), (12..22).collect::<Vec<u16>>());
+
+        remove_file(SOURCE_FILE).unwrap();
+    }
+
+    #[test]
+    fn window_grows_linearly_after_slow_start() {
+        const SOURCE_FILE: &str = "slow_start_source.txt";
+        let packet_size = 100;
+        let payload_size = packet_size - PacketHeader::bin_size();
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 64 * payload_size]).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut config = Config::new();
+        config.timeout = 60000;
+        let mut props = SenderConnectionProperties::new(
+            ConnectionProperties::new(1, 0, 15, packet_size as u16, sink.local_addr().unwrap())
+        );
+        props.auto_tune_window();
+        props.limit_growth(Some(4), Some(7));
+        let mut file = File::open(SOURCE_FILE).unwrap();
+
+        // every send part is acknowledged, the window doubles, then grows by one up to the cap
+        let mut windows = Vec::new();
+        for _ in 0..7 {
+            props.load_window(&mut file, &config);
+            let send = props.send_data(&socket, &config);
+            assert_eq!(send.len() as u16, props.effective_window);
+            windows.push(props.effective_window);
+            props.acknowledge(send.last().unwrap().seq, &config);
+        }
+        assert_eq!(windows, vec![1, 2, 4, 5, 6, 7, 7]);
+
+        remove_file(SOURCE_FILE).unwrap();
+    }
+
+    #[test]
+    fn pacing_reduces_loss_in_shallow_queue() {
+        const SOURCE_FILE: &str = "pacing_source.txt";
+        const BROKER_SEND_PART: &str = "127.0.0.1:3153";
+        const BROKER_RECV_PART: &str = "127.0.0.1:3154";
+        const RECEIVER_ADDR: &str = "127.0.0.1:3155";
+        const PARTS: u16 = 10;
+        let packet_size = 100;
+        let payload_size = packet_size - PacketHeader::bin_size();
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; PARTS as usize * payload_size]).unwrap();
+        // broker that holds at most three packets and sends one every 2ms
+        let broker_brk = Arc::new(AtomicBool::new(false));
+        let bc = broker::config::Config {
+            sender_bindaddr: String::from(BROKER_SEND_PART),
+            receiver_bindaddr: String::from(BROKER_RECV_PART),
+            receiver_addr: String::from(RECEIVER_ADDR),
+            delay_model: DelayModel::QueueFifo,
+            service_time: 2.0,
+            max_queue_bytes: Some(3 * packet_size),
+            ..broker::config::Config::new()
+        };
+        let bt = broker::breakable_logic(bc, broker_brk.clone());
+        sleep(Duration::from_millis(100));
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let receiver = UdpSocket::bind(RECEIVER_ADDR).unwrap();
+        receiver.set_read_timeout(Some(Duration::from_millis(300))).unwrap();
+
+        // send the whole window and count packets that went through
+        let delivered = |pacing: bool| -> usize {
+            let mut config = Config::new();
+            config.pacing = pacing;
+            let mut props = SenderConnectionProperties::new(ConnectionProperties::new(
+                1, 0, PARTS, packet_size as u16, SocketAddr::from_str(BROKER_SEND_PART).unwrap()
+            ));
+            props.rtt = Some(Duration::from_millis(40));
+            let mut file = File::open(SOURCE_FILE).unwrap();
+            props.load_window(&mut file, &config);
+            assert_eq!(props.send_data(&socket, &config).len() as u16, PARTS);
+            let mut buffer = vec![0; 1000];
+            let mut count = 0;
+            while receiver.recv_from(&mut buffer).is_ok() {
+                count += 1;
+            }
+            return count;
+        };
+        let without_pacing = delivered(false);
+        let with_pacing = delivered(true);
+        assert!(without_pacing < PARTS as usize, "{} packets delivered without pacing", without_pacing);
+        assert_eq!(with_pacing, PARTS as usize);
+
+        broker_brk.store(true, Ordering::SeqCst);
+        bt.join().unwrap();
+        remove_file(SOURCE_FILE).unwrap();
+    }
+
+    #[test]
+    fn window_wraps_around_sequence_numbers() {
+        const SOURCE_FILE: &str = "wraparound_source.txt";
+        let packet_size = 100;
+        let payload_size = packet_size - PacketHeader::bin_size();
+        File::create(SOURCE_FILE).unwrap().write_all(&vec![7; 12 * payload_size]).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sink = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut config = Config::new();
+        config.warmup_packets = 0;
+        let mut props = SenderConnectionProperties::resume(
+            ConnectionProperties::new(1, 0, 8, packet_size as u16, sink.local_addr().unwrap()), 65533, 0
+        );
+        let mut file = File::open(SOURCE_FILE).unwrap();
+        let first_send = |props: &mut SenderConnectionProperties| props.send_data(&socket, &config).into_iter()
+            .filter(|trace| trace.reason == SendReason::First)
+            .map(|trace| trace.seq)
+            .collect::<Vec<u16>>();
+
+        // the whole window crosses the wraparound
+        props.load_window(&mut file, &config);
+        assert_eq!(first_send(&mut props), vec![65533, 65534, 65535, 0, 1, 2, 3, 4]);
+        // part of the window is acknowledged and loaded again
+        assert!(props.acknowledge(65535, &config));
+        assert_eq!(props.window_position, 0);
+        props.load_window(&mut file, &config);
+        assert_eq!(first_send(&mut props), vec![5, 6, 7]);
+        assert!(props.acknowledge(7, &config));
+        assert_eq!((props.window_position, props.acknowledged_parts), (8, 11));
+        // the last part is shorter than the window
+        props.load_window(&mut file, &config);
+        props.load_window(&mut file, &config);
+        assert_eq!(first_send(&mut props), vec![8]);
+        assert!(!props.is_complete());
+        assert!(props.acknowledge(8, &config));
+        assert!(props.is_complete());
+        assert_eq!(props.acknowledged_bytes, 12 * payload_size as u64);
+
+        remove_file(SOURCE_FILE).unwrap();
+    }
+}
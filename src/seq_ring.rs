@@ -0,0 +1,202 @@
+/// Values of the sequence numbers following the first one, stored in the ring of fixed size.
+/// The slot is found from the distance to the first sequence number, not from the sequence number itself,
+/// as the sequence numbers wrap around at the size, that is not multiple of the ring size.
+pub struct SeqRing<T> {
+    slots: Vec<Option<T>>,
+    /// Slot of the first sequence number.
+    head: usize,
+    /// First sequence number.
+    start: u16,
+    /// Number of the slots from the head up to the last stored value.
+    span: usize,
+    /// Number of the stored values.
+    len: usize,
+}
+
+impl<T> SeqRing<T> {
+    /// Ring holding at most `capacity` values.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        return SeqRing {
+            slots: (0..capacity).map(|_| None).collect(),
+            head: 0,
+            start: 0,
+            span: 0,
+            len: 0,
+        };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.len;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+
+    /// Sequence number following the last stored value, or the first sequence number of the empty ring.
+    pub fn end(&self) -> u16 {
+        return self.start.wrapping_add(self.span as u16);
+    }
+
+    /// Slot of the `seq`, if it is within the ring.
+    fn slot(&self, seq: u16) -> Option<usize> {
+        let distance = seq.wrapping_sub(self.start) as usize;
+        if distance >= self.slots.len() {
+            return None;
+        }
+        return Some((self.head + distance) % self.slots.len());
+    }
+
+    pub fn get(&self, seq: u16) -> Option<&T> {
+        let slot = self.slot(seq)?;
+        return self.slots[slot].as_ref();
+    }
+
+    pub fn get_mut(&mut self, seq: u16) -> Option<&mut T> {
+        let slot = self.slot(seq)?;
+        return self.slots[slot].as_mut();
+    }
+
+    pub fn contains(&self, seq: u16) -> bool {
+        return self.get(seq).is_some();
+    }
+
+    /// Store the `value` with the `seq` after the last one, the empty ring starts at any `seq`.
+    /// Returns false when the ring is full or the `seq` doesn't follow the last one.
+    pub fn push(&mut self, seq: u16, value: T) -> bool {
+        if self.len == 0 {
+            self.start = seq;
+            self.span = 0;
+        }
+        if seq != self.end() {
+            return false;
+        }
+        return self.insert(seq, value);
+    }
+
+    /// Store the `value` with the `seq` anywhere within the ring, the ring doesn't move.
+    /// Returns false when the `seq` is outside of the ring or its value is stored already.
+    pub fn insert(&mut self, seq: u16, value: T) -> bool {
+        let slot = match self.slot(seq) {
+            Some(slot) if self.slots[slot].is_none() => slot,
+            _ => return false,
+        };
+        self.slots[slot] = Some(value);
+        self.span = self.span.max(seq.wrapping_sub(self.start) as usize + 1);
+        self.len += 1;
+        return true;
+    }
+
+    /// Remove the first value and move the ring to the following sequence number.
+    /// The ring doesn't move, when the first value is missing.
+    pub fn pop(&mut self) -> Option<T> {
+        let value = self.slots[self.head].take()?;
+        self.head = (self.head + 1) % self.slots.len();
+        self.start = self.start.wrapping_add(1);
+        self.span -= 1;
+        self.len -= 1;
+        return Some(value);
+    }
+
+    /// Remove all the values, the ring continues after the last of them.
+    pub fn clear(&mut self) {
+        let capacity = self.slots.len();
+        for distance in 0..self.span {
+            self.slots[(self.head + distance) % capacity] = None;
+        }
+        self.head = (self.head + self.span) % capacity;
+        self.start = self.end();
+        self.span = 0;
+        self.len = 0;
+    }
+
+    /// Stored values from the first one.
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        return (0..self.span).filter_map(move |distance| self.slots[(self.head + distance) % self.slots.len()].as_ref());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SeqRing;
+
+    #[test]
+    fn sequence_numbers_wrap_around() {
+        // the ring size doesn't divide the range of the sequence numbers
+        let mut ring = SeqRing::new(15);
+        for seq in 65530..=65535u16 {
+            assert!(ring.push(seq, seq));
+        }
+        for seq in 0..9u16 {
+            assert!(ring.push(seq, seq));
+        }
+        assert_eq!(ring.len(), 15);
+        assert!(!ring.push(9, 9));
+        for seq in (65530..=65535u16).chain(0..9) {
+            assert_eq!(ring.get(seq), Some(&seq));
+        }
+        assert_eq!(ring.get(9), None);
+        assert_eq!(ring.get(65529), None);
+        // the freed slots are reused by the following sequence numbers
+        for seq in 65530..=65535u16 {
+            assert_eq!(ring.pop(), Some(seq));
+        }
+        for seq in 9..15u16 {
+            assert!(ring.push(seq, seq));
+        }
+        assert_eq!(ring.values().cloned().collect::<Vec<u16>>(), (0..15).collect::<Vec<u16>>());
+        assert_eq!(ring.get(65535), None);
+    }
+
+    #[test]
+    fn partial_window() {
+        let mut ring = SeqRing::new(8);
+        assert!(ring.push(3, 'a'));
+        assert!(ring.push(4, 'b'));
+        // only the following sequence number may be stored
+        assert!(!ring.push(6, 'c'));
+        assert!(!ring.push(3, 'c'));
+        assert_eq!(ring.len(), 2);
+        *ring.get_mut(4).unwrap() = 'c';
+        assert_eq!(ring.pop(), Some('a'));
+        assert_eq!(ring.values().collect::<Vec<&char>>(), vec![&'c']);
+        assert_eq!(ring.get(3), None);
+        // the emptied ring continues from any sequence number
+        ring.clear();
+        assert!(ring.is_empty());
+        assert_eq!(ring.pop(), None);
+        assert!(ring.push(100, 'd'));
+        assert_eq!(ring.get(100), Some(&'d'));
+    }
+
+    #[test]
+    fn values_are_inserted_out_of_order() {
+        let mut ring = SeqRing::new(4);
+        for _ in 0..65534u16 {
+            assert!(ring.insert(ring.end(), '-'));
+            assert!(ring.pop().is_some());
+        }
+        // the ring starts at 65534 and holds the sequence numbers up to 1
+        assert!(ring.insert(1, 'd'));
+        assert!(ring.insert(65535, 'b'));
+        assert!(!ring.insert(65535, 'x'));
+        assert!(!ring.insert(2, 'x'));
+        assert_eq!((ring.len(), ring.end()), (2, 2));
+        // nothing is removed before the first value comes
+        assert_eq!(ring.pop(), None);
+        assert!(ring.insert(65534, 'a'));
+        assert_eq!(ring.pop(), Some('a'));
+        assert_eq!(ring.pop(), Some('b'));
+        assert_eq!(ring.pop(), None);
+        assert!(ring.contains(1));
+        assert!(ring.insert(0, 'c'));
+        assert!(ring.insert(2, 'e'));
+        assert_eq!(ring.values().collect::<String>(), "cde");
+        // the cleared ring continues after the last value
+        ring.clear();
+        assert_eq!(ring.end(), 3);
+        assert!(!ring.insert(2, 'x'));
+        assert!(ring.insert(3, 'f'));
+    }
+}
@@ -1,6 +1,13 @@
-use std::net::{UdpSocket, SocketAddr};
-use std::io::{ErrorKind, Result};
+use std::net::{UdpSocket, SocketAddr, SocketAddrV4};
+use std::io::{Error, ErrorKind, Result};
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
 use crate::Loggable;
+#[cfg(all(feature = "vectored", unix))]
+use std::io::IoSlice;
+#[cfg(all(feature = "vectored", unix))]
+use socket2::SockRef;
+#[cfg(all(feature = "batched", target_os = "linux"))]
+use std::os::unix::io::AsRawFd;
 
 pub fn recv_with_timeout(
     socket: &UdpSocket,
@@ -10,12 +17,225 @@ pub fn recv_with_timeout(
     // receive packet
     let result = socket.recv_from(buff.as_mut_slice());
     if let Err(e) = result {
-        let kind = e.kind();
-        if kind != ErrorKind::WouldBlock && kind != ErrorKind::TimedOut {
+        report_error(socket, &e, *log);
+        return Err(e);
+    }
+    return result;
+}
+
+/// Receive datagrams from the `socket` into the `buffers`, the read timeout applies only to the first one.
+/// With the `batched` feature on Linux the datagrams already waiting are read by single `recvmmsg` call,
+/// otherwise just one datagram is received.
+/// Returns size and source address of the datagrams in the order of the `buffers` they were stored into.
+#[cfg(all(feature = "batched", target_os = "linux"))]
+pub fn recv_batch(socket: &UdpSocket, buffers: &mut [Vec<u8>], log: Box<&dyn Loggable>) -> Result<Vec<(usize, SocketAddr)>> {
+    let mut addresses = vec![unsafe { std::mem::zeroed::<libc::sockaddr_storage>() }; buffers.len()];
+    let mut slices = buffers.iter_mut().map(|buffer| libc::iovec {
+        iov_base: buffer.as_mut_ptr() as *mut libc::c_void,
+        iov_len: buffer.len(),
+    }).collect::<Vec<libc::iovec>>();
+    let mut messages = slices.iter_mut().zip(addresses.iter_mut()).map(|(slice, address)| {
+        // the system fills the length of the datagram and its source, the rest of the header stays zeroed
+        let mut message: libc::mmsghdr = unsafe { std::mem::zeroed() };
+        message.msg_hdr.msg_name = address as *mut libc::sockaddr_storage as *mut libc::c_void;
+        message.msg_hdr.msg_namelen = std::mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        message.msg_hdr.msg_iov = slice;
+        message.msg_hdr.msg_iovlen = 1;
+        message
+    }).collect::<Vec<libc::mmsghdr>>();
+    // wait only for the first datagram, the following ones are taken when they are waiting already
+    let result = unsafe {
+        libc::recvmmsg(socket.as_raw_fd(), messages.as_mut_ptr(), messages.len() as libc::c_uint, libc::MSG_WAITFORONE, std::ptr::null_mut())
+    };
+    if result < 0 {
+        let e = Error::last_os_error();
+        report_error(socket, &e, *log);
+        return Err(e);
+    }
+    let received = messages[..result as usize].iter().zip(addresses.iter()).map(|(message, address)| {
+        let source = unsafe { socket2::SockAddr::new(*address, message.msg_hdr.msg_namelen) };
+        (message.msg_len as usize, source.as_socket().expect("Datagram without IP address"))
+    }).collect();
+    return Ok(received);
+}
+
+/// Receive datagrams from the `socket` into the `buffers`, the read timeout applies only to the first one.
+/// With the `batched` feature on Linux the datagrams already waiting are read by single `recvmmsg` call,
+/// otherwise just one datagram is received.
+/// Returns size and source address of the datagrams in the order of the `buffers` they were stored into.
+#[cfg(not(all(feature = "batched", target_os = "linux")))]
+pub fn recv_batch(socket: &UdpSocket, buffers: &mut [Vec<u8>], log: Box<&dyn Loggable>) -> Result<Vec<(usize, SocketAddr)>> {
+    let received = recv_with_timeout(socket, &mut buffers[0], log)?;
+    return Ok(vec![received]);
+}
+
+/// Bind the socket to the `addr`, that other sockets on the same host may bind to as well.
+/// The receivers of the group share the port, every one of them gets the datagrams send to the group.
+pub fn bind_shared(addr: SocketAddrV4) -> Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&SockAddr::from(addr))?;
+    return Ok(UdpSocket::from(socket));
+}
+
+/// Read packet from the `socket` only if it is already waiting there.
+pub fn recv_available(socket: &UdpSocket, buff: &mut [u8]) -> Option<(usize, SocketAddr)> {
+    socket.set_nonblocking(true).expect("Can't switch socket to nonblocking mode");
+    let result = socket.recv_from(buff);
+    socket.set_nonblocking(false).expect("Can't switch socket to blocking mode");
+    return result.ok();
+}
+
+/// Send packet consisting of the `parts` following each other to the `addr`.
+/// With the `vectored` feature the parts are passed to the system as they are,
+/// otherwise they are copied into the `buffer` first.
+#[cfg(all(feature = "vectored", unix))]
+pub fn send_parts(socket: &UdpSocket, parts: &[&[u8]], _buffer: &mut [u8], addr: SocketAddr) -> Result<usize> {
+    let slices = parts.iter().map(|part| IoSlice::new(part)).collect::<Vec<IoSlice>>();
+    return SockRef::from(socket).send_to_vectored(&slices, &SockAddr::from(addr));
+}
+
+/// Send packet consisting of the `parts` following each other to the `addr`.
+/// With the `vectored` feature the parts are passed to the system as they are,
+/// otherwise they are copied into the `buffer` first.
+#[cfg(not(all(feature = "vectored", unix)))]
+pub fn send_parts(socket: &UdpSocket, parts: &[&[u8]], buffer: &mut [u8], addr: SocketAddr) -> Result<usize> {
+    let mut size = 0;
+    for part in parts {
+        buffer[size..size + part.len()].copy_from_slice(part);
+        size += part.len();
+    }
+    return socket.send_to(&buffer[..size], addr);
+}
+
+/// Send every packet from the `packets` to the `addr`, in the order they are given.
+/// With the `batched` feature on Linux the packets are passed to the system together by `sendmmsg`.
+/// Returns the number of system calls used.
+#[cfg(all(feature = "batched", target_os = "linux"))]
+pub fn send_batch(socket: &UdpSocket, packets: &[Vec<u8>], addr: SocketAddr) -> Result<usize> {
+    let address = socket2::SockAddr::from(addr);
+    let mut slices = packets.iter().map(|packet| libc::iovec {
+        iov_base: packet.as_ptr() as *mut libc::c_void,
+        iov_len: packet.len(),
+    }).collect::<Vec<libc::iovec>>();
+    let mut messages = slices.iter_mut().map(|slice| {
+        // the system reads the messages only, the rest of the header stays zeroed
+        let mut message: libc::mmsghdr = unsafe { std::mem::zeroed() };
+        message.msg_hdr.msg_name = address.as_ptr() as *mut libc::c_void;
+        message.msg_hdr.msg_namelen = address.len();
+        message.msg_hdr.msg_iov = slice;
+        message.msg_hdr.msg_iovlen = 1;
+        message
+    }).collect::<Vec<libc::mmsghdr>>();
+    let mut sent = 0;
+    let mut calls = 0;
+    // the system may send only the beginning of the messages
+    while sent < messages.len() {
+        let remaining = &mut messages[sent..];
+        let result = unsafe { libc::sendmmsg(socket.as_raw_fd(), remaining.as_mut_ptr(), remaining.len() as libc::c_uint, 0) };
+        calls += 1;
+        if result < 0 {
+            return Err(Error::last_os_error());
+        }
+        sent += result as usize;
+    }
+    return Ok(calls);
+}
+
+/// Send every packet from the `packets` to the `addr`, in the order they are given.
+/// With the `batched` feature on Linux the packets are passed to the system together by `sendmmsg`.
+/// Returns the number of system calls used.
+#[cfg(not(all(feature = "batched", target_os = "linux")))]
+pub fn send_batch(socket: &UdpSocket, packets: &[Vec<u8>], addr: SocketAddr) -> Result<usize> {
+    for packet in packets {
+        socket.send_to(packet, addr)?;
+    }
+    return Ok(packets.len());
+}
+
+/// Error code of the system for the datagram, that is too long to be send.
+#[cfg(windows)]
+const EMSGSIZE: i32 = 10040;
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd", target_os = "openbsd", target_os = "netbsd"))]
+const EMSGSIZE: i32 = 40;
+#[cfg(not(any(windows, target_os = "macos", target_os = "ios", target_os = "freebsd", target_os = "openbsd", target_os = "netbsd")))]
+const EMSGSIZE: i32 = 90;
+
+/// Whether the send failed, because the datagram is bigger than the system can send.
+pub fn is_message_too_long(e: &Error) -> bool {
+    return e.raw_os_error() == Some(EMSGSIZE);
+}
+
+/// Log the receive error, unless it is just timeout or interruption of the call.
+fn report_error(socket: &UdpSocket, e: &Error, log: &dyn Loggable) {
+    match e.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted => {}
+        _ => {
             log.vlog(&format!("Could not receive from socket {:?}, ignoring", socket.local_addr()));
             log.vlog(&format!("Error: {}", e.to_string()));
         }
-        return Err(e);
     }
-    return result;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{report_error, send_batch, send_parts};
+    use crate::Loggable;
+    use std::cell::RefCell;
+    use std::io::{Error, ErrorKind};
+    use std::net::UdpSocket;
+
+    struct RecordingLog {
+        messages: RefCell<Vec<String>>,
+    }
+
+    impl Loggable for RecordingLog {
+        fn is_verbose(&self) -> bool {
+            true
+        }
+
+        fn log(&self, text: &str) -> () {
+            self.messages.borrow_mut().push(String::from(text));
+        }
+    }
+
+    #[test]
+    fn parts_are_send_as_single_packet() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let target = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut buffer = vec![0; 100];
+        let sent = send_parts(&socket, &[&[1, 2], &[], &[3, 4, 5]], &mut buffer, target.local_addr().unwrap()).unwrap();
+        assert_eq!(sent, 5);
+        let mut received = vec![0; 100];
+        let (size, _) = target.recv_from(&mut received).unwrap();
+        assert_eq!(&received[..size], &[1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn batch_is_received_in_order() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let target = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let packets = (0..20u8).map(|i| vec![i; 100 + i as usize]).collect::<Vec<Vec<u8>>>();
+        let calls = send_batch(&socket, &packets, target.local_addr().unwrap()).unwrap();
+        match cfg!(all(feature = "batched", target_os = "linux")) {
+            true => assert_eq!(calls, 1),
+            false => assert_eq!(calls, packets.len()),
+        };
+        let mut received = vec![0; 200];
+        for packet in &packets {
+            let (size, _) = target.recv_from(&mut received).unwrap();
+            assert_eq!(&received[..size], &packet[..]);
+        }
+    }
+
+    #[test]
+    fn interrupted_is_not_logged() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let log = RecordingLog { messages: RefCell::new(Vec::new()) };
+        report_error(&socket, &Error::from(ErrorKind::Interrupted), &log);
+        report_error(&socket, &Error::from(ErrorKind::WouldBlock), &log);
+        assert!(log.messages.borrow().is_empty());
+        report_error(&socket, &Error::from(ErrorKind::ConnectionRefused), &log);
+        assert_eq!(log.messages.borrow().len(), 2);
+    }
+}
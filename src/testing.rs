@@ -0,0 +1,116 @@
+use std::net::{SocketAddr, SocketAddrV4, UdpSocket};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use crate::{broker, receiver, sender, ConnectionProperties, TransferError};
+
+/// Sender, broker, and receiver connected over the loopback, on the ports the system assigned.
+/// The tests using it don't depend on fixed ports, so they may run concurrently.
+pub struct Harness {
+    /// Address of the sender.
+    pub sender_addr: SocketAddrV4,
+    /// Address of the broker, where the sender sends the packets.
+    pub broker_sender_addr: SocketAddrV4,
+    /// Address of the broker, from which the packets go to the receiver.
+    pub broker_receiver_addr: SocketAddrV4,
+    /// Address of the receiver.
+    pub receiver_addr: SocketAddrV4,
+}
+
+/// Transfer running in the harness.
+pub struct Transfer {
+    pub sender: JoinHandle<Result<ConnectionProperties, TransferError>>,
+    pub broker: JoinHandle<()>,
+    pub receiver: JoinHandle<Result<(), String>>,
+    /// Terminates the broker and the receiver.
+    pub brk: Arc<AtomicBool>,
+}
+
+/// Let the system choose `count` distinct unused ports on the loopback.
+/// The ports are released before they are returned, so the components can bind them.
+fn free_addresses(count: usize) -> Vec<SocketAddrV4> {
+    let sockets = (0..count)
+        .map(|_| UdpSocket::bind("127.0.0.1:0").expect("Can't bind socket to port assigned by the system"))
+        .collect::<Vec<UdpSocket>>();
+    return sockets.iter().map(|socket| match socket.local_addr().expect("Can't get address of the socket") {
+        SocketAddr::V4(addr) => addr,
+        SocketAddr::V6(addr) => panic!("Loopback address {} is not IPv4", addr),
+    }).collect();
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        let addresses = free_addresses(4);
+        return Harness {
+            sender_addr: addresses[0],
+            broker_sender_addr: addresses[1],
+            broker_receiver_addr: addresses[2],
+            receiver_addr: addresses[3],
+        };
+    }
+
+    /// Sender configuration, that sends to the broker.
+    pub fn sender_config(&self) -> sender::config::Config {
+        return sender::config::Config {
+            bind_addr: self.sender_addr.to_string(),
+            send_addr: self.broker_sender_addr.to_string(),
+            ..sender::config::Config::new()
+        };
+    }
+
+    /// Broker configuration, that forwards the packets between the sender and the receiver without any change.
+    pub fn broker_config(&self) -> broker::config::Config {
+        return broker::config::Config {
+            sender_bindaddr: self.broker_sender_addr.to_string(),
+            sender_addr: self.sender_addr.to_string(),
+            receiver_bindaddr: self.broker_receiver_addr.to_string(),
+            receiver_addr: self.receiver_addr.to_string(),
+            delay_mean: 0.0,
+            delay_std: 0.0,
+            drop_rate: 0.0,
+            modify_prob: 0.0,
+            ..broker::config::Config::new()
+        };
+    }
+
+    /// Receiver configuration, that stores the files into the `directory`.
+    pub fn receiver_config(&self, directory: &str) -> receiver::config::Config {
+        return receiver::config::Config {
+            bindaddr: self.receiver_addr.to_string(),
+            directory: String::from(directory),
+            ..receiver::config::Config::new()
+        };
+    }
+
+    /// Start the components with the given configurations.
+    /// The configurations should come from this harness, so the components find each other.
+    pub fn start(
+        &self,
+        sender_config: sender::config::Config,
+        broker_config: broker::config::Config,
+        receiver_config: receiver::config::Config,
+    ) -> Transfer {
+        let brk = Arc::new(AtomicBool::new(false));
+        let receiver = receiver::breakable_logic(receiver_config, brk.clone());
+        let broker = broker::breakable_logic(broker_config, brk.clone());
+        let sender = sender::breakable_logic(sender_config, Arc::new(AtomicBool::new(false)));
+        return Transfer {
+            sender,
+            broker,
+            receiver,
+            brk,
+        };
+    }
+}
+
+impl Transfer {
+    /// Wait for the sender to end, then terminate the broker and the receiver.
+    /// Returns result of the sender.
+    pub fn finish(self) -> Result<ConnectionProperties, TransferError> {
+        let result = self.sender.join().expect("Sender panicked");
+        self.brk.store(true, Ordering::SeqCst);
+        self.broker.join().expect("Broker panicked");
+        self.receiver.join().expect("Receiver panicked").expect("Receiver failed");
+        return result;
+    }
+}
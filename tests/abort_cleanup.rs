@@ -0,0 +1,81 @@
+use udp_transfer::{receiver, sender, TransferError};
+use udp_transfer::receiver::ReceiverEvent;
+use std::fs::{File, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn abort_cleanup(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 2 * 1024 * 1024;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create 2MB file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // create receiver
+    let (events, observed) = channel();
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        events: Some(events),
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create sender
+    let sender_brk = Arc::new(AtomicBool::new(false));
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 0,
+        quiet: true,
+        ..sender::config::Config::new()
+    };
+    let st= sender::breakable_logic(sc, sender_brk.clone());
+
+    // cancel the transfer, when part of the file is written
+    while !matches!(observed.recv().unwrap(), ReceiverEvent::FileWritten(_)) {}
+    sender_brk.store(true, Ordering::SeqCst);
+    let result = st.join().unwrap();
+
+    // the sender returns only after the receiver deleted the partial file
+    assert_eq!(result.unwrap_err(), TransferError::Cancelled(true));
+    assert_eq!(read_dir(TARGET_DIR).unwrap().count(), 0);
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,83 @@
+use udp_transfer::{receiver, sender};
+use udp_transfer::sender::SenderEvent;
+use std::fs::{File, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+
+const SOURCE_FILE: &str = "somefile.txt";
+const TARGET_DIR: &str = "received";
+const FILE_SIZE: usize = 2_000_000;
+const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+/// Transfer the file without any loss and return how many data packets were send again.
+fn retransmissions(coalesce_acks: bool) -> u32 {
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 64,
+        min_checksum: 0,
+        timeout: 1000,
+        linger: 0,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create sender with short timeout, so the late processed acknowledges cause sending again
+    let (events_sender, events) = channel();
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 64,
+        timeout: 2,
+        repetition: 1000,
+        checksum_size: 0,
+        coalesce_acks,
+        events: Some(events_sender),
+        ..sender::config::Config::new()
+    };
+    let st = sender::breakable_logic(sc, Arc::new(AtomicBool::new(false)));
+    st.join().unwrap().unwrap();
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    return match events.iter().last() {
+        Some(SenderEvent::Completed(stats)) => stats.retransmissions,
+        e => panic!("{:?}", e),
+    };
+}
+
+#[test]
+fn ack_coalescing(){
+    // create file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    let one_by_one = retransmissions(false);
+    let coalesced = retransmissions(true);
+    assert!(coalesced < one_by_one, "coalesced: {}, one by one: {}", coalesced, one_by_one);
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
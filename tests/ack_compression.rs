@@ -0,0 +1,58 @@
+use std::net::UdpSocket;
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+use udp_transfer::broker;
+use udp_transfer::broker::BrokerEvent;
+
+#[test]
+fn ack_compression(){
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+    const ACKS: u16 = 3;
+
+    // create broker, that holds the acknowledges long enough to wait together
+    let (events_sender, events) = channel();
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        drop_rate: 0.0,
+        delay_mean: 100.0,
+        read_timeout: 50,
+        ack_compression: true,
+        events: Some(events_sender),
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+    std::thread::sleep(Duration::from_millis(100));
+
+    let sender = UdpSocket::bind(SENDER_ADDR).unwrap();
+    sender.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+    let receiver = UdpSocket::bind(RECEIVED_ADDR).unwrap();
+    // burst of acknowledges of the connection 7
+    for ack in 0..ACKS {
+        let [high, low] = ack.to_be_bytes();
+        receiver.send_to(&[0, 0, 0, 7, 0, 0, high, low, 2], BROKER_RECV_PART).unwrap();
+    }
+    let mut buffer = [0; 64];
+    let mut delivered = 0;
+    while sender.recv_from(&mut buffer).is_ok() {
+        delivered += 1;
+    }
+    assert_eq!(delivered, ACKS);
+
+    broker_brk.store(true, Ordering::SeqCst);
+    bt.join().unwrap();
+    let reported: Vec<(u32, usize)> = events.try_iter().filter_map(|event| match event {
+        BrokerEvent::AckCompression(id, queued) => Some((id, queued)),
+        _ => None,
+    }).collect();
+    assert_eq!(reported, vec![(7, 2), (7, 3)]);
+}
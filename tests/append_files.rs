@@ -0,0 +1,91 @@
+use udp_transfer::{receiver, sender};
+use std::fs::{File, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::{Write, Read};
+use itertools::zip;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn append_files(){
+    const SOURCE_FILES: [&str; 3] = ["somefile.txt", "somefile_2.txt", "somefile_3.txt"];
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZES: [usize; 3] = [300_000, 1_000, 200_003];
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create three files and directory
+    let mut concatenated = Vec::new();
+    {
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut rng = rand::thread_rng();
+        for (path, size) in zip(&SOURCE_FILES, &FILE_SIZES) {
+            let mut file = File::create(path).unwrap();
+            let mut buffer = vec![0; *size];
+            for f in buffer.as_mut_slice() {
+                *f = rng.gen::<u8>();
+            }
+            file.write_all(&buffer).unwrap();
+            concatenated.extend(buffer);
+        }
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create sender
+    let sender_brk = Arc::new(AtomicBool::new(false));
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILES[0]),
+        append_files: SOURCE_FILES[1..].iter().map(|path| String::from(*path)).collect(),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 0,
+        ..sender::config::Config::new()
+    };
+    let st= sender::breakable_logic(sc, sender_brk);
+
+    // wait for sender and kill receiver afterwards
+    st.join().unwrap().unwrap();
+
+    // the single output is the concatenation of the files
+    {
+        let mut directory_read = read_dir(TARGET_DIR).unwrap();
+        let received_file = directory_read.next().unwrap().unwrap();
+        assert!(directory_read.next().is_none());
+        let mut received = File::open(received_file.path()).unwrap();
+        let mut received_vector = Vec::new();
+        received.read_to_end(&mut received_vector).unwrap();
+        assert_eq!(received_vector.len(), concatenated.len());
+        for (o, r) in zip(&concatenated, &received_vector) {
+            assert_eq!(o, r);
+        }
+    }
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    for path in SOURCE_FILES.iter() {
+        remove_file(path).unwrap();
+    }
+    remove_dir_all(TARGET_DIR).unwrap();
+}
\ No newline at end of file
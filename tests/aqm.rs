@@ -0,0 +1,85 @@
+use udp_transfer::broker;
+use udp_transfer::broker::BrokerEvent;
+use udp_transfer::broker::config::Aqm;
+use std::collections::HashMap;
+use std::net::UdpSocket;
+use std::str::FromStr;
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+
+#[test]
+fn aqm(){
+    const PACKETS: u16 = 300;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create broker, that delays every packet by millisecond for every waiting packet and drops from 50 waiting ones
+    let (events_sender, events) = channel();
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        drop_rate: 0.0,
+        aqm: Some(Aqm::from_str("1:50:150").unwrap()),
+        events: Some(events_sender),
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+    sleep(Duration::from_millis(100));
+
+    // remember when the packets arrived until nothing comes for a while
+    let receiver = UdpSocket::bind(RECEIVED_ADDR).unwrap();
+    receiver.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+    let counter = thread::spawn(move || {
+        let mut buffer = vec![0; 16];
+        let mut arrivals = HashMap::new();
+        while let Ok((size, _)) = receiver.recv_from(&mut buffer) {
+            assert_eq!(size, 2);
+            arrivals.insert(u16::from_be_bytes([buffer[0], buffer[1]]), Instant::now());
+        }
+        arrivals
+    });
+
+    // send the burst of packets through the broker
+    let sender = UdpSocket::bind(SENDER_ADDR).unwrap();
+    let mut sent_at = Vec::new();
+    for i in 0..PACKETS {
+        sent_at.push(Instant::now());
+        sender.send_to(&i.to_be_bytes(), BROKER_SEND_PART).unwrap();
+        if i % 50 == 0 {
+            sleep(Duration::from_millis(1));
+        }
+    }
+    let arrivals = counter.join().unwrap();
+
+    // end broker
+    broker_brk.store(true, Ordering::SeqCst);
+    bt.join().unwrap();
+
+    // the packets behind the longer queue waited longer
+    let latency = |seqs: std::ops::Range<u16>| {
+        let delivered = seqs.filter_map(|seq| arrivals.get(&seq).map(|arrival| *arrival - sent_at[seq as usize])).collect::<Vec<_>>();
+        delivered.iter().sum::<Duration>() / delivered.len() as u32
+    };
+    let front = latency(0..10);
+    let back = latency(30..40);
+    assert!(back > front + Duration::from_millis(10), "latency {:?} at the front and {:?} at the back", front, back);
+
+    // no packet was dropped before the queue reached the threshold, some were after it
+    assert!((0..40).all(|seq| arrivals.contains_key(&seq)));
+    let drops = events.try_iter().filter_map(|event| match event {
+        BrokerEvent::AqmDropped(_, depth) => Some(depth),
+        _ => None,
+    }).collect::<Vec<_>>();
+    assert!(!drops.is_empty());
+    assert!(drops.iter().all(|depth| *depth >= 50), "{:?}", drops);
+    assert_eq!(arrivals.len() + drops.len(), PACKETS as usize);
+}
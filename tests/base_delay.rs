@@ -0,0 +1,54 @@
+use udp_transfer::broker;
+use std::net::UdpSocket;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn base_delay(){
+    const PACKETS: u16 = 20;
+    const BASE_DELAY: u32 = 20;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create broker, that adds small jitter on top of the fixed delay
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        delay_mean: 0.0,
+        delay_std: 5.0,
+        base_delay_ms: BASE_DELAY,
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+    sleep(Duration::from_millis(100));
+
+    // send packets one by one through the broker
+    let receiver = UdpSocket::bind(RECEIVED_ADDR).unwrap();
+    receiver.set_read_timeout(Some(Duration::from_millis(1000))).unwrap();
+    let sender = UdpSocket::bind(SENDER_ADDR).unwrap();
+    let mut buffer = vec![0; 16];
+    let mut latencies = Vec::new();
+    for i in 0..PACKETS {
+        let start = Instant::now();
+        sender.send_to(&i.to_be_bytes(), BROKER_SEND_PART).unwrap();
+        receiver.recv_from(&mut buffer).unwrap();
+        latencies.push(start.elapsed());
+    }
+
+    // end broker
+    broker_brk.store(true, Ordering::SeqCst);
+    bt.join().unwrap();
+
+    // no packet is faster than the floor and the fastest one waits just for it
+    let fastest = latencies.iter().min().unwrap();
+    assert!(*fastest >= Duration::from_millis(BASE_DELAY as u64), "fastest packet {:?}", fastest);
+    assert!(*fastest < Duration::from_millis(BASE_DELAY as u64 + 5), "fastest packet {:?}", fastest);
+}
@@ -0,0 +1,91 @@
+use udp_transfer::{receiver, sender};
+use udp_transfer::receiver::ReceiverEvent;
+use std::fs::{File, read, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+
+#[test]
+fn batched_receive(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 1024 * 1024;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create file and directory
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create receiver
+    let (events_sender, events) = channel();
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 64,
+        min_checksum: 16,
+        timeout: 5000,
+        events: Some(events_sender),
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create sender, that sends big windows at once
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 64,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 16,
+        ..sender::config::Config::new()
+    };
+    sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // every datagram was read, with the batched reads by fewer system calls
+    let reads: Vec<usize> = events.try_iter().filter_map(|event| match event {
+        ReceiverEvent::DatagramsRead(count) => Some(count),
+        _ => None,
+    }).collect();
+    let datagrams: usize = reads.iter().sum();
+    assert!(reads.iter().all(|count| *count >= 1));
+    assert!(datagrams > FILE_SIZE / 1500);
+    if cfg!(all(feature = "batched", target_os = "linux")) {
+        assert!(reads.len() < datagrams, "{} datagrams by {} reads", datagrams, reads.len());
+    }
+    else {
+        assert_eq!(reads.len(), datagrams);
+    }
+
+    // compare files
+    {
+        let received_file = read_dir(TARGET_DIR).unwrap().next().unwrap().unwrap();
+        assert!(read(received_file.path()).unwrap() == content);
+    }
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -35,7 +35,8 @@ fn big_files(){
         max_packet_size: 1500,
         max_window_size: 15,
         min_checksum: 0,
-        timeout: 5000
+        timeout: 5000,
+        ..receiver::config::Config::new()
     };
     let rt = receiver::breakable_logic(rc, receiver_brk.clone());
 
@@ -50,7 +51,8 @@ fn big_files(){
         window_size: 15,
         timeout: 100,
         repetition: 10,
-        checksum_size: 0
+        checksum_size: 0,
+        ..sender::config::Config::new()
     };
     let st= sender::breakable_logic(sc, sender_brk);
 
@@ -0,0 +1,65 @@
+use std::net::UdpSocket;
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use udp_transfer::broker;
+
+#[test]
+fn broker_control(){
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+    const CONTROL_ADDR: &str = "127.0.0.1:3104";
+    const PACKETS: usize = 50;
+
+    // create broker, that forwards everything
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        drop_rate: 0.0,
+        read_timeout: 50,
+        control_bindaddr: Some(String::from(CONTROL_ADDR)),
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+    std::thread::sleep(Duration::from_millis(100));
+
+    let sender = UdpSocket::bind(SENDER_ADDR).unwrap();
+    let receiver = UdpSocket::bind(RECEIVED_ADDR).unwrap();
+    receiver.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+    let forwarded = || {
+        for i in 0..PACKETS {
+            sender.send_to(&[i as u8; 16], BROKER_SEND_PART).unwrap();
+        }
+        let mut buffer = [0; 64];
+        let mut count = 0;
+        while receiver.recv_from(&mut buffer).is_ok() {
+            count += 1;
+        }
+        return count;
+    };
+    let control = UdpSocket::bind("127.0.0.1:0").unwrap();
+    control.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    let command = |text: &str| {
+        control.send_to(text.as_bytes(), CONTROL_ADDR).unwrap();
+        let mut buffer = [0; 256];
+        let (size, _) = control.recv_from(&mut buffer).unwrap();
+        return String::from_utf8_lossy(&buffer[..size]).into_owned();
+    };
+
+    // all the packets pass until the drop rate is changed
+    assert_eq!(forwarded(), PACKETS);
+    assert_eq!(command("set drop_rate 1"), "OK");
+    assert_eq!(forwarded(), 0);
+    assert!(command("set drop_rate everything").starts_with("ERR"));
+    assert_eq!(command("set drop_rate 0"), "OK");
+    assert_eq!(forwarded(), PACKETS);
+
+    broker_brk.store(true, Ordering::SeqCst);
+    bt.join().unwrap();
+}
@@ -0,0 +1,125 @@
+use udp_transfer::{receiver, sender, broker};
+use udp_transfer::broker::BrokerEvent;
+use udp_transfer::broker::config::Direction;
+use std::fs::{File, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::{Write, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+
+#[test]
+fn chained_brokers(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 100_000;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const FIRST_BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const FIRST_BROKER_SEND_PART: &str = "127.0.0.1:3103";
+    const SECOND_BROKER_RECV_PART: &str = "127.0.0.1:3108";
+    const SECOND_BROKER_SEND_PART: &str = "127.0.0.1:3107";
+
+    // create file and directory
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVED_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // the first broker delays the packets and marks all of them as congested
+    let mut first = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(FIRST_BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(FIRST_BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        delay_mean: 5.0,
+        delay_std: 2.0,
+        mark_queue_bytes: Some(0),
+        ..broker::config::Config::new()
+    };
+    // the second broker drops the listed part, even when it is marked
+    let (events_sender, events) = channel();
+    let mut second = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(SECOND_BROKER_SEND_PART),
+        receiver_bindaddr: String::from(SECOND_BROKER_RECV_PART),
+        drop_seqs_to_receiver: vec![3],
+        events: Some(events_sender),
+        ..broker::config::Config::new()
+    };
+    first.chain(&mut second);
+    assert_eq!(first.receiver_addr, SECOND_BROKER_SEND_PART);
+    assert_eq!(second.sender_addr, FIRST_BROKER_RECV_PART);
+    assert_eq!(second.receiver_addr, RECEIVED_ADDR);
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let first_thread = broker::breakable_logic(first, broker_brk.clone());
+    let second_thread = broker::breakable_logic(second, broker_brk.clone());
+
+    // create sender
+    let sender_brk = Arc::new(AtomicBool::new(false));
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(FIRST_BROKER_SEND_PART),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 16,
+        ..sender::config::Config::new()
+    };
+    let st= sender::breakable_logic(sc, sender_brk);
+
+    // wait for sender, the answers went back through both brokers
+    st.join().unwrap().unwrap();
+
+    // compare files
+    {
+        let mut directory_read = read_dir(TARGET_DIR).unwrap();
+        let received_file = directory_read.next().unwrap().unwrap();
+        let mut received = Vec::new();
+        File::open(received_file.path()).unwrap().read_to_end(&mut received).unwrap();
+        assert!(received == content);
+    }
+
+    // end receiver and brokers
+    receiver_brk.store(true, Ordering::SeqCst);
+    broker_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+    first_thread.join().unwrap();
+    second_thread.join().unwrap();
+
+    let dropped: Vec<(Direction, u16)> = events.try_iter().filter_map(|event| match event {
+        BrokerEvent::SeqDropped(direction, seq) => Some((direction, seq)),
+        _ => None,
+    }).collect();
+    assert_eq!(dropped, vec![(Direction::ToReceiver, 3)]);
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
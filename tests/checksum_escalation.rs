@@ -0,0 +1,111 @@
+use udp_transfer::{receiver, sender, broker};
+use udp_transfer::sender::SenderEvent;
+use std::fs::{File, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::{Write, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+
+#[test]
+fn checksum_escalation(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 20_000;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create file and directory
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVED_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create broker, that damages many packets
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        delay_mean: 0.0,
+        delay_std: 0.0,
+        drop_rate: 0.0,
+        modify_prob: 0.002,
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+
+    // create sender, that asks for bigger checksum when more than 1% of the answers are damaged
+    let (events_sender, events) = channel();
+    let sender_brk = Arc::new(AtomicBool::new(false));
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 100,
+        send_addr: String::from(BROKER_SEND_PART),
+        window_size: 15,
+        timeout: 100,
+        repetition: 50,
+        checksum_size: 4,
+        checksum_escalation: Some(0.01),
+        events: Some(events_sender),
+        ..sender::config::Config::new()
+    };
+    let st= sender::breakable_logic(sc, sender_brk);
+
+    // wait for sender, the checksum grew during the transfer
+    st.join().unwrap().unwrap();
+    let increases: Vec<u16> = events.try_iter().filter_map(|event| match event {
+        SenderEvent::ChecksumIncreased(size) => Some(size),
+        _ => None,
+    }).collect();
+    assert!(!increases.is_empty());
+    assert!(increases.windows(2).all(|sizes| sizes[0] < sizes[1]), "{:?}", increases);
+    assert!(increases[0] > 4);
+
+    // compare files
+    {
+        let mut directory_read = read_dir(TARGET_DIR).unwrap();
+        let received_file = directory_read.next().unwrap().unwrap();
+        let mut received = Vec::new();
+        File::open(received_file.path()).unwrap().read_to_end(&mut received).unwrap();
+        assert!(received == content);
+    }
+
+    // end receiver and broker
+    receiver_brk.store(true, Ordering::SeqCst);
+    broker_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+    bt.join().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,89 @@
+use udp_transfer::{receiver, sender};
+use udp_transfer::receiver::ReceiverEvent;
+use std::collections::HashMap;
+use std::fs::{File, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn completed_source(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 50_000;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDRS: [&str; 2] = [
+        "127.0.0.1:3401",
+        "127.0.0.1:3402",
+    ];
+
+    // create file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // create receiver, that ends after one second without any packet
+    let (events_sender, events) = channel();
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 5000,
+        events: Some(events_sender),
+        ..receiver::config::Config::new()
+    };
+    let rt = thread::spawn(move || receiver::receive_all(rc, Duration::from_secs(1)));
+
+    // send the file from both addresses
+    let mut sources = HashMap::new();
+    for addr in SENDER_ADDRS.iter() {
+        let sc = sender::config::Config {
+            verbose: false,
+            bind_addr: String::from(*addr),
+            file: String::from(SOURCE_FILE),
+            packet_size: 1500,
+            send_addr: String::from(RECEIVER_ADDR),
+            window_size: 15,
+            timeout: 100,
+            repetition: 10,
+            checksum_size: 16,
+            ..sender::config::Config::new()
+        };
+        let props = sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+        sources.insert(props.id, addr.parse::<SocketAddr>().unwrap());
+    }
+
+    // every completed transfer reports the address its sender used
+    let completed = rt.join().unwrap().unwrap();
+    assert_eq!(completed.len(), 2);
+    for transfer in completed {
+        assert_eq!(transfer.source, sources[&transfer.id]);
+    }
+    let reported: Vec<SocketAddr> = events.try_iter().filter_map(|event| match event {
+        ReceiverEvent::Completed(_, _, source) => Some(source),
+        _ => None,
+    }).collect();
+    let expected: Vec<SocketAddr> = SENDER_ADDRS.iter().map(|addr| addr.parse().unwrap()).collect();
+    assert_eq!(reported, expected);
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
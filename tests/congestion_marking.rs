@@ -0,0 +1,118 @@
+use std::fs::{create_dir_all, File, read_dir, remove_dir_all, remove_file};
+use std::io::{Read, Write};
+use itertools::zip;
+use rand::Rng;
+use udp_transfer::{broker, receiver, sender};
+use udp_transfer::broker::config::DelayModel;
+use udp_transfer::sender::SenderEvent;
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn congestion_marking(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 512 * 1024;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3110";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create 512kB file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVED_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create broker, that marks the packets when more than 4 of them wait in the queue
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        packet_size: 1500,
+        delay_model: DelayModel::QueueFifo,
+        service_time: 1.0,
+        mark_queue_bytes: Some(4 * 1500),
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+
+    // create sender, that reports the congestion
+    let (events_sender, events) = channel();
+    let sender_brk = Arc::new(AtomicBool::new(false));
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(BROKER_SEND_PART),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 0,
+        events: Some(events_sender),
+        ..sender::config::Config::new()
+    };
+    let st= sender::breakable_logic(sc, sender_brk);
+
+    // wait for sender, it reduced the window
+    st.join().unwrap().unwrap();
+    let windows: Vec<u16> = events.into_iter().filter_map(|e| match e {
+        SenderEvent::CongestionReduced(window) => Some(window),
+        _ => None,
+    }).collect();
+    assert!(!windows.is_empty());
+    assert!(windows.iter().all(|window| *window < 15), "{:?}", windows);
+
+    // compare files
+    {
+        let mut original = File::open(SOURCE_FILE).unwrap();
+        let mut orig_vector = vec![0; FILE_SIZE];
+        assert_eq!(original.read(&mut orig_vector).unwrap(), FILE_SIZE);
+        let mut directory_read = read_dir(TARGET_DIR).unwrap();
+        let received_file = directory_read.next().unwrap().unwrap();
+        let path_to_received_file = String::from(received_file.path().to_str().unwrap());
+        let mut received = File::open(path_to_received_file).unwrap();
+        let mut received_vector = vec![0; FILE_SIZE];
+        assert_eq!(received.read(&mut received_vector).unwrap(), FILE_SIZE);
+        for (o, r) in zip(&orig_vector, &received_vector) {
+            assert_eq!(o, r);
+        }
+    }
+
+    // end receiver and broker
+    receiver_brk.store(true, Ordering::SeqCst);
+    broker_brk.store(true, Ordering::SeqCst);
+    bt.join().unwrap();
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
\ No newline at end of file
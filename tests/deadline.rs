@@ -0,0 +1,108 @@
+use udp_transfer::{receiver, sender, broker};
+use udp_transfer::sender::SenderEvent;
+use std::fs::{File, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::{Write, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+use std::time::{Duration, Instant};
+
+#[test]
+fn deadline(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 300_000;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create file and directory
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVED_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create broker with long round trip and mild loss, that keeps the tuned window small
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        delay_mean: 50.0,
+        delay_std: 0.0,
+        drop_rate: 0.01,
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+
+    // create sender, that should send the file in 4 seconds
+    let (events_sender, events) = channel();
+    let sender_brk = Arc::new(AtomicBool::new(false));
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(BROKER_SEND_PART),
+        window_size: 15,
+        timeout: 300,
+        repetition: 20,
+        checksum_size: 16,
+        auto_window: true,
+        deadline: Some(4000),
+        events: Some(events_sender),
+        ..sender::config::Config::new()
+    };
+    let started_at = Instant::now();
+    let st= sender::breakable_logic(sc, sender_brk);
+
+    // wait for sender, it hurried and ended in time
+    st.join().unwrap().unwrap();
+    assert!(started_at.elapsed() < Duration::from_millis(4000), "{:?}", started_at.elapsed());
+    assert!(events.try_iter().any(|event| matches!(event, SenderEvent::BehindSchedule)));
+
+    // compare files
+    {
+        let mut directory_read = read_dir(TARGET_DIR).unwrap();
+        let received_file = directory_read.next().unwrap().unwrap();
+        let mut received = Vec::new();
+        File::open(received_file.path()).unwrap().read_to_end(&mut received).unwrap();
+        assert!(received == content);
+    }
+
+    // end receiver and broker
+    receiver_brk.store(true, Ordering::SeqCst);
+    broker_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+    bt.join().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
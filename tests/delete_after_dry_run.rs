@@ -0,0 +1,66 @@
+use udp_transfer::{receiver, sender};
+use std::fs::{File, read, remove_file};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn delete_after_dry_run(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const FILE_SIZE: usize = 64 * 1024;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create file
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create receiver, that validates the content without storing it
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        dry_run: true,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create sender
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 0,
+        delete_after_verify: true,
+        ..sender::config::Config::new()
+    };
+    sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+
+    // the digest matches, but nothing was stored, so the source is kept
+    assert!(read(SOURCE_FILE).unwrap() == content);
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+}
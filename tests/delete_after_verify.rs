@@ -0,0 +1,105 @@
+use udp_transfer::{receiver, sender};
+use std::fs::{File, read, read_dir, remove_file, remove_dir_all, create_dir_all};
+use std::path::Path;
+use rand::{Rng};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn delete_after_verify(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 64 * 1024;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create file and directory
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create receiver, that accepts parts without checksum
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // the sender flips the last byte of the fifth data packet, so the receiver stores different content
+    let mut sent = 0;
+    let flip_fifth = move |packet: &mut [u8]| {
+        if packet[8] != 0x2 {
+            return;
+        }
+        sent += 1;
+        if sent == 5 {
+            let last = packet.len() - 1;
+            packet[last] ^= 0xFF;
+        }
+    };
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 0,
+        delete_after_verify: true,
+        on_send: Some(Mutex::new(Box::new(flip_fifth))),
+        ..sender::config::Config::new()
+    };
+    sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+
+    // the digest didn't match, so the source is kept
+    assert!(read(SOURCE_FILE).unwrap() == content);
+    remove_dir_all(TARGET_DIR).unwrap();
+    create_dir_all(TARGET_DIR).unwrap();
+
+    // the same transfer without the modification
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 0,
+        delete_after_verify: true,
+        ..sender::config::Config::new()
+    };
+    sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+
+    // the verified source was moved into the receiver
+    assert!(!Path::new(SOURCE_FILE).exists());
+    let received_file = read_dir(TARGET_DIR).unwrap().next().unwrap().unwrap();
+    assert!(read(received_file.path()).unwrap() == content);
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_dir_all(TARGET_DIR).unwrap();
+}
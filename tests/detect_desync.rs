@@ -0,0 +1,121 @@
+use udp_transfer::{receiver, sender, broker};
+use udp_transfer::broker::BrokerEvent;
+use udp_transfer::broker::config::Direction;
+use std::fs::{File, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+use std::thread::sleep;
+use std::time::Duration;
+
+/// Packet of the connection `id` with the `flag` and few bytes of content, without the checksum.
+fn packet(id: u32, flag: u8) -> Vec<u8> {
+    let mut packet = id.to_be_bytes().to_vec();
+    packet.extend_from_slice(&[0, 0, 0, 0, flag, 1, 2, 3]);
+    return packet;
+}
+
+#[test]
+fn detect_desync(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 20_000;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+    const INJECT_ADDR: &str = "127.0.0.1:3401";
+
+    // create file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVED_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create broker, that checks the flags of the connections
+    let (events_sender, events) = channel();
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        delay_mean: 0.0,
+        delay_std: 0.0,
+        drop_rate: 0.0,
+        detect_desync: true,
+        events: Some(events_sender),
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+
+    // the regular transfer follows the protocol
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(BROKER_SEND_PART),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 16,
+        ..sender::config::Config::new()
+    };
+    sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+
+    // inject data of unknown connection and end of connection without any data
+    let socket = UdpSocket::bind(INJECT_ADDR).unwrap();
+    socket.send_to(&packet(7, 0x2), BROKER_SEND_PART).unwrap();
+    sleep(Duration::from_millis(50));
+    socket.send_to(&packet(9, 0x1), BROKER_RECV_PART).unwrap();
+    sleep(Duration::from_millis(50));
+    socket.send_to(&packet(9, 0x8), BROKER_SEND_PART).unwrap();
+    sleep(Duration::from_millis(200));
+
+    // end receiver and broker
+    receiver_brk.store(true, Ordering::SeqCst);
+    broker_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+    bt.join().unwrap();
+
+    // only the injected sequences were reported
+    let desyncs: Vec<(Direction, u32, String)> = events.try_iter().filter_map(|event| match event {
+        BrokerEvent::Desync(direction, id, description) => Some((direction, id, description)),
+        _ => None,
+    }).collect();
+    assert_eq!(desyncs, vec![
+        (Direction::ToReceiver, 7, String::from("Data before init")),
+        (Direction::ToReceiver, 9, String::from("End before any data")),
+    ]);
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,86 @@
+use udp_transfer::{receiver, sender, ErrorReason, TransferError};
+use udp_transfer::receiver::ReceiverEvent;
+use std::fs::{File, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::{self, ErrorKind, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::channel;
+
+#[test]
+fn disk_full(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 200_000;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const WRITES: usize = 20;
+
+    // create file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // create receiver, whose disk gets full in the middle of the transfer
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counted = attempts.clone();
+    let (events_sender, events) = channel();
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 5000,
+        on_write: Some(Arc::new(move |_, _| {
+            match counted.fetch_add(1, Ordering::SeqCst) < WRITES {
+                true => Ok(()),
+                false => Err(io::Error::from(ErrorKind::StorageFull)),
+            }
+        })),
+        events: Some(events_sender),
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create sender
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 16,
+        ..sender::config::Config::new()
+    };
+    let result = sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap();
+
+    // the sender reports why the receiver closed the connection
+    assert_eq!(result.unwrap_err(), TransferError::ReceiverClosed(ErrorReason::DiskFull));
+    assert!(attempts.load(Ordering::SeqCst) > WRITES);
+
+    // end receiver, the partial file is deleted
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+    let events = events.try_iter().collect::<Vec<ReceiverEvent>>();
+    assert!(events.iter().any(|e| matches!(e, ReceiverEvent::ConnectionClosed(reason) if reason == "disk full")), "{:?}", events);
+    assert_eq!(read_dir(TARGET_DIR).unwrap().count(), 0);
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
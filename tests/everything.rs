@@ -39,7 +39,8 @@ fn everything(){
         max_packet_size: 1000,
         max_window_size: 15,
         min_checksum: 32,
-        timeout: 5000
+        timeout: 5000,
+        ..receiver::config::Config::new()
     };
     let rt = receiver::breakable_logic(rc, receiver_brk.clone());
 
@@ -55,7 +56,8 @@ fn everything(){
         delay_mean: 50.0,
         delay_std: 10.0,
         drop_rate: 0.01,
-        modify_prob: 0.0001
+        modify_prob: 0.0001,
+        ..broker::config::Config::new()
     };
     let bt = broker::breakable_logic(bc, broker_brk.clone());
 
@@ -71,6 +73,7 @@ fn everything(){
         timeout: 100,
         repetition: 100,
         checksum_size: 16,
+        ..sender::config::Config::new()
     };
     let st= sender::breakable_logic(sc, sender_brk);
 
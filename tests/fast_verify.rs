@@ -0,0 +1,92 @@
+use udp_transfer::{receiver, sender, TransferError};
+use udp_transfer::receiver::ReceiverEvent;
+use std::fs::{File, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::channel;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn fast_verify(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 64 * 1024;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // create receiver, that accepts parts without checksum
+    let (events, observed) = channel();
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        events: Some(events),
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create sender, that flips the last byte of the fifth data packet it sends
+    let mut sent = 0;
+    let flip_fifth = move |packet: &mut [u8]| {
+        if packet[8] != 0x2 {
+            return;
+        }
+        sent += 1;
+        if sent == 5 {
+            let last = packet.len() - 1;
+            packet[last] ^= 0xFF;
+        }
+    };
+    let sender_brk = Arc::new(AtomicBool::new(false));
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 16,
+        fast_verify: true,
+        on_send: Some(Mutex::new(Box::new(flip_fifth))),
+        ..sender::config::Config::new()
+    };
+    let st= sender::breakable_logic(sc, sender_brk);
+
+    // the modified packet passed without checksum, only the digest at the end revealed it
+    let e = st.join().unwrap().unwrap_err();
+    assert!(matches!(&e, TransferError::Failed(reason) if reason.contains("digest")), "{}", e);
+    let rejected = observed.try_iter()
+        .filter(|event| matches!(event, ReceiverEvent::CorruptedDropped))
+        .count();
+    assert_eq!(rejected, 0);
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
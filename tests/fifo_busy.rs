@@ -0,0 +1,96 @@
+#![cfg(unix)]
+use udp_transfer::{receiver, sender, ErrorReason, TransferError};
+use udp_transfer::sender::{Control, SenderEvent};
+use std::fs::{File, remove_file};
+use std::process::Command;
+use rand::{Rng};
+use std::io::{Write, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+use std::thread;
+
+#[test]
+fn fifo_busy(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const FIFO: &str = "received_fifo_busy";
+    const FILE_SIZE: usize = 100_000;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: [&str; 2] = ["127.0.0.1:3101", "127.0.0.1:3102"];
+
+    // create file and the pipe
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_file(FIFO) { _ => {}};
+        assert!(Command::new("mkfifo").arg(FIFO).status().unwrap().success());
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // the reader consumes the pipe until the receiver closes it
+    let reader = thread::spawn(|| {
+        let mut received = Vec::new();
+        File::open(FIFO).unwrap().read_to_end(&mut received).unwrap();
+        received
+    });
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        fifo: Some(String::from(FIFO)),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    let sender_config = |index: usize| sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR[index]),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 0,
+        ..sender::config::Config::new()
+    };
+
+    // the first sender holds the connection before sending the data
+    let (events_sender, events) = channel();
+    let control = Control::new();
+    control.pause();
+    let first = sender::controlled_logic(sender::config::Config {
+        events: Some(events_sender),
+        ..sender_config(0)
+    }, control.clone());
+    while !matches!(events.recv().unwrap(), SenderEvent::Paused) {}
+
+    // the second sender is refused, the content of both can't be mixed in the pipe
+    let result = sender::breakable_logic(sender_config(1), Arc::new(AtomicBool::new(false))).join().unwrap();
+    assert!(matches!(result, Err(TransferError::ReceiverClosed(ErrorReason::Busy))), "{:?}", result);
+
+    // the first sender writes the whole content for the reader
+    control.resume();
+    first.join().unwrap().unwrap();
+    let received = reader.join().unwrap();
+    assert_eq!(received.len(), FILE_SIZE);
+    assert!(received == content);
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    remove_file(SOURCE_FILE).unwrap();
+    remove_file(FIFO).unwrap();
+}
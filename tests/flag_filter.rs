@@ -0,0 +1,107 @@
+use udp_transfer::{receiver, sender, broker, TransferError};
+use udp_transfer::broker::config::FlagFilter;
+use udp_transfer::packet::Flag;
+use std::fs::{File, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn flag_filter(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 20_000;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVED_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create broker, that drops all the end packets
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        drop_rate: 0.0,
+        flag_filter: Some(FlagFilter::from_str("deny:end").unwrap()),
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+
+    // create sender
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(BROKER_SEND_PART),
+        window_size: 15,
+        timeout: 100,
+        repetition: 5,
+        checksum_size: 16,
+        ..sender::config::Config::new()
+    };
+    let st = sender::breakable_logic(sc, Arc::new(AtomicBool::new(false)));
+
+    // the data passed, but the sender never got the end packet through
+    let e = st.join().unwrap().unwrap_err();
+    assert!(matches!(&e, TransferError::Failed(reason) if reason.contains("End packet timeout")), "{}", e);
+
+    // end receiver and broker
+    receiver_brk.store(true, Ordering::SeqCst);
+    broker_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+    bt.join().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
+
+#[test]
+fn data_filter_covers_padded(){
+    // the padded data packet is the data packet too
+    let deny = FlagFilter::from_str("deny:data").unwrap();
+    assert!(!deny.forwards(&Flag::Data));
+    assert!(!deny.forwards(&Flag::Padded));
+    assert!(deny.forwards(&Flag::End));
+    let allow = FlagFilter::from_str("allow:data,end").unwrap();
+    assert!(allow.forwards(&Flag::Padded));
+    assert!(!allow.forwards(&Flag::Ack));
+    // only the padded data are selected by their own flag
+    let padded = FlagFilter::from_str("deny:padded").unwrap();
+    assert!(padded.forwards(&Flag::Data));
+    assert!(!padded.forwards(&Flag::Padded));
+}
@@ -37,7 +37,8 @@ fn forced_smaller_size(){
         max_packet_size: 1500,
         max_window_size: 15,
         min_checksum: 0,
-        timeout: 5000
+        timeout: 5000,
+        ..receiver::config::Config::new()
     };
     let rt = receiver::breakable_logic(rc, receiver_brk.clone());
 
@@ -53,7 +54,8 @@ fn forced_smaller_size(){
         delay_mean: 0.0,
         delay_std: 0.0,
         drop_rate: 0.0,
-        modify_prob: 0.0
+        modify_prob: 0.0,
+        ..broker::config::Config::new()
     };
     let bt = broker::breakable_logic(bc, broker_brk.clone());
 
@@ -68,7 +70,8 @@ fn forced_smaller_size(){
         window_size: 15,
         timeout: 100,
         repetition: 10,
-        checksum_size: 0
+        checksum_size: 0,
+        ..sender::config::Config::new()
     };
     let st= sender::breakable_logic(sc, sender_brk);
 
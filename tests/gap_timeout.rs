@@ -0,0 +1,107 @@
+use udp_transfer::{receiver, sender, broker};
+use std::fs::{File, read, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[test]
+fn gap_timeout(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 30_000;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+    const SENDER_TIMEOUT: u32 = 1000;
+
+    // create file
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // transfer the file without and with the repeated acknowledges of the stuck window
+    let mut durations = Vec::new();
+    for gap_timeout in [None, Some(50)].iter() {
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+
+        // create receiver
+        let receiver_brk = Arc::new(AtomicBool::new(false));
+        let rc = receiver::config::Config {
+            verbose: false,
+            bindaddr: String::from(RECEIVED_ADDR),
+            directory: String::from(TARGET_DIR),
+            max_packet_size: 1500,
+            max_window_size: 15,
+            min_checksum: 0,
+            timeout: 5000,
+            gap_timeout: *gap_timeout,
+            ..receiver::config::Config::new()
+        };
+        let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+        // create broker, that drops the second part once
+        let broker_brk = Arc::new(AtomicBool::new(false));
+        let bc = broker::config::Config {
+            verbose: false,
+            sender_bindaddr: String::from(BROKER_SEND_PART),
+            sender_addr: String::from(SENDER_ADDR),
+            receiver_bindaddr: String::from(BROKER_RECV_PART),
+            receiver_addr: String::from(RECEIVED_ADDR),
+            delay_mean: 0.0,
+            delay_std: 0.0,
+            drop_rate: 0.0,
+            drop_seqs_to_receiver: vec![1],
+            ..broker::config::Config::new()
+        };
+        let bt = broker::breakable_logic(bc, broker_brk.clone());
+
+        // create sender, that sends the part again after three repeated acknowledges
+        let sc = sender::config::Config {
+            verbose: false,
+            bind_addr: String::from(SENDER_ADDR),
+            file: String::from(SOURCE_FILE),
+            packet_size: 1500,
+            send_addr: String::from(BROKER_SEND_PART),
+            window_size: 2,
+            timeout: SENDER_TIMEOUT,
+            repetition: 10,
+            checksum_size: 16,
+            fast_retransmit: Some(3),
+            ..sender::config::Config::new()
+        };
+        let started_at = Instant::now();
+        sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+        durations.push(started_at.elapsed());
+
+        // compare files
+        {
+            let received_file = read_dir(TARGET_DIR).unwrap().next().unwrap().unwrap();
+            assert!(read(received_file.path()).unwrap() == content);
+        }
+
+        // end receiver and broker
+        receiver_brk.store(true, Ordering::SeqCst);
+        broker_brk.store(true, Ordering::SeqCst);
+        rt.join().unwrap().unwrap();
+        bt.join().unwrap();
+    }
+
+    // only the sender timeout recovers the lost part, the repeated acknowledges recover it sooner
+    assert!(durations[0] >= Duration::from_millis(SENDER_TIMEOUT as u64));
+    assert!(durations[1] < Duration::from_millis(SENDER_TIMEOUT as u64));
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
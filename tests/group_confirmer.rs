@@ -0,0 +1,118 @@
+use udp_transfer::{receiver, sender};
+use udp_transfer::packet::{DataPacket, ErrorPacket, Flag, Packet, PacketHeader, ToBin};
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::fs::{File, read, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn group_confirmer(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 200_000;
+    const RECEIVER_BIND: &str = "0.0.0.0:3100";
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const MEMBER_ADDR: &str = "127.0.0.1:3102";
+    const MULTICAST_GROUP: &str = "239.255.0.1";
+    const MULTICAST_ADDR: &str = "239.255.0.1:3100";
+
+    // create file and directory
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create the confirming receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_BIND),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        multicast_group: Some(String::from(MULTICAST_GROUP)),
+        multicast_interface: String::from("127.0.0.1"),
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // another member of the group answers the data by the acknowledge ahead and by the error
+    let member_brk = Arc::new(AtomicBool::new(false));
+    let member_end = member_brk.clone();
+    let member = thread::spawn(move || {
+        let group = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP)).unwrap();
+        group.set_reuse_address(true).unwrap();
+        group.bind(&SockAddr::from(SocketAddrV4::from_str(RECEIVER_BIND).unwrap())).unwrap();
+        let group = UdpSocket::from(group);
+        group.join_multicast_v4(&Ipv4Addr::from_str(MULTICAST_GROUP).unwrap(), &Ipv4Addr::LOCALHOST).unwrap();
+        group.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+        let answers = UdpSocket::bind(MEMBER_ADDR).unwrap();
+        let mut buffer = vec![0; 2000];
+        let mut answered = 0;
+        while !member_end.load(Ordering::SeqCst) {
+            let (size, from) = match group.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(_) => continue,
+            };
+            let header = match PacketHeader::from_bin(&buffer[..size]) {
+                Ok(header) if header.flag == Flag::Data => header,
+                _ => continue,
+            };
+            let ack = Packet::from(DataPacket::new_receiver(header.id, header.seq, header.seq.wrapping_add(5)));
+            let error = Packet::from(ErrorPacket::unknown_connection(header.id));
+            for packet in [ack, error].iter() {
+                let size = packet.to_bin_buff(&mut buffer, 0);
+                answers.send_to(&buffer[..size], from).unwrap();
+            }
+            answered += 1;
+        }
+        answered
+    });
+    thread::sleep(Duration::from_millis(100));
+
+    // create sender
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(MULTICAST_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 0,
+        multicast_ttl: Some(1),
+        confirmer_addr: Some(String::from(RECEIVER_ADDR)),
+        ..sender::config::Config::new()
+    };
+    sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+
+    // the answers of the other member were ignored
+    member_brk.store(true, Ordering::SeqCst);
+    assert!(member.join().unwrap() > 0);
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+    let received_file = read_dir(TARGET_DIR).unwrap().next().unwrap().unwrap();
+    assert!(read(received_file.path()).unwrap() == content);
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,71 @@
+use std::fs::{create_dir_all, File, read_dir, remove_dir_all, remove_file};
+use std::io::{Read, Write};
+use itertools::zip;
+use rand::Rng;
+use udp_transfer::testing::Harness;
+
+#[test]
+fn harness_passthrough(){
+    // the names are not shared with other tests, so they can run concurrently
+    const SOURCE_FILE: &str = "harness_passthrough.txt";
+    const TARGET_DIR: &str = "received_harness_passthrough";
+    const FILE_SIZE: usize = 2 * 1024 * 1024;
+
+    // create 2MB file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // wire sender, broker, and receiver on the ports assigned by the system
+    let harness = Harness::new();
+    let rc = udp_transfer::receiver::config::Config {
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        ..harness.receiver_config(TARGET_DIR)
+    };
+    let bc = udp_transfer::broker::config::Config {
+        packet_size: 1500,
+        ..harness.broker_config()
+    };
+    let sc = udp_transfer::sender::config::Config {
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 0,
+        ..harness.sender_config()
+    };
+    harness.start(sc, bc, rc).finish().unwrap();
+
+    // compare files
+    {
+        let mut original = File::open(SOURCE_FILE).unwrap();
+        let mut orig_vector = vec![0; FILE_SIZE];
+        assert_eq!(original.read(&mut orig_vector).unwrap(), FILE_SIZE);
+        let mut directory_read = read_dir(TARGET_DIR).unwrap();
+        let received_file = directory_read.next().unwrap().unwrap();
+        let path_to_received_file = String::from(received_file.path().to_str().unwrap());
+        let mut received = File::open(path_to_received_file).unwrap();
+        let mut received_vector = vec![0; FILE_SIZE];
+        assert_eq!(received.read(&mut received_vector).unwrap(), FILE_SIZE);
+        for (o, r) in zip(&orig_vector, &received_vector) {
+            assert_eq!(o, r);
+        }
+    }
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
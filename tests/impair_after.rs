@@ -0,0 +1,52 @@
+use udp_transfer::broker;
+use std::net::UdpSocket;
+use std::thread::sleep;
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn impair_after(){
+    const PACKETS: u16 = 10;
+    const CLEAN_PACKETS: u64 = 4;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create broker, that drops everything after the first packets
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        drop_rate: 1.0,
+        impair_after_packets: CLEAN_PACKETS,
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+    sleep(Duration::from_millis(100));
+
+    // send numbered packets through the broker
+    let receiver = UdpSocket::bind(RECEIVED_ADDR).unwrap();
+    receiver.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+    let sender = UdpSocket::bind(SENDER_ADDR).unwrap();
+    for i in 0..PACKETS {
+        sender.send_to(&i.to_be_bytes(), BROKER_SEND_PART).unwrap();
+    }
+    let mut buffer = vec![0; 16];
+    let mut received = Vec::new();
+    while let Ok((size, _)) = receiver.recv_from(&mut buffer) {
+        assert_eq!(size, 2);
+        received.push(u16::from_be_bytes([buffer[0], buffer[1]]));
+    }
+
+    // end broker
+    broker_brk.store(true, Ordering::SeqCst);
+    bt.join().unwrap();
+
+    // only the packets before the warmup passed
+    assert_eq!(received, (0..CLEAN_PACKETS as u16).collect::<Vec<u16>>());
+}
@@ -0,0 +1,65 @@
+use udp_transfer::broker;
+use udp_transfer::broker::config::ImpairmentProfile;
+use std::net::UdpSocket;
+use std::str::FromStr;
+use std::thread::{self, sleep};
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn impairment_profiles(){
+    const PACKETS: u16 = 2000;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create broker, that is clean three times more often than it drops everything
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        drop_rate: 0.0,
+        profiles: vec![
+            ImpairmentProfile::from_str("clean:3:0:0:0:0").unwrap(),
+            ImpairmentProfile::from_str("lossy:1:1:0:0:0").unwrap(),
+        ],
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+    sleep(Duration::from_millis(100));
+
+    // count the delivered packets until nothing comes for a while
+    let receiver = UdpSocket::bind(RECEIVED_ADDR).unwrap();
+    receiver.set_read_timeout(Some(Duration::from_millis(500))).unwrap();
+    let counter = thread::spawn(move || {
+        let mut buffer = vec![0; 16];
+        let mut received = 0;
+        while receiver.recv_from(&mut buffer).is_ok() {
+            received += 1;
+        }
+        received
+    });
+
+    // send packets through the broker
+    let sender = UdpSocket::bind(SENDER_ADDR).unwrap();
+    for i in 0..PACKETS {
+        sender.send_to(&i.to_be_bytes(), BROKER_SEND_PART).unwrap();
+        if i % 50 == 0 {
+            sleep(Duration::from_millis(1));
+        }
+    }
+    let received = counter.join().unwrap();
+
+    // end broker
+    broker_brk.store(true, Ordering::SeqCst);
+    bt.join().unwrap();
+
+    // about quarter of the packets was dropped by the lossy profile
+    let delivered = received as f32 / PACKETS as f32;
+    assert!(delivered > 0.7 && delivered < 0.8, "{} of {} packets delivered", received, PACKETS);
+}
@@ -0,0 +1,114 @@
+use udp_transfer::{receiver, sender, broker};
+use udp_transfer::receiver::ReceiverEvent;
+use std::collections::BTreeSet;
+use std::fs::{File, read, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+
+#[test]
+fn max_buffered_parts(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 100_000;
+    const MAX_BUFFERED: usize = 3;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create file and directory
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create receiver, that buffers only few parts
+    let (events_sender, events) = channel();
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVED_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        max_buffered_parts: Some(MAX_BUFFERED),
+        events: Some(events_sender),
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create broker, that reorders the packets
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        delay_mean: 5.0,
+        delay_std: 30.0,
+        drop_rate: 0.0,
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+
+    // create sender
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(BROKER_SEND_PART),
+        window_size: 15,
+        timeout: 100,
+        repetition: 20,
+        checksum_size: 16,
+        ..sender::config::Config::new()
+    };
+    sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+
+    // end receiver and broker
+    receiver_brk.store(true, Ordering::SeqCst);
+    broker_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+    bt.join().unwrap();
+
+    // the parts after the window position never exceed the limit, some parts were dropped because of it
+    let mut buffered = BTreeSet::new();
+    let mut dropped = 0;
+    for event in events.try_iter() {
+        match event {
+            ReceiverEvent::DataStored(seq, _) => {
+                assert!(buffered.len() <= MAX_BUFFERED, "{:?} buffered", buffered);
+                buffered.insert(seq);
+            }
+            ReceiverEvent::WindowAdvanced(position) => buffered = buffered.split_off(&position),
+            ReceiverEvent::BufferFull(_) => dropped += 1,
+            _ => {}
+        };
+    }
+    assert!(dropped > 0);
+
+    // compare files
+    {
+        let received_file = read_dir(TARGET_DIR).unwrap().next().unwrap().unwrap();
+        assert!(read(received_file.path()).unwrap() == content);
+    }
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
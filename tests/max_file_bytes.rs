@@ -0,0 +1,83 @@
+use udp_transfer::{receiver, sender};
+use std::fs::{File, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::{Write, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn max_file_bytes(){
+    const ALLOWED_FILE: &str = "somefile.txt";
+    const TOO_BIG_FILE: &str = "otherfile.txt";
+    const TARGET_DIR: &str = "received";
+    const MAX_FILE_BYTES: usize = 100 * 1024;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create file exactly at the limit, file one byte over the limit and directory
+    let mut rng = rand::thread_rng();
+    let mut content = vec![0; MAX_FILE_BYTES + 1];
+    {
+        match remove_file(ALLOWED_FILE) { _ => {}};
+        match remove_file(TOO_BIG_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        File::create(ALLOWED_FILE).unwrap().write_all(&content[..MAX_FILE_BYTES]).unwrap();
+        File::create(TOO_BIG_FILE).unwrap().write_all(&content).unwrap();
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        max_file_bytes: Some(MAX_FILE_BYTES as u64),
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // send both files
+    let send = |file: &str| {
+        let sc = sender::config::Config {
+            verbose: false,
+            bind_addr: String::from(SENDER_ADDR),
+            file: String::from(file),
+            packet_size: 1500,
+            send_addr: String::from(RECEIVER_ADDR),
+            window_size: 15,
+            timeout: 100,
+            repetition: 10,
+            checksum_size: 0,
+            ..sender::config::Config::new()
+        };
+        sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap()
+    };
+    send(ALLOWED_FILE).unwrap();
+    assert!(send(TOO_BIG_FILE).is_err());
+
+    // only the allowed file is stored
+    {
+        let files = read_dir(TARGET_DIR).unwrap().map(|f| f.unwrap().path()).collect::<Vec<_>>();
+        assert_eq!(files.len(), 1);
+        let mut received = Vec::new();
+        File::open(&files[0]).unwrap().read_to_end(&mut received).unwrap();
+        assert_eq!(received, &content[..MAX_FILE_BYTES]);
+    }
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_file(ALLOWED_FILE).unwrap();
+    remove_file(TOO_BIG_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
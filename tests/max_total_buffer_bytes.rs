@@ -0,0 +1,128 @@
+use udp_transfer::{receiver, sender};
+use udp_transfer::receiver::ReceiverEvent;
+use std::fs::{File, read, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+use std::thread::{self, sleep};
+use std::time::Duration;
+
+#[test]
+fn max_total_buffer_bytes(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 100_000;
+    const MAX_TOTAL: usize = 30_000;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDRS: [&str; 4] = [
+        "127.0.0.1:3401",
+        "127.0.0.1:3402",
+        "127.0.0.1:3403",
+        "127.0.0.1:3404",
+    ];
+
+    // create file and directory
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create receiver, that bounds the memory of all the connections
+    let (events_sender, events) = channel();
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let status = Arc::new(Mutex::new(Vec::new()));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 5000,
+        max_total_buffer_bytes: Some(MAX_TOTAL),
+        events: Some(events_sender),
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::monitored_logic(rc, receiver_brk.clone(), status.clone());
+
+    // watch the memory of the connections
+    let watch_brk = Arc::new(AtomicBool::new(false));
+    let watch = {
+        let watch_brk = watch_brk.clone();
+        thread::spawn(move || {
+            let mut most_buffered = 0;
+            while !watch_brk.load(Ordering::SeqCst) {
+                let buffered = status.lock().unwrap().iter().map(|snapshot| snapshot.buffered_bytes).sum::<usize>();
+                most_buffered = usize::max(most_buffered, buffered);
+                sleep(Duration::from_millis(1));
+            }
+            most_buffered
+        })
+    };
+
+    // create senders, that damage the first part the first time, so the receivers buffer the rest of the window
+    let senders_threads = SENDER_ADDRS.iter().map(|addr| {
+        let mut damaged = false;
+        let damage_first = move |packet: &mut [u8]| {
+            if packet[8] == 0x2 && packet[4..6] == [0, 0] && !damaged {
+                damaged = true;
+                let last = packet.len() - 1;
+                packet[last] ^= 0xFF;
+            }
+        };
+        let sc = sender::config::Config {
+            verbose: false,
+            bind_addr: String::from(*addr),
+            file: String::from(SOURCE_FILE),
+            packet_size: 1500,
+            send_addr: String::from(RECEIVER_ADDR),
+            window_size: 15,
+            timeout: 200,
+            repetition: 10,
+            checksum_size: 16,
+            on_send: Some(Mutex::new(Box::new(damage_first))),
+            ..sender::config::Config::new()
+        };
+        sender::breakable_logic(sc, Arc::new(AtomicBool::new(false)))
+    }).collect::<Vec<_>>();
+    // the senders of the closed connections fail
+    for thread in senders_threads {
+        let _ = thread.join().unwrap();
+    }
+
+    // end receiver
+    watch_brk.store(true, Ordering::SeqCst);
+    let most_buffered = watch.join().unwrap();
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // the connections never buffered more than allowed together, some data were refused or connections closed for it
+    assert!(most_buffered > 0);
+    assert!(most_buffered <= MAX_TOTAL, "{}b buffered", most_buffered);
+    let events = events.try_iter().collect::<Vec<_>>();
+    assert!(events.iter().any(|event| match event {
+        ReceiverEvent::BufferFull(_) => true,
+        ReceiverEvent::ConnectionClosed(reason) => reason == "memory limit",
+        _ => false,
+    }));
+    // the completed files are whole
+    for event in events {
+        if let ReceiverEvent::Completed(path, _, _) = event {
+            assert!(read(path).unwrap() == content);
+        }
+    }
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,81 @@
+#![cfg(unix)]
+use udp_transfer::{receiver, sender};
+use std::fs::{File, metadata, read, remove_file, remove_dir_all, create_dir_all, set_permissions, Permissions};
+use rand::{Rng};
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn metadata_transfer(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 20_000;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const MODE: u32 = 0o640;
+
+    // create file with the permissions and modification time in the past
+    let mut content = vec![0; FILE_SIZE];
+    let modified = UNIX_EPOCH + Duration::new(1_000_000_000, 123_456_789);
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+        file.set_modified(modified).unwrap();
+        set_permissions(SOURCE_FILE, Permissions::from_mode(MODE)).unwrap();
+    }
+
+    // create receiver
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = thread::spawn(move || receiver::receive_all(rc, Duration::from_secs(1)));
+
+    // create sender, that sends the metadata
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 16,
+        send_metadata: true,
+        ..sender::config::Config::new()
+    };
+    sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+
+    // the received file has the same content, permissions and modification time
+    let completed = rt.join().unwrap().unwrap();
+    assert_eq!(completed.len(), 1);
+    assert_eq!(completed[0].original_path.as_deref(), Some(SOURCE_FILE));
+    assert_eq!(Path::new(&completed[0].path), Path::new(TARGET_DIR).join(SOURCE_FILE));
+    assert!(read(&completed[0].path).unwrap() == content);
+    let received = metadata(&completed[0].path).unwrap();
+    assert_eq!(received.permissions().mode() & 0o777, MODE);
+    assert_eq!(received.modified().unwrap(), modified);
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
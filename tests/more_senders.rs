@@ -41,7 +41,8 @@ fn more_senders(){
         max_packet_size: 1500,
         max_window_size: 15,
         min_checksum: 0,
-        timeout: 5000
+        timeout: 5000,
+        ..receiver::config::Config::new()
     };
     let rt = receiver::breakable_logic(rc, receiver_brk.clone());
 
@@ -57,7 +58,8 @@ fn more_senders(){
             window_size: 15,
             timeout: 100,
             repetition: 10,
-            checksum_size: 0
+            checksum_size: 0,
+            ..sender::config::Config::new()
         };
         sender::breakable_logic(sc, sender_brk)
     }).collect::<Vec<_>>();
@@ -0,0 +1,75 @@
+use udp_transfer::{receiver, sender, broker};
+use std::fs::{remove_dir_all, create_dir_all};
+use std::net::UdpSocket;
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn mtu_probe(){
+    const TARGET_DIR: &str = "received";
+    const BROKER_MTU: u32 = 700;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create directory
+    {
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVED_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create broker
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        packet_size: BROKER_MTU,
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+
+    // probe the packet size
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        packet_size: 1500,
+        send_addr: String::from(BROKER_SEND_PART),
+        timeout: 100,
+        checksum_size: 16,
+        mtu_probe: true,
+        mtu_floor: 100,
+        ..sender::config::Config::new()
+    };
+    let socket = UdpSocket::bind(sc.bind_addr()).unwrap();
+    socket.set_read_timeout(Some(Duration::from_millis(sc.timeout as u64))).unwrap();
+    let packet_size = sender::probe_packet_size(&sc, &socket, sc.send_addr(), Arc::new(AtomicBool::new(false)));
+    assert_eq!(packet_size as u32, BROKER_MTU);
+
+    // end receiver and broker
+    receiver_brk.store(true, Ordering::SeqCst);
+    broker_brk.store(true, Ordering::SeqCst);
+    bt.join().unwrap();
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,92 @@
+use udp_transfer::{receiver, sender};
+use std::fs::{File, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::{Write, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn multi_bind(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 1024 * 1024;
+    const RECEIVER_ADDR: [&str; 2] = [
+        "127.0.0.1:3100",
+        "127.0.0.1:3102",
+    ];
+    const SENDER_ADDR: [&str; 2] = [
+        "127.0.0.1:3101",
+        "127.0.0.1:3103",
+    ];
+
+    // create 1MB file and directory
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create receiver bound to both the addresses
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR[0]),
+        next_bindaddrs: vec![String::from(RECEIVER_ADDR[1])],
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create senders, every one of them sending to the other address at the same time
+    let senders_threads = SENDER_ADDR.iter().zip(RECEIVER_ADDR.iter()).map(|(bind_addr, send_addr)| {
+        let sender_brk = Arc::new(AtomicBool::new(false));
+        let sc = sender::config::Config {
+            verbose: false,
+            bind_addr: String::from(*bind_addr),
+            file: String::from(SOURCE_FILE),
+            packet_size: 1500,
+            send_addr: String::from(*send_addr),
+            window_size: 15,
+            timeout: 100,
+            repetition: 10,
+            checksum_size: 16,
+            ..sender::config::Config::new()
+        };
+        sender::breakable_logic(sc, sender_brk)
+    }).collect::<Vec<_>>();
+
+    // wait for senders
+    for thread in senders_threads {
+        thread.join().unwrap().unwrap();
+    }
+
+    // compare files
+    {
+        let received_files = read_dir(TARGET_DIR).unwrap().map(|f| f.unwrap().path()).collect::<Vec<_>>();
+        assert_eq!(received_files.len(), 2);
+        for path in received_files {
+            let mut received = Vec::new();
+            File::open(path).unwrap().read_to_end(&mut received).unwrap();
+            assert!(received == content);
+        }
+    }
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
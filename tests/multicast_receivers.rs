@@ -0,0 +1,87 @@
+use udp_transfer::{receiver, sender};
+use std::fs::{File, read, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn multicast_receivers(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIRS: [&str; 2] = ["received", "received_second"];
+    const FILE_SIZE: usize = 200_000;
+    const RECEIVER_BIND: &str = "0.0.0.0:3100";
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const MULTICAST_GROUP: &str = "239.255.0.1";
+    const MULTICAST_ADDR: &str = "239.255.0.1:3100";
+
+    // create file and directories
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        for dir in TARGET_DIRS.iter() {
+            match remove_dir_all(dir) { _ => {}};
+            create_dir_all(dir).unwrap();
+        }
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create two receivers of the group sharing the port
+    let receivers = TARGET_DIRS.iter().map(|dir| {
+        let rc = receiver::config::Config {
+            verbose: false,
+            bindaddr: String::from(RECEIVER_BIND),
+            directory: String::from(*dir),
+            max_packet_size: 1500,
+            max_window_size: 15,
+            min_checksum: 0,
+            timeout: 5000,
+            multicast_group: Some(String::from(MULTICAST_GROUP)),
+            multicast_interface: String::from("127.0.0.1"),
+            ..receiver::config::Config::new()
+        };
+        thread::spawn(move || receiver::receive_all(rc, Duration::from_secs(1)))
+    }).collect::<Vec<_>>();
+    thread::sleep(Duration::from_millis(100));
+
+    // create sender, only one of the receivers confirms the connection
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(MULTICAST_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 0,
+        multicast_ttl: Some(1),
+        confirmer_addr: Some(String::from(RECEIVER_ADDR)),
+        ..sender::config::Config::new()
+    };
+    sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+
+    // both receivers stored the whole file under the confirmed connection
+    let completed = receivers.into_iter()
+        .map(|rt| rt.join().unwrap().unwrap())
+        .collect::<Vec<_>>();
+    for transfers in &completed {
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].id, completed[0][0].id);
+        assert!(read(&transfers[0].path).unwrap() == content);
+    }
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    for dir in TARGET_DIRS.iter() {
+        remove_dir_all(dir).unwrap();
+    }
+}
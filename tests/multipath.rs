@@ -0,0 +1,155 @@
+use udp_transfer::{receiver, sender, broker};
+use udp_transfer::broker::config::{Multipath, PathAssignment};
+use std::fs::{File, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::{Write, Read};
+use std::net::UdpSocket;
+use std::thread::sleep;
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn packets_are_reordered_between_paths(){
+    const PACKETS: u16 = 60;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3104";
+    const SENDER_ADDR: &str = "127.0.0.1:3105";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3106";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3107";
+
+    // create broker with fast and slow path taken in turns
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        multipath: Some(Multipath {
+            assignment: PathAssignment::RoundRobin,
+            delays: [(0.0, 2.0), (40.0, 10.0)],
+        }),
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+    sleep(Duration::from_millis(100));
+
+    // send numbered packets through the broker
+    let receiver = UdpSocket::bind(RECEIVED_ADDR).unwrap();
+    receiver.set_read_timeout(Some(Duration::from_millis(1000))).unwrap();
+    let sender = UdpSocket::bind(SENDER_ADDR).unwrap();
+    for i in 0..PACKETS {
+        sender.send_to(&i.to_be_bytes(), BROKER_SEND_PART).unwrap();
+        sleep(Duration::from_millis(1));
+    }
+    let mut buffer = vec![0; 16];
+    let order = (0..PACKETS).map(|_| {
+        receiver.recv_from(&mut buffer).unwrap();
+        u16::from_be_bytes([buffer[0], buffer[1]])
+    }).collect::<Vec<u16>>();
+
+    // end broker
+    broker_brk.store(true, Ordering::SeqCst);
+    bt.join().unwrap();
+
+    // every path delivers its packets in order, the fast one overtakes the slow one
+    let fast = order.iter().cloned().filter(|seq| seq % 2 == 0).collect::<Vec<u16>>();
+    let slow = order.iter().cloned().filter(|seq| seq % 2 == 1).collect::<Vec<u16>>();
+    assert_eq!(fast, (0..PACKETS).step_by(2).collect::<Vec<u16>>(), "{:?}", order);
+    assert_eq!(slow, (1..PACKETS).step_by(2).collect::<Vec<u16>>(), "{:?}", order);
+    let first_slow = order.iter().position(|seq| *seq == 1).unwrap();
+    assert!(first_slow > 5, "{:?}", order);
+}
+
+#[test]
+fn multipath(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 1024 * 1024;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create 1MB file and directory
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVED_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 10000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create broker splitting the packets by their header
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        multipath: Some(Multipath {
+            assignment: PathAssignment::Hash,
+            delays: [(5.0, 5.0), (30.0, 10.0)],
+        }),
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+
+    // create sender
+    let sender_brk = Arc::new(AtomicBool::new(false));
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(BROKER_SEND_PART),
+        window_size: 15,
+        timeout: 200,
+        repetition: 100,
+        checksum_size: 16,
+        ..sender::config::Config::new()
+    };
+    let st = sender::breakable_logic(sc, sender_brk);
+
+    // wait for sender
+    st.join().unwrap().unwrap();
+
+    // compare files
+    {
+        let mut directory_read = read_dir(TARGET_DIR).unwrap();
+        let received_file = directory_read.next().unwrap().unwrap();
+        let mut received = Vec::new();
+        File::open(received_file.path()).unwrap().read_to_end(&mut received).unwrap();
+        assert!(received == content);
+    }
+
+    // end receiver and broker
+    receiver_brk.store(true, Ordering::SeqCst);
+    broker_brk.store(true, Ordering::SeqCst);
+    bt.join().unwrap();
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
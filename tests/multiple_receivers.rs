@@ -0,0 +1,97 @@
+use udp_transfer::{receiver, sender};
+use udp_transfer::sender::SenderEvent;
+use std::fs::{File, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::{Write, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+
+#[test]
+fn multiple_receivers(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIRS: [&str; 2] = ["received", "received_second"];
+    const FILE_SIZE: usize = 100_000;
+    const RECEIVER_ADDRS: [&str; 2] = ["127.0.0.1:3100", "127.0.0.1:3102"];
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create file and directories
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        for dir in &TARGET_DIRS {
+            match remove_dir_all(dir) { _ => {}};
+            create_dir_all(dir).unwrap();
+        }
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create receivers
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let receivers = RECEIVER_ADDRS.iter().zip(TARGET_DIRS.iter()).map(|(addr, dir)| {
+        let rc = receiver::config::Config {
+            verbose: false,
+            bindaddr: String::from(*addr),
+            directory: String::from(*dir),
+            max_packet_size: 1500,
+            max_window_size: 15,
+            min_checksum: 0,
+            timeout: 5000,
+            ..receiver::config::Config::new()
+        };
+        receiver::breakable_logic(rc, receiver_brk.clone())
+    }).collect::<Vec<_>>();
+
+    // create sender, that sends the file to both receivers
+    let (events_sender, events) = channel();
+    let sender_brk = Arc::new(AtomicBool::new(false));
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDRS[0]),
+        next_send_addrs: vec![String::from(RECEIVER_ADDRS[1])],
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 16,
+        events: Some(events_sender),
+        ..sender::config::Config::new()
+    };
+    let st= sender::breakable_logic(sc, sender_brk);
+
+    // wait for sender, every receiver got the whole file
+    st.join().unwrap().unwrap();
+    let completed = events.try_iter().filter_map(|event| match event {
+        SenderEvent::Completed(stats) => Some(stats.bytes),
+        _ => None,
+    }).collect::<Vec<u64>>();
+    assert_eq!(completed, vec![FILE_SIZE as u64, FILE_SIZE as u64]);
+
+    // compare files
+    for dir in &TARGET_DIRS {
+        let mut directory_read = read_dir(dir).unwrap();
+        let received_file = directory_read.next().unwrap().unwrap();
+        let mut received = Vec::new();
+        File::open(received_file.path()).unwrap().read_to_end(&mut received).unwrap();
+        assert!(received == content);
+    }
+
+    // end receivers
+    receiver_brk.store(true, Ordering::SeqCst);
+    for rt in receivers {
+        rt.join().unwrap().unwrap();
+    }
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    for dir in &TARGET_DIRS {
+        remove_dir_all(dir).unwrap();
+    }
+}
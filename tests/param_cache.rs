@@ -0,0 +1,96 @@
+use udp_transfer::{receiver, sender};
+use std::fs::{File, read, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+#[test]
+fn param_cache(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const CACHE_FILE: &str = "param_cache.txt";
+    const FILE_SIZE: usize = 64 * 1024;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create file and directory
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_file(CACHE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create receiver, that accepts smaller packets than the sender requests
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 800,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // send the file twice and count the init packets of every transfer
+    let mut init_packets = Vec::new();
+    for _ in 0..2 {
+        let sent = Arc::new(AtomicUsize::new(0));
+        let counter = sent.clone();
+        let count_init = move |packet: &mut [u8]| {
+            if packet[8] == 0x1 {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        };
+        let sc = sender::config::Config {
+            verbose: false,
+            bind_addr: String::from(SENDER_ADDR),
+            file: String::from(SOURCE_FILE),
+            packet_size: 1500,
+            send_addr: String::from(RECEIVER_ADDR),
+            window_size: 15,
+            timeout: 100,
+            repetition: 10,
+            checksum_size: 16,
+            param_cache_path: Some(String::from(CACHE_FILE)),
+            on_send: Some(Mutex::new(Box::new(count_init))),
+            ..sender::config::Config::new()
+        };
+        let props = sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+        assert_eq!(props.packet_size, 800);
+        init_packets.push(sent.load(Ordering::SeqCst));
+    }
+
+    // the first connection lowered the packet size, the second one started from the cached size
+    assert!(init_packets[0] > 1);
+    assert_eq!(init_packets[1], 1);
+
+    // compare files
+    {
+        let received = read_dir(TARGET_DIR).unwrap()
+            .map(|entry| read(entry.unwrap().path()).unwrap())
+            .collect::<Vec<Vec<u8>>>();
+        assert_eq!(received.len(), 2);
+        assert!(received.iter().all(|file| file == &content));
+    }
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_file(CACHE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
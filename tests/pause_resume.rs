@@ -0,0 +1,99 @@
+use udp_transfer::{receiver, sender};
+use udp_transfer::sender::{Control, SenderEvent};
+use std::fs::{File, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::{Write, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+#[test]
+fn pause_resume(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 200_000;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create file and directory
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create receiver, that drops the connection idle for 500ms
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 500,
+        reject_unknown: true,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create sender
+    let (events_sender, events) = channel();
+    let control = Control::new();
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 16,
+        events: Some(events_sender),
+        ..sender::config::Config::new()
+    };
+    let st = sender::controlled_logic(sc, control.clone());
+
+    // pause the transfer once the window moved, the window in flight is still acknowledged
+    while !matches!(events.recv().unwrap(), SenderEvent::WindowAdvanced(_)) {}
+    control.pause();
+    while !matches!(events.recv().unwrap(), SenderEvent::Paused) {}
+    std::thread::sleep(Duration::from_millis(300));
+    events.try_iter().for_each(drop);
+
+    // nothing is send for longer than the receiver keeps the idle connection
+    std::thread::sleep(Duration::from_millis(1500));
+    let paused_events = events.try_iter().collect::<Vec<SenderEvent>>();
+    assert!(paused_events.iter().all(|e| !matches!(e, SenderEvent::WindowAdvanced(_) | SenderEvent::Failed(_))), "{:?}", paused_events);
+
+    // the resumed transfer continues to the end
+    control.resume();
+    st.join().unwrap().unwrap();
+    let events = events.iter().collect::<Vec<SenderEvent>>();
+    assert!(events.iter().any(|e| matches!(e, SenderEvent::Resumed)), "{:?}", events);
+    assert!(matches!(events.last(), Some(SenderEvent::Completed(_))), "{:?}", events);
+    {
+        let mut directory_read = read_dir(TARGET_DIR).unwrap();
+        let received_file = directory_read.next().unwrap().unwrap();
+        let mut received = Vec::new();
+        File::open(received_file.path()).unwrap().read_to_end(&mut received).unwrap();
+        assert!(received == content);
+    }
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
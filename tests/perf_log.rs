@@ -0,0 +1,85 @@
+use udp_transfer::{receiver, sender};
+use std::fs::{File, read_to_string, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn perf_log(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 20_000;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const PERF_LOG: &str = "perf.log";
+
+    // create file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create sender, that writes the performance log
+    match remove_file(PERF_LOG) { _ => {}};
+    let sender_brk = Arc::new(AtomicBool::new(false));
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 16,
+        perf_log_path: Some(String::from(PERF_LOG)),
+        ..sender::config::Config::new()
+    };
+    let st= sender::breakable_logic(sc, sender_brk);
+    st.join().unwrap().unwrap();
+
+    // the log has every value on its own line in the stable order
+    let log = read_to_string(PERF_LOG).unwrap();
+    let values: Vec<(&str, &str)> = log.lines().map(|line| {
+        let mut parts = line.splitn(2, '=');
+        (parts.next().unwrap(), parts.next().unwrap())
+    }).collect();
+    let names: Vec<&str> = values.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, vec!["bytes", "parts", "packets", "retransmissions", "duration_ms", "rtt_min_us", "rtt_smoothed_us", "rtt_max_us"]);
+    assert_eq!(values[0].1, FILE_SIZE.to_string());
+    for (name, value) in &values {
+        assert!(value.parse::<u64>().is_ok(), "{}={}", name, value);
+    }
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_file(PERF_LOG).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
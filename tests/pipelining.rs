@@ -0,0 +1,111 @@
+use std::fs::{create_dir_all, File, read_dir, remove_dir_all, remove_file};
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+use itertools::zip;
+use rand::Rng;
+use udp_transfer::{broker, receiver, sender};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn pipelining(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 512 * 1024;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create 512kB file
+    let mut orig_vector = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in orig_vector.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&orig_vector).unwrap();
+    }
+
+    // transfer the file over high latency link with the given pipeline depth
+    let transfer = |pipeline_depth: u16| -> Duration {
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+
+        // create receiver, that accepts only a single window of the sender
+        let receiver_brk = Arc::new(AtomicBool::new(false));
+        let rc = receiver::config::Config {
+            verbose: false,
+            bindaddr: String::from(RECEIVED_ADDR),
+            directory: String::from(TARGET_DIR),
+            max_packet_size: 1500,
+            max_window_size: 8,
+            min_checksum: 16,
+            timeout: 5000,
+            ..receiver::config::Config::new()
+        };
+        let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+        // create broker with 30ms delay in every direction
+        let broker_brk = Arc::new(AtomicBool::new(false));
+        let bc = broker::config::Config {
+            verbose: false,
+            sender_bindaddr: String::from(BROKER_SEND_PART),
+            sender_addr: String::from(SENDER_ADDR),
+            receiver_bindaddr: String::from(BROKER_RECV_PART),
+            receiver_addr: String::from(RECEIVED_ADDR),
+            delay_mean: 30.0,
+            delay_std: 0.0,
+            ..broker::config::Config::new()
+        };
+        let bt = broker::breakable_logic(bc, broker_brk.clone());
+
+        // create sender
+        let start = Instant::now();
+        let sender_brk = Arc::new(AtomicBool::new(false));
+        let sc = sender::config::Config {
+            verbose: false,
+            bind_addr: String::from(SENDER_ADDR),
+            file: String::from(SOURCE_FILE),
+            packet_size: 1500,
+            send_addr: String::from(BROKER_SEND_PART),
+            window_size: 8,
+            pipeline_depth,
+            timeout: 500,
+            repetition: 10,
+            checksum_size: 16,
+            ..sender::config::Config::new()
+        };
+        let st = sender::breakable_logic(sc, sender_brk);
+        st.join().unwrap().unwrap();
+        let duration = start.elapsed();
+
+        // compare files
+        let mut directory_read = read_dir(TARGET_DIR).unwrap();
+        let received_file = directory_read.next().unwrap().unwrap();
+        let mut received = File::open(received_file.path()).unwrap();
+        let mut received_vector = vec![0; FILE_SIZE];
+        assert_eq!(received.read(&mut received_vector).unwrap(), FILE_SIZE);
+        for (o, r) in zip(&orig_vector, &received_vector) {
+            assert_eq!(o, r);
+        }
+
+        // end receiver and broker
+        receiver_brk.store(true, Ordering::SeqCst);
+        rt.join().unwrap().unwrap();
+        broker_brk.store(true, Ordering::SeqCst);
+        bt.join().unwrap();
+        return duration;
+    };
+
+    // more windows in flight keep the link full
+    let single = transfer(1);
+    let pipelined = transfer(4);
+    assert!(pipelined * 2 < single, "pipelined {:?}, single {:?}", pipelined, single);
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,59 @@
+use udp_transfer::broker;
+use std::net::UdpSocket;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn processing_delay(){
+    const PACKETS: u16 = 50;
+    const PROCESSING_MS: f32 = 2.0;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create broker, that processes every packet for a while
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        drop_rate: 0.0,
+        processing_delay_ms: PROCESSING_MS,
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+    sleep(Duration::from_millis(100));
+
+    // send the burst of numbered packets through the broker
+    let receiver = UdpSocket::bind(RECEIVED_ADDR).unwrap();
+    receiver.set_read_timeout(Some(Duration::from_millis(1000))).unwrap();
+    let sender = UdpSocket::bind(SENDER_ADDR).unwrap();
+    let sent_at = Instant::now();
+    for i in 0..PACKETS {
+        sender.send_to(&i.to_be_bytes(), BROKER_SEND_PART).unwrap();
+    }
+
+    // packets arrive in order, every one waits for the processing of the packets before it
+    let mut buffer = vec![0; 16];
+    let mut latencies = Vec::new();
+    for i in 0..PACKETS {
+        let (size, _) = receiver.recv_from(&mut buffer).unwrap();
+        assert_eq!(size, 2);
+        assert_eq!(u16::from_be_bytes([buffer[0], buffer[1]]), i);
+        latencies.push(sent_at.elapsed());
+    }
+    assert!(latencies.windows(2).all(|pair| pair[0] <= pair[1]));
+    let processing = Duration::from_secs_f32(PROCESSING_MS / 1000.0);
+    let last = latencies[PACKETS as usize - 1];
+    assert!(last >= processing * PACKETS as u32, "last packet delivered after {:?}", last);
+    assert!(last - latencies[0] >= processing * (PACKETS - 1) as u32, "{:?}", latencies);
+
+    // end broker
+    broker_brk.store(true, Ordering::SeqCst);
+    bt.join().unwrap();
+}
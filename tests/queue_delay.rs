@@ -0,0 +1,53 @@
+use udp_transfer::broker;
+use udp_transfer::broker::config::DelayModel;
+use std::net::UdpSocket;
+use std::thread::sleep;
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn queue_delay(){
+    const PACKETS: u16 = 200;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create broker
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        delay_mean: 20.0,
+        delay_std: 0.0,
+        delay_model: DelayModel::QueueFifo,
+        service_time: 0.1,
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+    sleep(Duration::from_millis(100));
+
+    // send numbered packets through the broker
+    let receiver = UdpSocket::bind(RECEIVED_ADDR).unwrap();
+    receiver.set_read_timeout(Some(Duration::from_millis(1000))).unwrap();
+    let sender = UdpSocket::bind(SENDER_ADDR).unwrap();
+    for i in 0..PACKETS {
+        sender.send_to(&i.to_be_bytes(), BROKER_SEND_PART).unwrap();
+    }
+
+    // packets arrive in the same order
+    let mut buffer = vec![0; 16];
+    for i in 0..PACKETS {
+        let (size, _) = receiver.recv_from(&mut buffer).unwrap();
+        assert_eq!(size, 2);
+        assert_eq!(u16::from_be_bytes([buffer[0], buffer[1]]), i);
+    }
+
+    // end broker
+    broker_brk.store(true, Ordering::SeqCst);
+    bt.join().unwrap();
+}
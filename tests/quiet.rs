@@ -0,0 +1,49 @@
+use std::fs::{File, remove_file, remove_dir_all, create_dir_all};
+use std::process::{Command, Stdio};
+use rand::{Rng};
+use std::io::Write;
+
+#[test]
+fn quiet(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 256 * 1024;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create 256kB file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // run receiver and sender binaries
+    let receiver = Command::new(env!("CARGO_BIN_EXE_receiver"))
+        .args(&["--quiet", "--addr", RECEIVER_ADDR, "-d", TARGET_DIR, "--stop_after", "1"])
+        .stdout(Stdio::piped())
+        .spawn().unwrap();
+    let sender = Command::new(env!("CARGO_BIN_EXE_sender"))
+        .args(&["--quiet", "--bind", SENDER_ADDR, "--addr", RECEIVER_ADDR, "-f", SOURCE_FILE])
+        .stdout(Stdio::piped())
+        .spawn().unwrap();
+
+    // nothing is printed
+    let sender_output = sender.wait_with_output().unwrap();
+    let receiver_output = receiver.wait_with_output().unwrap();
+    assert!(sender_output.status.success());
+    assert!(receiver_output.status.success());
+    assert_eq!(String::from_utf8_lossy(&sender_output.stdout), "");
+    assert_eq!(String::from_utf8_lossy(&receiver_output.stdout), "");
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,74 @@
+use udp_transfer::{receiver, sender};
+use std::fs::{File, metadata, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn receive_all(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 50_000;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // create receiver, that ends after one second without any packet
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = thread::spawn(move || receiver::receive_all(rc, Duration::from_secs(1)));
+
+    // send the file twice
+    for _ in 0..2 {
+        let sc = sender::config::Config {
+            verbose: false,
+            bind_addr: String::from(SENDER_ADDR),
+            file: String::from(SOURCE_FILE),
+            packet_size: 1500,
+            send_addr: String::from(RECEIVER_ADDR),
+            window_size: 15,
+            timeout: 100,
+            repetition: 10,
+            checksum_size: 16,
+            ..sender::config::Config::new()
+        };
+        sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+    }
+
+    // the receiver ends by itself and reports both files
+    let completed = rt.join().unwrap().unwrap();
+    assert_eq!(completed.len(), 2);
+    assert_ne!(completed[0].id, completed[1].id);
+    for transfer in completed {
+        assert_eq!(transfer.bytes, FILE_SIZE as u64);
+        assert_eq!(metadata(&transfer.path).unwrap().len(), FILE_SIZE as u64);
+    }
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,100 @@
+use udp_transfer::{receiver, sender};
+use udp_transfer::receiver::ReceiverEvent;
+use std::fs::{File, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+
+#[test]
+fn receiver_events(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 20_000;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // create receiver, that reports what happens
+    let (events_sender, events) = channel();
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        events: Some(events_sender),
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create sender
+    let sender_brk = Arc::new(AtomicBool::new(false));
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 0,
+        ..sender::config::Config::new()
+    };
+    let st= sender::breakable_logic(sc, sender_brk);
+    st.join().unwrap().unwrap();
+
+    // end receiver, the channel is closed together with it
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+    // the reads from the socket don't belong to the connection
+    let events: Vec<ReceiverEvent> = events.iter()
+        .filter(|e| !matches!(e, ReceiverEvent::DatagramsRead(_)))
+        .collect();
+
+    // the connection is accepted first and the file is completed last
+    assert!(matches!(events.first(), Some(ReceiverEvent::ConnectionAccepted(_, p)) if p.packet_size == 1500), "{:?}", events);
+    let received_file = read_dir(TARGET_DIR).unwrap().next().unwrap().unwrap();
+    match events.last() {
+        Some(ReceiverEvent::Completed(path, bytes, _)) => {
+            assert_eq!(*bytes, FILE_SIZE as u64);
+            assert_eq!(path, received_file.path().to_str().unwrap());
+        }
+        e => panic!("{:?}", e),
+    };
+    // every stored part was written into the file
+    let stored: usize = events.iter().map(|e| match e {
+        ReceiverEvent::DataStored(_, bytes) => *bytes,
+        _ => 0,
+    }).sum();
+    let written: usize = events.iter().map(|e| match e {
+        ReceiverEvent::FileWritten(bytes) => *bytes,
+        _ => 0,
+    }).sum();
+    assert_eq!(stored, FILE_SIZE);
+    assert_eq!(written, FILE_SIZE);
+    assert!(events.iter().any(|e| matches!(e, ReceiverEvent::WindowAdvanced(_))), "{:?}", events);
+    assert!(events.iter().all(|e| !matches!(e, ReceiverEvent::ConnectionClosed(_))), "{:?}", events);
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,108 @@
+use udp_transfer::{broker, receiver, sender, TransferError};
+use std::fs::{File, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{sleep, JoinHandle};
+use std::time::{Duration, Instant};
+
+const TARGET_DIR: &str = "received";
+const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+
+fn start_receiver(brk: Arc<AtomicBool>) -> JoinHandle<Result<(), String>> {
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 100,
+        reject_unknown: true,
+        ..receiver::config::Config::new()
+    };
+    return receiver::breakable_logic(rc, brk);
+}
+
+#[test]
+fn receiver_restart(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const FILE_SIZE: usize = 2 * 1024 * 1024;
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // create receiver
+    let first_brk = Arc::new(AtomicBool::new(false));
+    let first = start_receiver(first_brk.clone());
+
+    // create broker, that slows the transfer down
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVER_ADDR),
+        packet_size: 1500,
+        delay_mean: 5.0,
+        delay_std: 0.0,
+        drop_rate: 0.0,
+        modify_prob: 0.0,
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+
+    // create sender, that would try for seconds without the error packet
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(BROKER_SEND_PART),
+        window_size: 1,
+        timeout: 100,
+        repetition: 50,
+        checksum_size: 16,
+        ..sender::config::Config::new()
+    };
+    let st = sender::breakable_logic(sc, Arc::new(AtomicBool::new(false)));
+
+    // restart the receiver in the middle of the transfer
+    sleep(Duration::from_millis(300));
+    first_brk.store(true, Ordering::SeqCst);
+    first.join().unwrap().unwrap();
+    let second_brk = Arc::new(AtomicBool::new(false));
+    let second = start_receiver(second_brk.clone());
+    let restarted_at = Instant::now();
+
+    // the sender ends as soon as the new receiver rejects the connection
+    let e = st.join().unwrap().unwrap_err();
+    assert!(matches!(e, TransferError::ReceiverGone(_)), "{:?}", e);
+    assert!(restarted_at.elapsed() < Duration::from_millis(1000), "{:?}", restarted_at.elapsed());
+
+    // end receiver and broker
+    second_brk.store(true, Ordering::SeqCst);
+    broker_brk.store(true, Ordering::SeqCst);
+    second.join().unwrap().unwrap();
+    bt.join().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,77 @@
+use udp_transfer::{receiver, sender};
+use std::fs::{File, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn receiver_seed(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 10_000;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create file
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // transfer the file to fresh receivers with the seeds
+    let mut ids = Vec::new();
+    for seed in [42, 42, 43].iter() {
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+
+        // create receiver
+        let receiver_brk = Arc::new(AtomicBool::new(false));
+        let rc = receiver::config::Config {
+            verbose: false,
+            bindaddr: String::from(RECEIVER_ADDR),
+            directory: String::from(TARGET_DIR),
+            max_packet_size: 1500,
+            max_window_size: 15,
+            min_checksum: 16,
+            timeout: 5000,
+            seed: Some(*seed),
+            ..receiver::config::Config::new()
+        };
+        let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+        // create sender
+        let sc = sender::config::Config {
+            verbose: false,
+            bind_addr: String::from(SENDER_ADDR),
+            file: String::from(SOURCE_FILE),
+            packet_size: 1500,
+            send_addr: String::from(RECEIVER_ADDR),
+            window_size: 15,
+            timeout: 100,
+            repetition: 10,
+            checksum_size: 16,
+            ..sender::config::Config::new()
+        };
+        let props = sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+        ids.push(props.id);
+
+        // end receiver
+        receiver_brk.store(true, Ordering::SeqCst);
+        rt.join().unwrap().unwrap();
+    }
+
+    // the same seed allocates the same connection identifier
+    assert_eq!(ids[0], ids[1]);
+    assert_ne!(ids[0], ids[2]);
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
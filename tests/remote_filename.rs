@@ -0,0 +1,87 @@
+use udp_transfer::{receiver, sender};
+use std::env::current_dir;
+use std::fs::{File, read, read_dir, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn remote_filename(){
+    const SOURCE_DIR: &str = "hostile_source";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 20_000;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create the files and directories
+    let mut contents = Vec::new();
+    {
+        match remove_dir_all(SOURCE_DIR) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(format!("{}/inner", SOURCE_DIR)).unwrap();
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut rng = rand::thread_rng();
+        for name in ["first.txt", "second.txt"].iter() {
+            let mut content = vec![0; FILE_SIZE];
+            for f in content.as_mut_slice() {
+                *f = rng.gen::<u8>();
+            }
+            File::create(format!("{}/{}", SOURCE_DIR, name)).unwrap().write_all(&content).unwrap();
+            contents.push(content);
+        }
+    }
+
+    // create receiver
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = thread::spawn(move || receiver::receive_all(rc, Duration::from_secs(1)));
+
+    // send the files under the names leading out of the directory
+    let absolute = current_dir().unwrap().join(SOURCE_DIR).join("second.txt");
+    let names = vec![
+        format!("{}/inner/../first.txt", SOURCE_DIR),
+        String::from(absolute.to_str().unwrap()),
+    ];
+    for name in names {
+        let sc = sender::config::Config {
+            verbose: false,
+            bind_addr: String::from(SENDER_ADDR),
+            file: name,
+            packet_size: 1500,
+            send_addr: String::from(RECEIVER_ADDR),
+            window_size: 15,
+            timeout: 100,
+            repetition: 10,
+            checksum_size: 16,
+            send_metadata: true,
+            ..sender::config::Config::new()
+        };
+        sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+    }
+
+    // the files are stored in the directory under their last path component
+    let completed = rt.join().unwrap().unwrap();
+    assert_eq!(completed.len(), 2);
+    assert_eq!(read_dir(TARGET_DIR).unwrap().count(), 2);
+    assert_eq!(Path::new(&completed[0].path), Path::new(TARGET_DIR).join("first.txt"));
+    assert_eq!(Path::new(&completed[1].path), Path::new(TARGET_DIR).join("second.txt"));
+    assert!(read(&completed[0].path).unwrap() == contents[0]);
+    assert!(read(&completed[1].path).unwrap() == contents[1]);
+    assert_eq!(read_dir(SOURCE_DIR).unwrap().count(), 3);
+
+    // delete files
+    remove_dir_all(SOURCE_DIR).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,115 @@
+use udp_transfer::{receiver, sender, broker};
+use std::fs::{File, read_dir, remove_file, remove_dir_all, create_dir_all, read_to_string};
+use rand::{Rng};
+use std::io::{Write, Read};
+use std::path::Path;
+use std::thread::sleep;
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn resume_manifest(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const MANIFEST_FILE: &str = "somefile.manifest";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 2 * 1024 * 1024;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+    // create 2MB file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_file(MANIFEST_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVED_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create broker slowing down the transfer
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        delay_mean: 5.0,
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+
+    let sender_config = || sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(BROKER_SEND_PART),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 0,
+        manifest_path: Some(String::from(MANIFEST_FILE)),
+        manifest_interval: 50,
+        ..sender::config::Config::new()
+    };
+
+    // kill the sender after part of the file is acknowledged
+    let sender_brk = Arc::new(AtomicBool::new(false));
+    let st = sender::breakable_logic(sender_config(), sender_brk.clone());
+    while !read_to_string(MANIFEST_FILE).map_or(false, |m| m.contains("position=") && !m.contains("position=0\n")) {
+        sleep(Duration::from_millis(5));
+    }
+    sender_brk.store(true, Ordering::SeqCst);
+    assert!(st.join().unwrap().is_err());
+    assert!(Path::new(MANIFEST_FILE).exists());
+
+    // restart the sender, it continues in the same connection
+    let st = sender::breakable_logic(sender_config(), Arc::new(AtomicBool::new(false)));
+    st.join().unwrap().unwrap();
+    assert!(!Path::new(MANIFEST_FILE).exists());
+
+    // compare files, there is only the resumed one
+    {
+        let mut original = File::open(SOURCE_FILE).unwrap();
+        let mut orig_vector = Vec::new();
+        original.read_to_end(&mut orig_vector).unwrap();
+        let files = read_dir(TARGET_DIR).unwrap().map(|f| f.unwrap().path()).collect::<Vec<_>>();
+        assert_eq!(files.len(), 1);
+        let mut received_vector = Vec::new();
+        File::open(&files[0]).unwrap().read_to_end(&mut received_vector).unwrap();
+        assert_eq!(orig_vector, received_vector);
+    }
+
+    // end receiver and broker
+    receiver_brk.store(true, Ordering::SeqCst);
+    broker_brk.store(true, Ordering::SeqCst);
+    bt.join().unwrap();
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,104 @@
+use udp_transfer::{receiver, sender};
+use udp_transfer::sender::{Control, SenderEvent};
+use std::fs::{File, read, read_dir, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+
+#[test]
+fn same_filename(){
+    const SOURCE_DIRS: [&str; 2] = ["same_first", "same_second"];
+    const SOURCE_NAME: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 100_000;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: [&str; 2] = ["127.0.0.1:3101", "127.0.0.1:3102"];
+
+    // create files with the same name and different content
+    let mut contents = Vec::new();
+    {
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut rng = rand::thread_rng();
+        for dir in SOURCE_DIRS.iter() {
+            match remove_dir_all(dir) { _ => {}};
+            create_dir_all(dir).unwrap();
+            let mut content = vec![0; FILE_SIZE];
+            for f in content.as_mut_slice() {
+                *f = rng.gen::<u8>();
+            }
+            File::create(Path::new(dir).join(SOURCE_NAME)).unwrap().write_all(&content).unwrap();
+            contents.push(content);
+        }
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    let sender_config = |index: usize| sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR[index]),
+        file: String::from(Path::new(SOURCE_DIRS[index]).join(SOURCE_NAME).to_str().unwrap()),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 16,
+        send_metadata: true,
+        ..sender::config::Config::new()
+    };
+
+    // the first sender names its file and waits before sending the data
+    let (events_sender, events) = channel();
+    let control = Control::new();
+    control.pause();
+    let first = sender::controlled_logic(sender::config::Config {
+        events: Some(events_sender),
+        ..sender_config(0)
+    }, control.clone());
+    while !matches!(events.recv().unwrap(), SenderEvent::Paused) {}
+
+    // the second sender transfers the file with the same name meanwhile
+    sender::breakable_logic(sender_config(1), Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+    control.resume();
+    first.join().unwrap().unwrap();
+
+    // the file with the same name sent again doesn't replace the stored ones
+    sender::breakable_logic(sender_config(1), Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // each file is stored separately
+    {
+        assert_eq!(read_dir(TARGET_DIR).unwrap().count(), 3);
+        let first_path = Path::new(TARGET_DIR).join(SOURCE_NAME);
+        let second_path = Path::new(TARGET_DIR).join(format!("{}_1", SOURCE_NAME));
+        let third_path = Path::new(TARGET_DIR).join(format!("{}_2", SOURCE_NAME));
+        assert!(read(first_path).unwrap() == contents[0]);
+        assert!(read(second_path).unwrap() == contents[1]);
+        assert!(read(third_path).unwrap() == contents[1]);
+    }
+
+    // delete files
+    for dir in SOURCE_DIRS.iter() {
+        remove_dir_all(dir).unwrap();
+    }
+    remove_dir_all(TARGET_DIR).unwrap();
+}
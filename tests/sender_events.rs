@@ -0,0 +1,92 @@
+use udp_transfer::{receiver, sender};
+use udp_transfer::sender::SenderEvent;
+use std::fs::{File, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+
+#[test]
+fn sender_events(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 20_000;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create sender, that reports what happens
+    let (events_sender, events) = channel();
+    let sender_brk = Arc::new(AtomicBool::new(false));
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 0,
+        events: Some(events_sender),
+        ..sender::config::Config::new()
+    };
+    let st= sender::breakable_logic(sc, sender_brk);
+    st.join().unwrap().unwrap();
+
+    // the channel is closed together with the sender
+    let events: Vec<SenderEvent> = events.iter().collect();
+    assert!(matches!(events.first(), Some(SenderEvent::ConnectionEstablished(p)) if p.packet_size == 1500), "{:?}", events);
+    let positions: Vec<u16> = events.iter().filter_map(|e| match e {
+        SenderEvent::WindowAdvanced(position) => Some(*position),
+        _ => None,
+    }).collect();
+    assert!(!positions.is_empty(), "{:?}", events);
+    assert!(positions.windows(2).all(|w| w[0] < w[1]), "{:?}", positions);
+    assert!(events.iter().all(|e| !matches!(e, SenderEvent::Failed(_))), "{:?}", events);
+    match events.last() {
+        Some(SenderEvent::Completed(stats)) => {
+            assert_eq!(stats.bytes, FILE_SIZE as u64);
+            assert!(stats.throughput() > 0.0);
+            let expected = FILE_SIZE as f64 / 1_000_000.0 / stats.elapsed.as_secs_f64();
+            assert!((stats.throughput() - expected).abs() < 1e-6 * expected, "{}", stats.summary());
+        }
+        e => panic!("{:?}", e),
+    };
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,98 @@
+use udp_transfer::{receiver, sender, ErrorReason, TransferError};
+use udp_transfer::sender::SenderEvent;
+use std::fs::{File, copy, read, read_dir, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+
+#[test]
+fn start_offset(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const OTHER_FILE: &str = "otherfile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 512 * 1024;
+    const START_OFFSET: usize = FILE_SIZE / 2 + 123;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+
+    // create 512kB file and directory, the receiver has the first half of the file already
+    let mut original = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in original.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        File::create(SOURCE_FILE).unwrap().write_all(&original).unwrap();
+        File::create(Path::new(TARGET_DIR).join(SOURCE_FILE)).unwrap().write_all(&original[..START_OFFSET]).unwrap();
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // sender, that sends only the second half of the file
+    let sender_config = |file: &str| sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(file),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 16,
+        start_offset: START_OFFSET as u64,
+        quiet: true,
+        ..sender::config::Config::new()
+    };
+    let (events_sender, events) = channel();
+    let sc = sender::config::Config {
+        events: Some(events_sender),
+        ..sender_config(SOURCE_FILE)
+    };
+    sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+
+    // only the send content counts into the statistics
+    match events.iter().last() {
+        Some(SenderEvent::Completed(stats)) => assert_eq!(stats.bytes, (FILE_SIZE - START_OFFSET) as u64),
+        e => panic!("{:?}", e),
+    };
+
+    // the second half continues the file, that the receiver has
+    {
+        assert_eq!(read_dir(TARGET_DIR).unwrap().count(), 1);
+        let received = read(Path::new(TARGET_DIR).join(SOURCE_FILE)).unwrap();
+        assert!(received == original);
+    }
+
+    // the receiver has no file to continue under the other name
+    copy(SOURCE_FILE, OTHER_FILE).unwrap();
+    let result = sender::breakable_logic(sender_config(OTHER_FILE), Arc::new(AtomicBool::new(false))).join().unwrap();
+    assert!(matches!(result, Err(TransferError::ReceiverClosed(ErrorReason::MissingTarget))), "{:?}", result);
+    assert_eq!(read_dir(TARGET_DIR).unwrap().count(), 1);
+
+    // end receiver
+    receiver_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_file(OTHER_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
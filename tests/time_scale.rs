@@ -0,0 +1,57 @@
+use udp_transfer::broker;
+use std::net::UdpSocket;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const PACKETS: u16 = 10;
+const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+const SENDER_ADDR: &str = "127.0.0.1:3101";
+const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+
+/// Measure the average time packet needs to pass the broker with the given `time_scale`.
+fn average_latency(time_scale: f32) -> Duration {
+    // create broker
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        delay_mean: 50.0,
+        delay_std: 0.0,
+        time_scale,
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+    sleep(Duration::from_millis(100));
+
+    // send packets one by one through the broker
+    let receiver = UdpSocket::bind(RECEIVED_ADDR).unwrap();
+    receiver.set_read_timeout(Some(Duration::from_millis(1000))).unwrap();
+    let sender = UdpSocket::bind(SENDER_ADDR).unwrap();
+    let mut buffer = vec![0; 16];
+    let mut total = Duration::from_millis(0);
+    for i in 0..PACKETS {
+        let start = Instant::now();
+        sender.send_to(&i.to_be_bytes(), BROKER_SEND_PART).unwrap();
+        receiver.recv_from(&mut buffer).unwrap();
+        total += start.elapsed();
+    }
+
+    // end broker
+    broker_brk.store(true, Ordering::SeqCst);
+    bt.join().unwrap();
+    return total / PACKETS as u32;
+}
+
+#[test]
+fn time_scale(){
+    let normal = average_latency(1.0);
+    let doubled = average_latency(2.0);
+    let ratio = doubled.as_secs_f32() / normal.as_secs_f32();
+    assert!(ratio > 1.7 && ratio < 2.3, "latency {:?} with scale 1 and {:?} with scale 2", normal, doubled);
+}
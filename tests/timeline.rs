@@ -0,0 +1,115 @@
+use udp_transfer::{receiver, sender, broker};
+use std::fs::{File, read_to_string, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[test]
+fn timeline(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 20_000;
+    const RECEIVED_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const BROKER_RECV_PART: &str = "127.0.0.1:3102";
+    const BROKER_SEND_PART: &str = "127.0.0.1:3103";
+    const TIMELINE: &str = "timeline.csv";
+    const DROPPED_SEQ: u16 = 5;
+
+    // create file and directory
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        let mut buffer = vec![0; FILE_SIZE];
+        for f in buffer.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&buffer).unwrap();
+    }
+
+    // create receiver
+    let receiver_brk = Arc::new(AtomicBool::new(false));
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVED_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        ..receiver::config::Config::new()
+    };
+    let rt = receiver::breakable_logic(rc, receiver_brk.clone());
+
+    // create broker, that drops single part once
+    let broker_brk = Arc::new(AtomicBool::new(false));
+    let bc = broker::config::Config {
+        verbose: false,
+        sender_bindaddr: String::from(BROKER_SEND_PART),
+        sender_addr: String::from(SENDER_ADDR),
+        receiver_bindaddr: String::from(BROKER_RECV_PART),
+        receiver_addr: String::from(RECEIVED_ADDR),
+        delay_mean: 0.0,
+        delay_std: 0.0,
+        drop_rate: 0.0,
+        drop_seqs_to_receiver: vec![DROPPED_SEQ],
+        ..broker::config::Config::new()
+    };
+    let bt = broker::breakable_logic(bc, broker_brk.clone());
+
+    // create sender, that writes the timeline
+    match remove_file(TIMELINE) { _ => {}};
+    let sender_brk = Arc::new(AtomicBool::new(false));
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(BROKER_SEND_PART),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 16,
+        timeline_path: Some(String::from(TIMELINE)),
+        ..sender::config::Config::new()
+    };
+    let st= sender::breakable_logic(sc, sender_brk);
+    st.join().unwrap().unwrap();
+
+    // the dropped part was send more times and acknowledged once, after the last transmission
+    let timeline = read_to_string(TIMELINE).unwrap();
+    let mut lines = timeline.lines();
+    assert_eq!(lines.next(), Some("seq,event,attempt,micros"));
+    let events: Vec<(u16, String, u16, u64)> = lines.map(|line| {
+        let values: Vec<&str> = line.split(',').collect();
+        (values[0].parse().unwrap(), String::from(values[1]), values[2].parse().unwrap(), values[3].parse().unwrap())
+    }).collect();
+    let dropped: Vec<&(u16, String, u16, u64)> = events.iter().filter(|(seq, _, _, _)| *seq == DROPPED_SEQ).collect();
+    let sends: Vec<u64> = dropped.iter().filter(|(_, event, _, _)| event == "send").map(|(_, _, _, at)| *at).collect();
+    let acks: Vec<(u16, u64)> = dropped.iter().filter(|(_, event, _, _)| event == "ack").map(|(_, _, attempt, at)| (*attempt, *at)).collect();
+    assert!(sends.len() >= 2, "{:?}", dropped);
+    assert_eq!(acks.len(), 1, "{:?}", dropped);
+    assert_eq!(acks[0].0 as usize, sends.len());
+    assert!(acks[0].1 >= *sends.last().unwrap());
+    // every send part was acknowledged exactly once
+    let mut send: Vec<u16> = events.iter().filter(|(_, event, _, _)| event == "send").map(|(seq, _, _, _)| *seq).collect();
+    let acknowledged: Vec<u16> = events.iter().filter(|(_, event, _, _)| event == "ack").map(|(seq, _, _, _)| *seq).collect();
+    send.sort();
+    send.dedup();
+    assert_eq!(send.len(), acknowledged.len());
+
+    // end receiver and broker
+    receiver_brk.store(true, Ordering::SeqCst);
+    broker_brk.store(true, Ordering::SeqCst);
+    rt.join().unwrap().unwrap();
+    bt.join().unwrap();
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_file(TIMELINE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
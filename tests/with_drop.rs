@@ -39,7 +39,8 @@ fn with_drop(){
         max_packet_size: 1500,
         max_window_size: 15,
         min_checksum: 0,
-        timeout: 5000
+        timeout: 5000,
+        ..receiver::config::Config::new()
     };
     let rt = receiver::breakable_logic(rc, receiver_brk.clone());
 
@@ -55,7 +56,8 @@ fn with_drop(){
         delay_mean: 0.0,
         delay_std: 0.0,
         drop_rate: 0.2,
-        modify_prob: 0.0
+        modify_prob: 0.0,
+        ..broker::config::Config::new()
     };
     let bt = broker::breakable_logic(bc, broker_brk.clone());
 
@@ -70,7 +72,8 @@ fn with_drop(){
         window_size: 15,
         timeout: 100,
         repetition: 10,
-        checksum_size: 0
+        checksum_size: 0,
+        ..sender::config::Config::new()
     };
     let st= sender::breakable_logic(sc, sender_brk);
 
@@ -0,0 +1,80 @@
+use udp_transfer::{receiver, sender};
+use std::fs::{File, read, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::{self, ErrorKind, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn write_retries(){
+    const SOURCE_FILE: &str = "somefile.txt";
+    const TARGET_DIR: &str = "received";
+    const FILE_SIZE: usize = 20_000;
+    const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+    const SENDER_ADDR: &str = "127.0.0.1:3101";
+    const FAILURES: usize = 3;
+
+    // create file and directory
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        match remove_dir_all(TARGET_DIR) { _ => {}};
+        create_dir_all(TARGET_DIR).unwrap();
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // create receiver, whose disk is full for the first few writes
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counted = attempts.clone();
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 16,
+        timeout: 5000,
+        write_retries: 5,
+        write_backoff: 10,
+        on_write: Some(Arc::new(move |_, _| {
+            match counted.fetch_add(1, Ordering::SeqCst) < FAILURES {
+                true => Err(io::Error::from(ErrorKind::StorageFull)),
+                false => Ok(()),
+            }
+        })),
+        ..receiver::config::Config::new()
+    };
+    let rt = thread::spawn(move || receiver::receive_all(rc, Duration::from_secs(1)));
+
+    // create sender
+    let sc = sender::config::Config {
+        verbose: false,
+        bind_addr: String::from(SENDER_ADDR),
+        file: String::from(SOURCE_FILE),
+        packet_size: 1500,
+        send_addr: String::from(RECEIVER_ADDR),
+        window_size: 15,
+        timeout: 100,
+        repetition: 10,
+        checksum_size: 16,
+        ..sender::config::Config::new()
+    };
+    sender::breakable_logic(sc, Arc::new(AtomicBool::new(false))).join().unwrap().unwrap();
+
+    // the failed writes were retried and the file is complete
+    let completed = rt.join().unwrap().unwrap();
+    assert_eq!(completed.len(), 1);
+    assert!(read(&completed[0].path).unwrap() == content);
+    assert!(attempts.load(Ordering::SeqCst) > FAILURES);
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}
@@ -0,0 +1,103 @@
+use udp_transfer::{receiver, sender};
+use std::fs::{File, read, remove_file, remove_dir_all, create_dir_all};
+use rand::{Rng};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::AtomicBool;
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+const SOURCE_FILE: &str = "somefile.txt";
+const TARGET_DIR: &str = "received";
+const FILE_SIZE: usize = 100_000;
+const WRITE_DELAY: Duration = Duration::from_millis(5);
+const RECEIVER_ADDR: &str = "127.0.0.1:3100";
+const SENDER_ADDRS: [&str; 2] = [
+    "127.0.0.1:3401",
+    "127.0.0.1:3402",
+];
+
+/// Send the file by two senders at once to the receiver with the slow disk and the `writer_threads`.
+/// Returns the median time between the datagrams the receiver read.
+fn median_read_gap(writer_threads: usize, content: &[u8]) -> Duration {
+    match remove_dir_all(TARGET_DIR) { _ => {}};
+    create_dir_all(TARGET_DIR).unwrap();
+
+    // create receiver, that remembers when it read the datagrams and waits for the disk on every write
+    let reads = Arc::new(Mutex::new(Vec::new()));
+    let read_times = reads.clone();
+    let rc = receiver::config::Config {
+        verbose: false,
+        bindaddr: String::from(RECEIVER_ADDR),
+        directory: String::from(TARGET_DIR),
+        max_packet_size: 1500,
+        max_window_size: 15,
+        min_checksum: 0,
+        timeout: 5000,
+        writer_threads,
+        on_recv: Some(Mutex::new(Box::new(move |_, _| read_times.lock().unwrap().push(Instant::now())))),
+        on_write: Some(Arc::new(|_, _| {
+            sleep(WRITE_DELAY);
+            return Ok(());
+        })),
+        ..receiver::config::Config::new()
+    };
+    let rt = thread::spawn(move || receiver::receive_all(rc, Duration::from_secs(1)));
+
+    // create senders
+    let senders_threads = SENDER_ADDRS.iter().map(|addr| {
+        let sc = sender::config::Config {
+            verbose: false,
+            bind_addr: String::from(*addr),
+            file: String::from(SOURCE_FILE),
+            packet_size: 1500,
+            send_addr: String::from(RECEIVER_ADDR),
+            window_size: 15,
+            timeout: 500,
+            repetition: 20,
+            checksum_size: 0,
+            ..sender::config::Config::new()
+        };
+        sender::breakable_logic(sc, Arc::new(AtomicBool::new(false)))
+    }).collect::<Vec<_>>();
+    for thread in senders_threads {
+        thread.join().unwrap().unwrap();
+    }
+
+    // both files are complete
+    let completed = rt.join().unwrap().unwrap();
+    assert_eq!(completed.len(), 2);
+    for transfer in completed {
+        assert!(read(&transfer.path).unwrap() == content);
+    }
+
+    let reads = reads.lock().unwrap();
+    let mut gaps = reads.windows(2).map(|pair| pair[1] - pair[0]).collect::<Vec<_>>();
+    gaps.sort();
+    return gaps[gaps.len() / 2];
+}
+
+#[test]
+fn writer_threads(){
+    // create file
+    let mut content = vec![0; FILE_SIZE];
+    {
+        match remove_file(SOURCE_FILE) { _ => {}};
+        let mut file = File::create(SOURCE_FILE).unwrap();
+        let mut rng = rand::thread_rng();
+        for f in content.as_mut_slice() {
+            *f = rng.gen::<u8>();
+        }
+        file.write_all(&content).unwrap();
+    }
+
+    // the main loop waits for every write, the writer threads let it read the socket meanwhile
+    let serial = median_read_gap(0, &content);
+    let threaded = median_read_gap(2, &content);
+    assert!(serial >= WRITE_DELAY, "median gap {:?} when writing in the main loop", serial);
+    assert!(threaded * 2 < serial, "median gap {:?} with writer threads and {:?} without", threaded, serial);
+
+    // delete files
+    remove_file(SOURCE_FILE).unwrap();
+    remove_dir_all(TARGET_DIR).unwrap();
+}